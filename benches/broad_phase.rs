@@ -0,0 +1,76 @@
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use ultraviolet::Vec3;
+
+use rust_game_engine::math::aabb_bouding_box::AABBBoundingBox;
+use rust_game_engine::physics::broad_phase::DynamicAabbTree;
+
+const BODY_COUNT: usize = 5000;
+const QUERY_COUNT: usize = 2000;
+
+// Small deterministic xorshift generator so runs are comparable without pulling in a rand dependency
+struct Random(u32);
+
+impl Random {
+    fn next_f32(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0 as f32 / u32::MAX as f32
+    }
+
+    fn next_box(&mut self, world_size: f32, max_half_extent: f32) -> AABBBoundingBox {
+        let center = Vec3::new(self.next_f32(), self.next_f32(), self.next_f32()) * world_size;
+        let half_extents = Vec3::new(self.next_f32(), self.next_f32(), self.next_f32()) * max_half_extent + Vec3::broadcast(0.1);
+        AABBBoundingBox::from_center_half_extents(center, half_extents)
+    }
+}
+
+fn measure(name: &str, mut f: impl FnMut() -> usize) -> Duration {
+    let start = Instant::now();
+    let result = f();
+    let elapsed = start.elapsed();
+    println!("{name:<32} {elapsed:>12.3?} ({result} results)");
+
+    elapsed
+}
+
+fn main() {
+    let mut random = Random(0x2545_f491);
+    let boxes: Vec<AABBBoundingBox> = (0..BODY_COUNT).map(|_| random.next_box(200.0, 2.0)).collect();
+    let queries: Vec<AABBBoundingBox> = (0..QUERY_COUNT).map(|_| random.next_box(200.0, 5.0)).collect();
+
+    let mut tree = DynamicAabbTree::with_margin(0.0);
+    let build = measure("tree build", || {
+        for (i, bounding_box) in boxes.iter().enumerate() {
+            tree.insert(*bounding_box, i);
+        }
+        tree.len()
+    });
+
+    let brute_force_query = measure("brute force region queries", || {
+        queries.iter().map(|query| boxes.iter().filter(|b| black_box(b).collides_with(*query)).count()).sum()
+    });
+    let tree_query = measure("tree region queries", || {
+        queries.iter().map(|query| black_box(tree.query(*query)).len()).sum()
+    });
+
+    let brute_force_pairs = measure("brute force overlapping pairs", || {
+        let mut count = 0;
+        for i in 0..boxes.len() {
+            for j in i + 1..boxes.len() {
+                if black_box(boxes[i]).collides_with(boxes[j]) {
+                    count += 1;
+                }
+            }
+        }
+        count
+    });
+    let tree_pairs = measure("tree overlapping pairs", || black_box(tree.overlapping_pairs()).len());
+
+    println!();
+    println!("tree height {}, build {build:.3?}", tree.height());
+    println!("region query speedup   {:.1}x", brute_force_query.as_secs_f64() / tree_query.as_secs_f64());
+    println!("overlap pairs speedup  {:.1}x", brute_force_pairs.as_secs_f64() / tree_pairs.as_secs_f64());
+}
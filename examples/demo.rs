@@ -0,0 +1,69 @@
+use ultraviolet::Vec3;
+
+use rust_game_engine::assets::AssetManager;
+use rust_game_engine::ecs::components::{RenderModel, Script, StaticCollider, Transform};
+use rust_game_engine::ecs::world::{Entity, World};
+use rust_game_engine::engine::{Engine, EngineConfig};
+use rust_game_engine::error::EngineResult;
+use rust_game_engine::graphics::node_3d::Node3D;
+use rust_game_engine::graphics::post_processing::{PostProcessor, PostProcessSettings};
+use rust_game_engine::graphics::scene::Scene;
+use rust_game_engine::graphics::scene_description::SceneDescription;
+use rust_game_engine::math::rotation::Rotation;
+use rust_game_engine::physics::collision_shape::CollisionShape;
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("{e}");
+        std::process::exit(1);
+    }
+}
+
+fn run() -> EngineResult<()> {
+    let mut engine = Engine::new(EngineConfig::default());
+
+    let mut assets = AssetManager::new();
+
+    let shader_program_font = assets.load_shader("res/shaders/font.vs", "res/shaders/font.fs")?;
+
+    shader_program_font.get().bind();
+    shader_program_font.get().set_int("tex", 0);
+
+    let shader_program = assets.load_shader("res/shaders/default.vs", "res/shaders/default.fs")?;
+
+    let mut scene = if std::env::args().any(|arg| arg == "--default-scene") {
+        SceneDescription::load_from_file("res/scenes/default.json")?.build(&mut assets)?
+    } else {
+        create_physics_test_scene(&mut assets)?
+    };
+
+    let mut post_processor = PostProcessor::new(&mut assets, PostProcessSettings::default(), engine.width() as u32, engine.height() as u32)?;
+    post_processor.set_color_grading_lut(Some(assets.load_texture("res/textures/lut_neutral.png")?));
+    engine.set_post_processor(Some(post_processor));
+
+    engine.run(&mut scene, &mut assets, &shader_program, &shader_program_font);
+
+    Ok(())
+}
+
+fn create_physics_test_scene(assets: &mut AssetManager) -> EngineResult<Scene<'static>> {
+    let mut scene = SceneDescription::load_from_file("res/scenes/physics_test.json")?.build(assets)?;
+    let container_model = assets.load_model("res/models/container.obj")?;
+
+    // A spinning pillar that lives in the ECS world and collides with the player
+    let pillar_node = Node3D { world_position: Vec3::new(-3.0, 1.0, -3.0), scale: Vec3::new(0.5, 2.0, 0.5), rotation: Rotation::default() };
+    let world = scene.world_mut();
+    let pillar = world.spawn();
+    world.insert(pillar, Transform::new(pillar_node));
+    world.insert(pillar, RenderModel::new(container_model.clone()));
+    world.insert(pillar, StaticCollider::new(CollisionShape::bounding_box_from_model(&container_model.get()), &pillar_node));
+    world.insert(pillar, Script::new(|entity: Entity, world: &mut World, dt: f32| {
+        let mut transform = world.get_mut::<Transform>(entity).unwrap();
+        transform.node3d.rotation = Rotation::from_rotation_y(dt) * transform.node3d.rotation;
+        if let Some(mut static_collider) = world.get_mut::<StaticCollider>(entity) {
+            static_collider.rebuild(&transform.node3d);
+        }
+    }));
+
+    Ok(scene)
+}
\ No newline at end of file
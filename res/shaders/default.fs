@@ -1,13 +1,302 @@
 #version 330 core
 out vec4 FragColor;
 
-in vec3 ourColor;
 in vec2 TexCoord;
+in vec3 FragPos;
+in vec3 Normal;
+in vec4 Tangent;
+in vec4 VertexColor;
+in float ViewDepth;
 
-uniform sampler2D texture1;
-uniform sampler2D texture2;
+#define MAX_LIGHTS 16
+#define DIRECTIONAL 0
+#define POINT 1
+#define SPOT 2
+#define MAX_CASCADES 4
+#define PI 3.14159265359
+
+struct Light {
+	vec4 position; // w is the light type
+	vec4 direction; // w is the range
+	vec4 color;
+	vec4 cone; // Cosines of the inner and outer angles, then the shadow slot (-1 for none)
+};
+
+layout (std140) uniform Lights {
+	vec4 ambient;
+	vec4 cameraPosition;
+	ivec4 lightCount;
+	Light lights[MAX_LIGHTS];
+};
+
+// Colour textures are sRGB encoded, lighting happens in linear space
+uniform sampler2D baseColorTexture;
+uniform sampler2D metallicRoughnessTexture; // Roughness in G, metallic in B
+uniform sampler2D normalTexture; // Tangent space
+uniform sampler2D occlusionTexture; // R channel
+uniform sampler2D emissiveTexture;
+uniform sampler2D specularTexture; // Tints specularColor
+uniform bool hasBaseColorTexture;
+uniform bool hasMetallicRoughnessTexture;
+uniform bool hasNormalTexture;
+uniform bool hasOcclusionTexture;
+uniform bool hasEmissiveTexture;
+uniform bool hasSpecularTexture;
+uniform vec4 baseColor;
+uniform float metallic;
+uniform float roughness;
+uniform float normalScale;
+uniform float occlusionStrength;
+uniform vec3 emissiveColor;
+uniform vec3 specularColor; // Scales the dielectric reflectance, from MTL Ks
+uniform vec3 ambientColor; // Scales the ambient and environment light, from MTL Ka
+uniform float alphaCutoff;
+
+uniform bool hasEnvironment;
+uniform samplerCube irradianceMap;
+uniform samplerCube prefilteredMap;
+uniform sampler2D brdfLut;
+uniform float prefilteredMaxLevel;
+uniform float environmentIntensity;
+
+uniform bool gammaCorrect; // Drawing straight to the window, otherwise post-processing encodes the colour
+
+uniform bool shadowsEnabled;
+uniform sampler2DArrayShadow shadowCascades;
+uniform mat4 cascadeMatrices[MAX_CASCADES];
+uniform float cascadeSplits[MAX_CASCADES]; // View depth where each cascade ends
+uniform int cascadeCount;
+// Sampler arrays can only be indexed with constants in GLSL 3.30
+uniform samplerCubeShadow pointShadowMap0;
+uniform samplerCubeShadow pointShadowMap1;
+uniform samplerCubeShadow pointShadowMap2;
+uniform samplerCubeShadow pointShadowMap3;
+uniform float depthBias;
+uniform float normalBias;
+uniform int pcfRadius;
+
+// Smooth inverse square falloff that reaches zero at the light's range
+float attenuation(float distance, float range)
+{
+	float fade = clamp(1.0 - pow(distance / range, 4.0), 0.0, 1.0);
+	return fade * fade / (distance * distance + 1.0);
+}
+
+float cascadeShadow(vec3 normal, vec3 lightDir)
+{
+	int cascade = 0;
+	while (cascade < cascadeCount - 1 && ViewDepth > cascadeSplits[cascade])
+		cascade++;
+	if (ViewDepth > cascadeSplits[cascadeCount - 1])
+		return 1.0;
+
+	vec4 lightSpace = cascadeMatrices[cascade] * vec4(FragPos + normal * normalBias, 1.0);
+	vec3 coords = lightSpace.xyz / lightSpace.w * 0.5 + 0.5;
+	if (coords.z > 1.0)
+		return 1.0;
+
+	float bias = max(depthBias * (1.0 - dot(normal, lightDir)), depthBias * 0.1);
+	vec2 texelSize = 1.0 / vec2(textureSize(shadowCascades, 0).xy);
+	float lit = 0.0;
+	for (int x = -pcfRadius; x <= pcfRadius; x++)
+		for (int y = -pcfRadius; y <= pcfRadius; y++)
+			lit += texture(shadowCascades, vec4(coords.xy + vec2(x, y) * texelSize, cascade, coords.z - bias));
+
+	int width = 2 * pcfRadius + 1;
+	return lit / float(width * width);
+}
+
+float samplePointShadow(samplerCubeShadow map, vec3 fromLight, float depth)
+{
+	float lit = 0.0;
+	// Offsets spread with distance so the penumbra stays the same size in texels
+	float spread = 0.002 * length(fromLight);
+	for (int x = -pcfRadius; x <= pcfRadius; x++)
+		for (int y = -pcfRadius; y <= pcfRadius; y++)
+			for (int z = -pcfRadius; z <= pcfRadius; z++)
+				lit += texture(map, vec4(fromLight + vec3(x, y, z) * spread, depth));
+
+	int width = 2 * pcfRadius + 1;
+	return lit / float(width * width * width);
+}
+
+float pointShadow(int slot, vec3 normal, Light light)
+{
+	vec3 fromLight = FragPos + normal * normalBias - light.position.xyz;
+	float depth = length(fromLight) / light.direction.w - depthBias;
+
+	switch (slot)
+	{
+		case 0: return samplePointShadow(pointShadowMap0, fromLight, depth);
+		case 1: return samplePointShadow(pointShadowMap1, fromLight, depth);
+		case 2: return samplePointShadow(pointShadowMap2, fromLight, depth);
+		case 3: return samplePointShadow(pointShadowMap3, fromLight, depth);
+	}
+	return 1.0;
+}
+
+vec3 toLinear(vec3 color)
+{
+	return pow(color, vec3(2.2));
+}
+
+vec3 toDisplay(vec3 color)
+{
+	return gammaCorrect ? pow(color, vec3(1.0 / 2.2)) : color;
+}
+
+// GGX normal distribution
+float distributionGgx(float nDotH, float roughness)
+{
+	float a2 = roughness * roughness * roughness * roughness;
+	float denominator = nDotH * nDotH * (a2 - 1.0) + 1.0;
+	return a2 / (PI * denominator * denominator);
+}
+
+// Smith geometry term with the Schlick-GGX approximation for direct light
+float geometrySmith(float nDotV, float nDotL, float roughness)
+{
+	float k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+	return nDotV / (nDotV * (1.0 - k) + k) * nDotL / (nDotL * (1.0 - k) + k);
+}
+
+vec3 fresnelSchlick(float cosTheta, vec3 f0)
+{
+	return f0 + (1.0 - f0) * pow(clamp(1.0 - cosTheta, 0.0, 1.0), 5.0);
+}
+
+vec3 fresnelSchlickRoughness(float cosTheta, vec3 f0, float roughness)
+{
+	return f0 + (max(vec3(1.0 - roughness), f0) - f0) * pow(clamp(1.0 - cosTheta, 0.0, 1.0), 5.0);
+}
+
+vec3 surfaceNormal()
+{
+	// Meshes without normals still get ambient and emissive light
+	if (length(Normal) == 0.0)
+		return vec3(0.0);
+
+	vec3 normal = normalize(Normal);
+	if (!hasNormalTexture || length(Tangent.xyz) == 0.0)
+		return normal;
+
+	vec3 tangent = normalize(Tangent.xyz - normal * dot(normal, Tangent.xyz));
+	vec3 bitangent = cross(normal, tangent) * Tangent.w;
+	vec3 mapped = texture(normalTexture, TexCoord).xyz * 2.0 - 1.0;
+	mapped.xy *= normalScale;
+	return normalize(mat3(tangent, bitangent, normal) * mapped);
+}
 
 void main()
 {
-	FragColor = mix(texture(texture1, TexCoord), texture(texture2, TexCoord), 0.2);
+	vec4 color = baseColor * VertexColor;
+	if (hasBaseColorTexture)
+	{
+		vec4 texel = texture(baseColorTexture, TexCoord);
+		color *= vec4(toLinear(texel.rgb), texel.a);
+	}
+
+	if (color.a < alphaCutoff)
+		discard;
+
+	float metalness = metallic;
+	float perceptualRoughness = roughness;
+	if (hasMetallicRoughnessTexture)
+	{
+		vec4 texel = texture(metallicRoughnessTexture, TexCoord);
+		perceptualRoughness *= texel.g;
+		metalness *= texel.b;
+	}
+	// Perfectly smooth surfaces would give single pixel highlights
+	perceptualRoughness = clamp(perceptualRoughness, 0.04, 1.0);
+	metalness = clamp(metalness, 0.0, 1.0);
+
+	float occlusion = 1.0;
+	if (hasOcclusionTexture)
+		occlusion = 1.0 + occlusionStrength * (texture(occlusionTexture, TexCoord).r - 1.0);
+
+	vec3 emissive = emissiveColor;
+	if (hasEmissiveTexture)
+		emissive *= toLinear(texture(emissiveTexture, TexCoord).rgb);
+
+	vec3 specularTint = specularColor;
+	if (hasSpecularTexture)
+		specularTint *= toLinear(texture(specularTexture, TexCoord).rgb);
+
+	vec3 normal = surfaceNormal();
+	vec3 viewDir = normalize(cameraPosition.xyz - FragPos);
+	float nDotV = max(dot(normal, viewDir), 0.0001);
+
+	// Dielectrics reflect about 4% head on, metals tint reflections with their colour and have no diffuse
+	vec3 f0 = mix(min(vec3(0.04) * specularTint, vec3(1.0)), color.rgb, metalness);
+	vec3 diffuseColor = color.rgb * (1.0 - metalness);
+	vec3 lighting = vec3(0.0);
+
+	for (int i = 0; i < lightCount.x; i++)
+	{
+		Light light = lights[i];
+		int type = int(light.position.w);
+
+		vec3 lightDir;
+		float intensity = 1.0;
+		if (type == DIRECTIONAL)
+		{
+			lightDir = -light.direction.xyz;
+		}
+		else
+		{
+			vec3 toLight = light.position.xyz - FragPos;
+			float distance = length(toLight);
+			lightDir = toLight / distance;
+			intensity = attenuation(distance, light.direction.w);
+
+			if (type == SPOT)
+			{
+				float theta = dot(lightDir, -light.direction.xyz);
+				intensity *= clamp((theta - light.cone.y) / max(light.cone.x - light.cone.y, 0.0001), 0.0, 1.0);
+			}
+		}
+
+		float nDotL = dot(normal, lightDir);
+		if (nDotL <= 0.0)
+			continue;
+
+		int shadowSlot = int(light.cone.z);
+		if (shadowsEnabled && shadowSlot >= 0)
+		{
+			if (type == DIRECTIONAL)
+				intensity *= cascadeShadow(normal, lightDir);
+			else if (type == POINT)
+				intensity *= pointShadow(shadowSlot, normal, light);
+		}
+
+		// Cook-Torrance specular with a Lambertian diffuse
+		vec3 halfway = normalize(lightDir + viewDir);
+		vec3 fresnel = fresnelSchlick(max(dot(halfway, viewDir), 0.0), f0);
+		float distribution = distributionGgx(max(dot(normal, halfway), 0.0), perceptualRoughness);
+		float geometry = geometrySmith(nDotV, nDotL, perceptualRoughness);
+		vec3 specular = fresnel * distribution * geometry / (4.0 * nDotV * nDotL + 0.0001);
+		vec3 diffuse = (1.0 - fresnel) * diffuseColor / PI;
+
+		lighting += light.color.rgb * intensity * (diffuse + specular) * nDotL;
+	}
+
+	vec3 ambientLight;
+	if (hasEnvironment)
+	{
+		// Split-sum approximation: irradiance for diffuse, prefiltered reflections scaled by the BRDF LUT for specular
+		vec3 fresnel = fresnelSchlickRoughness(nDotV, f0, perceptualRoughness);
+		vec3 irradiance = texture(irradianceMap, normal).rgb;
+		vec3 reflected = reflect(-viewDir, normal);
+		vec3 prefiltered = textureLod(prefilteredMap, reflected, perceptualRoughness * prefilteredMaxLevel).rgb;
+		vec2 brdf = texture(brdfLut, vec2(nDotV, perceptualRoughness)).rg;
+		vec3 specular = prefiltered * (fresnel * brdf.x + brdf.y);
+		ambientLight = ((1.0 - fresnel) * diffuseColor * irradiance + specular) * environmentIntensity;
+	}
+	else
+	{
+		ambientLight = ambient.rgb * color.rgb;
+	}
+
+	FragColor = vec4(toDisplay(lighting + ambientLight * ambientColor * occlusion + emissive), color.a);
 }
@@ -1,8 +1,16 @@
 #version 330 core
 layout (location = 0) in vec3 aPos;
 layout (location = 1) in vec2 aTexCoord;
+layout (location = 2) in vec3 aNormal;
+layout (location = 3) in vec4 aTangent; // w is the bitangent sign
+layout (location = 5) in vec4 aColor; // White for meshes without vertex colours
 
 out vec2 TexCoord;
+out vec3 FragPos;
+out vec3 Normal;
+out vec4 Tangent;
+out vec4 VertexColor;
+out float ViewDepth;
 
 uniform mat4 model;
 uniform mat4 view;
@@ -10,6 +18,13 @@ uniform mat4 projection;
 
 void main()
 {
-	gl_Position = projection * view * model * vec4(aPos, 1.0);
+	vec4 worldPos = model * vec4(aPos, 1.0);
+	vec4 viewPos = view * worldPos;
+	gl_Position = projection * viewPos;
+	ViewDepth = -viewPos.z;
 	TexCoord = vec2(aTexCoord.x, aTexCoord.y);
+	FragPos = worldPos.xyz;
+	Normal = mat3(transpose(inverse(model))) * aNormal;
+	Tangent = vec4(mat3(model) * aTangent.xyz, aTangent.w);
+	VertexColor = aColor;
 }
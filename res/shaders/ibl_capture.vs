@@ -0,0 +1,13 @@
+#version 330 core
+layout (location = 0) in vec3 aPos;
+
+out vec3 LocalPos;
+
+// One cube face seen from the centre of the environment
+uniform mat4 viewProjection;
+
+void main()
+{
+	LocalPos = aPos;
+	gl_Position = viewProjection * vec4(aPos, 1.0);
+}
@@ -0,0 +1,76 @@
+#version 330 core
+out vec4 FragColor;
+
+in vec3 LocalPos;
+
+uniform samplerCube environmentMap;
+uniform float roughness;
+
+#define PI 3.14159265359
+#define SAMPLE_COUNT 1024u
+
+float radicalInverse(uint bits)
+{
+	bits = (bits << 16u) | (bits >> 16u);
+	bits = ((bits & 0x55555555u) << 1u) | ((bits & 0xAAAAAAAAu) >> 1u);
+	bits = ((bits & 0x33333333u) << 2u) | ((bits & 0xCCCCCCCCu) >> 2u);
+	bits = ((bits & 0x0F0F0F0Fu) << 4u) | ((bits & 0xF0F0F0F0u) >> 4u);
+	bits = ((bits & 0x00FF00FFu) << 8u) | ((bits & 0xFF00FF00u) >> 8u);
+	return float(bits) * 2.3283064365386963e-10;
+}
+
+vec2 hammersley(uint i, uint count)
+{
+	return vec2(float(i) / float(count), radicalInverse(i));
+}
+
+vec3 importanceSampleGgx(vec2 xi, vec3 normal, float roughness)
+{
+	float a = roughness * roughness;
+	float phi = 2.0 * PI * xi.x;
+	float cosTheta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+	float sinTheta = sqrt(1.0 - cosTheta * cosTheta);
+	vec3 halfway = vec3(cos(phi) * sinTheta, sin(phi) * sinTheta, cosTheta);
+
+	vec3 up = abs(normal.z) < 0.999 ? vec3(0.0, 0.0, 1.0) : vec3(1.0, 0.0, 0.0);
+	vec3 tangent = normalize(cross(up, normal));
+	vec3 bitangent = cross(normal, tangent);
+	return normalize(tangent * halfway.x + bitangent * halfway.y + normal * halfway.z);
+}
+
+float distributionGgx(float nDotH, float roughness)
+{
+	float a2 = roughness * roughness * roughness * roughness;
+	float denominator = nDotH * nDotH * (a2 - 1.0) + 1.0;
+	return a2 / (PI * denominator * denominator);
+}
+
+// Assumes the view direction equals the normal, which loses stretched reflections at grazing angles
+void main()
+{
+	vec3 normal = normalize(LocalPos);
+	float resolution = float(textureSize(environmentMap, 0).x);
+	float texelSolidAngle = 4.0 * PI / (6.0 * resolution * resolution);
+
+	vec3 color = vec3(0.0);
+	float totalWeight = 0.0;
+	for (uint i = 0u; i < SAMPLE_COUNT; i++)
+	{
+		vec3 halfway = importanceSampleGgx(hammersley(i, SAMPLE_COUNT), normal, roughness);
+		vec3 lightDir = normalize(2.0 * dot(normal, halfway) * halfway - normal);
+		float nDotL = dot(normal, lightDir);
+		if (nDotL <= 0.0)
+			continue;
+
+		// Samples covering a large solid angle read from a blurrier mip level to avoid bright dots
+		float nDotH = max(dot(normal, halfway), 0.0);
+		float pdf = distributionGgx(nDotH, roughness) / 4.0 + 0.0001;
+		float sampleSolidAngle = 1.0 / (float(SAMPLE_COUNT) * pdf + 0.0001);
+		float mipLevel = roughness == 0.0 ? 0.0 : 0.5 * log2(sampleSolidAngle / texelSolidAngle);
+
+		color += pow(textureLod(environmentMap, lightDir, mipLevel).rgb, vec3(2.2)) * nDotL;
+		totalWeight += nDotL;
+	}
+
+	FragColor = vec4(color / totalWeight, 1.0);
+}
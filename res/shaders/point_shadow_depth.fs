@@ -0,0 +1,19 @@
+#version 330 core
+in vec2 TexCoord;
+in vec3 FragPos;
+
+uniform sampler2D baseColorTexture;
+uniform bool hasBaseColorTexture;
+uniform vec4 baseColor;
+uniform float alphaCutoff;
+uniform vec3 lightPosition;
+uniform float farPlane;
+
+void main()
+{
+	if (hasBaseColorTexture && baseColor.a * texture(baseColorTexture, TexCoord).a < alphaCutoff)
+		discard;
+
+	// Linear distance to the light, so every face of the cube compares the same way
+	gl_FragDepth = length(FragPos - lightPosition) / farPlane;
+}
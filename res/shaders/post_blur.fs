@@ -0,0 +1,23 @@
+#version 330 core
+out vec4 FragColor;
+
+in vec2 TexCoord;
+
+#define TAPS 5
+
+uniform sampler2D image;
+uniform vec3 direction; // One texel along the blur axis
+uniform float weights[TAPS];
+
+void main()
+{
+	vec3 color = texture(image, TexCoord).rgb * weights[0];
+	for (int i = 1; i < TAPS; i++)
+	{
+		vec2 offset = direction.xy * float(i);
+		color += texture(image, TexCoord + offset).rgb * weights[i];
+		color += texture(image, TexCoord - offset).rgb * weights[i];
+	}
+
+	FragColor = vec4(color, 1.0);
+}
@@ -0,0 +1,18 @@
+#version 330 core
+out vec4 FragColor;
+
+in vec2 TexCoord;
+
+uniform sampler2D sceneTexture;
+uniform float threshold;
+uniform float exposure;
+
+void main()
+{
+	vec3 color = texture(sceneTexture, TexCoord).rgb * exposure;
+	float luminance = dot(color, vec3(0.2126, 0.7152, 0.0722));
+
+	// Soft knee so pixels don't pop in as they cross the threshold
+	float contribution = clamp((luminance - threshold) / max(luminance, 0.0001), 0.0, 1.0);
+	FragColor = vec4(color * contribution, 1.0);
+}
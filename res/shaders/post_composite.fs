@@ -0,0 +1,70 @@
+#version 330 core
+out vec4 FragColor;
+
+in vec2 TexCoord;
+
+#define TONE_MAPPING_NONE 0
+#define TONE_MAPPING_REINHARD 1
+#define TONE_MAPPING_ACES 2
+
+uniform sampler2D sceneTexture;
+uniform sampler2D bloomTexture;
+uniform sampler2D colorGradingLut;
+uniform float exposure;
+uniform int toneMapping;
+uniform bool bloomEnabled;
+uniform float bloomIntensity;
+uniform bool vignetteEnabled;
+uniform float vignetteStrength;
+uniform float vignetteRadius;
+uniform bool colorGradingEnabled;
+uniform float gamma; // 1 leaves the colour as is
+
+// Narkowicz's fit of the ACES filmic curve
+vec3 aces(vec3 color)
+{
+	return clamp((color * (2.51 * color + 0.03)) / (color * (2.43 * color + 0.59) + 0.14), 0.0, 1.0);
+}
+
+// The LUT is a strip of N slices of N×N texels, blue picks the slice
+vec3 gradeColor(vec3 color)
+{
+	float size = float(textureSize(colorGradingLut, 0).y);
+	float slice = color.b * (size - 1.0);
+	float lower = floor(slice);
+	float upper = min(lower + 1.0, size - 1.0);
+
+	// Stay half a texel inside each slice so neighbouring slices don't bleed in
+	vec2 inSlice = (color.rg * (size - 1.0) + 0.5) / vec2(size * size, size);
+	vec3 lowerColor = texture(colorGradingLut, inSlice + vec2(lower / size, 0.0)).rgb;
+	vec3 upperColor = texture(colorGradingLut, inSlice + vec2(upper / size, 0.0)).rgb;
+
+	return mix(lowerColor, upperColor, slice - lower);
+}
+
+void main()
+{
+	vec3 color = texture(sceneTexture, TexCoord).rgb * exposure;
+	if (bloomEnabled)
+		color += texture(bloomTexture, TexCoord).rgb * bloomIntensity;
+
+	if (toneMapping == TONE_MAPPING_REINHARD)
+		color = color / (color + vec3(1.0));
+	else if (toneMapping == TONE_MAPPING_ACES)
+		color = aces(color);
+	color = clamp(color, 0.0, 1.0);
+
+	if (vignetteEnabled)
+	{
+		float distance = length(TexCoord - 0.5) * 2.0;
+		color *= 1.0 - vignetteStrength * smoothstep(vignetteRadius, 1.5, distance);
+	}
+
+	color = pow(color, vec3(1.0 / gamma));
+
+	// Grading LUTs are authored on display colours
+	if (colorGradingEnabled)
+		color = gradeColor(color);
+
+	FragColor = vec4(color, 1.0);
+}
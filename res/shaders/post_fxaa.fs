@@ -0,0 +1,136 @@
+#version 330 core
+out vec4 FragColor;
+
+in vec2 TexCoord;
+
+#define EDGE_THRESHOLD_MIN 0.0312
+#define EDGE_THRESHOLD_MAX 0.125
+#define SUBPIXEL_QUALITY 0.75
+#define SEARCH_STEPS 10
+
+uniform sampler2D screenTexture;
+uniform vec3 inverseScreenSize; // 1 / width, 1 / height
+
+float luma(vec3 color)
+{
+	return dot(color, vec3(0.299, 0.587, 0.114));
+}
+
+float lumaAt(vec2 uv)
+{
+	return luma(texture(screenTexture, uv).rgb);
+}
+
+// FXAA 3.11 quality preset, simplified: find the edge direction, walk along it to its ends and blend across it
+void main()
+{
+	vec2 texel = inverseScreenSize.xy;
+	vec3 center = texture(screenTexture, TexCoord).rgb;
+	float lumaCenter = luma(center);
+	float lumaDown = lumaAt(TexCoord + vec2(0.0, -texel.y));
+	float lumaUp = lumaAt(TexCoord + vec2(0.0, texel.y));
+	float lumaLeft = lumaAt(TexCoord + vec2(-texel.x, 0.0));
+	float lumaRight = lumaAt(TexCoord + vec2(texel.x, 0.0));
+
+	float lumaMin = min(lumaCenter, min(min(lumaDown, lumaUp), min(lumaLeft, lumaRight)));
+	float lumaMax = max(lumaCenter, max(max(lumaDown, lumaUp), max(lumaLeft, lumaRight)));
+	float lumaRange = lumaMax - lumaMin;
+	if (lumaRange < max(EDGE_THRESHOLD_MIN, lumaMax * EDGE_THRESHOLD_MAX))
+	{
+		FragColor = vec4(center, 1.0);
+		return;
+	}
+
+	float lumaDownLeft = lumaAt(TexCoord - texel);
+	float lumaUpRight = lumaAt(TexCoord + texel);
+	float lumaUpLeft = lumaAt(TexCoord + vec2(-texel.x, texel.y));
+	float lumaDownRight = lumaAt(TexCoord + vec2(texel.x, -texel.y));
+
+	float lumaDownUp = lumaDown + lumaUp;
+	float lumaLeftRight = lumaLeft + lumaRight;
+	float lumaLeftCorners = lumaDownLeft + lumaUpLeft;
+	float lumaDownCorners = lumaDownLeft + lumaDownRight;
+	float lumaRightCorners = lumaDownRight + lumaUpRight;
+	float lumaUpCorners = lumaUpRight + lumaUpLeft;
+
+	float edgeHorizontal = abs(-2.0 * lumaLeft + lumaLeftCorners) + abs(-2.0 * lumaCenter + lumaDownUp) * 2.0 + abs(-2.0 * lumaRight + lumaRightCorners);
+	float edgeVertical = abs(-2.0 * lumaUp + lumaUpCorners) + abs(-2.0 * lumaCenter + lumaLeftRight) * 2.0 + abs(-2.0 * lumaDown + lumaDownCorners);
+	bool isHorizontal = edgeHorizontal >= edgeVertical;
+
+	// Pick the side of the edge with the steeper gradient
+	float luma1 = isHorizontal ? lumaDown : lumaLeft;
+	float luma2 = isHorizontal ? lumaUp : lumaRight;
+	float gradient1 = luma1 - lumaCenter;
+	float gradient2 = luma2 - lumaCenter;
+	bool is1Steepest = abs(gradient1) >= abs(gradient2);
+	float gradientScaled = 0.25 * max(abs(gradient1), abs(gradient2));
+
+	float stepLength = isHorizontal ? texel.y : texel.x;
+	float lumaLocalAverage;
+	if (is1Steepest)
+	{
+		stepLength = -stepLength;
+		lumaLocalAverage = 0.5 * (luma1 + lumaCenter);
+	}
+	else
+	{
+		lumaLocalAverage = 0.5 * (luma2 + lumaCenter);
+	}
+
+	vec2 currentUv = TexCoord;
+	if (isHorizontal)
+		currentUv.y += stepLength * 0.5;
+	else
+		currentUv.x += stepLength * 0.5;
+
+	// Walk both ways along the edge until the luma changes
+	vec2 offset = isHorizontal ? vec2(texel.x, 0.0) : vec2(0.0, texel.y);
+	vec2 uv1 = currentUv - offset;
+	vec2 uv2 = currentUv + offset;
+	float lumaEnd1 = lumaAt(uv1) - lumaLocalAverage;
+	float lumaEnd2 = lumaAt(uv2) - lumaLocalAverage;
+	bool reached1 = abs(lumaEnd1) >= gradientScaled;
+	bool reached2 = abs(lumaEnd2) >= gradientScaled;
+
+	for (int i = 0; i < SEARCH_STEPS && !(reached1 && reached2); i++)
+	{
+		if (!reached1)
+		{
+			uv1 -= offset;
+			lumaEnd1 = lumaAt(uv1) - lumaLocalAverage;
+			reached1 = abs(lumaEnd1) >= gradientScaled;
+		}
+		if (!reached2)
+		{
+			uv2 += offset;
+			lumaEnd2 = lumaAt(uv2) - lumaLocalAverage;
+			reached2 = abs(lumaEnd2) >= gradientScaled;
+		}
+	}
+
+	float distance1 = isHorizontal ? (TexCoord.x - uv1.x) : (TexCoord.y - uv1.y);
+	float distance2 = isHorizontal ? (uv2.x - TexCoord.x) : (uv2.y - TexCoord.y);
+	bool isDirection1 = distance1 < distance2;
+	float distanceFinal = min(distance1, distance2);
+	float edgeLength = distance1 + distance2;
+	float pixelOffset = -distanceFinal / edgeLength + 0.5;
+
+	// Only blend when the centre is on the dark or bright side the edge end says it should be
+	bool isLumaCenterSmaller = lumaCenter < lumaLocalAverage;
+	bool correctVariation = ((isDirection1 ? lumaEnd1 : lumaEnd2) < 0.0) != isLumaCenterSmaller;
+	float finalOffset = correctVariation ? pixelOffset : 0.0;
+
+	// Sub-pixel aliasing, e.g. thin lines
+	float lumaAverage = (1.0 / 12.0) * (2.0 * (lumaDownUp + lumaLeftRight) + lumaLeftCorners + lumaRightCorners);
+	float subPixelOffset = clamp(abs(lumaAverage - lumaCenter) / lumaRange, 0.0, 1.0);
+	subPixelOffset = (-2.0 * subPixelOffset + 3.0) * subPixelOffset * subPixelOffset;
+	finalOffset = max(finalOffset, subPixelOffset * subPixelOffset * SUBPIXEL_QUALITY);
+
+	vec2 finalUv = TexCoord;
+	if (isHorizontal)
+		finalUv.y += finalOffset * stepLength;
+	else
+		finalUv.x += finalOffset * stepLength;
+
+	FragColor = vec4(texture(screenTexture, finalUv).rgb, 1.0);
+}
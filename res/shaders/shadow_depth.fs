@@ -0,0 +1,15 @@
+#version 330 core
+in vec2 TexCoord;
+in vec3 FragPos;
+
+uniform sampler2D baseColorTexture;
+uniform bool hasBaseColorTexture;
+uniform vec4 baseColor;
+uniform float alphaCutoff;
+
+void main()
+{
+	// Alpha tested surfaces cast shadows with their holes
+	if (hasBaseColorTexture && baseColor.a * texture(baseColorTexture, TexCoord).a < alphaCutoff)
+		discard;
+}
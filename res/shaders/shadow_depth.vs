@@ -0,0 +1,17 @@
+#version 330 core
+layout (location = 0) in vec3 aPos;
+layout (location = 1) in vec2 aTexCoord;
+
+out vec2 TexCoord;
+out vec3 FragPos;
+
+uniform mat4 model;
+uniform mat4 lightSpace;
+
+void main()
+{
+	vec4 worldPos = model * vec4(aPos, 1.0);
+	gl_Position = lightSpace * worldPos;
+	TexCoord = aTexCoord;
+	FragPos = worldPos.xyz;
+}
@@ -4,8 +4,11 @@ out vec4 FragColor;
 in vec3 TexCoords;
 
 uniform samplerCube skybox;
+uniform bool gammaCorrect; // Drawing straight to the window, otherwise post-processing encodes the colour
 
 void main()
-{    
-    FragColor = texture(skybox, TexCoords);
-}
\ No newline at end of file
+{
+    // sRGB images, the scene is lit and tone mapped in linear space. Encoding straight back gives the image as is
+    vec4 color = texture(skybox, TexCoords);
+    FragColor = gammaCorrect ? color : vec4(pow(color.rgb, vec3(2.2)), color.a);
+}
@@ -0,0 +1,158 @@
+use std::cell::{Ref, RefCell, RefMut};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::error::EngineResult;
+
+struct AssetSlot<T> {
+    key: Option<String>, // None for assets that were not loaded through a cache
+    asset: RefCell<T>,
+}
+
+// Shared reference to an asset, the asset stays loaded while any handle to it exists
+pub struct Handle<T> {
+    slot: Rc<AssetSlot<T>>,
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Self { slot: self.slot.clone() }
+    }
+}
+
+impl<T> Handle<T> {
+    // Wraps an asset that was created in code rather than loaded from a file
+    pub fn new(asset: T) -> Self {
+        Self { slot: Rc::new(AssetSlot { key: None, asset: RefCell::new(asset) }) }
+    }
+
+    fn with_key(key: &str, asset: T) -> Self {
+        Self { slot: Rc::new(AssetSlot { key: Some(key.to_owned()), asset: RefCell::new(asset) }) }
+    }
+
+    pub fn key(&self) -> Option<&str> {
+        self.slot.key.as_deref()
+    }
+
+    pub fn get(&self) -> Ref<'_, T> {
+        self.slot.asset.borrow()
+    }
+
+    pub fn get_mut(&self) -> RefMut<'_, T> {
+        self.slot.asset.borrow_mut()
+    }
+
+    // Swaps the asset for every holder of this handle, returns the previous version
+    pub fn replace(&self, asset: T) -> T {
+        self.slot.asset.replace(asset)
+    }
+
+    pub fn ptr_eq(&self, other: &Handle<T>) -> bool {
+        Rc::ptr_eq(&self.slot, &other.slot)
+    }
+
+    // Same for every clone of this handle while the asset is alive, e.g. to sort or group by asset
+    pub fn id(&self) -> usize {
+        Rc::as_ptr(&self.slot) as usize
+    }
+
+    // Handles held outside of the cache the asset was loaded through
+    pub fn ref_count(&self) -> usize {
+        Rc::strong_count(&self.slot) - usize::from(self.slot.key.is_some())
+    }
+}
+
+// Assets of one type keyed by path, every key is loaded at most once
+pub struct AssetCache<T> {
+    assets: HashMap<String, Handle<T>>,
+}
+
+impl<T> Default for AssetCache<T> {
+    fn default() -> Self {
+        Self { assets: HashMap::new() }
+    }
+}
+
+impl<T> AssetCache<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.assets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.assets.is_empty()
+    }
+
+    pub fn get(&self, key: &str) -> Option<Handle<T>> {
+        self.assets.get(key).cloned()
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item=&str> {
+        self.assets.keys().map(String::as_str)
+    }
+
+    pub fn get_or_load(&mut self, key: &str, load: impl FnOnce(&str) -> EngineResult<T>) -> EngineResult<Handle<T>> {
+        if let Some(handle) = self.assets.get(key) {
+            return Ok(handle.clone());
+        }
+
+        let handle = Handle::with_key(key, load(key)?);
+        self.assets.insert(key.to_owned(), handle.clone());
+
+        Ok(handle)
+    }
+
+    // Drops every asset that nothing outside the cache refers to, returns how many were dropped
+    pub fn unload_unused(&mut self) -> usize {
+        let count = self.assets.len();
+        self.assets.retain(|_, handle| handle.ref_count() > 0);
+
+        count - self.assets.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn assets_are_loaded_once_and_unloaded_when_unused() {
+        let mut cache = AssetCache::new();
+        let mut loads = 0;
+        let mut load = |key: &str| {
+            loads += 1;
+            Ok(key.len())
+        };
+
+        let first = cache.get_or_load("res/models/container.obj", &mut load).unwrap();
+        let second = cache.get_or_load("res/models/container.obj", &mut load).unwrap();
+        let other = cache.get_or_load("res/models/cottage.obj", &mut load).unwrap();
+        assert!(first.ptr_eq(&second) && !first.ptr_eq(&other));
+        assert_eq!((first.ref_count(), other.ref_count()), (2, 1));
+
+        drop(other);
+        assert_eq!(cache.unload_unused(), 1);
+        drop(first);
+        assert_eq!(cache.unload_unused(), 0);
+        drop(second);
+        assert_eq!(cache.unload_unused(), 1);
+        assert!(cache.is_empty());
+
+        cache.get_or_load("res/models/container.obj", &mut load).unwrap();
+        assert_eq!(loads, 3);
+    }
+
+    #[test]
+    fn replacing_an_asset_updates_every_handle() {
+        let handle = Handle::new(1);
+        let copy = handle.clone();
+
+        assert_eq!(handle.replace(2), 1);
+        assert_eq!(*copy.get(), 2);
+        assert_eq!(copy.ref_count(), 2);
+        assert!(copy.key().is_none());
+    }
+}
@@ -0,0 +1,86 @@
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::graphics::gltf_model;
+
+// Source files of one asset with the modification times they had when it was last (re)loaded
+pub struct WatchedFiles {
+    files: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+impl WatchedFiles {
+    pub fn new(paths: impl IntoIterator<Item=PathBuf>) -> Self {
+        Self { files: paths.into_iter().map(|path| { let modified = modified_time(&path); (path, modified) }).collect() }
+    }
+
+    pub fn paths(&self) -> impl Iterator<Item=&Path> {
+        self.files.iter().map(|(path, _)| path.as_path())
+    }
+
+    // True if any file was modified, created or deleted since the last poll
+    pub fn poll(&mut self) -> bool {
+        let mut changed = false;
+        for (path, last_modified) in self.files.iter_mut() {
+            let modified = modified_time(path);
+            if modified != *last_modified {
+                *last_modified = modified;
+                changed = true;
+            }
+        }
+
+        changed
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+// The model file and the material libraries or buffers it references, textures are watched as assets of their own
+pub fn model_source_files(path: &str) -> Vec<PathBuf> {
+    if gltf_model::is_gltf_path(path) {
+        return std::iter::once(PathBuf::from(path)).chain(gltf_model::external_buffer_files(path)).collect();
+    }
+
+    let obj_path = PathBuf::from(path);
+    let directory = obj_path.parent().map(Path::to_path_buf).unwrap_or_default();
+    let material_libraries: Vec<PathBuf> = std::fs::read_to_string(&obj_path).unwrap_or_default().lines()
+        .filter_map(|line| line.trim().strip_prefix("mtllib "))
+        .map(|library| directory.join(library.trim()))
+        .collect();
+
+    std::iter::once(obj_path).chain(material_libraries).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn changes_are_reported_once() {
+        let directory = std::env::temp_dir().join(format!("hot_reload_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let obj_path = directory.join("crate.obj");
+        fs::write(&obj_path, "mtllib crate.mtl\nv 0 0 0\n").unwrap();
+        fs::write(directory.join("crate.mtl"), "newmtl crate\n").unwrap();
+
+        let sources = model_source_files(obj_path.to_str().unwrap());
+        assert_eq!(sources, vec![obj_path.clone(), directory.join("crate.mtl")]);
+
+        let mut watched = WatchedFiles::new(sources);
+        assert!(!watched.poll());
+
+        let file = fs::File::options().write(true).open(directory.join("crate.mtl")).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(5)).unwrap();
+        assert!(watched.poll());
+        assert!(!watched.poll());
+
+        fs::remove_file(&obj_path).unwrap();
+        assert!(watched.poll());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
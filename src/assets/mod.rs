@@ -0,0 +1,216 @@
+pub mod handle;
+pub mod hot_reload;
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::assets::handle::{AssetCache, Handle};
+use crate::assets::hot_reload::{model_source_files, WatchedFiles};
+use crate::error::{EngineError, EngineResult};
+use crate::graphics::model::Model;
+use crate::graphics::true_type_font::TrueTypeFont;
+use crate::opengl::texture::Texture;
+use crate::shader::Shader;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+enum AssetKind {
+    Model,
+    Texture,
+    Shader,
+}
+
+// Loads every asset at most once per path, textures are shared between all the models that use them
+#[derive(Default)]
+pub struct AssetManager {
+    models: AssetCache<Model>,
+    textures: AssetCache<Texture>,
+    shaders: AssetCache<Shader>,
+    fonts: AssetCache<TrueTypeFont<'static>>,
+    watched: HashMap<(AssetKind, String), WatchedFiles>,
+    reload_errors: HashMap<String, EngineError>, // Latest failed reload per asset key, cleared once it reloads fine
+}
+
+impl AssetManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load_model(&mut self, path: &str) -> EngineResult<Handle<Model>> {
+        let textures = &mut self.textures;
+        let model = self.models.get_or_load(path, |path| Model::load_from_file_with_textures(path, |texture_path| textures.get_or_load(texture_path, Texture::from_image_path)))?;
+
+        self.watch_new_assets();
+        Ok(model)
+    }
+
+    pub fn load_texture(&mut self, path: &str) -> EngineResult<Handle<Texture>> {
+        let texture = self.textures.get_or_load(path, Texture::from_image_path)?;
+
+        self.watch_new_assets();
+        Ok(texture)
+    }
+
+    pub fn load_shader(&mut self, vertex_path: &str, fragment_path: &str) -> EngineResult<Handle<Shader>> {
+        let shader = self.shaders.get_or_load(&shader_key(vertex_path, fragment_path), |_| Shader::from_files(vertex_path, fragment_path))?;
+
+        self.watch_new_assets();
+        Ok(shader)
+    }
+
+    pub fn load_font(&mut self, path: &str) -> EngineResult<Handle<TrueTypeFont<'static>>> {
+        self.fonts.get_or_load(path, TrueTypeFont::load_from_file)
+    }
+
+    pub fn models(&self) -> &AssetCache<Model> {
+        &self.models
+    }
+
+    pub fn textures(&self) -> &AssetCache<Texture> {
+        &self.textures
+    }
+
+    pub fn shaders(&self) -> &AssetCache<Shader> {
+        &self.shaders
+    }
+
+    pub fn fonts(&self) -> &AssetCache<TrueTypeFont<'static>> {
+        &self.fonts
+    }
+
+    // Models go first so the textures only they were holding can be dropped in the same call
+    pub fn unload_unused(&mut self) -> usize {
+        let unloaded = self.models.unload_unused() + self.textures.unload_unused() + self.shaders.unload_unused() + self.fonts.unload_unused();
+
+        let (models, textures, shaders) = (&self.models, &self.textures, &self.shaders);
+        self.watched.retain(|(kind, key), _| match kind {
+            AssetKind::Model => models.get(key).is_some(),
+            AssetKind::Texture => textures.get(key).is_some(),
+            AssetKind::Shader => shaders.get(key).is_some(),
+        });
+
+        unloaded
+    }
+
+    // Reloads the assets whose files changed on disk, every handle sees the new version. Returns the keys that reloaded,
+    // so whatever was built from the old version, like static body colliders, can be rebuilt.
+    // An asset that fails to reload keeps its previous version and its error is kept until the next successful reload
+    pub fn reload_changed(&mut self) -> Vec<String> {
+        let changed: Vec<(AssetKind, String)> = self.watched.iter_mut()
+            .filter_map(|(asset, files)| files.poll().then(|| asset.clone()))
+            .collect();
+
+        let mut reloaded = Vec::new();
+        for (kind, key) in changed {
+            match self.reload(kind, &key) {
+                Ok(()) => {
+                    self.reload_errors.remove(&key);
+                    reloaded.push(key);
+                }
+                Err(error) => {
+                    self.reload_errors.insert(key, error);
+                }
+            }
+        }
+        self.watch_new_assets();
+
+        reloaded
+    }
+
+    pub fn reload_errors(&self) -> impl Iterator<Item=(&str, &EngineError)> {
+        self.reload_errors.iter().map(|(key, error)| (key.as_str(), error))
+    }
+
+    fn reload(&mut self, kind: AssetKind, key: &str) -> EngineResult<()> {
+        match kind {
+            AssetKind::Model => {
+                let Some(handle) = self.models.get(key) else {
+                    return Ok(());
+                };
+
+                let textures = &mut self.textures;
+                handle.replace(Model::load_from_file_with_textures(key, |texture_path| textures.get_or_load(texture_path, Texture::from_image_path))?);
+            }
+            AssetKind::Texture => {
+                if let Some(handle) = self.textures.get(key) {
+                    handle.replace(Texture::from_image_path(key)?);
+                }
+            }
+            AssetKind::Shader => {
+                if let Some(handle) = self.shaders.get(key) {
+                    let (vertex_path, fragment_path) = split_shader_key(key);
+                    handle.replace(Shader::from_files(vertex_path, fragment_path)?);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn watch_new_assets(&mut self) {
+        let models = self.models.keys().map(|key| (AssetKind::Model, key));
+        let textures = self.textures.keys().map(|key| (AssetKind::Texture, key));
+        let shaders = self.shaders.keys().map(|key| (AssetKind::Shader, key));
+
+        for (kind, key) in models.chain(textures).chain(shaders) {
+            if self.watched.contains_key(&(kind, key.to_owned())) {
+                continue;
+            }
+
+            let files = match kind {
+                AssetKind::Model => model_source_files(key),
+                AssetKind::Texture => vec![PathBuf::from(key)],
+                AssetKind::Shader => {
+                    let (vertex_path, fragment_path) = split_shader_key(key);
+                    vec![PathBuf::from(vertex_path), PathBuf::from(fragment_path)]
+                }
+            };
+            self.watched.insert((kind, key.to_owned()), WatchedFiles::new(files));
+        }
+    }
+}
+
+fn shader_key(vertex_path: &str, fragment_path: &str) -> String {
+    format!("{vertex_path}|{fragment_path}")
+}
+
+// Vertex and fragment paths of a shader loaded through load_shader
+pub(crate) fn split_shader_key(key: &str) -> (&str, &str) {
+    key.split_once('|').unwrap_or((key, key))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::time::{Duration, SystemTime};
+
+    use super::*;
+
+    #[test]
+    fn failed_reload_keeps_the_previous_version_and_reports_the_error() {
+        let directory = std::env::temp_dir().join(format!("asset_reload_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let obj_path = directory.join("crate.obj");
+        fs::write(&obj_path, "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").unwrap();
+        let key = obj_path.to_str().unwrap();
+
+        // Meshes need a GL context, so the cache is filled directly with an empty model
+        let mut assets = AssetManager::new();
+        let model = assets.models.get_or_load(key, |_| Ok(Model::from_meshes(Vec::new()))).unwrap();
+        assets.watch_new_assets();
+        assert!(assets.reload_changed().is_empty());
+
+        // A face pointing past the vertices fails to parse, before any mesh is created
+        fs::write(&obj_path, "v 0 0 0\nf 1 2 3\n").unwrap();
+        fs::File::options().write(true).open(&obj_path).unwrap().set_modified(SystemTime::now() + Duration::from_secs(5)).unwrap();
+        assert!(assets.reload_changed().is_empty());
+
+        assert!(assets.models().get(key).unwrap().ptr_eq(&model));
+        assert!(model.get().meshes().is_empty());
+        let errors: Vec<(&str, &EngineError)> = assets.reload_errors().collect();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, key);
+        assert!(matches!(errors[0].1, EngineError::ObjLoad { .. }), "{}", errors[0].1);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -51,26 +51,14 @@ impl Camera {
         self.pitch += y_offset * self.mouse_sensitivity;
 
         if constrain_pitch {
-            if self.pitch > 89.0 {
-                self.pitch = 89.0;
-            }
-            if self.pitch < -89.0 {
-                self.pitch = -89.0;
-            }
+            self.pitch = self.pitch.clamp(-89.0, 89.0);
         }
 
         self.update_camera_vectors();
     }
 
     pub fn process_mouse_scroll(&mut self, y_offset: f32) {
-        self.zoom -= y_offset;
-
-        if self.zoom < 1.0 {
-            self.zoom = 1.0;
-        }
-        if self.zoom > 45.0 {
-            self.zoom = 45.0;
-        }
+        self.zoom = (self.zoom - y_offset).clamp(1.0, 45.0);
     }
 
     fn update_camera_vectors(&mut self) {
@@ -0,0 +1,100 @@
+use crate::ecs::world::Entity;
+
+// Sparse set: components are packed densely for iteration and found through the entity index
+pub struct ComponentStorage<T> {
+    dense: Vec<T>,
+    entities: Vec<Entity>,
+    sparse: Vec<Option<usize>>,
+}
+
+impl<T> Default for ComponentStorage<T> {
+    fn default() -> Self {
+        Self { dense: Vec::new(), entities: Vec::new(), sparse: Vec::new() }
+    }
+}
+
+impl<T> ComponentStorage<T> {
+    pub fn len(&self) -> usize {
+        self.dense.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dense.is_empty()
+    }
+
+    pub fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    fn dense_index(&self, entity: Entity) -> Option<usize> {
+        let index = (*self.sparse.get(entity.index())?)?;
+        (self.entities[index] == entity).then_some(index)
+    }
+
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.dense_index(entity).is_some()
+    }
+
+    // Returns the component the entity had before, if any
+    pub fn insert(&mut self, entity: Entity, component: T) -> Option<T> {
+        if let Some(index) = self.dense_index(entity) {
+            return Some(std::mem::replace(&mut self.dense[index], component));
+        }
+
+        if self.sparse.len() <= entity.index() {
+            self.sparse.resize(entity.index() + 1, None);
+        }
+        // A stale component of a despawned entity with the same index is dropped here
+        if let Some(index) = self.sparse[entity.index()] {
+            let stale = self.entities[index];
+            self.remove(stale);
+        }
+
+        self.sparse[entity.index()] = Some(self.dense.len());
+        self.dense.push(component);
+        self.entities.push(entity);
+
+        None
+    }
+
+    pub fn remove(&mut self, entity: Entity) -> Option<T> {
+        let index = self.sparse.get(entity.index()).copied().flatten()?;
+        if self.entities[index] != entity {
+            return None;
+        }
+
+        self.sparse[entity.index()] = None;
+        let component = self.dense.swap_remove(index);
+        self.entities.swap_remove(index);
+        if let Some(moved) = self.entities.get(index) {
+            self.sparse[moved.index()] = Some(index);
+        }
+
+        Some(component)
+    }
+
+    pub fn get(&self, entity: Entity) -> Option<&T> {
+        self.dense_index(entity).map(|index| &self.dense[index])
+    }
+
+    pub fn get_mut(&mut self, entity: Entity) -> Option<&mut T> {
+        self.dense_index(entity).map(|index| &mut self.dense[index])
+    }
+
+    // Packed in the same order as entities()
+    pub fn components(&self) -> &[T] {
+        &self.dense
+    }
+
+    pub fn components_mut(&mut self) -> &mut [T] {
+        &mut self.dense
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item=(Entity, &T)> {
+        self.entities.iter().copied().zip(self.dense.iter())
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item=(Entity, &mut T)> {
+        self.entities.iter().copied().zip(self.dense.iter_mut())
+    }
+}
@@ -0,0 +1,150 @@
+use ultraviolet::Vec3;
+
+use crate::assets::handle::Handle;
+use crate::ecs::world::{Entity, World};
+use crate::graphics::model::Model;
+use crate::graphics::node_3d::Node3D;
+use crate::graphics::rigid_body_3d::RigidBody3D;
+use crate::physics::collider::Collider;
+use crate::physics::collision_shape::CollisionShape;
+
+pub struct Transform {
+    pub node3d: Node3D,
+    previous: Node3D, // State at the start of the last fixed step, for interpolated rendering
+}
+
+impl Transform {
+    pub fn new(node3d: Node3D) -> Self {
+        Self { node3d, previous: node3d }
+    }
+
+    pub fn save_previous(&mut self) {
+        self.previous = self.node3d;
+    }
+
+    pub fn interpolated(&self, alpha: f32) -> Node3D {
+        self.node3d.interpolated(&self.previous, alpha)
+    }
+}
+
+pub struct RenderModel {
+    pub model: Handle<Model>,
+    pub visible: bool,
+    pub casts_shadows: bool,
+}
+
+impl RenderModel {
+    pub fn new(model: Handle<Model>) -> Self {
+        Self { model, visible: true, casts_shadows: true }
+    }
+}
+
+// Baked into world space, call rebuild after moving the entity's Transform
+pub struct StaticCollider {
+    shape: CollisionShape,
+    model: Option<Handle<Model>>, // Set when the shape is the model's triangles, rebuilt when the model reloads
+    node3d: Node3D,
+    collider: Collider,
+}
+
+impl StaticCollider {
+    pub fn new(shape: CollisionShape, node3d: &Node3D) -> Self {
+        let collider = Collider::new(&shape, node3d);
+
+        Self { shape, model: None, node3d: *node3d, collider }
+    }
+
+    // Collides with the exact triangles of the model
+    pub fn with_model_collision(model: Handle<Model>, node3d: &Node3D) -> Self {
+        let shape = CollisionShape::triangle_mesh_from_model(&model.get());
+
+        Self { model: Some(model), ..Self::new(shape, node3d) }
+    }
+
+    pub fn rebuild(&mut self, node3d: &Node3D) {
+        self.node3d = *node3d;
+        self.collider = Collider::new(&self.shape, node3d);
+    }
+
+    // Called after the model was reloaded so a collider built with with_model_collision uses its new triangles
+    pub fn refresh_model(&mut self) {
+        if let Some(model) = &self.model {
+            self.shape = CollisionShape::triangle_mesh_from_model(&model.get());
+            let node3d = self.node3d;
+            self.rebuild(&node3d);
+        }
+    }
+
+    pub fn shape(&self) -> &CollisionShape {
+        &self.shape
+    }
+
+    pub fn model(&self) -> Option<&Handle<Model>> {
+        self.model.as_ref()
+    }
+
+    pub fn collider(&self) -> &Collider {
+        &self.collider
+    }
+}
+
+// The body owns the simulated transform, the rigid body system copies it into the entity's Transform
+pub struct RigidBody {
+    pub body: RigidBody3D,
+}
+
+impl RigidBody {
+    pub fn new(body: RigidBody3D) -> Self {
+        Self { body }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum LightKind {
+    Directional { direction: Vec3 },
+    Point { range: f32 },
+    Spot { direction: Vec3, range: f32, inner_angle: f32, outer_angle: f32 }, // Angles in radians
+}
+
+// Point and spot lights are positioned by the entity's Transform
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Light {
+    pub kind: LightKind,
+    pub color: Vec3,
+    pub intensity: f32,
+}
+
+impl Light {
+    pub fn new(kind: LightKind, color: Vec3, intensity: f32) -> Self {
+        Self { kind, color, intensity }
+    }
+}
+
+pub trait Behaviour {
+    fn update(&mut self, entity: Entity, world: &mut World, dt: f32);
+}
+
+impl<F: FnMut(Entity, &mut World, f32)> Behaviour for F {
+    fn update(&mut self, entity: Entity, world: &mut World, dt: f32) {
+        self(entity, world, dt)
+    }
+}
+
+// The behaviour is taken out while it runs, so it can freely access its own entity's components
+pub struct Script {
+    behaviour: Option<Box<dyn Behaviour>>,
+}
+
+impl Script {
+    pub fn new(behaviour: impl Behaviour + 'static) -> Self {
+        Self { behaviour: Some(Box::new(behaviour)) }
+    }
+
+    pub(crate) fn take(&mut self) -> Option<Box<dyn Behaviour>> {
+        self.behaviour.take()
+    }
+
+    pub(crate) fn restore(&mut self, behaviour: Box<dyn Behaviour>) {
+        self.behaviour = Some(behaviour);
+    }
+}
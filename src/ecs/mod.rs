@@ -0,0 +1,5 @@
+pub mod component_storage;
+pub mod components;
+pub mod schedule;
+pub mod systems;
+pub mod world;
@@ -0,0 +1,157 @@
+use crate::ecs::world::World;
+use crate::error::{EngineError, EngineResult};
+
+pub trait System {
+    fn run(&mut self, world: &mut World, dt: f32);
+}
+
+impl<F: FnMut(&mut World, f32)> System for F {
+    fn run(&mut self, world: &mut World, dt: f32) {
+        self(world, dt)
+    }
+}
+
+pub struct SystemDescriptor {
+    name: String,
+    system: Box<dyn System>,
+    after: Vec<String>,
+    before: Vec<String>,
+}
+
+impl SystemDescriptor {
+    pub fn new(name: &str, system: impl System + 'static) -> Self {
+        Self { name: name.to_owned(), system: Box::new(system), after: Vec::new(), before: Vec::new() }
+    }
+
+    pub fn after(mut self, name: &str) -> Self {
+        self.after.push(name.to_owned());
+        self
+    }
+
+    pub fn before(mut self, name: &str) -> Self {
+        self.before.push(name.to_owned());
+        self
+    }
+}
+
+// Runs systems in an order that satisfies their before/after constraints, ties keep insertion order
+#[derive(Default)]
+pub struct Schedule {
+    systems: Vec<SystemDescriptor>,
+    order: Vec<usize>,
+}
+
+impl Schedule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // A system whose constraints would form a cycle is rejected and the schedule is left as it was
+    pub fn add_system(&mut self, descriptor: SystemDescriptor) -> EngineResult<&mut Self> {
+        self.systems.push(descriptor);
+        match self.sort() {
+            Ok(order) => {
+                self.order = order;
+                Ok(self)
+            }
+            Err(cycle) => {
+                self.systems.pop();
+                Err(EngineError::SystemOrderCycle(cycle))
+            }
+        }
+    }
+
+    pub fn remove_system(&mut self, name: &str) -> bool {
+        let count = self.systems.len();
+        self.systems.retain(|system| system.name != name);
+        // Dropping a system only removes constraints, so the rest still sorts
+        self.order = self.sort().unwrap_or_default();
+
+        self.systems.len() != count
+    }
+
+    pub fn system_names(&self) -> Vec<&str> {
+        self.order.iter().map(|index| self.systems[*index].name.as_str()).collect()
+    }
+
+    pub fn run(&mut self, world: &mut World, dt: f32) {
+        for index in self.order.iter() {
+            self.systems[*index].system.run(world, dt);
+        }
+    }
+
+    // Kahn's algorithm, constraints naming systems that are not in the schedule are ignored.
+    // Fails with the names of the systems left on a cycle
+    fn sort(&self) -> Result<Vec<usize>, Vec<String>> {
+        let index_of = |name: &str| self.systems.iter().position(|system| system.name == name);
+        let mut successors = vec![Vec::new(); self.systems.len()];
+        let mut incoming = vec![0; self.systems.len()];
+
+        for (index, system) in self.systems.iter().enumerate() {
+            let edges = system.after.iter().filter_map(|name| index_of(name)).map(|before| (before, index))
+                .chain(system.before.iter().filter_map(|name| index_of(name)).map(|after| (index, after)));
+
+            for (from, to) in edges {
+                successors[from].push(to);
+                incoming[to] += 1;
+            }
+        }
+
+        let mut order = Vec::with_capacity(self.systems.len());
+        while order.len() < self.systems.len() {
+            let Some(next) = (0..self.systems.len()).find(|index| incoming[*index] == 0 && !order.contains(index)) else {
+                return Err((0..self.systems.len()).filter(|index| !order.contains(index)).map(|index| self.systems[index].name.clone()).collect());
+            };
+
+            order.push(next);
+            for successor in &successors[next] {
+                incoming[*successor] -= 1;
+            }
+        }
+
+        Ok(order)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Log(Vec<&'static str>);
+
+    fn logger(name: &'static str) -> impl FnMut(&mut World, f32) {
+        move |world: &mut World, _| world.resource_mut::<Log>().unwrap().0.push(name)
+    }
+
+    #[test]
+    fn systems_run_in_constraint_order() {
+        let mut schedule = Schedule::new();
+        schedule
+            .add_system(SystemDescriptor::new("render", logger("render"))).unwrap()
+            .add_system(SystemDescriptor::new("physics", logger("physics")).before("render").after("input")).unwrap()
+            .add_system(SystemDescriptor::new("scripts", logger("scripts")).after("input").before("physics")).unwrap()
+            .add_system(SystemDescriptor::new("input", logger("input"))).unwrap();
+
+        let mut world = World::new();
+        world.insert_resource(Log(Vec::new()));
+        schedule.run(&mut world, 0.0);
+
+        assert_eq!(world.resource::<Log>().unwrap().0, vec!["input", "scripts", "physics", "render"]);
+        assert!(schedule.remove_system("scripts"));
+        assert_eq!(schedule.system_names(), vec!["input", "physics", "render"]);
+    }
+
+    #[test]
+    fn cycles_are_rejected_when_added() {
+        let mut schedule = Schedule::new();
+        schedule.add_system(SystemDescriptor::new("a", logger("a")).after("b")).unwrap();
+        let error = schedule.add_system(SystemDescriptor::new("b", logger("b")).after("a")).err().unwrap();
+        assert!(matches!(&error, EngineError::SystemOrderCycle(names) if names == &["a", "b"]), "{error}");
+
+        // The schedule keeps running without the rejected system
+        let mut world = World::new();
+        world.insert_resource(Log(Vec::new()));
+        schedule.run(&mut world, 0.0);
+        assert_eq!(world.resource::<Log>().unwrap().0, vec!["a"]);
+    }
+}
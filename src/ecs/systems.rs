@@ -0,0 +1,131 @@
+use ultraviolet::Mat4;
+
+use crate::assets::handle::Handle;
+use crate::ecs::components::{RenderModel, RigidBody, Script, Transform};
+use crate::ecs::schedule::{Schedule, SystemDescriptor};
+use crate::ecs::world::World;
+use crate::graphics::model::Model;
+use crate::graphics::render_queue::RenderQueue;
+use crate::math::frustum::Frustum;
+use crate::shader::Shader;
+
+pub const SAVE_TRANSFORMS_SYSTEM: &str = "save_transforms";
+pub const SCRIPT_SYSTEM: &str = "scripts";
+pub const RIGID_BODY_SYSTEM: &str = "rigid_bodies";
+
+// Systems every scene runs each fixed step, games add their own around these by name
+pub fn default_schedule() -> Schedule {
+    let mut schedule = Schedule::new();
+    for descriptor in [
+        SystemDescriptor::new(SAVE_TRANSFORMS_SYSTEM, save_transforms_system),
+        SystemDescriptor::new(SCRIPT_SYSTEM, script_system).after(SAVE_TRANSFORMS_SYSTEM),
+        SystemDescriptor::new(RIGID_BODY_SYSTEM, rigid_body_system).after(SCRIPT_SYSTEM),
+    ] {
+        schedule.add_system(descriptor).expect("default systems are ordered in a line");
+    }
+
+    schedule
+}
+
+pub fn save_transforms_system(world: &mut World, _dt: f32) {
+    world.query_mut::<Transform>(|_, transform| transform.save_previous());
+}
+
+pub fn script_system(world: &mut World, dt: f32) {
+    for entity in world.entities_with::<Script>() {
+        let Some(mut behaviour) = world.get_mut::<Script>(entity).and_then(|mut script| script.take()) else {
+            continue;
+        };
+
+        behaviour.update(entity, world, dt);
+
+        // The script may have despawned its entity or removed the component
+        if let Some(mut script) = world.get_mut::<Script>(entity) {
+            script.restore(behaviour);
+        }
+    }
+}
+
+// The scene's physics step simulates RigidBody components along with its own bodies, this copies the result into the entity's Transform
+pub fn rigid_body_system(world: &mut World, _dt: f32) {
+    world.query2_mut::<RigidBody, Transform>(|_, rigid_body, transform| transform.node3d = rigid_body.body.node3d);
+}
+
+pub fn queue_models(world: &World, queue: &mut RenderQueue, frustum: &Frustum, alpha: f32) {
+    visible_render_models(world, frustum, alpha, false, |model, model_matrix| queue.push_model(model, model_matrix));
+}
+
+pub fn draw_shadow_casters(world: &World, shader_program: &Shader, frustum: &Frustum, alpha: f32) {
+    visible_render_models(world, frustum, alpha, true, |model, model_matrix| {
+        shader_program.set_mat4("model", model_matrix);
+        model.get().draw(shader_program);
+    });
+}
+
+fn visible_render_models(world: &World, frustum: &Frustum, alpha: f32, shadow_casters_only: bool, mut visit: impl FnMut(&Handle<Model>, Mat4)) {
+    world.query2::<RenderModel, Transform>(|_, render_model, transform| {
+        if !render_model.visible || (shadow_casters_only && !render_model.casts_shadows) {
+            return;
+        }
+
+        let node3d = transform.interpolated(alpha);
+        if !frustum.intersects_aabb(&node3d.world_bounding_box(render_model.model.get().bounding_box())) {
+            return;
+        }
+
+        visit(&render_model.model, node3d.model_matrix());
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use ultraviolet::Vec3;
+
+    use super::*;
+    use crate::ecs::world::Entity;
+    use crate::graphics::node_3d::Node3D;
+    use crate::graphics::rigid_body_3d::RigidBody3D;
+    use crate::math::aabb_bouding_box::AABBBoundingBox;
+    use crate::math::rotation::Rotation;
+    use crate::ecs::components::StaticCollider;
+    use crate::physics;
+    use crate::physics::broad_phase::DynamicAabbTree;
+    use crate::physics::collision_shape::CollisionShape;
+    use crate::physics::EntityProxies;
+
+    fn node3d(position: Vec3) -> Node3D {
+        Node3D { world_position: position, scale: Vec3::new(1.0, 1.0, 1.0), rotation: Rotation::default() }
+    }
+
+    #[test]
+    fn rigid_bodies_rest_on_static_colliders_and_scripts_run() {
+        let mut world = World::new();
+        let floor = world.spawn();
+        world.insert(floor, StaticCollider::new(CollisionShape::Box { half_extents: Vec3::new(5.0, 0.5, 5.0) }, &node3d(Vec3::new(0.0, -0.5, 0.0))));
+
+        let model = Handle::new(Model::from_meshes(Vec::new()));
+        let bounding_box = AABBBoundingBox::from_center_half_extents(Vec3::default(), Vec3::broadcast(0.5));
+        let crate_entity = world.spawn();
+        world.insert(crate_entity, Transform::new(node3d(Vec3::new(0.0, 2.0, 0.0))));
+        world.insert(crate_entity, RigidBody::new(RigidBody3D::new(node3d(Vec3::new(0.0, 2.0, 0.0)), model, bounding_box, 1.0)));
+
+        let spinner = world.spawn();
+        world.insert(spinner, Transform::new(node3d(Vec3::default())));
+        world.insert(spinner, Script::new(|entity: Entity, world: &mut World, dt: f32| {
+            let mut transform = world.get_mut::<Transform>(entity).unwrap();
+            transform.node3d.rotation = Rotation::from_rotation_y(dt) * transform.node3d.rotation;
+        }));
+
+        // Physics runs before the schedule each fixed step, as in Scene::fixed_update
+        let mut schedule = default_schedule();
+        let (mut broad_phase, mut entity_proxies) = (DynamicAabbTree::new(), EntityProxies::new());
+        for _ in 0..180 {
+            physics::step_rigid_bodies(&mut [], &[], &[], &world, &mut entity_proxies, &mut broad_phase, 1.0 / 60.0);
+            schedule.run(&mut world, 1.0 / 60.0);
+        }
+
+        let height = world.get::<Transform>(crate_entity).unwrap().node3d.world_position.y;
+        assert!((height - 0.5).abs() < 0.05, "crate rests at {height}");
+        assert!(world.get::<Transform>(spinner).unwrap().node3d.rotation.angle_to(&Rotation::from_rotation_y(3.0)) < 1e-3);
+    }
+}
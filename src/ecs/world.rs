@@ -0,0 +1,252 @@
+use std::any::{Any, TypeId};
+use std::cell::{Ref, RefCell, RefMut};
+use std::collections::HashMap;
+
+use crate::ecs::component_storage::ComponentStorage;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Entity {
+    index: u32,
+    generation: u32, // Bumped on despawn so old handles stop matching a reused index
+}
+
+impl Entity {
+    pub fn index(&self) -> usize {
+        self.index as usize
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+// Lets the world drop an entity's components without knowing their types
+trait AnyStorage {
+    fn remove_entity(&self, entity: Entity);
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<T: 'static> AnyStorage for RefCell<ComponentStorage<T>> {
+    fn remove_entity(&self, entity: Entity) {
+        self.borrow_mut().remove(entity);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+// Each component type has its own storage behind a RefCell, so systems can borrow several types at once
+#[derive(Default)]
+pub struct World {
+    generations: Vec<u32>,
+    alive: Vec<bool>,
+    free_indices: Vec<u32>,
+    storages: HashMap<TypeId, Box<dyn AnyStorage>>,
+    resources: HashMap<TypeId, RefCell<Box<dyn Any>>>,
+}
+
+impl World {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn spawn(&mut self) -> Entity {
+        match self.free_indices.pop() {
+            Some(index) => {
+                self.alive[index as usize] = true;
+                Entity { index, generation: self.generations[index as usize] }
+            }
+            None => {
+                self.generations.push(0);
+                self.alive.push(true);
+                Entity { index: self.generations.len() as u32 - 1, generation: 0 }
+            }
+        }
+    }
+
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        if !self.is_alive(entity) {
+            return false;
+        }
+
+        for storage in self.storages.values() {
+            storage.remove_entity(entity);
+        }
+
+        self.alive[entity.index()] = false;
+        self.generations[entity.index()] += 1;
+        self.free_indices.push(entity.index);
+
+        true
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.alive.get(entity.index()).copied().unwrap_or(false) && self.generations[entity.index()] == entity.generation
+    }
+
+    pub fn entity_count(&self) -> usize {
+        self.alive.iter().filter(|alive| **alive).count()
+    }
+
+    // Returns false when the entity is no longer alive
+    pub fn insert<T: 'static>(&mut self, entity: Entity, component: T) -> bool {
+        if !self.is_alive(entity) {
+            return false;
+        }
+
+        self.storages.entry(TypeId::of::<T>()).or_insert_with(|| Box::new(RefCell::new(ComponentStorage::<T>::default())));
+        self.storage_mut::<T>().unwrap().insert(entity, component);
+
+        true
+    }
+
+    pub fn remove<T: 'static>(&mut self, entity: Entity) -> Option<T> {
+        self.storage_mut::<T>()?.remove(entity)
+    }
+
+    pub fn has<T: 'static>(&self, entity: Entity) -> bool {
+        self.storage::<T>().is_some_and(|storage| storage.contains(entity))
+    }
+
+    pub fn get<T: 'static>(&self, entity: Entity) -> Option<Ref<'_, T>> {
+        Ref::filter_map(self.storage::<T>()?, |storage| storage.get(entity)).ok()
+    }
+
+    pub fn get_mut<T: 'static>(&self, entity: Entity) -> Option<RefMut<'_, T>> {
+        RefMut::filter_map(self.storage_mut::<T>()?, |storage| storage.get_mut(entity)).ok()
+    }
+
+    // Panics if the same component type is already mutably borrowed, like RefCell::borrow
+    pub fn storage<T: 'static>(&self) -> Option<Ref<'_, ComponentStorage<T>>> {
+        Some(self.cell::<T>()?.borrow())
+    }
+
+    pub fn storage_mut<T: 'static>(&self) -> Option<RefMut<'_, ComponentStorage<T>>> {
+        Some(self.cell::<T>()?.borrow_mut())
+    }
+
+    fn cell<T: 'static>(&self) -> Option<&RefCell<ComponentStorage<T>>> {
+        self.storages.get(&TypeId::of::<T>())?.as_any().downcast_ref()
+    }
+
+    pub fn query<A: 'static>(&self, mut f: impl FnMut(Entity, &A)) {
+        if let Some(a) = self.storage::<A>() {
+            a.iter().for_each(|(entity, a)| f(entity, a));
+        }
+    }
+
+    pub fn query_mut<A: 'static>(&self, mut f: impl FnMut(Entity, &mut A)) {
+        if let Some(mut a) = self.storage_mut::<A>() {
+            a.iter_mut().for_each(|(entity, a)| f(entity, a));
+        }
+    }
+
+    // Visits every entity that has both components
+    pub fn query2<A: 'static, B: 'static>(&self, mut f: impl FnMut(Entity, &A, &B)) {
+        let (Some(a), Some(b)) = (self.storage::<A>(), self.storage::<B>()) else {
+            return;
+        };
+
+        for (entity, a) in a.iter() {
+            if let Some(b) = b.get(entity) {
+                f(entity, a, b);
+            }
+        }
+    }
+
+    pub fn query2_mut<A: 'static, B: 'static>(&self, mut f: impl FnMut(Entity, &mut A, &mut B)) {
+        let (Some(mut a), Some(mut b)) = (self.storage_mut::<A>(), self.storage_mut::<B>()) else {
+            return;
+        };
+
+        for (entity, a) in a.iter_mut() {
+            if let Some(b) = b.get_mut(entity) {
+                f(entity, a, b);
+            }
+        }
+    }
+
+    // Entities that have component A, for systems that need to touch the world while iterating
+    pub fn entities_with<A: 'static>(&self) -> Vec<Entity> {
+        self.storage::<A>().map_or(Vec::new(), |storage| storage.entities().to_vec())
+    }
+
+    pub fn insert_resource<T: 'static>(&mut self, resource: T) {
+        self.resources.insert(TypeId::of::<T>(), RefCell::new(Box::new(resource)));
+    }
+
+    pub fn remove_resource<T: 'static>(&mut self) -> Option<T> {
+        let resource = self.resources.remove(&TypeId::of::<T>())?.into_inner();
+        resource.downcast().ok().map(|resource| *resource)
+    }
+
+    pub fn resource<T: 'static>(&self) -> Option<Ref<'_, T>> {
+        Ref::filter_map(self.resources.get(&TypeId::of::<T>())?.borrow(), |resource| resource.downcast_ref()).ok()
+    }
+
+    pub fn resource_mut<T: 'static>(&self) -> Option<RefMut<'_, T>> {
+        RefMut::filter_map(self.resources.get(&TypeId::of::<T>())?.borrow_mut(), |resource| resource.downcast_mut()).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Position(i32);
+
+    #[derive(Debug, PartialEq)]
+    struct Velocity(i32);
+
+    #[test]
+    fn despawned_entities_lose_components_and_stay_invalid() {
+        let mut world = World::new();
+        let first = world.spawn();
+        world.insert(first, Position(1));
+        world.despawn(first);
+
+        let second = world.spawn();
+        assert_eq!(first.index(), second.index());
+        assert!(!world.is_alive(first));
+        assert!(!world.insert(first, Position(5)));
+        assert!(world.get::<Position>(second).is_none());
+
+        world.insert(second, Position(2));
+        assert_eq!(*world.get::<Position>(second).unwrap(), Position(2));
+        assert!(world.get::<Position>(first).is_none());
+    }
+
+    #[test]
+    fn queries_join_component_types() {
+        let mut world = World::new();
+        for i in 0..6 {
+            let entity = world.spawn();
+            world.insert(entity, Position(i));
+            if i % 2 == 0 {
+                world.insert(entity, Velocity(10));
+            }
+        }
+        let removed = world.entities_with::<Velocity>()[0];
+        world.remove::<Velocity>(removed);
+
+        world.query2_mut::<Position, Velocity>(|_, position, velocity| position.0 += velocity.0);
+
+        let mut positions = Vec::new();
+        world.query::<Position>(|_, position| positions.push(position.0));
+        positions.sort();
+        assert_eq!(positions, vec![0, 1, 3, 5, 12, 14]);
+    }
+
+    #[test]
+    fn resources_are_typed() {
+        let mut world = World::new();
+        world.insert_resource(Velocity(3));
+        world.resource_mut::<Velocity>().unwrap().0 += 1;
+
+        assert_eq!(*world.resource::<Velocity>().unwrap(), Velocity(4));
+        assert!(world.resource::<Position>().is_none());
+        assert_eq!(world.remove_resource::<Velocity>(), Some(Velocity(4)));
+    }
+}
@@ -0,0 +1,280 @@
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+use beryllium::*;
+use beryllium::events::{SDL_Keycode, SDLK_ESCAPE, SDLK_F1, SDLK_F2, SDLK_F3, SDLK_F4, SDLK_F5, SDLK_F6};
+use beryllium::video::GlSwapInterval::{Immediate, Vsync};
+use beryllium::video::GlWindow;
+
+use crate::assets::AssetManager;
+use crate::assets::handle::Handle;
+use crate::error::EngineResult;
+use crate::graphics::post_processing::{PostEffect, PostProcessor};
+use crate::graphics::scene::Scene;
+use crate::opengl;
+use crate::opengl::{BlendFactor, Capability, UnpackAlignment};
+use crate::opengl::ClearBitFlags::{ColorBuffer, DepthBuffer};
+use crate::physics::FIXED_TIMESTEP;
+use crate::shader::Shader;
+use crate::timestep::{DEFAULT_MAX_STEPS_PER_FRAME, FixedTimestep};
+
+pub struct EngineConfig<'a> {
+    pub title: &'a str,
+    pub width: i32,
+    pub height: i32,
+    pub vsync: bool,
+    pub fixed_timestep: f32, // Seconds per simulation step
+    pub max_steps_per_frame: u32,
+    pub deterministic: bool, // One simulation step per rendered frame regardless of elapsed time
+    pub hot_reload: bool, // Reload assets whose files change on disk while running
+    pub clear_color: [f32; 4],
+}
+
+const HOT_RELOAD_INTERVAL: Duration = Duration::from_millis(500);
+
+const POST_EFFECT_KEYS: [(SDL_Keycode, PostEffect); 6] = [
+    (SDLK_F1, PostEffect::ToneMapping),
+    (SDLK_F2, PostEffect::Bloom),
+    (SDLK_F3, PostEffect::Fxaa),
+    (SDLK_F4, PostEffect::Vignette),
+    (SDLK_F5, PostEffect::ColorGrading),
+    (SDLK_F6, PostEffect::GammaCorrection),
+];
+
+impl Default for EngineConfig<'_> {
+    fn default() -> Self {
+        Self {
+            title: "OpenGL",
+            width: 1280,
+            height: 720,
+            vsync: true,
+            fixed_timestep: FIXED_TIMESTEP,
+            max_steps_per_frame: DEFAULT_MAX_STEPS_PER_FRAME,
+            deterministic: false,
+            hot_reload: cfg!(debug_assertions),
+            clear_color: [0.2, 0.3, 0.3, 1.0],
+        }
+    }
+}
+
+pub struct Engine {
+    sdl: Sdl,
+    window: GlWindow,
+    width: i32,
+    height: i32,
+    keys_held: HashSet<SDL_Keycode>,
+    timestep: FixedTimestep,
+    hot_reload: bool,
+    clear_color: [f32; 4],
+    post_processor: Option<PostProcessor>,
+    post_processing_error: Option<String>, // Why the post-processor couldn't be resized, shown with the reload errors
+}
+
+impl Engine {
+    pub fn new(config: EngineConfig) -> Self {
+        let sdl = Sdl::init(init::InitFlags::EVERYTHING);
+        sdl.set_gl_context_major_version(3).unwrap();
+        sdl.set_gl_context_minor_version(3).unwrap();
+        sdl.set_gl_profile(video::GlProfile::Core).unwrap();
+        #[cfg(target_os = "macos")]
+        {
+            sdl
+                .set_gl_context_flags(video::GlContextFlags::FORWARD_COMPATIBLE)
+                .unwrap();
+        }
+
+        let win_args = video::CreateWinArgs {
+            title: config.title,
+            width: config.width,
+            height: config.height,
+            allow_high_dpi: true,
+            borderless: false,
+            resizable: false,
+        };
+
+        let window = sdl
+            .create_gl_window(win_args)
+            .expect("couldn't make a window and context");
+        window.set_swap_interval(if config.vsync { Vsync } else { Immediate }).unwrap();
+        sdl.set_relative_mouse_mode(true).unwrap();
+
+        opengl::load_gl(&window);
+        opengl::enable(Capability::DepthTest);
+        opengl::enable(Capability::Blending);
+        opengl::enable(Capability::CubeMapSeamless);
+        opengl::blend_func(BlendFactor::SrcAlpha, BlendFactor::OneMinusSrcAlpha);
+        opengl::pixel_store_unpack_alignment(UnpackAlignment::One);
+
+        Self {
+            sdl,
+            window,
+            width: config.width,
+            height: config.height,
+            keys_held: HashSet::new(),
+            timestep: if config.deterministic {
+                FixedTimestep::deterministic(config.fixed_timestep)
+            } else {
+                FixedTimestep::new(config.fixed_timestep, config.max_steps_per_frame)
+            },
+            hot_reload: config.hot_reload,
+            clear_color: config.clear_color,
+            post_processor: None,
+            post_processing_error: None,
+        }
+    }
+
+    pub fn aspect_ratio(&self) -> f32 {
+        self.width as f32 / self.height as f32
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    // Without one the scene is drawn straight to the window. F1-F6 toggle its effects while running
+    pub fn set_post_processor(&mut self, post_processor: Option<PostProcessor>) {
+        self.post_processor = post_processor;
+    }
+
+    pub fn post_processor_mut(&mut self) -> Option<&mut PostProcessor> {
+        self.post_processor.as_mut()
+    }
+
+    // Shaders are passed as handles so hot reload can swap them between frames
+    pub fn run(&mut self, scene: &mut Scene, assets: &mut AssetManager, shader_program: &Handle<Shader>, shader_program_font: &Handle<Shader>) {
+        let mut last_time = Instant::now();
+        let mut last_reload_check = last_time;
+        let mut mouse_delta = (0, 0);
+
+        'main_loop: loop {
+            let mut mouse_moved = false;
+            while let Some((event, _)) = self.sdl.poll_events() {
+                match event {
+                    events::Event::Quit => break 'main_loop,
+                    events::Event::Key { pressed, repeat, keycode, .. } => {
+                        if keycode == SDLK_ESCAPE {
+                            break 'main_loop;
+                        }
+
+                        if pressed && repeat == 0 {
+                            if let (Some(post_processor), Some((_, effect))) = (&mut self.post_processor, POST_EFFECT_KEYS.iter().find(|(key, _)| *key == keycode)) {
+                                post_processor.settings_mut().toggle(*effect);
+                            }
+                        }
+
+                        if pressed {
+                            self.keys_held.insert(keycode);
+                        } else {
+                            self.keys_held.remove(&keycode);
+                        }
+                    }
+                    events::Event::MouseMotion { x_delta, y_delta, .. } => {
+                        mouse_delta.0 = x_delta;
+                        mouse_delta.1 = -y_delta;
+                        mouse_moved = true;
+                    }
+                    events::Event::WindowResized { width, height, .. } => {
+                        self.width = width;
+                        self.height = height;
+                        opengl::viewport(0, 0, width, height);
+                    }
+                    _ => (),
+                }
+            }
+
+            let time = Instant::now();
+            let frame_time = time.duration_since(last_time).as_secs_f32();
+            last_time = time;
+
+            let mouse_delta = if mouse_moved {
+                Some(mouse_delta)
+            } else {
+                None
+            };
+
+            if self.hot_reload && time.duration_since(last_reload_check) >= HOT_RELOAD_INTERVAL {
+                last_reload_check = time;
+                scene.refresh_models(&assets.reload_changed());
+                scene.set_message(status_message(assets, self.post_processing_error.as_deref()));
+            }
+
+            scene.process_input(&self.keys_held, mouse_delta);
+
+            for _ in 0..self.timestep.advance(frame_time) {
+                scene.fixed_update(self.timestep.step());
+            }
+
+            let (width, height) = (self.width as u32, self.height as u32);
+            let error = keep_on_failure(&mut self.post_processor, |post_processor| post_processor.resize(width, height))
+                .map(|error| format!("Couldn't resize post-processing, drawing at the previous size: {error}"));
+            if error != self.post_processing_error {
+                self.post_processing_error = error;
+                scene.set_message(status_message(assets, self.post_processing_error.as_deref()));
+            }
+
+            scene.set_gamma_correction(self.post_processor.is_none());
+            match &self.post_processor {
+                Some(post_processor) => {
+                    post_processor.begin(self.clear_color);
+                    scene.draw(&shader_program.get(), self.aspect_ratio(), self.timestep.alpha());
+                    post_processor.apply([0, 0, self.width, self.height]);
+                    opengl::clear(DepthBuffer.into());
+                }
+                None => {
+                    let [red, green, blue, alpha] = self.clear_color;
+                    opengl::clear_color(red, green, blue, alpha);
+                    opengl::clear(ColorBuffer | DepthBuffer);
+                    scene.draw(&shader_program.get(), self.aspect_ratio(), self.timestep.alpha());
+                }
+            }
+            scene.draw_overlay(&shader_program_font.get());
+
+            self.window.swap_window();
+        }
+    }
+}
+
+// Keeps the value when `update` fails, e.g. a post-processor whose targets can't be recreated keeps drawing at its old size
+fn keep_on_failure<T>(value: &mut Option<T>, update: impl FnOnce(&mut T) -> EngineResult<()>) -> Option<String> {
+    let error = value.as_mut().map(update)?.err()?;
+
+    Some(error.to_string())
+}
+
+fn status_message(assets: &AssetManager, post_processing_error: Option<&str>) -> Option<String> {
+    let mut errors: Vec<String> = assets.reload_errors().map(|(_, error)| format!("Reload failed, keeping the previous version: {error}")).collect();
+    errors.sort();
+    errors.extend(post_processing_error.map(str::to_owned));
+
+    (!errors.is_empty()).then(|| errors.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::EngineError;
+
+    #[test]
+    fn failed_updates_keep_the_value_and_report_why() {
+        let mut value = Some(1);
+        assert_eq!(keep_on_failure(&mut value, |value| { *value += 1; Ok(()) }), None);
+        assert_eq!(value, Some(2));
+
+        let message = keep_on_failure(&mut value, |_| Err(EngineError::FramebufferIncomplete("missing attachment")));
+        assert_eq!(value, Some(2));
+        assert!(message.is_some_and(|message| message.contains("missing attachment")));
+
+        assert_eq!(keep_on_failure(&mut None::<i32>, |_| panic!("nothing to update")), None);
+    }
+
+    #[test]
+    fn status_message_lists_post_processing_errors() {
+        let assets = AssetManager::new();
+        assert_eq!(status_message(&assets, None), None);
+        assert_eq!(status_message(&assets, Some("Couldn't resize post-processing: no memory")).as_deref(), Some("Couldn't resize post-processing: no memory"));
+    }
+}
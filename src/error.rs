@@ -0,0 +1,65 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+use image::{ColorType, ImageError};
+use tobj::LoadError;
+
+#[derive(Debug)]
+pub enum EngineError {
+    Io { path: String, source: std::io::Error },
+    ImageDecode { path: String, source: ImageError },
+    UnsupportedColorType(ColorType),
+    ObjLoad { path: String, source: LoadError },
+    EmptyModel { path: String },
+    FontParse { path: String },
+    ShaderCompile { path: String, log: String },
+    ShaderLink { vertex_path: String, fragment_path: String, log: String },
+    GlAllocation(&'static str),
+    FramebufferIncomplete(&'static str),
+    InvalidRenderTarget(String),
+    GltfLoad { path: String, source: gltf::Error },
+    InvalidGltf { path: String, reason: String },
+    SceneParse { path: String, source: serde_json::Error },
+    SceneReference { node: String, parent: String },
+    SceneSave(String),
+    SystemOrderCycle(Vec<String>),
+}
+
+pub type EngineResult<T> = Result<T, EngineError>;
+
+impl Display for EngineError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EngineError::Io { path, source } => write!(f, "Couldn't read {path}: {source}"),
+            EngineError::ImageDecode { path, source } => write!(f, "Couldn't decode image {path}: {source}"),
+            EngineError::UnsupportedColorType(color) => write!(f, "Unsupported color type {color:?}"),
+            EngineError::ObjLoad { path, source } => write!(f, "Couldn't load OBJ/MTL {path}: {source}"),
+            EngineError::EmptyModel { path } => write!(f, "Obj file {path} contains no models"),
+            EngineError::FontParse { path } => write!(f, "Couldn't parse font {path}"),
+            EngineError::ShaderCompile { path, log } => write!(f, "Failed to compile shader {path}: {log}"),
+            EngineError::ShaderLink { vertex_path, fragment_path, log } => write!(f, "Failed to link shader program ({vertex_path}, {fragment_path}): {log}"),
+            EngineError::GlAllocation(object) => write!(f, "Failed to allocate {object}"),
+            EngineError::FramebufferIncomplete(status) => write!(f, "Framebuffer is incomplete: {status}"),
+            EngineError::InvalidRenderTarget(reason) => write!(f, "Invalid render target: {reason}"),
+            EngineError::GltfLoad { path, source } => write!(f, "Couldn't load glTF {path}: {source}"),
+            EngineError::InvalidGltf { path, reason } => write!(f, "Invalid glTF {path}: {reason}"),
+            EngineError::SceneParse { path, source } => write!(f, "Couldn't parse scene {path}: {source}"),
+            EngineError::SceneReference { node, parent } => write!(f, "Scene node {node} has an unknown parent {parent}"),
+            EngineError::SceneSave(reason) => write!(f, "Couldn't save scene: {reason}"),
+            EngineError::SystemOrderCycle(systems) => write!(f, "System ordering has a cycle between {systems:?}"),
+        }
+    }
+}
+
+impl Error for EngineError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            EngineError::Io { source, .. } => Some(source),
+            EngineError::ImageDecode { source, .. } => Some(source),
+            EngineError::ObjLoad { source, .. } => Some(source),
+            EngineError::GltfLoad { source, .. } => Some(source),
+            EngineError::SceneParse { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,368 @@
+use std::path::{Path, PathBuf};
+
+use gltf::buffer;
+use gltf::image::Source;
+use gltf::mesh::Mode;
+use gltf::Gltf;
+use ultraviolet::{Mat4, Vec2, Vec3, Vec4};
+
+use crate::assets::handle::Handle;
+use crate::error::{EngineError, EngineResult};
+use crate::graphics::material::{parameter, CullMode, Material, MaterialValue, RenderState, TextureSlot};
+use crate::graphics::mesh::Mesh;
+use crate::graphics::model::{Model, ModelNode};
+use crate::graphics::node_3d::Node3D;
+use crate::graphics::skin::{JointWeights, Skin};
+use crate::graphics::vertex::{Vertex, VertexAttribute, VertexLayout};
+use crate::graphics::vertex_processing::{compute_normals, compute_tangents, normalized_or_zero, NormalMode};
+use crate::math::rotation::Rotation;
+use crate::opengl::texture::Texture;
+
+pub(crate) fn is_gltf_path(path: &str) -> bool {
+    matches!(Path::new(path).extension().and_then(|extension| extension.to_str()), Some("gltf" | "glb"))
+}
+
+// Buffer files referenced by a glTF file, images are loaded as textures and watched on their own
+pub(crate) fn external_buffer_files(path: &str) -> Vec<PathBuf> {
+    let Ok(gltf) = Gltf::open(path) else {
+        return Vec::new();
+    };
+
+    let directory = directory_of(path);
+    gltf.document.buffers()
+        .filter_map(|buffer| match buffer.source() {
+            buffer::Source::Uri(uri) if !uri.starts_with("data:") => Some(directory.join(uri)),
+            _ => None,
+        })
+        .collect()
+}
+
+// One primitive read from the buffers, not uploaded to the GPU yet
+struct PrimitiveData {
+    vertices: Vec<Vertex>,
+    indices: Vec<u32>,
+    material: Handle<Material>,
+    layout: VertexLayout,
+    skin: Option<(usize, Vec<JointWeights>)>,
+}
+
+impl PrimitiveData {
+    fn into_mesh(self) -> EngineResult<Mesh> {
+        let mut mesh = Mesh::with_layout(self.vertices, self.indices, self.material, self.layout)?;
+        if let Some((skin, joint_weights)) = self.skin {
+            mesh.set_skin(skin, joint_weights);
+        }
+
+        Ok(mesh)
+    }
+}
+
+struct GltfData {
+    primitives: Vec<PrimitiveData>,
+    nodes: Vec<ModelNode>,
+    skins: Vec<Skin>,
+}
+
+pub(crate) fn load_from_file(path: &str, load_texture: impl FnMut(&str) -> EngineResult<Handle<Texture>>) -> EngineResult<Model> {
+    let GltfData { primitives, nodes, skins } = read_file(path, load_texture)?;
+    let meshes = primitives.into_iter().map(PrimitiveData::into_mesh).collect::<EngineResult<Vec<Mesh>>>()?;
+
+    Ok(Model::from_hierarchy(meshes, nodes, skins))
+}
+
+// Images stored as separate files go through `load_texture` so they can be shared, embedded ones are decoded here
+fn read_file(path: &str, mut load_texture: impl FnMut(&str) -> EngineResult<Handle<Texture>>) -> EngineResult<GltfData> {
+    let directory = directory_of(path);
+    let Gltf { document, blob } = Gltf::open(path).map_err(|source| EngineError::GltfLoad { path: path.to_owned(), source })?;
+    let buffers = gltf::import_buffers(&document, Some(&directory), blob).map_err(|source| EngineError::GltfLoad { path: path.to_owned(), source })?;
+
+    let mut images: Vec<Option<Handle<Texture>>> = vec![None; document.images().len()];
+    let mut texture = |texture: gltf::Texture| -> EngineResult<Handle<Texture>> {
+        let image = texture.source();
+        if let Some(handle) = &images[image.index()] {
+            return Ok(handle.clone());
+        }
+
+        let name = format!("{path} image {}", image.index());
+        let handle = match image.source() {
+            Source::View { view, .. } => {
+                let bytes = &buffers[view.buffer().index()][view.offset()..view.offset() + view.length()];
+                Handle::new(Texture::from_image_bytes(bytes, &name)?)
+            }
+            Source::Uri { uri, .. } if uri.starts_with("data:") => {
+                let data = buffer::Data::from_source(buffer::Source::Uri(uri), None).map_err(|source| EngineError::GltfLoad { path: path.to_owned(), source })?;
+                Handle::new(Texture::from_image_bytes(&data, &name)?)
+            }
+            Source::Uri { uri, .. } => load_texture(&directory.join(uri).to_string_lossy())?,
+        };
+
+        images[image.index()] = Some(handle.clone());
+        Ok(handle)
+    };
+
+    let mut materials = Vec::with_capacity(document.materials().len());
+    for material in document.materials() {
+        materials.push(Handle::new(load_material(&material, &mut texture)?));
+    }
+
+    let mut nodes: Vec<ModelNode> = document.nodes().map(|node| {
+        let (translation, rotation, scale) = node.transform().decomposed();
+        let local = Node3D {
+            world_position: Vec3::from(translation),
+            scale: Vec3::from(scale),
+            rotation: Rotation::from_components(rotation[0], rotation[1], rotation[2], rotation[3]),
+        };
+
+        ModelNode { name: node.name().map(str::to_owned), parent: None, local, world_matrix: local.model_matrix(), meshes: Vec::new() }
+    }).collect();
+
+    for node in document.nodes() {
+        for child in node.children() {
+            nodes[child.index()].parent = Some(node.index());
+        }
+    }
+
+    // Nodes outside of the displayed scene keep their local matrix and are not drawn
+    let roots: Vec<gltf::Node> = match document.default_scene().or_else(|| document.scenes().next()) {
+        Some(scene) => scene.nodes().collect(),
+        None => document.nodes().filter(|node| nodes[node.index()].parent.is_none()).collect(),
+    };
+
+    let mut primitives = Vec::new();
+    let mut default_material = None; // Shared by every primitive without a material
+    let mut stack: Vec<(gltf::Node, Mat4)> = roots.into_iter().map(|node| (node, Mat4::identity())).collect();
+    while let Some((node, parent_matrix)) = stack.pop() {
+        let world_matrix = parent_matrix * nodes[node.index()].world_matrix;
+        nodes[node.index()].world_matrix = world_matrix;
+
+        if let Some(mesh) = node.mesh() {
+            // Skinned vertices are already in model space, the joints place them
+            let skin = node.skin().map(|skin| skin.index());
+            let transform = if skin.is_some() { Mat4::identity() } else { world_matrix };
+
+            for primitive in mesh.primitives() {
+                let material = match primitive.material().index() {
+                    Some(index) => materials[index].clone(),
+                    None => default_material.get_or_insert_with(|| Handle::new(Material::default())).clone(),
+                };
+
+                if let Some(data) = read_primitive(path, &primitive, &buffers, transform, material, skin)? {
+                    nodes[node.index()].meshes.push(primitives.len());
+                    primitives.push(data);
+                }
+            }
+        }
+
+        stack.extend(node.children().map(|child| (child, world_matrix)));
+    }
+
+    if primitives.is_empty() {
+        return Err(EngineError::EmptyModel { path: path.to_owned() });
+    }
+
+    let skins = document.skins().map(|skin| {
+        let reader = skin.reader(|buffer| buffers.get(buffer.index()).map(|data| &data[..]));
+        let joints: Vec<usize> = skin.joints().map(|joint| joint.index()).collect();
+        let inverse_bind_matrices = match reader.read_inverse_bind_matrices() {
+            Some(matrices) => matrices.map(Mat4::from).collect(),
+            None => vec![Mat4::identity(); joints.len()],
+        };
+
+        Skin { name: skin.name().map(str::to_owned), joints, inverse_bind_matrices, skeleton: skin.skeleton().map(|node| node.index()) }
+    }).collect();
+
+    Ok(GltfData { primitives, nodes, skins })
+}
+
+fn directory_of(path: &str) -> PathBuf {
+    Path::new(path).parent().map(Path::to_path_buf).unwrap_or_default()
+}
+
+// None for point and line modes
+fn triangle_list(mode: Mode, indices: Vec<u32>) -> Option<Vec<u32>> {
+    match mode {
+        Mode::Triangles => Some(indices),
+        Mode::TriangleStrip => Some((2..indices.len()).flat_map(|i| {
+            // Every other triangle of a strip is wound the other way
+            if i % 2 == 0 { [indices[i - 2], indices[i - 1], indices[i]] } else { [indices[i - 1], indices[i - 2], indices[i]] }
+        }).collect()),
+        Mode::TriangleFan => Some((2..indices.len()).flat_map(|i| [indices[0], indices[i - 1], indices[i]]).collect()),
+        Mode::Points | Mode::Lines | Mode::LineLoop | Mode::LineStrip => None,
+    }
+}
+
+fn load_material(material: &gltf::Material, texture: &mut impl FnMut(gltf::Texture) -> EngineResult<Handle<Texture>>) -> EngineResult<Material> {
+    let pbr = material.pbr_metallic_roughness();
+    let mut load = |info: Option<gltf::Texture>| info.map(&mut *texture).transpose();
+
+    let mut converted = Material::new(material.name().unwrap_or_default());
+    converted.set_parameter(parameter::BASE_COLOR, MaterialValue::Color(Vec4::from(pbr.base_color_factor())));
+    converted.set_parameter(parameter::METALLIC, MaterialValue::Float(pbr.metallic_factor()));
+    converted.set_parameter(parameter::ROUGHNESS, MaterialValue::Float(pbr.roughness_factor()));
+    converted.set_parameter(parameter::NORMAL_SCALE, MaterialValue::Float(material.normal_texture().map_or(1.0, |info| info.scale())));
+    converted.set_parameter(parameter::OCCLUSION_STRENGTH, MaterialValue::Float(material.occlusion_texture().map_or(1.0, |info| info.strength())));
+    converted.set_parameter(parameter::EMISSIVE_COLOR, MaterialValue::Vec3(Vec3::from(material.emissive_factor())));
+    converted.set_texture(TextureSlot::BaseColor, load(pbr.base_color_texture().map(|info| info.texture()))?);
+    converted.set_texture(TextureSlot::MetallicRoughness, load(pbr.metallic_roughness_texture().map(|info| info.texture()))?);
+    converted.set_texture(TextureSlot::Normal, load(material.normal_texture().map(|info| info.texture()))?);
+    converted.set_texture(TextureSlot::Occlusion, load(material.occlusion_texture().map(|info| info.texture()))?);
+    converted.set_texture(TextureSlot::Emissive, load(material.emissive_texture().map(|info| info.texture()))?);
+
+    match material.alpha_mode() {
+        gltf::material::AlphaMode::Opaque => {}
+        gltf::material::AlphaMode::Mask => converted.set_parameter(parameter::ALPHA_CUTOFF, MaterialValue::Float(material.alpha_cutoff().unwrap_or(0.5))),
+        gltf::material::AlphaMode::Blend => converted.render_state = RenderState::transparent(),
+    }
+    // glTF faces are wound counter-clockwise, single sided ones are only seen from the front
+    if !material.double_sided() {
+        converted.render_state.cull = CullMode::Back;
+    }
+
+    Ok(converted)
+}
+
+// Returns None for point and line primitives, which meshes can't draw
+fn read_primitive(path: &str, primitive: &gltf::Primitive, buffers: &[buffer::Data], transform: Mat4, material: Handle<Material>, skin: Option<usize>) -> EngineResult<Option<PrimitiveData>> {
+    let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(|data| &data[..]));
+
+    let positions: Vec<Vec3> = reader.read_positions()
+        .ok_or_else(|| EngineError::InvalidGltf { path: path.to_owned(), reason: format!("primitive {} has no positions", primitive.index()) })?
+        .map(Vec3::from)
+        .collect();
+    let vertex_count = positions.len();
+
+    let normals = reader.read_normals().map(|normals| normals.map(Vec3::from).collect::<Vec<_>>());
+    let tangents = reader.read_tangents().map(|tangents| tangents.map(Vec4::from).collect::<Vec<_>>());
+    // glTF UVs start at the top left like image rows, so unlike OBJ they don't need flipping
+    let tex_coords: Vec<Vec2> = reader.read_tex_coords(0).map_or_else(|| vec![Vec2::zero(); vertex_count], |tex_coords| tex_coords.into_f32().map(Vec2::from).collect());
+    let tex_coords2 = reader.read_tex_coords(1).map(|tex_coords| tex_coords.into_f32().map(Vec2::from).collect::<Vec<_>>());
+    let colors = reader.read_colors(0).map(|colors| colors.into_rgba_f32().map(Vec4::from).collect::<Vec<_>>());
+
+    let normal_matrix = transform.inversed().transposed();
+    let mut vertices: Vec<Vertex> = (0..vertex_count).map(|i| {
+        let mut vertex = Vertex::new(transform.transform_point3(positions[i]), tex_coords[i]);
+        if let Some(normals) = &normals {
+            vertex = vertex.with_normal(normalized_or_zero(normal_matrix.transform_vec3(normals[i])));
+        }
+        if let Some(tangents) = &tangents {
+            let tangent = normalized_or_zero(transform.transform_vec3(tangents[i].xyz()));
+            vertex = vertex.with_tangent(Vec4::new(tangent.x, tangent.y, tangent.z, tangents[i].w));
+        }
+        if let Some(tex_coords2) = &tex_coords2 {
+            vertex = vertex.with_tex_coord2(tex_coords2[i]);
+        }
+        if let Some(colors) = &colors {
+            vertex = vertex.with_color(colors[i]);
+        }
+
+        vertex
+    }).collect();
+
+    let indices: Vec<u32> = reader.read_indices().map_or_else(|| (0..vertex_count as u32).collect(), |indices| indices.into_u32().collect());
+    let Some(mut indices) = triangle_list(primitive.mode(), indices) else {
+        return Ok(None);
+    };
+
+    // Mirroring transforms flip the winding
+    if transform.determinant() < 0.0 {
+        for triangle in indices.chunks_exact_mut(3) {
+            triangle.swap(1, 2);
+        }
+    }
+
+    let mut joint_weights: Option<Vec<JointWeights>> = match (reader.read_joints(0), reader.read_weights(0)) {
+        (Some(joints), Some(weights)) => Some(joints.into_u16().zip(weights.into_f32())
+            .map(|(joints, weights)| JointWeights { joints, weights: Vec4::from(weights) })
+            .collect()),
+        _ => None,
+    };
+
+    // The spec asks for flat normals when a primitive has none, that splits the vertices so per-vertex data follows them
+    if normals.is_none() {
+        let source_indices = indices.clone();
+        compute_normals(&mut vertices, &mut indices, NormalMode::Flat);
+        joint_weights = joint_weights.map(|weights| source_indices.iter().map(|index| weights[*index as usize]).collect());
+    }
+    if tangents.is_none() {
+        compute_tangents(&mut vertices, &indices);
+    }
+
+    let mut layout = VertexLayout::default();
+    if tex_coords2.is_some() {
+        layout = layout.with(VertexAttribute::TexCoord2);
+    }
+    if colors.is_some() {
+        layout = layout.with(VertexAttribute::Color);
+    }
+
+    let skin = skin.zip(joint_weights);
+
+    Ok(Some(PrimitiveData { vertices, indices, material, layout, skin }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_and_fans_become_triangle_lists() {
+        assert_eq!(triangle_list(Mode::TriangleStrip, vec![0, 1, 2, 3, 4]), Some(vec![0, 1, 2, 2, 1, 3, 2, 3, 4]));
+        assert_eq!(triangle_list(Mode::TriangleFan, vec![0, 1, 2, 3]), Some(vec![0, 1, 2, 0, 2, 3]));
+        assert_eq!(triangle_list(Mode::Lines, vec![0, 1]), None);
+    }
+
+    #[test]
+    fn reads_meshes_nodes_materials_and_skins() {
+        let data = read_file("res/models/skinned_triangle.gltf", |path| panic!("the fixture has no image files, asked for {path}")).unwrap();
+        let positions = |primitive: &PrimitiveData| primitive.vertices.iter().map(Vertex::position).collect::<Vec<Vec3>>();
+        assert_eq!(data.primitives.len(), 3);
+
+        // Unskinned meshes have the root's translation and the child's translation and scale baked in
+        let names: Vec<Option<&str>> = data.nodes.iter().map(|node| node.name.as_deref()).collect();
+        assert_eq!(names, vec![Some("root"), Some("child"), Some("skinned"), Some("joint_a"), Some("joint_b")]);
+        assert_eq!(data.nodes.iter().map(|node| node.parent).collect::<Vec<_>>(), vec![None, Some(0), Some(0), Some(0), Some(3)]);
+        assert_eq!(data.nodes[4].world_matrix.transform_point3(Vec3::zero()), Vec3::new(0.0, 3.0, 0.0));
+
+        let triangle = &data.primitives[data.nodes[1].meshes[0]];
+        assert_eq!(positions(triangle), vec![Vec3::new(1.0, 2.0, 0.0), Vec3::new(3.0, 2.0, 0.0), Vec3::new(1.0, 4.0, 0.0)]);
+        assert!(triangle.vertices.iter().all(|vertex| vertex.normal() == Vec3::unit_z()));
+        assert_eq!(triangle.indices, vec![0, 1, 2]);
+        assert!(triangle.skin.is_none());
+
+        let red = triangle.material.get();
+        assert_eq!(red.name, "red");
+        assert_eq!(red.parameter(parameter::BASE_COLOR), Some(MaterialValue::Color(Vec4::new(1.0, 0.0, 0.0, 1.0))));
+        assert_eq!(red.parameter(parameter::METALLIC), Some(MaterialValue::Float(0.0)));
+        assert_eq!(red.parameter(parameter::ROUGHNESS), Some(MaterialValue::Float(0.5)));
+        assert_eq!(red.parameter(parameter::ALPHA_CUTOFF), Some(MaterialValue::Float(0.25)));
+        assert_eq!(red.render_state, RenderState { cull: CullMode::Back, ..RenderState::default() });
+
+        // Skinned vertices stay in model space, the joint weights follow them
+        let [skinned, bare] = [0, 1].map(|i| &data.primitives[data.nodes[2].meshes[i]]);
+        assert_eq!(positions(skinned), vec![Vec3::zero(), Vec3::unit_x(), Vec3::unit_y()]);
+        let (skin, joint_weights) = skinned.skin.as_ref().unwrap();
+        assert_eq!(*skin, 0);
+        assert_eq!(joint_weights[1], JointWeights { joints: [0, 1, 0, 0], weights: Vec4::new(0.5, 0.5, 0.0, 0.0) });
+
+        let glass = skinned.material.get();
+        assert_eq!(glass.name, "glass");
+        assert_eq!(glass.render_state, RenderState::transparent());
+
+        // Without a material or normals the primitive gets the default material and flat normals
+        assert!(bare.material.get().name.is_empty() && bare.skin.is_none());
+        assert!(bare.vertices.iter().all(|vertex| vertex.normal() == Vec3::unit_z()));
+
+        let rig = &data.skins[0];
+        assert_eq!((rig.name.as_deref(), rig.joints.as_slice(), rig.skeleton), (Some("rig"), &[3, 4][..], Some(3)));
+        for (joint, inverse_bind_matrix) in rig.joints.iter().zip(&rig.inverse_bind_matrices) {
+            assert_eq!((*inverse_bind_matrix * data.nodes[*joint].world_matrix).transform_point3(Vec3::zero()), Vec3::zero());
+        }
+    }
+
+    #[test]
+    fn gltf_files_are_recognised_by_extension() {
+        assert!(is_gltf_path("res/models/helmet.gltf"));
+        assert!(is_gltf_path("res/models/helmet.glb"));
+        assert!(!is_gltf_path("res/models/container.obj"));
+    }
+}
@@ -0,0 +1,178 @@
+use ultraviolet::Vec3;
+
+use crate::assets::AssetManager;
+use crate::error::EngineResult;
+use crate::graphics::post_processing::POST_VERTEX_SHADER;
+use crate::graphics::skybox::Skybox;
+use crate::math::cube_map;
+use crate::opengl;
+use crate::opengl::{Capability, Primitive};
+use crate::opengl::framebuffer::{Attachment, Framebuffer};
+use crate::opengl::texture::{MagFilterParam, MinFilterParam, Texture, TextureFormat, TextureType, WrapCoordinate, WrapParam};
+use crate::opengl::vertex_array_object::VertexArrayObject;
+use crate::shader::Shader;
+
+pub const CAPTURE_VERTEX_SHADER: &str = "res/shaders/ibl_capture.vs";
+pub const IRRADIANCE_FRAGMENT_SHADER: &str = "res/shaders/ibl_irradiance.fs";
+pub const PREFILTER_FRAGMENT_SHADER: &str = "res/shaders/ibl_prefilter.fs";
+pub const BRDF_FRAGMENT_SHADER: &str = "res/shaders/ibl_brdf.fs";
+
+const IRRADIANCE_SIZE: u32 = 32;
+const PREFILTERED_SIZE: u32 = 128;
+const PREFILTERED_MIP_LEVELS: u32 = 5;
+const BRDF_LUT_SIZE: u32 = 512;
+
+// After the material and shadow units
+const IRRADIANCE_UNIT: usize = 11;
+const PREFILTERED_UNIT: usize = 12;
+const BRDF_LUT_UNIT: usize = 13;
+
+// Ambient light from the sky, baked once from the skybox: diffuse irradiance, specular reflections blurred
+// per roughness in the mip levels, and the split-sum BRDF scale and bias
+pub struct EnvironmentLighting {
+    irradiance: Texture,
+    prefiltered: Texture,
+    brdf_lut: Texture,
+    pub intensity: f32,
+}
+
+impl EnvironmentLighting {
+    // Leaves the framebuffer, viewport and depth test as they were
+    pub fn from_skybox(skybox: &Skybox, assets: &mut AssetManager) -> EngineResult<Self> {
+        let irradiance_shader = assets.load_shader(CAPTURE_VERTEX_SHADER, IRRADIANCE_FRAGMENT_SHADER)?;
+        let prefilter_shader = assets.load_shader(CAPTURE_VERTEX_SHADER, PREFILTER_FRAGMENT_SHADER)?;
+        let brdf_shader = assets.load_shader(POST_VERTEX_SHADER, BRDF_FRAGMENT_SHADER)?;
+
+        let irradiance = new_environment_cube(IRRADIANCE_SIZE, false)?;
+        let prefiltered = new_environment_cube(PREFILTERED_SIZE, true)?;
+        let brdf_lut = Texture::new(TextureType::Texture2d)?;
+        brdf_lut.load_empty(BRDF_LUT_SIZE, BRDF_LUT_SIZE, TextureFormat::Rg16F);
+        brdf_lut.set_min_filter(MinFilterParam::Linear);
+        brdf_lut.set_mag_filter(MagFilterParam::Linear);
+        brdf_lut.set_wrap(WrapCoordinate::S, WrapParam::ClampToEdge);
+        brdf_lut.set_wrap(WrapCoordinate::T, WrapParam::ClampToEdge);
+
+        let (previous_framebuffer, viewport) = (Framebuffer::current_binding(), opengl::get_viewport());
+        let framebuffer = Framebuffer::new()?;
+        framebuffer.bind();
+        Framebuffer::set_draw_buffers(1);
+        opengl::disable(Capability::DepthTest);
+
+        let result = (|| -> EngineResult<()> {
+            let irradiance_shader = irradiance_shader.get();
+            irradiance_shader.bind();
+            bind_environment(&irradiance_shader, skybox);
+            render_cube(&irradiance_shader, skybox, &irradiance, IRRADIANCE_SIZE, 0)?;
+
+            let prefilter_shader = prefilter_shader.get();
+            prefilter_shader.bind();
+            bind_environment(&prefilter_shader, skybox);
+            for mip_level in 0..PREFILTERED_MIP_LEVELS {
+                prefilter_shader.set_float("roughness", prefilter_roughness(mip_level, PREFILTERED_MIP_LEVELS));
+                render_cube(&prefilter_shader, skybox, &prefiltered, PREFILTERED_SIZE >> mip_level, mip_level)?;
+            }
+
+            let brdf_shader = brdf_shader.get();
+            brdf_shader.bind();
+            Framebuffer::attach_texture(Attachment::Color(0), &brdf_lut, TextureType::Texture2d);
+            Framebuffer::check_complete()?;
+            opengl::viewport(0, 0, BRDF_LUT_SIZE as i32, BRDF_LUT_SIZE as i32);
+            let vao = VertexArrayObject::new()?;
+            vao.bind();
+            opengl::draw_arrays(Primitive::Triangles, 0, 3);
+            VertexArrayObject::unbind();
+
+            Ok(())
+        })();
+
+        opengl::enable(Capability::DepthTest);
+        Framebuffer::restore_binding(previous_framebuffer);
+        opengl::viewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+        result?;
+
+        Ok(Self { irradiance, prefiltered, brdf_lut, intensity: 1.0 })
+    }
+
+    pub fn bind(&self, shader_program: &Shader) {
+        Self::bind_samplers(shader_program);
+        shader_program.set_bool("hasEnvironment", true);
+        shader_program.set_float("environmentIntensity", self.intensity);
+        shader_program.set_float("prefilteredMaxLevel", (PREFILTERED_MIP_LEVELS - 1) as f32);
+
+        for (unit, texture) in [(IRRADIANCE_UNIT, &self.irradiance), (PREFILTERED_UNIT, &self.prefiltered), (BRDF_LUT_UNIT, &self.brdf_lut)] {
+            Texture::set_active_texture(unit);
+            texture.bind();
+        }
+        Texture::set_active_texture(0);
+    }
+
+    // Scenes without a sky fall back to the flat ambient light
+    pub fn bind_disabled(shader_program: &Shader) {
+        Self::bind_samplers(shader_program);
+        shader_program.set_bool("hasEnvironment", false);
+    }
+
+    fn bind_samplers(shader_program: &Shader) {
+        shader_program.set_int("irradianceMap", IRRADIANCE_UNIT as i64);
+        shader_program.set_int("prefilteredMap", PREFILTERED_UNIT as i64);
+        shader_program.set_int("brdfLut", BRDF_LUT_UNIT as i64);
+    }
+}
+
+// Mip level `n` of the prefiltered map holds reflections for this roughness
+pub fn prefilter_roughness(mip_level: u32, mip_levels: u32) -> f32 {
+    if mip_levels <= 1 {
+        return 0.0;
+    }
+
+    mip_level as f32 / (mip_levels - 1) as f32
+}
+
+fn new_environment_cube(size: u32, mipmapped: bool) -> EngineResult<Texture> {
+    let texture = Texture::new(TextureType::CubeMap)?;
+    texture.load_empty_cube(size, TextureFormat::Rgba16F);
+    texture.set_min_filter(if mipmapped { MinFilterParam::LinearMipmapLinear } else { MinFilterParam::Linear });
+    texture.set_mag_filter(MagFilterParam::Linear);
+    texture.set_wrap(WrapCoordinate::S, WrapParam::ClampToEdge);
+    texture.set_wrap(WrapCoordinate::T, WrapParam::ClampToEdge);
+    texture.set_wrap(WrapCoordinate::R, WrapParam::ClampToEdge);
+    if mipmapped {
+        texture.generate_mipmaps();
+    }
+
+    Ok(texture)
+}
+
+fn bind_environment(shader_program: &Shader, skybox: &Skybox) {
+    Texture::set_active_texture(0);
+    skybox.texture().bind();
+    shader_program.set_int("environmentMap", 0);
+}
+
+fn render_cube(shader_program: &Shader, skybox: &Skybox, target: &Texture, size: u32, mip_level: u32) -> EngineResult<()> {
+    opengl::viewport(0, 0, size as i32, size as i32);
+
+    for (face, view_projection) in cube_map::face_view_projections(Vec3::zero(), 0.1, 10.0).into_iter().enumerate() {
+        Framebuffer::attach_cube_face(Attachment::Color(0), target, face as u32, mip_level);
+        if face == 0 {
+            Framebuffer::check_complete()?;
+        }
+
+        shader_program.set_mat4("viewProjection", view_projection);
+        skybox.draw_cube();
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mip_levels_span_the_roughness_range() {
+        let roughness: Vec<f32> = (0..PREFILTERED_MIP_LEVELS).map(|mip_level| prefilter_roughness(mip_level, PREFILTERED_MIP_LEVELS)).collect();
+        assert_eq!(roughness, vec![0.0, 0.25, 0.5, 0.75, 1.0]);
+        assert_eq!(prefilter_roughness(0, 1), 0.0);
+    }
+}
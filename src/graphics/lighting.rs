@@ -0,0 +1,181 @@
+use std::mem::size_of;
+
+use ultraviolet::Vec3;
+
+use crate::ecs::components::{Light, LightKind, Transform};
+use crate::ecs::world::World;
+use crate::error::EngineResult;
+use crate::opengl::uniform_buffer_object::UniformBufferObject;
+use crate::opengl::vertex_buffer_object::BufferUsage;
+use crate::shader::Shader;
+
+// Must match MAX_LIGHTS and the Lights block in the shaders
+pub const MAX_LIGHTS: usize = 16;
+pub const LIGHTS_BLOCK: &str = "Lights";
+pub const LIGHTS_BINDING: u32 = 0;
+
+const DIRECTIONAL: f32 = 0.0;
+const POINT: f32 = 1.0;
+const SPOT: f32 = 2.0;
+
+const NO_SHADOW: f32 = -1.0;
+
+// std140 layout, everything is a vec4 so no padding rules come into play
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+struct LightData {
+    position: [f32; 4], // w is the light type
+    direction: [f32; 4], // w is the range
+    color: [f32; 4], // Premultiplied by the intensity
+    cone: [f32; 4], // Cosines of the inner and outer angles, then the shadow slot
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LightBlock {
+    ambient: [f32; 4],
+    camera_position: [f32; 4],
+    light_count: [i32; 4],
+    lights: [LightData; MAX_LIGHTS],
+}
+
+impl LightBlock {
+    // Lights come from entities with a Light component, a Transform places point and spot lights and turns directions.
+    // Past MAX_LIGHTS directional lights are kept first, then the lights closest to the camera
+    pub fn collect(world: &World, ambient: Vec3, camera_position: Vec3) -> Self {
+        let mut lights: Vec<(f32, LightData)> = Vec::new();
+        world.query::<Light>(|entity, light| {
+            let transform = world.get::<Transform>(entity);
+            let position = transform.as_ref().map_or(Vec3::zero(), |transform| transform.node3d.world_position);
+            let turn = |direction: Vec3| transform.as_ref().map_or(direction, |transform| transform.node3d.rotation * direction).normalized();
+            let color = light.color * light.intensity;
+
+            let (kind, direction, range, cone) = match light.kind {
+                LightKind::Directional { direction } => (DIRECTIONAL, turn(direction), 0.0, [0.0, 0.0, NO_SHADOW, 0.0]),
+                LightKind::Point { range } => (POINT, Vec3::zero(), range, [0.0, 0.0, NO_SHADOW, 0.0]),
+                LightKind::Spot { direction, range, inner_angle, outer_angle } => (SPOT, turn(direction), range, [inner_angle.cos(), outer_angle.cos(), NO_SHADOW, 0.0]),
+            };
+
+            let priority = if kind == DIRECTIONAL { -1.0 } else { (position - camera_position).mag_sq() };
+            lights.push((priority, LightData {
+                position: [position.x, position.y, position.z, kind],
+                direction: [direction.x, direction.y, direction.z, range],
+                color: [color.x, color.y, color.z, 0.0],
+                cone,
+            }));
+        });
+        lights.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut block = Self {
+            ambient: [ambient.x, ambient.y, ambient.z, 0.0],
+            camera_position: [camera_position.x, camera_position.y, camera_position.z, 1.0],
+            light_count: [lights.len().min(MAX_LIGHTS) as i32, 0, 0, 0],
+            lights: [LightData::default(); MAX_LIGHTS],
+        };
+        for (slot, (_, light)) in block.lights.iter_mut().zip(lights) {
+            *slot = light;
+        }
+
+        block
+    }
+
+    pub fn light_count(&self) -> usize {
+        self.light_count[0] as usize
+    }
+
+    // The first directional light gets the cascades and the nearest point lights get the cube maps, in slot order
+    pub fn assign_shadow_slots(&mut self, max_point_shadows: usize) -> ShadowedLights {
+        let mut shadowed = ShadowedLights { sun: None, points: Vec::new() };
+        for light in self.lights.iter_mut().take(self.light_count[0] as usize) {
+            let kind = light.position[3];
+            if kind == DIRECTIONAL && shadowed.sun.is_none() {
+                shadowed.sun = Some(Vec3::new(light.direction[0], light.direction[1], light.direction[2]));
+                light.cone[2] = 0.0;
+            } else if kind == POINT && shadowed.points.len() < max_point_shadows {
+                light.cone[2] = shadowed.points.len() as f32;
+                shadowed.points.push((Vec3::new(light.position[0], light.position[1], light.position[2]), light.direction[3]));
+            }
+        }
+
+        shadowed
+    }
+}
+
+pub struct ShadowedLights {
+    pub sun: Option<Vec3>, // Direction
+    pub points: Vec<(Vec3, f32)>, // Position and range
+}
+
+pub struct LightBuffer {
+    ubo: UniformBufferObject,
+}
+
+impl LightBuffer {
+    pub fn new() -> EngineResult<Self> {
+        let ubo = UniformBufferObject::new()?;
+        ubo.bind();
+        UniformBufferObject::load_data(size_of::<LightBlock>(), std::ptr::null::<LightBlock>(), BufferUsage::DynamicDraw);
+        ubo.bind_base(LIGHTS_BINDING);
+        UniformBufferObject::unbind();
+
+        Ok(Self { ubo })
+    }
+
+    pub fn upload(&self, block: &LightBlock) {
+        self.ubo.bind();
+        UniformBufferObject::load_sub_data(0, size_of::<LightBlock>(), block as *const LightBlock);
+        UniformBufferObject::unbind();
+    }
+
+    // Shaders are bound to the block every frame so hot reloaded programs pick it up
+    pub fn bind_to(&self, shader_program: &Shader) {
+        shader_program.bind_uniform_block(LIGHTS_BLOCK, LIGHTS_BINDING);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::graphics::node_3d::Node3D;
+    use crate::math::rotation::Rotation;
+
+    use super::*;
+
+    fn light_at(world: &mut World, position: Vec3, kind: LightKind) {
+        let entity = world.spawn();
+        world.insert(entity, Light::new(kind, Vec3::one(), 2.0));
+        world.insert(entity, Transform::new(Node3D { world_position: position, scale: Vec3::one(), rotation: Rotation::from_rotation_y(std::f32::consts::FRAC_PI_2) }));
+    }
+
+    #[test]
+    fn directional_and_nearest_lights_are_kept() {
+        let mut world = World::new();
+        for i in 0..MAX_LIGHTS {
+            light_at(&mut world, Vec3::new(10.0 + i as f32, 0.0, 0.0), LightKind::Point { range: 5.0 });
+        }
+        light_at(&mut world, Vec3::new(1.0, 0.0, 0.0), LightKind::Spot { direction: -Vec3::unit_z(), range: 5.0, inner_angle: 0.0, outer_angle: std::f32::consts::FRAC_PI_3 });
+        let sun = world.spawn();
+        world.insert(sun, Light::new(LightKind::Directional { direction: Vec3::new(0.0, -2.0, 0.0) }, Vec3::one(), 1.0));
+
+        let block = LightBlock::collect(&world, Vec3::broadcast(0.1), Vec3::zero());
+
+        assert_eq!(block.light_count(), MAX_LIGHTS);
+        assert_eq!(block.lights[0].position[3], DIRECTIONAL);
+        assert_eq!(block.lights[0].direction[..3], [0.0, -1.0, 0.0]);
+
+        // The spot light is the nearest, its direction follows the transform's rotation
+        let spot = block.lights[1];
+        assert_eq!(spot.position, [1.0, 0.0, 0.0, SPOT]);
+        assert!((Vec3::new(spot.direction[0], spot.direction[1], spot.direction[2]) + Vec3::unit_x()).mag() < 1e-5);
+        assert!((spot.cone[1] - 0.5).abs() < 1e-5);
+        assert_eq!(spot.color[..3], [2.0, 2.0, 2.0]);
+
+        // The farthest point light didn't fit
+        assert_eq!(block.lights[MAX_LIGHTS - 1].position[0], 10.0 + (MAX_LIGHTS - 3) as f32);
+
+        let mut block = block;
+        let shadowed = block.assign_shadow_slots(2);
+        assert_eq!(shadowed.sun, Some(Vec3::new(0.0, -1.0, 0.0)));
+        assert_eq!(shadowed.points, vec![(Vec3::new(10.0, 0.0, 0.0), 5.0), (Vec3::new(11.0, 0.0, 0.0), 5.0)]);
+        assert_eq!([block.lights[0].cone[2], block.lights[1].cone[2], block.lights[2].cone[2], block.lights[3].cone[2], block.lights[4].cone[2]], [0.0, NO_SHADOW, 0.0, 1.0, NO_SHADOW]);
+    }
+}
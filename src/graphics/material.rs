@@ -0,0 +1,261 @@
+use std::collections::BTreeMap;
+
+use ultraviolet::{Vec2, Vec3, Vec4};
+
+use crate::assets::handle::Handle;
+use crate::opengl;
+use crate::opengl::{BlendFactor, Capability, Face};
+use crate::opengl::texture::Texture;
+use crate::shader::Shader;
+
+// Every slot has its own texture unit and sampler name, shaders declare the slots they read
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TextureSlot {
+    BaseColor,
+    MetallicRoughness, // Roughness in G, metallic in B
+    Normal,
+    Occlusion,
+    Emissive,
+    Specular, // RGB multiplies the specular colour
+}
+
+impl TextureSlot {
+    pub const ALL: [TextureSlot; 6] = [TextureSlot::BaseColor, TextureSlot::MetallicRoughness, TextureSlot::Normal, TextureSlot::Occlusion, TextureSlot::Emissive, TextureSlot::Specular];
+
+    pub fn unit(self) -> usize {
+        self as usize
+    }
+
+    pub fn sampler_name(self) -> &'static str {
+        match self {
+            TextureSlot::BaseColor => "baseColorTexture",
+            TextureSlot::MetallicRoughness => "metallicRoughnessTexture",
+            TextureSlot::Normal => "normalTexture",
+            TextureSlot::Occlusion => "occlusionTexture",
+            TextureSlot::Emissive => "emissiveTexture",
+            TextureSlot::Specular => "specularTexture",
+        }
+    }
+
+    // Bool uniform telling the shader whether the slot has a texture
+    pub fn flag_name(self) -> &'static str {
+        match self {
+            TextureSlot::BaseColor => "hasBaseColorTexture",
+            TextureSlot::MetallicRoughness => "hasMetallicRoughnessTexture",
+            TextureSlot::Normal => "hasNormalTexture",
+            TextureSlot::Occlusion => "hasOcclusionTexture",
+            TextureSlot::Emissive => "hasEmissiveTexture",
+            TextureSlot::Specular => "hasSpecularTexture",
+        }
+    }
+}
+
+// Uniform names of the parameters default.fs reads
+pub mod parameter {
+    pub const BASE_COLOR: &str = "baseColor"; // Linear RGBA, multiplied with the base colour texture
+    pub const METALLIC: &str = "metallic";
+    pub const ROUGHNESS: &str = "roughness";
+    pub const NORMAL_SCALE: &str = "normalScale";
+    pub const OCCLUSION_STRENGTH: &str = "occlusionStrength";
+    pub const EMISSIVE_COLOR: &str = "emissiveColor";
+    pub const SPECULAR_COLOR: &str = "specularColor"; // Linear RGB, scales the 4% reflectance of dielectrics (F0)
+    pub const AMBIENT_COLOR: &str = "ambientColor"; // Linear RGB, scales the ambient and environment light
+    pub const ALPHA_CUTOFF: &str = "alphaCutoff"; // Fragments with a lower alpha are discarded
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MaterialValue {
+    Bool(bool),
+    Int(i64),
+    Float(f32),
+    Vec2(Vec2),
+    Vec3(Vec3),
+    Vec4(Vec4),
+    Color(Vec4), // Linear RGBA, uploaded as a vec4
+}
+
+impl MaterialValue {
+    fn upload(self, shader_program: &Shader, name: &str) {
+        match self {
+            MaterialValue::Bool(value) => shader_program.set_bool(name, value),
+            MaterialValue::Int(value) => shader_program.set_int(name, value),
+            MaterialValue::Float(value) => shader_program.set_float(name, value),
+            MaterialValue::Vec2(value) => shader_program.set_vec2(name, value.x, value.y),
+            MaterialValue::Vec3(value) => shader_program.set_vec3(name, value.x, value.y, value.z),
+            MaterialValue::Vec4(value) | MaterialValue::Color(value) => shader_program.set_vec4(name, value.x, value.y, value.z, value.w),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    Opaque,
+    Alpha,
+    Additive,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum CullMode {
+    None,
+    Back,
+    Front,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RenderState {
+    pub blend: BlendMode, // Anything but Opaque is drawn after opaque meshes, back to front
+    pub cull: CullMode,
+    pub depth_write: bool,
+}
+
+impl Default for RenderState {
+    fn default() -> Self {
+        Self { blend: BlendMode::Opaque, cull: CullMode::None, depth_write: true }
+    }
+}
+
+impl RenderState {
+    // Alpha blended and not hiding what is drawn behind it later
+    pub fn transparent() -> Self {
+        Self { blend: BlendMode::Alpha, depth_write: false, ..Self::default() }
+    }
+
+    pub fn is_transparent(&self) -> bool {
+        self.blend != BlendMode::Opaque
+    }
+
+    pub fn apply(&self) {
+        match self.blend {
+            BlendMode::Opaque => opengl::disable(Capability::Blending),
+            BlendMode::Alpha => {
+                opengl::enable(Capability::Blending);
+                opengl::blend_func(BlendFactor::SrcAlpha, BlendFactor::OneMinusSrcAlpha);
+            }
+            BlendMode::Additive => {
+                opengl::enable(Capability::Blending);
+                opengl::blend_func(BlendFactor::SrcAlpha, BlendFactor::One);
+            }
+        }
+
+        match self.cull {
+            CullMode::None => opengl::disable(Capability::FaceCulling),
+            CullMode::Back | CullMode::Front => {
+                opengl::enable(Capability::FaceCulling);
+                opengl::cull_face(if self.cull == CullMode::Back { Face::Back } else { Face::Front });
+            }
+        }
+
+        opengl::depth_mask(self.depth_write);
+    }
+}
+
+// A shader with the values it is drawn with, meshes share one through a Handle.
+// Loaded models share materials between their own meshes only, every model file gets its own copies
+#[derive(Clone)]
+pub struct Material {
+    pub name: String,
+    pub shader: Option<Handle<Shader>>, // None draws with the shader the pass was given
+    pub render_state: RenderState,
+    textures: [Option<Handle<Texture>>; TextureSlot::ALL.len()],
+    parameters: BTreeMap<String, MaterialValue>, // Uniform name to value
+}
+
+impl Default for Material {
+    // The glTF default material. Uniforms keep their values between draws, so every parameter default.fs reads is set here
+    fn default() -> Self {
+        let mut material = Self {
+            name: String::new(),
+            shader: None,
+            render_state: RenderState::default(),
+            textures: Default::default(),
+            parameters: BTreeMap::new(),
+        };
+
+        material.set_parameter(parameter::BASE_COLOR, MaterialValue::Color(Vec4::one()));
+        material.set_parameter(parameter::METALLIC, MaterialValue::Float(1.0));
+        material.set_parameter(parameter::ROUGHNESS, MaterialValue::Float(1.0));
+        material.set_parameter(parameter::NORMAL_SCALE, MaterialValue::Float(1.0));
+        material.set_parameter(parameter::OCCLUSION_STRENGTH, MaterialValue::Float(1.0));
+        material.set_parameter(parameter::EMISSIVE_COLOR, MaterialValue::Vec3(Vec3::zero()));
+        material.set_parameter(parameter::SPECULAR_COLOR, MaterialValue::Vec3(Vec3::one()));
+        material.set_parameter(parameter::AMBIENT_COLOR, MaterialValue::Vec3(Vec3::one()));
+        material.set_parameter(parameter::ALPHA_CUTOFF, MaterialValue::Float(0.0));
+
+        material
+    }
+}
+
+impl Material {
+    pub fn new(name: &str) -> Self {
+        Self { name: name.to_owned(), ..Self::default() }
+    }
+
+    pub fn with_shader(name: &str, shader: Handle<Shader>) -> Self {
+        Self { shader: Some(shader), ..Self::new(name) }
+    }
+
+    pub fn texture(&self, slot: TextureSlot) -> Option<&Handle<Texture>> {
+        self.textures[slot.unit()].as_ref()
+    }
+
+    pub fn set_texture(&mut self, slot: TextureSlot, texture: Option<Handle<Texture>>) {
+        self.textures[slot.unit()] = texture;
+    }
+
+    pub fn parameter(&self, name: &str) -> Option<MaterialValue> {
+        self.parameters.get(name).copied()
+    }
+
+    pub fn set_parameter(&mut self, name: &str, value: MaterialValue) {
+        self.parameters.insert(name.to_owned(), value);
+    }
+
+    pub fn parameters(&self) -> impl Iterator<Item=(&str, MaterialValue)> {
+        self.parameters.iter().map(|(name, value)| (name.as_str(), *value))
+    }
+
+    // Uploads the parameters and binds the textures to their slots' units, render state is applied separately
+    pub fn bind(&self, shader_program: &Shader) {
+        for (name, value) in &self.parameters {
+            value.upload(shader_program, name);
+        }
+
+        for slot in TextureSlot::ALL {
+            let texture = self.texture(slot);
+            shader_program.set_bool(slot.flag_name(), texture.is_some());
+            if let Some(texture) = texture {
+                Texture::set_active_texture(slot.unit());
+                shader_program.set_int(slot.sampler_name(), slot.unit() as i64);
+                texture.get().bind();
+            }
+        }
+        Texture::set_active_texture(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn texture_slots_have_their_own_units_and_names() {
+        for (i, slot) in TextureSlot::ALL.into_iter().enumerate() {
+            assert_eq!(slot.unit(), i);
+            assert!(TextureSlot::ALL[..i].iter().all(|other| other.sampler_name() != slot.sampler_name() && other.flag_name() != slot.flag_name()));
+        }
+    }
+
+    #[test]
+    fn parameters_override_the_defaults() {
+        let mut material = Material::default();
+        assert_eq!(material.parameter(parameter::ROUGHNESS), Some(MaterialValue::Float(1.0)));
+        assert_eq!(material.parameter("glow"), None);
+
+        material.set_parameter(parameter::ROUGHNESS, MaterialValue::Float(0.3));
+        material.set_parameter("glow", MaterialValue::Color(Vec4::new(1.0, 0.5, 0.0, 1.0)));
+        assert_eq!(material.parameter(parameter::ROUGHNESS), Some(MaterialValue::Float(0.3)));
+        assert_eq!(material.parameters().filter(|(name, _)| *name == parameter::ROUGHNESS).count(), 1);
+        assert!(material.texture(TextureSlot::Normal).is_none());
+        assert!(!material.render_state.is_transparent() && RenderState::transparent().is_transparent());
+    }
+}
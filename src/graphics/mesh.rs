@@ -1,11 +1,18 @@
 use std::mem::size_of;
 
-use crate::graphics::vertex::Vertex;
+use ultraviolet::Vec3;
+
+use crate::assets::handle::Handle;
+use crate::error::EngineResult;
+use crate::graphics::material::Material;
+use crate::graphics::skin::JointWeights;
+use crate::graphics::vertex::{Vertex, VertexAttribute, VertexLayout};
+use crate::math::aabb_bouding_box::AABBBoundingBox;
+use crate::math::bounding_sphere::BoundingSphere;
 use crate::opengl::draw_elements;
 use crate::opengl::element_buffer_object::ElementBufferObject;
 use crate::opengl::ElementType::UnsignedInt;
 use crate::opengl::Primitive::Triangles;
-use crate::opengl::texture::Texture;
 use crate::opengl::vertex_array_object::VertexArrayObject;
 use crate::opengl::vertex_array_object::VertexAttribType::Float;
 use crate::opengl::vertex_buffer_object::{BufferUsage, VertexBufferObject};
@@ -14,56 +21,127 @@ use crate::shader::Shader;
 pub struct Mesh {
     vertices: Vec<Vertex>,
     indices: Vec<u32>,
-    textures: Vec<Texture>,
+    layout: VertexLayout,
+    material: Handle<Material>, // Shared with the other meshes that use it
+    skin: Option<usize>, // Index into the model's skins
+    joint_weights: Vec<JointWeights>, // One per vertex when the mesh is skinned, kept on the CPU only
+    bounding_box: AABBBoundingBox, // Local space
+    bounding_sphere: BoundingSphere,
     vao: VertexArrayObject,
     vbo: VertexBufferObject,
     ebo: ElementBufferObject,
 }
 
 impl Mesh {
-    pub fn new(vertices: Vec<Vertex>, indices: Vec<u32>, textures: Vec<Texture>) -> Self {
-        let mut mesh = Self {
+    pub fn new(vertices: Vec<Vertex>, indices: Vec<u32>, material: Handle<Material>) -> EngineResult<Self> {
+        Self::with_layout(vertices, indices, material, VertexLayout::default())
+    }
+
+    pub fn with_layout(vertices: Vec<Vertex>, indices: Vec<u32>, material: Handle<Material>, layout: VertexLayout) -> EngineResult<Self> {
+        let positions: Vec<Vec3> = vertices.iter().map(|vertex| vertex.position()).collect();
+        let bounding_box = AABBBoundingBox::from_points(&positions);
+        let bounding_sphere = BoundingSphere::from_points(&positions);
+
+        let mesh = Self {
             vertices,
             indices,
-            textures,
-            vao: VertexArrayObject::new().unwrap(),
-            vbo: VertexBufferObject::new().unwrap(),
-            ebo: ElementBufferObject::new().unwrap(),
+            layout,
+            material,
+            skin: None,
+            joint_weights: Vec::new(),
+            bounding_box,
+            bounding_sphere,
+            vao: VertexArrayObject::new()?,
+            vbo: VertexBufferObject::new()?,
+            ebo: ElementBufferObject::new()?,
         };
 
         mesh.setup();
 
-        mesh
+        Ok(mesh)
     }
 
-    pub fn draw(&self, shader: &Shader) {
-        for (i, texture) in self.textures.iter().enumerate() {
-            Texture::set_active_texture(i);
-            // TODO: Handle different texture types
+    pub fn vertices(&self) -> &[Vertex] {
+        &self.vertices
+    }
 
-            shader.set_int(format!("texture{}", i + 1).to_owned().as_str(), i.try_into().unwrap());
-            texture.bind();
-        }
+    pub fn indices(&self) -> &[u32] {
+        &self.indices
+    }
 
+    pub fn layout(&self) -> &VertexLayout {
+        &self.layout
+    }
+
+    pub fn material(&self) -> &Handle<Material> {
+        &self.material
+    }
+
+    pub fn set_material(&mut self, material: Handle<Material>) {
+        self.material = material;
+    }
+
+    pub fn skin(&self) -> Option<usize> {
+        self.skin
+    }
+
+    pub fn joint_weights(&self) -> &[JointWeights] {
+        &self.joint_weights
+    }
+
+    pub fn set_skin(&mut self, skin: usize, joint_weights: Vec<JointWeights>) {
+        self.skin = Some(skin);
+        self.joint_weights = joint_weights;
+    }
+
+    pub fn bounding_box(&self) -> AABBBoundingBox {
+        self.bounding_box
+    }
+
+    pub fn bounding_sphere(&self) -> BoundingSphere {
+        self.bounding_sphere
+    }
+
+    pub fn triangles(&self) -> impl Iterator<Item=[Vec3; 3]> + '_ {
+        self.indices.chunks_exact(3).map(|triangle| {
+            [
+                self.vertices[triangle[0] as usize].position(),
+                self.vertices[triangle[1] as usize].position(),
+                self.vertices[triangle[2] as usize].position(),
+            ]
+        })
+    }
+
+    pub fn draw(&self, shader: &Shader) {
+        self.material.get().bind(shader);
+        self.draw_geometry();
+    }
+
+    // With whatever material is bound, lets the render queue skip binding the same one again
+    pub fn draw_geometry(&self) {
         self.vao.bind();
 
+        // The constant is context state rather than part of the VAO, so it is set for every mesh without colours
+        if !self.layout.contains(VertexAttribute::Color) {
+            VertexArrayObject::set_constant_attribute(VertexAttribute::Color.location(), [1.0, 1.0, 1.0, 1.0]);
+        }
+
         draw_elements(Triangles, self.indices.len(), UnsignedInt);
 
         VertexArrayObject::unbind();
-        Texture::set_active_texture(0);
     }
 
     fn setup(&self) {
         self.vao.bind();
         self.vbo.bind();
 
-        VertexBufferObject::load_data(self.vertices.len() * size_of::<Vertex>(), self.vertices.as_ptr(), BufferUsage::StaticDraw);
+        let data = self.layout.interleave(&self.vertices);
+        VertexBufferObject::load_data(data.len() * size_of::<f32>(), data.as_ptr(), BufferUsage::StaticDraw);
         self.ebo.bind();
         ElementBufferObject::load_data(self.indices.len() * size_of::<u32>(), self.indices.as_ptr());
 
-        VertexArrayObject::set_vertex_attribute(0, 3, Float, false, size_of::<Vertex>(), 0);
-        VertexArrayObject::set_vertex_attribute(1, 3, Float, false, size_of::<Vertex>(), size_of::<[f32; 3]>());
-
-        // TODO: Other attributes when appliable
+        for (attribute, offset) in self.layout.offsets() {
+            VertexArrayObject::set_vertex_attribute(attribute.location(), attribute.component_count(), Float, false, self.layout.stride(), offset);
+        }
     }
 }
\ No newline at end of file
@@ -1,9 +1,21 @@
+pub mod gltf_model;
+pub mod ibl;
+pub mod lighting;
+pub mod material;
 pub mod mesh;
 pub mod vertex;
+pub mod vertex_processing;
 pub mod model;
 pub mod scene;
+pub mod scene_description;
+pub mod scene_graph;
+pub mod shadows;
 pub mod static_body_3d;
+pub mod rigid_body_3d;
 pub mod node_3d;
 pub mod true_type_font;
+pub mod skin;
 pub mod skybox;
 pub mod player_character;
+pub mod post_processing;
+pub mod render_queue;
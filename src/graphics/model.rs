@@ -3,95 +3,284 @@ use std::fs::File;
 use std::io::BufReader;
 use std::path::Path;
 
-use tobj::Material;
-use ultraviolet::{Vec2, Vec3};
+use tobj::LoadError;
+use ultraviolet::{Mat4, Vec2, Vec3, Vec4};
 
+use crate::assets::handle::{AssetCache, Handle};
+use crate::error::{EngineError, EngineResult};
+use crate::graphics::gltf_model;
+use crate::graphics::material::{parameter, Material, MaterialValue, RenderState, TextureSlot};
 use crate::graphics::mesh::Mesh;
-use crate::graphics::vertex::Vertex;
-use crate::opengl::texture::{MagFilterParam, MinFilterParam, Texture, TextureType, WrapCoordinate, WrapParam};
+use crate::graphics::node_3d::Node3D;
+use crate::graphics::skin::Skin;
+use crate::graphics::vertex::{Vertex, VertexAttribute, VertexLayout};
+use crate::graphics::vertex_processing::{compute_normals, compute_tangents, NormalMode};
+use crate::math::aabb_bouding_box::AABBBoundingBox;
+use crate::math::bounding_sphere::BoundingSphere;
+use crate::opengl::texture::Texture;
 use crate::shader::Shader;
 
+const DEFAULT_SHININESS: f32 = 32.0;
+
+// Skinning is parse-only: skins and per-vertex joint weights are kept for animation code, but they are not uploaded
+// to the GPU and skinned meshes are drawn in their bind pose
 pub struct Model {
     meshes: Vec<Mesh>,
+    nodes: Vec<ModelNode>, // Empty for formats without a node hierarchy
+    skins: Vec<Skin>,
+    bounding_box: AABBBoundingBox, // Local space, computed once at load
+    bounding_sphere: BoundingSphere,
 }
 
-fn load_meshes_from_models(models: Vec<tobj::Model>, materials: Vec<Material>, path_root: &Path) -> Vec<Mesh> {
-    let mut meshes = Vec::<Mesh>::new();
-
-    for model in models {
-        // Assuming that all models will be textured
-        let material_id = match model.mesh.material_id {
-            None => panic!("Model doesn't have material id"),
-            Some(id) => id,
-        };
-
-        // TODO: Other textures
-        let material = materials[material_id].clone();
-        let mut material_path = path_root.to_path_buf();
-        material_path.push(material.diffuse_texture.expect("Missing diffuse texture"));
-
-        let texture = Texture::new(TextureType::Texture2d).expect("Failed to allocate texture");
+// Node of an imported hierarchy, node transforms are already baked into the vertices of unskinned meshes
+#[derive(Clone, Debug)]
+pub struct ModelNode {
+    pub name: Option<String>,
+    pub parent: Option<usize>,
+    pub local: Node3D,
+    pub world_matrix: Mat4, // Model space, at load time
+    pub meshes: Vec<usize>, // Indices into the model's meshes
+}
 
-        texture.set_wrap(WrapCoordinate::S, WrapParam::Repeat);
-        texture.set_wrap(WrapCoordinate::T, WrapParam::Repeat);
-        texture.set_min_filter(MinFilterParam::Linear);
-        texture.set_mag_filter(MagFilterParam::Linear);
+fn load_meshes_from_models(models: Vec<tobj::Model>, materials: Vec<tobj::Material>, path_root: &Path, mut load_texture: impl FnMut(&str) -> EngineResult<Handle<Texture>>) -> EngineResult<Vec<Mesh>> {
+    let mut meshes = Vec::<Mesh>::new();
 
-        texture.load_from_image_path(material_path.to_str().unwrap(), true);
+    let mut converted = Vec::with_capacity(materials.len());
+    for material in &materials {
+        converted.push(Handle::new(material_from_mtl(material, path_root, &mut load_texture)?));
+    }
 
+    // Meshes without a material share one default
+    let mut default_material = None;
+    for model in models {
+        let material = model.mesh.material_id.and_then(|material_id| converted.get(material_id)).cloned()
+            .unwrap_or_else(|| default_material.get_or_insert_with(|| Handle::new(default_obj_material())).clone());
 
         let mesh = &model.mesh;
         let num_vertices = mesh.positions.len() / 3;
 
         let mut vertices: Vec<Vertex> = Vec::with_capacity(num_vertices);
-        let indices: Vec<u32> = mesh.indices.clone();
+        let mut indices: Vec<u32> = mesh.indices.clone();
 
-        let (p, t) = (&mesh.positions, &mesh.texcoords);
+        let (p, t, n, c) = (&mesh.positions, &mesh.texcoords, &mesh.normals, &mesh.vertex_color);
         for i in 0..num_vertices {
             // NOTE: Flipping V coord for texture here, so it displays properly, if some problem with flipped textures occur in the future it may be the reason
-            vertices.push(Vertex::new(Vec3::new(p[i * 3], p[i * 3 + 1], p[i * 3 + 2]), Vec2::new(t[i * 2], -t[i * 2 + 1])));
+            let tex_coord = if t.is_empty() { Vec2::zero() } else { Vec2::new(t[i * 2], -t[i * 2 + 1]) };
+            let mut vertex = Vertex::new(Vec3::new(p[i * 3], p[i * 3 + 1], p[i * 3 + 2]), tex_coord);
+            if !n.is_empty() {
+                vertex = vertex.with_normal(Vec3::new(n[i * 3], n[i * 3 + 1], n[i * 3 + 2]));
+            }
+            if !c.is_empty() {
+                vertex = vertex.with_color(Vec4::new(c[i * 3], c[i * 3 + 1], c[i * 3 + 2], 1.0));
+            }
+
+            vertices.push(vertex);
+        }
+
+        if n.is_empty() {
+            compute_normals(&mut vertices, &mut indices, NormalMode::Smooth);
         }
+        compute_tangents(&mut vertices, &indices);
 
-        meshes.push(Mesh::new(vertices, indices, vec![texture]));
+        let layout = if c.is_empty() { VertexLayout::default() } else { VertexLayout::default().with(VertexAttribute::Color) };
+        meshes.push(Mesh::with_layout(vertices, indices, material, layout)?);
     }
 
-    meshes
+    Ok(meshes)
+}
+
+fn material_from_mtl(material: &tobj::Material, path_root: &Path, load_texture: &mut impl FnMut(&str) -> EngineResult<Handle<Texture>>) -> EngineResult<Material> {
+    let mut texture = |statement: Option<&String>| -> EngineResult<Option<Handle<Texture>>> {
+        statement.map(|statement| load_texture(&path_root.join(texture_file(statement)).to_string_lossy())).transpose()
+    };
+
+    let dissolve = material.dissolve.unwrap_or(1.0);
+    let diffuse = material.diffuse.unwrap_or([1.0; 3]);
+    let shininess = material.shininess.unwrap_or(DEFAULT_SHININESS);
+
+    let mut converted = Material::new(&material.name);
+    converted.set_parameter(parameter::BASE_COLOR, MaterialValue::Color(Vec4::new(diffuse[0], diffuse[1], diffuse[2], dissolve)));
+    // Phong materials are never metallic
+    converted.set_parameter(parameter::METALLIC, MaterialValue::Float(0.0));
+    converted.set_parameter(parameter::ROUGHNESS, MaterialValue::Float(phong_roughness(shininess)));
+    converted.set_parameter(parameter::NORMAL_SCALE, MaterialValue::Float(material.normal_texture.as_deref().and_then(bump_multiplier).unwrap_or(1.0)));
+    converted.set_parameter(parameter::EMISSIVE_COLOR, MaterialValue::Vec3(Vec3::from(material.emissive.unwrap_or([0.0; 3]))));
+    // Ks and map_Ks tint the 4% reflectance of a PBR dielectric, Ka scales the ambient light like it did for Phong
+    converted.set_parameter(parameter::SPECULAR_COLOR, MaterialValue::Vec3(Vec3::from(material.specular.unwrap_or([1.0; 3]))));
+    converted.set_parameter(parameter::AMBIENT_COLOR, MaterialValue::Vec3(Vec3::from(material.ambient.unwrap_or([1.0; 3]))));
+    converted.set_texture(TextureSlot::BaseColor, texture(material.diffuse_texture.as_ref())?);
+    converted.set_texture(TextureSlot::Normal, texture(material.normal_texture.as_ref())?);
+    converted.set_texture(TextureSlot::Emissive, texture(material.unknown_param.get("map_Ke"))?);
+    converted.set_texture(TextureSlot::Specular, texture(material.specular_texture.as_ref())?);
+    if dissolve < 1.0 {
+        converted.render_state = RenderState::transparent();
+    }
+
+    Ok(converted)
+}
+
+// Roughness that gives a highlight about the size of a Phong highlight with this shininess
+fn phong_roughness(shininess: f32) -> f32 {
+    (2.0 / (shininess + 2.0)).sqrt()
+}
+
+// For meshes without `usemtl`, the glTF default material is fully metallic and would render them as chrome
+fn default_obj_material() -> Material {
+    let mut material = Material::default();
+    material.set_parameter(parameter::METALLIC, MaterialValue::Float(0.0));
+    material.set_parameter(parameter::ROUGHNESS, MaterialValue::Float(phong_roughness(DEFAULT_SHININESS)));
+
+    material
+}
+
+// Texture statements may start with options like `-bm 0.5`, the file name comes last
+fn texture_file(statement: &str) -> &str {
+    statement.split_whitespace().last().unwrap_or(statement)
+}
+
+fn bump_multiplier(statement: &str) -> Option<f32> {
+    let mut words = statement.split_whitespace();
+    words.position(|word| word == "-bm")?;
+
+    words.next()?.parse().ok()
 }
 
 impl Model {
-    pub fn load_from_file(path: &str) -> Self {
-        let mut dir = env::current_dir().unwrap();
+    // Meshes of this model that use the same image share one texture
+    pub fn load_from_file(path: &str) -> EngineResult<Self> {
+        let mut textures = AssetCache::new();
+
+        Self::load_from_file_with_textures(path, |texture_path| textures.get_or_load(texture_path, Texture::from_image_path))
+    }
+
+    // OBJ/MTL, or glTF 2.0 for .gltf and .glb files
+    pub fn load_from_file_with_textures(path: &str, load_texture: impl FnMut(&str) -> EngineResult<Handle<Texture>>) -> EngineResult<Self> {
+        if gltf_model::is_gltf_path(path) {
+            return gltf_model::load_from_file(path, load_texture);
+        }
+
+        let mut dir = env::current_dir().map_err(|source| EngineError::Io { path: path.to_owned(), source })?;
         dir.push(path);
         dir.pop();
 
-        let mut reader = BufReader::new(File::open(path).expect("Failed to open obj file"));
+        let file = File::open(path).map_err(|source| EngineError::Io { path: path.to_owned(), source })?;
+        let mut reader = BufReader::new(file);
         let (models, materials) = tobj::load_obj_buf(&mut reader, &tobj::LoadOptions { triangulate: true, single_index: true, ..Default::default() }, |p| {
             let mut mtl_path = dir.clone();
             mtl_path.push(p);
 
-            let f = File::open(&mtl_path).expect(format!("Couldn't open MTL file, path {}", mtl_path.clone().to_str().unwrap()).as_str());
+            let f = File::open(&mtl_path).map_err(|_| LoadError::OpenFileFailed)?;
             tobj::load_mtl_buf(&mut BufReader::new(f))
-        }).unwrap();
+        }).map_err(|source| EngineError::ObjLoad { path: path.to_owned(), source })?;
 
         if models.is_empty() {
-            panic!("Obj file contains no models");
+            return Err(EngineError::EmptyModel { path: path.to_owned() });
         }
 
-        let materials = match materials {
-            Err(e) => panic!("Materials loading error {e}"),
-            Ok(m) => m,
-        };
+        let materials = materials.map_err(|source| EngineError::ObjLoad { path: path.to_owned(), source })?;
+
+        let meshes = load_meshes_from_models(models, materials, &dir, load_texture)?;
 
-        let meshes = load_meshes_from_models(models, materials, &dir);
+        Ok(Self::from_meshes(meshes))
+    }
+
+    pub fn from_meshes(meshes: Vec<Mesh>) -> Self {
+        Self::from_hierarchy(meshes, Vec::new(), Vec::new())
+    }
+
+    pub fn from_hierarchy(meshes: Vec<Mesh>, nodes: Vec<ModelNode>, skins: Vec<Skin>) -> Self {
+        let bounding_box = meshes.iter().map(|mesh| mesh.bounding_box()).reduce(|a, b| a.union(b)).unwrap_or_default();
+        let bounding_sphere = meshes.iter().map(|mesh| mesh.bounding_sphere()).reduce(|a, b| a.merged(b)).unwrap_or_default();
 
         Self {
             meshes,
+            nodes,
+            skins,
+            bounding_box,
+            bounding_sphere,
         }
     }
 
+    // Tests can't create meshes without a GL context, this stands in for a loaded model's geometry
+    #[cfg(test)]
+    pub(crate) fn with_bounding_box(bounding_box: AABBBoundingBox) -> Self {
+        Self { bounding_box, ..Self::from_meshes(Vec::new()) }
+    }
+
+    pub fn bounding_box(&self) -> AABBBoundingBox {
+        self.bounding_box
+    }
+
+    pub fn bounding_sphere(&self) -> BoundingSphere {
+        self.bounding_sphere
+    }
+
+    pub fn meshes(&self) -> &[Mesh] {
+        &self.meshes
+    }
+
+    pub fn nodes(&self) -> &[ModelNode] {
+        &self.nodes
+    }
+
+    // Parsed only, nothing poses the meshes with these yet
+    pub fn skins(&self) -> &[Skin] {
+        &self.skins
+    }
+
+    pub fn triangles(&self) -> impl Iterator<Item=[Vec3; 3]> + '_ {
+        self.meshes.iter().flat_map(|mesh| mesh.triangles())
+    }
+
     pub fn draw(&self, shader_program: &Shader) {
         for mesh in &self.meshes {
             mesh.draw(shader_program);
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn untextured_mtl_materials_keep_their_colours() {
+        let mtl = "newmtl glass\nKa 0.1 0.1 0.1\nKd 0.2 0.4 0.6\nKs 0.5 0.5 0.5\nKe 0.5 0 0\nNs 48\nd 0.25\n";
+        let (materials, _) = tobj::load_mtl_buf(&mut Cursor::new(mtl)).unwrap();
+
+        let material = material_from_mtl(&materials[0], Path::new("res/models"), &mut |path: &str| panic!("no textures expected, got {path}")).unwrap();
+
+        assert_eq!(material.name, "glass");
+        assert_eq!(material.parameter(parameter::BASE_COLOR), Some(MaterialValue::Color(Vec4::new(0.2, 0.4, 0.6, 0.25))));
+        assert_eq!(material.parameter(parameter::AMBIENT_COLOR), Some(MaterialValue::Vec3(Vec3::broadcast(0.1))));
+        assert_eq!(material.parameter(parameter::SPECULAR_COLOR), Some(MaterialValue::Vec3(Vec3::broadcast(0.5))));
+        assert_eq!(material.parameter(parameter::EMISSIVE_COLOR), Some(MaterialValue::Vec3(Vec3::new(0.5, 0.0, 0.0))));
+        assert_eq!(material.parameter(parameter::METALLIC), Some(MaterialValue::Float(0.0)));
+        assert!(matches!(material.parameter(parameter::ROUGHNESS), Some(MaterialValue::Float(roughness)) if (roughness - 0.2).abs() < 1e-6));
+        assert_eq!(material.render_state, RenderState::transparent());
+        assert!(material.texture(TextureSlot::BaseColor).is_none() && material.texture(TextureSlot::Specular).is_none());
+    }
+
+    #[test]
+    fn meshes_without_a_material_are_dielectric() {
+        let unnamed = material_from_mtl(&tobj::Material::default(), Path::new("res/models"), &mut |path: &str| panic!("no textures expected, got {path}")).unwrap();
+        let default = default_obj_material();
+
+        assert_eq!(default.parameter(parameter::METALLIC), Some(MaterialValue::Float(0.0)));
+        assert_eq!(default.parameter(parameter::ROUGHNESS), unnamed.parameter(parameter::ROUGHNESS));
+        assert_eq!(default.parameter(parameter::BASE_COLOR), unnamed.parameter(parameter::BASE_COLOR));
+        // Without Ka and Ks the ambient light and reflectance are left as they are
+        assert_eq!(unnamed.parameter(parameter::AMBIENT_COLOR), Some(MaterialValue::Vec3(Vec3::one())));
+        assert_eq!(unnamed.parameter(parameter::SPECULAR_COLOR), default.parameter(parameter::SPECULAR_COLOR));
+    }
+
+    #[test]
+    fn texture_statements_are_split_from_their_options() {
+        assert_eq!(texture_file("textures/brick.png"), "textures/brick.png");
+        assert_eq!(texture_file("-bm 0.5 textures/brick_normal.png"), "textures/brick_normal.png");
+        assert_eq!(bump_multiplier("-bm 0.5 textures/brick_normal.png"), Some(0.5));
+        assert_eq!(bump_multiplier("textures/brick_normal.png"), None);
+    }
+}
@@ -1,10 +1,43 @@
-use ultraviolet::Vec3;
+use ultraviolet::{Mat4, Vec3};
 
+use crate::math::aabb_bouding_box::AABBBoundingBox;
+use crate::math::bounding_sphere::BoundingSphere;
 use crate::math::rotation::Rotation;
 
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Node3D {
     pub world_position: Vec3,
     pub scale: Vec3,
     pub rotation: Rotation,
-    // TODO: Rotation, transform ???
-}
\ No newline at end of file
+}
+
+impl Node3D {
+    // Splits a model matrix without shear back into position, rotation and scale
+    pub fn from_matrix(matrix: Mat4) -> Self {
+        let scale = Vec3::new(matrix.cols[0].xyz().mag(), matrix.cols[1].xyz().mag(), matrix.cols[2].xyz().mag());
+        let rotation = matrix * Mat4::from_nonuniform_scale(Vec3::new(1.0 / scale.x, 1.0 / scale.y, 1.0 / scale.z));
+
+        Node3D { world_position: matrix.cols[3].xyz(), scale, rotation: Rotation::from_matrix(rotation) }
+    }
+
+    pub fn model_matrix(&self) -> Mat4 {
+        Mat4::from_translation(self.world_position) * self.rotation.rotation_matrix() * Mat4::from_nonuniform_scale(self.scale)
+    }
+
+    // Blends from `previous` (t = 0) to this node (t = 1)
+    pub fn interpolated(&self, previous: &Node3D, t: f32) -> Node3D {
+        Node3D {
+            world_position: previous.world_position + (self.world_position - previous.world_position) * t,
+            scale: previous.scale + (self.scale - previous.scale) * t,
+            rotation: previous.rotation.slerp(&self.rotation, t),
+        }
+    }
+
+    pub fn world_bounding_box(&self, local_bounding_box: AABBBoundingBox) -> AABBBoundingBox {
+        local_bounding_box.transformed(self.model_matrix())
+    }
+
+    pub fn world_bounding_sphere(&self, local_bounding_sphere: BoundingSphere) -> BoundingSphere {
+        local_bounding_sphere.transformed(self.model_matrix())
+    }
+}
@@ -3,72 +3,72 @@ use ultraviolet::{Mat4, Vec3};
 use crate::camera::Camera;
 use crate::graphics::node_3d::Node3D;
 use crate::math::aabb_bouding_box::AABBBoundingBox;
+use crate::math::capsule::Capsule;
+use crate::math::rotation::Rotation;
+use crate::physics::character_controller::{CharacterController, CharacterControllerConfig, CharacterInput};
+use crate::physics::collider::Collider;
 
-const SPEED: f32 = 20.5;
-
-#[derive(Debug)]
-pub enum MovementDirection {
-    Forward,
-    Backward,
-    Left,
-    Right,
-    Up,
-    Down,
-}
-
+// The node position is at the character's feet, the camera sits at the controller's eye height above it
 pub struct PlayerCharacter {
     node3d: Node3D,
+    previous_position: Vec3,
     camera: Camera,
-    movement_speed: f32,
-    bounding_box: AABBBoundingBox,
-    vertical_velocity: f32,
-    // TODO: RigidBody
+    controller: CharacterController,
 }
 
 impl PlayerCharacter {
     pub fn new(node3d: Node3D, camera: Camera, height: f32) -> Self {
-        let width = 0.5;
-        let bounding_box = AABBBoundingBox { x_min: -(width / 2.0), x_max: width / 2.0, y_max: height, y_min: 0.0, z_min: -(width / 2.0), z_max: width / 2.0 };
+        let config = CharacterControllerConfig { height, crouch_height: height * 0.6, ..Default::default() };
 
+        Self::with_controller_config(node3d, camera, config)
+    }
+
+    pub fn with_controller_config(node3d: Node3D, camera: Camera, config: CharacterControllerConfig) -> Self {
         Self {
+            previous_position: node3d.world_position,
             node3d,
             camera,
-            movement_speed: SPEED,
-            bounding_box,
-            vertical_velocity: 0.0,
+            controller: CharacterController::new(config),
         }
     }
 
     pub fn get_camera_view_matrix(&self) -> Mat4 {
-        self.camera.get_view_matrix(self.node3d.world_position)
+        self.get_interpolated_camera_view_matrix(1.0)
     }
 
-    pub fn get_look_direction(&self) -> Vec3 {
-        self.camera.front
-    }
+    // Camera placed between the positions of the last two fixed steps so movement stays smooth at any frame rate
+    pub fn get_interpolated_camera_view_matrix(&self, alpha: f32) -> Mat4 {
+        let position = self.previous_position + (self.node3d.world_position - self.previous_position) * alpha;
 
-    pub fn process_keyboard(&mut self, direction: MovementDirection, dt: f32) {
-        let velocity = self.movement_speed * dt;
+        self.camera.get_view_matrix(position + Vec3::new(0.0, self.controller.eye_height(), 0.0))
+    }
 
-        match direction {
-            MovementDirection::Forward => self.node3d.world_position += self.camera.front * velocity,
-            MovementDirection::Backward => self.node3d.world_position -= self.camera.front * velocity,
-            MovementDirection::Left => self.node3d.world_position -= self.camera.right * velocity,
-            MovementDirection::Right => self.node3d.world_position += self.camera.right * velocity,
-            _ => (),
-            //CameraMovement::Up => self.height += velocity,
-            //CameraMovement::Down => self.height -= velocity,
+    // Transform of the camera itself, nodes attached under it move and turn with the view
+    pub fn get_camera_node3d(&self, alpha: f32) -> Node3D {
+        let position = self.previous_position + (self.node3d.world_position - self.previous_position) * alpha;
+        Node3D {
+            world_position: position + Vec3::new(0.0, self.controller.eye_height(), 0.0) + self.camera.position,
+            scale: Vec3::new(1.0, 1.0, 1.0),
+            rotation: Rotation::look_at(self.camera.front, self.camera.up),
         }
+    }
 
-        // TODO: Reenable ?
-        //self.node3d.world_position.y = self.height; // Keeps camera from flying so that the y position can only be changed with up/down movement keys
-        self.node3d.world_position.y = 1.6;
+    pub fn get_eye_position(&self) -> Vec3 {
+        self.node3d.world_position + Vec3::new(0.0, self.controller.eye_height(), 0.0) + self.camera.position
+    }
+
+    pub fn get_look_direction(&self) -> Vec3 {
+        self.camera.front
     }
 
     pub fn process_mouse_movement(&mut self, x_offset: f32, y_offset: f32, constrain_pitch: bool) {
         self.camera.process_mouse_movement(x_offset, y_offset, constrain_pitch);
     }
 
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
     pub fn get_camera_zoom(&self) -> f32 {
         self.camera.zoom
     }
@@ -81,56 +81,43 @@ impl PlayerCharacter {
         self.node3d.world_position.x = x;
         self.node3d.world_position.y = y;
         self.node3d.world_position.z = z;
+        self.previous_position = self.node3d.world_position;
     }
 
-    pub fn check_collision(&self, bounding_box: AABBBoundingBox) -> bool {
-        let bounding_box_translated = self.get_bounding_box_translated();
-        bounding_box_translated.collides_with(bounding_box)
+    pub fn controller(&self) -> &CharacterController {
+        &self.controller
     }
 
-    fn get_bounding_box_translated(&self) -> AABBBoundingBox {
-        let mut bounding_box = self.bounding_box;
-        let half_width = (bounding_box.x_max - bounding_box.x_min) / 2.0;
-        let half_height = (bounding_box.y_max - bounding_box.y_min) / 2.0;
-        let half_depth = (bounding_box.z_max - bounding_box.z_min) / 2.0;
-
-        bounding_box.x_min = self.node3d.world_position.x - half_width;
-        bounding_box.x_max = self.node3d.world_position.x + half_width;
-
-        bounding_box.y_min = self.node3d.world_position.y - half_height;
-        bounding_box.y_max = self.node3d.world_position.y + half_height;
-
-        bounding_box.z_min = self.node3d.world_position.z - half_depth;
-        bounding_box.z_max = self.node3d.world_position.z + half_depth;
+    pub fn controller_mut(&mut self) -> &mut CharacterController {
+        &mut self.controller
+    }
 
-        bounding_box
+    pub fn get_capsule(&self) -> Capsule {
+        self.controller.capsule(self.node3d.world_position)
     }
 
-    pub fn add_vertical_velocity(&mut self, velocity: f32) {
-        self.vertical_velocity += velocity;
-        if self.vertical_velocity <= -55.0 {
-            // terminal velocity
-            self.vertical_velocity = -55.0;
-        }
+    pub fn get_bounding_box_translated(&self) -> AABBBoundingBox {
+        self.get_capsule().bounding_box()
+    }
 
-        /*if self.vertical_velocity > 5.0 {
-            self.vertical_velocity = 5.0;
-        }*/
+    pub fn get_reach_bounding_box(&self, dt: f32) -> AABBBoundingBox {
+        self.controller.reach_bounding_box(self.node3d.world_position, dt)
     }
 
-    pub fn get_vertical_velocity(&self) -> f32 {
-        self.vertical_velocity
+    pub fn step(&mut self, input: &CharacterInput, dt: f32, obstacles: &[&Collider]) {
+        self.previous_position = self.node3d.world_position;
+        self.node3d.world_position = self.controller.step(self.node3d.world_position, input, dt, obstacles);
     }
 
-    pub fn reset_vertical_velocity(&mut self) {
-        self.vertical_velocity = 0.0;
+    pub fn get_velocity(&self) -> Vec3 {
+        self.controller.velocity()
     }
 
-    pub fn get_half_height(&self) -> f32 {
-        (self.bounding_box.y_max - self.bounding_box.y_min) / 2.0
+    pub fn is_on_ground(&self) -> bool {
+        self.controller.is_grounded()
     }
 
     pub fn get_right_direction(&self) -> Vec3 {
         self.camera.right
     }
-}
\ No newline at end of file
+}
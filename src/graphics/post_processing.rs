@@ -0,0 +1,394 @@
+use crate::assets::AssetManager;
+use crate::assets::handle::Handle;
+use crate::error::EngineResult;
+use crate::opengl;
+use crate::opengl::{Capability, Primitive};
+use crate::opengl::ClearBitFlags::{ColorBuffer, DepthBuffer};
+use crate::opengl::framebuffer::Framebuffer;
+use crate::opengl::render_target::{RenderTarget, RenderTargetConfig};
+use crate::opengl::texture::{Texture, TextureFormat};
+use crate::opengl::vertex_array_object::VertexArrayObject;
+use crate::shader::Shader;
+
+pub const POST_VERTEX_SHADER: &str = "res/shaders/post.vs";
+pub const BRIGHT_PASS_FRAGMENT_SHADER: &str = "res/shaders/post_bright.fs";
+pub const BLUR_FRAGMENT_SHADER: &str = "res/shaders/post_blur.fs";
+pub const COMPOSITE_FRAGMENT_SHADER: &str = "res/shaders/post_composite.fs";
+pub const FXAA_FRAGMENT_SHADER: &str = "res/shaders/post_fxaa.fs";
+
+// Taps on each side of the centre, must match the blur shader
+const BLUR_TAPS: usize = 5;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ToneMapping {
+    None, // Clamps, HDR values above 1 are lost
+    Reinhard,
+    Aces,
+}
+
+impl ToneMapping {
+    fn shader_value(self) -> i64 {
+        match self {
+            ToneMapping::None => 0,
+            ToneMapping::Reinhard => 1,
+            ToneMapping::Aces => 2,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PostEffect {
+    ToneMapping,
+    Bloom,
+    Fxaa,
+    Vignette,
+    ColorGrading,
+    GammaCorrection,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PostProcessSettings {
+    pub exposure: f32, // Multiplies the HDR colour before tone mapping
+    pub tone_mapping: ToneMapping,
+    pub bloom: bool,
+    pub bloom_threshold: f32, // Luminance above which pixels start to glow
+    pub bloom_intensity: f32,
+    pub bloom_blur_passes: u32, // Each pass blurs horizontally, then vertically
+    pub fxaa: bool,
+    pub vignette: bool,
+    pub vignette_strength: f32,
+    pub vignette_radius: f32, // Distance from the centre where darkening starts, 1 is the middle of an edge
+    pub color_grading: bool, // Needs a LUT, see PostProcessor::set_color_grading_lut
+    pub gamma_correction: bool, // The scene is lit in linear colour, turn off only for shaders that output display colours
+    pub gamma: f32,
+}
+
+impl Default for PostProcessSettings {
+    fn default() -> Self {
+        Self {
+            exposure: 1.0,
+            tone_mapping: ToneMapping::Aces,
+            bloom: true,
+            bloom_threshold: 1.0,
+            bloom_intensity: 0.5,
+            bloom_blur_passes: 3,
+            fxaa: true,
+            vignette: false,
+            vignette_strength: 0.4,
+            vignette_radius: 0.75,
+            color_grading: false,
+            gamma_correction: true,
+            gamma: 2.2,
+        }
+    }
+}
+
+impl PostProcessSettings {
+    pub fn is_enabled(&self, effect: PostEffect) -> bool {
+        match effect {
+            PostEffect::ToneMapping => self.tone_mapping != ToneMapping::None,
+            PostEffect::Bloom => self.bloom,
+            PostEffect::Fxaa => self.fxaa,
+            PostEffect::Vignette => self.vignette,
+            PostEffect::ColorGrading => self.color_grading,
+            PostEffect::GammaCorrection => self.gamma_correction,
+        }
+    }
+
+    // Enabling tone mapping picks ACES when it was off
+    pub fn set_enabled(&mut self, effect: PostEffect, enabled: bool) {
+        match effect {
+            PostEffect::ToneMapping => {
+                if !enabled {
+                    self.tone_mapping = ToneMapping::None;
+                } else if self.tone_mapping == ToneMapping::None {
+                    self.tone_mapping = ToneMapping::Aces;
+                }
+            }
+            PostEffect::Bloom => self.bloom = enabled,
+            PostEffect::Fxaa => self.fxaa = enabled,
+            PostEffect::Vignette => self.vignette = enabled,
+            PostEffect::ColorGrading => self.color_grading = enabled,
+            PostEffect::GammaCorrection => self.gamma_correction = enabled,
+        }
+    }
+
+    pub fn toggle(&mut self, effect: PostEffect) {
+        self.set_enabled(effect, !self.is_enabled(effect));
+    }
+}
+
+// Something sized to the window, only recreated when the size changes. A failed recreation keeps the previous one,
+// and so does a zero sized window (minimised), which nothing can be created at
+pub struct WindowSized<T> {
+    width: u32,
+    height: u32,
+    value: T,
+}
+
+impl<T> WindowSized<T> {
+    pub fn new(width: u32, height: u32, create: impl FnOnce(u32, u32) -> EngineResult<T>) -> EngineResult<Self> {
+        Ok(Self { width, height, value: create(width, height)? })
+    }
+
+    pub fn get(&self) -> &T {
+        &self.value
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    // Returns whether it was recreated
+    pub fn resize(&mut self, width: u32, height: u32, create: impl FnOnce(u32, u32) -> EngineResult<T>) -> EngineResult<bool> {
+        if width == 0 || height == 0 || (width, height) == self.size() {
+            return Ok(false);
+        }
+
+        self.value = create(width, height)?;
+        (self.width, self.height) = (width, height);
+
+        Ok(true)
+    }
+}
+
+struct ScreenTargets {
+    scene: RenderTarget,
+    bloom: [RenderTarget; 2], // Half resolution, blurred back and forth
+    ldr: RenderTarget, // Tone mapped result that FXAA reads
+}
+
+// The scene is drawn into an HDR target between begin() and apply(), which runs the effects and writes to the window
+pub struct PostProcessor {
+    settings: PostProcessSettings,
+    targets: WindowSized<ScreenTargets>,
+    color_grading_lut: Option<Handle<Texture>>,
+    bright_pass_shader: Handle<Shader>,
+    blur_shader: Handle<Shader>,
+    composite_shader: Handle<Shader>,
+    fxaa_shader: Handle<Shader>,
+    vao: VertexArrayObject, // Empty, the vertex shader makes a fullscreen triangle from gl_VertexID
+}
+
+impl PostProcessor {
+    pub fn new(assets: &mut AssetManager, settings: PostProcessSettings, width: u32, height: u32) -> EngineResult<Self> {
+        Ok(Self {
+            settings,
+            targets: WindowSized::new(width, height, create_targets)?,
+            color_grading_lut: None,
+            bright_pass_shader: assets.load_shader(POST_VERTEX_SHADER, BRIGHT_PASS_FRAGMENT_SHADER)?,
+            blur_shader: assets.load_shader(POST_VERTEX_SHADER, BLUR_FRAGMENT_SHADER)?,
+            composite_shader: assets.load_shader(POST_VERTEX_SHADER, COMPOSITE_FRAGMENT_SHADER)?,
+            fxaa_shader: assets.load_shader(POST_VERTEX_SHADER, FXAA_FRAGMENT_SHADER)?,
+            vao: VertexArrayObject::new()?,
+        })
+    }
+
+    pub fn settings(&self) -> &PostProcessSettings {
+        &self.settings
+    }
+
+    pub fn settings_mut(&mut self) -> &mut PostProcessSettings {
+        &mut self.settings
+    }
+
+    // A strip of N slices of N×N texels side by side (N² wide, N high), red along x, green along y and blue across slices
+    pub fn set_color_grading_lut(&mut self, lut: Option<Handle<Texture>>) {
+        self.color_grading_lut = lut;
+    }
+
+    // On failure the previous targets are kept, drawing then continues at the old size
+    pub fn resize(&mut self, width: u32, height: u32) -> EngineResult<()> {
+        self.targets.resize(width, height, create_targets)?;
+
+        Ok(())
+    }
+
+    pub fn begin(&self, clear_color: [f32; 4]) {
+        self.targets.get().scene.bind();
+        opengl::clear_color(clear_color[0], clear_color[1], clear_color[2], clear_color[3]);
+        opengl::clear(ColorBuffer | DepthBuffer);
+    }
+
+    // Leaves the window's framebuffer bound with `viewport` set
+    pub fn apply(&self, viewport: [i32; 4]) {
+        let (settings, targets) = (&self.settings, self.targets.get());
+        opengl::disable(Capability::DepthTest);
+        opengl::disable(Capability::Blending);
+        self.vao.bind();
+
+        let scene_texture = targets.scene.color_texture(0).expect("scene target has a colour texture");
+        let bloom_texture = if settings.bloom { Some(self.render_bloom(scene_texture)) } else { None };
+
+        if settings.fxaa {
+            targets.ldr.bind();
+        } else {
+            Self::bind_window(viewport);
+        }
+
+        let composite_shader = self.composite_shader.get();
+        composite_shader.bind();
+        composite_shader.set_float("exposure", settings.exposure);
+        composite_shader.set_int("toneMapping", settings.tone_mapping.shader_value());
+        bind_texture(&composite_shader, "sceneTexture", 0, scene_texture);
+        composite_shader.set_bool("bloomEnabled", bloom_texture.is_some());
+        if let Some(bloom_texture) = bloom_texture {
+            bind_texture(&composite_shader, "bloomTexture", 1, bloom_texture);
+            composite_shader.set_float("bloomIntensity", settings.bloom_intensity);
+        }
+        composite_shader.set_bool("vignetteEnabled", settings.vignette);
+        composite_shader.set_float("vignetteStrength", settings.vignette_strength);
+        composite_shader.set_float("vignetteRadius", settings.vignette_radius);
+        let color_grading_lut = self.color_grading_lut.as_ref().filter(|_| settings.color_grading);
+        composite_shader.set_bool("colorGradingEnabled", color_grading_lut.is_some());
+        if let Some(lut) = color_grading_lut {
+            bind_texture(&composite_shader, "colorGradingLut", 2, &lut.get());
+        }
+        composite_shader.set_float("gamma", if settings.gamma_correction { settings.gamma } else { 1.0 });
+        draw_fullscreen_triangle();
+
+        if settings.fxaa {
+            Self::bind_window(viewport);
+
+            let fxaa_shader = self.fxaa_shader.get();
+            fxaa_shader.bind();
+            let ldr_texture = targets.ldr.color_texture(0).expect("LDR target has a colour texture");
+            bind_texture(&fxaa_shader, "screenTexture", 0, ldr_texture);
+            fxaa_shader.set_vec3("inverseScreenSize", 1.0 / targets.ldr.width() as f32, 1.0 / targets.ldr.height() as f32, 0.0);
+            draw_fullscreen_triangle();
+        }
+
+        VertexArrayObject::unbind();
+        Texture::set_active_texture(0);
+        opengl::enable(Capability::DepthTest);
+        opengl::enable(Capability::Blending);
+    }
+
+    // Bright pass into the first target, then ping-pong blurs that end in it again
+    fn render_bloom(&self, scene_texture: &Texture) -> &Texture {
+        let [first, second] = &self.targets.get().bloom;
+
+        let bright_pass_shader = self.bright_pass_shader.get();
+        first.bind();
+        bright_pass_shader.bind();
+        bright_pass_shader.set_float("threshold", self.settings.bloom_threshold);
+        bright_pass_shader.set_float("exposure", self.settings.exposure);
+        bind_texture(&bright_pass_shader, "sceneTexture", 0, scene_texture);
+        draw_fullscreen_triangle();
+
+        let blur_shader = self.blur_shader.get();
+        blur_shader.bind();
+        for (i, weight) in gaussian_weights(BLUR_TAPS).iter().enumerate() {
+            blur_shader.set_float(&format!("weights[{i}]"), *weight);
+        }
+        for _ in 0..self.settings.bloom_blur_passes {
+            for (source, destination, direction) in [(first, second, (1.0, 0.0)), (second, first, (0.0, 1.0))] {
+                destination.bind();
+                blur_shader.set_vec3("direction", direction.0 / source.width() as f32, direction.1 / source.height() as f32, 0.0);
+                bind_texture(&blur_shader, "image", 0, source.color_texture(0).expect("bloom target has a colour texture"));
+                draw_fullscreen_triangle();
+            }
+        }
+
+        first.color_texture(0).expect("bloom target has a colour texture")
+    }
+
+    fn bind_window(viewport: [i32; 4]) {
+        Framebuffer::bind_default();
+        opengl::viewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+    }
+}
+
+fn create_targets(width: u32, height: u32) -> EngineResult<ScreenTargets> {
+    let scene = RenderTarget::new(RenderTargetConfig { color_formats: vec![TextureFormat::Rgba16F], ..RenderTargetConfig::new(width, height) })?;
+
+    let (bloom_width, bloom_height) = ((width / 2).max(1), (height / 2).max(1));
+    let bloom_config = RenderTargetConfig { color_formats: vec![TextureFormat::Rgba16F], depth_format: None, ..RenderTargetConfig::new(bloom_width, bloom_height) };
+    let bloom = [RenderTarget::new(bloom_config.clone())?, RenderTarget::new(bloom_config)?];
+
+    let ldr = RenderTarget::new(RenderTargetConfig { depth_format: None, ..RenderTargetConfig::new(width, height) })?;
+
+    Ok(ScreenTargets { scene, bloom, ldr })
+}
+
+fn bind_texture(shader_program: &Shader, sampler: &str, unit: usize, texture: &Texture) {
+    Texture::set_active_texture(unit);
+    texture.bind();
+    shader_program.set_int(sampler, unit as i64);
+}
+
+fn draw_fullscreen_triangle() {
+    opengl::draw_arrays(Primitive::Triangles, 0, 3);
+}
+
+// Centre weight first, then one per tap on each side, normalised so the whole kernel sums to one
+pub fn gaussian_weights(taps: usize) -> Vec<f32> {
+    let sigma = taps as f32 / 2.0;
+    let weights: Vec<f32> = (0..taps).map(|i| (-((i * i) as f32) / (2.0 * sigma * sigma)).exp()).collect();
+    let sum = weights[0] + 2.0 * weights[1..].iter().sum::<f32>();
+
+    weights.iter().map(|weight| weight / sum).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::EngineError;
+
+    #[test]
+    fn effects_toggle() {
+        let mut settings = PostProcessSettings::default();
+        for effect in [PostEffect::ToneMapping, PostEffect::Bloom, PostEffect::Fxaa, PostEffect::Vignette, PostEffect::ColorGrading, PostEffect::GammaCorrection] {
+            let enabled = settings.is_enabled(effect);
+            settings.toggle(effect);
+            assert_eq!(settings.is_enabled(effect), !enabled, "{effect:?}");
+        }
+
+        settings.tone_mapping = ToneMapping::Reinhard;
+        settings.set_enabled(PostEffect::ToneMapping, true);
+        assert_eq!(settings.tone_mapping, ToneMapping::Reinhard);
+        settings.toggle(PostEffect::ToneMapping);
+        settings.toggle(PostEffect::ToneMapping);
+        assert_eq!(settings.tone_mapping, ToneMapping::Aces);
+    }
+
+    #[test]
+    fn targets_are_recreated_only_when_the_size_changes() {
+        let mut created = Vec::new();
+        let mut create = |width: u32, height: u32| {
+            created.push((width, height));
+            Ok(created.len())
+        };
+
+        let mut targets = WindowSized::new(1280, 720, &mut create).unwrap();
+        assert!(!targets.resize(1280, 720, &mut create).unwrap());
+        assert!(targets.resize(1920, 1080, &mut create).unwrap());
+        assert_eq!((targets.size(), *targets.get()), ((1920, 1080), 2));
+
+        // Minimising reports 0×0, the targets wait for a real size
+        assert!(!targets.resize(0, 0, &mut create).unwrap());
+        assert!(!targets.resize(1920, 0, &mut create).unwrap());
+        assert_eq!(targets.size(), (1920, 1080));
+        assert_eq!(created, vec![(1280, 720), (1920, 1080)]);
+    }
+
+    #[test]
+    fn failed_resize_keeps_the_previous_targets() {
+        let mut targets = WindowSized::new(1280, 720, |_, _| Ok("first")).unwrap();
+
+        let result = targets.resize(1920, 1080, |_, _| Err(EngineError::FramebufferIncomplete("missing attachment")));
+        assert!(matches!(result, Err(EngineError::FramebufferIncomplete(_))));
+        assert_eq!((targets.size(), *targets.get()), ((1280, 720), "first"));
+
+        // The next resize tries again
+        assert!(targets.resize(1920, 1080, |_, _| Ok("second")).unwrap());
+    }
+
+    #[test]
+    fn blur_kernel_is_normalised() {
+        let weights = gaussian_weights(BLUR_TAPS);
+        assert_eq!(weights.len(), BLUR_TAPS);
+        assert!((weights[0] + 2.0 * weights[1..].iter().sum::<f32>() - 1.0).abs() < 1e-5);
+        assert!(weights.windows(2).all(|pair| pair[0] > pair[1]));
+    }
+}
@@ -0,0 +1,142 @@
+use std::cmp::Ordering;
+
+use ogl33::GLuint;
+use ultraviolet::{Mat4, Vec3};
+
+use crate::assets::handle::Handle;
+use crate::graphics::material::{BlendMode, RenderState};
+use crate::graphics::model::Model;
+use crate::shader::Shader;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DrawKey {
+    pub transparent: bool,
+    pub shader: GLuint, // 0 for the pass's shader
+    pub material: usize, // Handle id
+    pub distance: f32, // From the camera
+}
+
+impl DrawKey {
+    // Opaque draws first, grouped by shader and then material so state only changes between groups.
+    // Transparent draws after them, far to near so they blend over what is behind them
+    pub fn draw_order(&self, other: &DrawKey) -> Ordering {
+        match (self.transparent, other.transparent) {
+            (false, true) => Ordering::Less,
+            (true, false) => Ordering::Greater,
+            (false, false) => (self.shader, self.material).cmp(&(other.shader, other.material)),
+            (true, true) => other.distance.total_cmp(&self.distance),
+        }
+    }
+}
+
+struct DrawCall {
+    key: DrawKey,
+    model: Handle<Model>,
+    mesh: usize,
+    model_matrix: Mat4,
+}
+
+// Meshes of one pass, collected first so they can be drawn with as few shader and material switches as possible
+pub struct RenderQueue {
+    camera_position: Vec3,
+    draws: Vec<DrawCall>,
+}
+
+impl RenderQueue {
+    pub fn new(camera_position: Vec3) -> Self {
+        Self { camera_position, draws: Vec::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.draws.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.draws.is_empty()
+    }
+
+    pub fn push_model(&mut self, model: &Handle<Model>, model_matrix: Mat4) {
+        for (index, mesh) in model.get().meshes().iter().enumerate() {
+            let material = mesh.material().get();
+            let center = model_matrix.transform_point3(mesh.bounding_box().center());
+
+            self.draws.push(DrawCall {
+                key: DrawKey {
+                    transparent: material.render_state.is_transparent(),
+                    shader: material.shader.as_ref().map_or(0, |shader| shader.get().program_id),
+                    material: mesh.material().id(),
+                    distance: (center - self.camera_position).mag(),
+                },
+                model: model.clone(),
+                mesh: index,
+                model_matrix,
+            });
+        }
+    }
+
+    // Materials without a shader use `shader_program`. `bind_frame` sets per frame uniforms like the camera and lights,
+    // it is called once for every shader the first time it is used
+    pub fn draw(&mut self, shader_program: &Shader, mut bind_frame: impl FnMut(&Shader)) {
+        self.draws.sort_by(|a, b| a.key.draw_order(&b.key));
+
+        let mut prepared_shaders = Vec::new();
+        let (mut current_shader, mut current_material, mut current_state) = (None, None, None);
+        for draw in &self.draws {
+            let model = draw.model.get();
+            let mesh = &model.meshes()[draw.mesh];
+            let material = mesh.material().get();
+            let material_shader = material.shader.as_ref().map(|shader| shader.get());
+            let shader = material_shader.as_deref().unwrap_or(shader_program);
+
+            if current_shader != Some(shader.program_id) {
+                shader.bind();
+                if !prepared_shaders.contains(&shader.program_id) {
+                    bind_frame(shader);
+                    prepared_shaders.push(shader.program_id);
+                }
+                current_shader = Some(shader.program_id);
+                current_material = None;
+            }
+            if current_state != Some(material.render_state) {
+                material.render_state.apply();
+                current_state = Some(material.render_state);
+            }
+            if current_material != Some(draw.key.material) {
+                material.bind(shader);
+                current_material = Some(draw.key.material);
+            }
+
+            shader.set_mat4("model", draw.model_matrix);
+            mesh.draw_geometry();
+        }
+
+        // Back to the state Engine::new sets up, which the skybox and text are drawn with
+        RenderState { blend: BlendMode::Alpha, ..RenderState::default() }.apply();
+        shader_program.bind();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(transparent: bool, shader: GLuint, material: usize, distance: f32) -> DrawKey {
+        DrawKey { transparent, shader, material, distance }
+    }
+
+    #[test]
+    fn opaque_draws_are_grouped_and_transparent_ones_go_back_to_front() {
+        let mut keys = [
+            key(true, 0, 1, 2.0),
+            key(false, 7, 2, 1.0),
+            key(false, 0, 3, 9.0),
+            key(true, 7, 2, 8.0),
+            key(false, 0, 1, 5.0),
+            key(false, 7, 2, 3.0),
+        ];
+        keys.sort_by(DrawKey::draw_order);
+
+        let order: Vec<(bool, GLuint, usize)> = keys.iter().map(|key| (key.transparent, key.shader, key.material)).collect();
+        assert_eq!(order, vec![(false, 0, 1), (false, 0, 3), (false, 7, 2), (false, 7, 2), (true, 7, 2), (true, 0, 1)]);
+    }
+}
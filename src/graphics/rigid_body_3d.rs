@@ -0,0 +1,239 @@
+use ultraviolet::{Mat4, Vec3};
+
+use crate::assets::handle::Handle;
+use crate::graphics::model::Model;
+use crate::graphics::node_3d::Node3D;
+use crate::math::aabb_bouding_box::AABBBoundingBox;
+use crate::physics::TERMINAL_VELOCITY;
+use crate::shader::Shader;
+
+const SLEEP_LINEAR_VELOCITY: f32 = 0.05;
+const SLEEP_ANGULAR_VELOCITY: f32 = 0.05;
+const SLEEP_TIME: f32 = 0.5;
+
+pub struct RigidBody3D {
+    pub node3d: Node3D,
+    previous_node3d: Node3D, // State at the start of the last fixed step, for interpolated rendering
+    pub model: Handle<Model>,
+    pub bounding_box: AABBBoundingBox, // Unrotated and relative to node3d.world_position
+    model_bounds: bool, // The bounding box is the model's scaled bounds, rebuilt when the model reloads
+    pub linear_velocity: Vec3,
+    pub angular_velocity: Vec3,
+    pub restitution: f32,
+    pub friction: f32,
+    pub linear_damping: f32,
+    pub angular_damping: f32,
+    inverse_mass: f32,
+    inverse_inertia: Vec3,
+    force: Vec3,
+    torque: Vec3,
+    sleep_timer: f32,
+    sleeping: bool,
+}
+
+impl RigidBody3D {
+    pub fn new(node3d: Node3D, model: Handle<Model>, bounding_box: AABBBoundingBox, mass: f32) -> Self {
+        let mut body = Self {
+            node3d,
+            previous_node3d: node3d,
+            model,
+            bounding_box,
+            model_bounds: false,
+            linear_velocity: Vec3::default(),
+            angular_velocity: Vec3::default(),
+            restitution: 0.3,
+            friction: 0.5,
+            linear_damping: 0.01,
+            angular_damping: 0.05,
+            inverse_mass: 0.0,
+            inverse_inertia: Vec3::default(),
+            force: Vec3::default(),
+            torque: Vec3::default(),
+            sleep_timer: 0.0,
+            sleeping: false,
+        };
+        body.set_mass(mass);
+
+        body
+    }
+
+    // The collision box is the model's bounds scaled by the node, world_bounding_box rotates it with the body
+    pub fn with_model_bounds(node3d: Node3D, model: Handle<Model>, mass: f32) -> Self {
+        let bounding_box = scaled_model_bounds(&model, node3d.scale);
+
+        Self { model_bounds: true, ..Self::new(node3d, model, bounding_box, mass) }
+    }
+
+    // Called after the model was reloaded so a body built with with_model_bounds collides with its new bounds
+    pub fn refresh_model(&mut self) {
+        if self.model_bounds {
+            self.bounding_box = scaled_model_bounds(&self.model, self.node3d.scale);
+            self.set_mass(self.mass());
+        }
+    }
+
+    pub fn has_model_bounds(&self) -> bool {
+        self.model_bounds
+    }
+
+    // Bounds of the rendered model, these match world_bounding_box for bodies built with with_model_bounds
+    pub fn render_bounding_box(&self) -> AABBBoundingBox {
+        self.node3d.world_bounding_box(self.model.get().bounding_box())
+    }
+
+    // A mass of zero (or less) makes the body immovable
+    pub fn set_mass(&mut self, mass: f32) {
+        if mass <= 0.0 {
+            self.inverse_mass = 0.0;
+            self.inverse_inertia = Vec3::default();
+            return;
+        }
+
+        // Solid box inertia tensor (diagonal)
+        let size = self.bounding_box.size();
+        let (x2, y2, z2) = (size.x * size.x, size.y * size.y, size.z * size.z);
+        let inertia = Vec3::new(y2 + z2, x2 + z2, x2 + y2) * (mass / 12.0);
+
+        self.inverse_mass = 1.0 / mass;
+        self.inverse_inertia = Vec3::new(inverse_or_zero(inertia.x), inverse_or_zero(inertia.y), inverse_or_zero(inertia.z));
+    }
+
+    pub fn mass(&self) -> f32 {
+        inverse_or_zero(self.inverse_mass)
+    }
+
+    pub fn inverse_mass(&self) -> f32 {
+        self.inverse_mass
+    }
+
+    pub fn inverse_inertia(&self) -> Vec3 {
+        self.inverse_inertia
+    }
+
+    pub fn is_static(&self) -> bool {
+        self.inverse_mass == 0.0
+    }
+
+    pub fn is_sleeping(&self) -> bool {
+        self.sleeping
+    }
+
+    pub fn wake_up(&mut self) {
+        self.sleeping = false;
+        self.sleep_timer = 0.0;
+    }
+
+    pub fn apply_force(&mut self, force: Vec3) {
+        self.force += force;
+        self.wake_up();
+    }
+
+    pub fn apply_force_at_point(&mut self, force: Vec3, point: Vec3) {
+        self.apply_force(force);
+        self.torque += (point - self.center_of_mass()).cross(force);
+    }
+
+    pub fn apply_torque(&mut self, torque: Vec3) {
+        self.torque += torque;
+        self.wake_up();
+    }
+
+    pub fn apply_impulse(&mut self, impulse: Vec3) {
+        self.linear_velocity += impulse * self.inverse_mass;
+        self.wake_up();
+    }
+
+    pub fn apply_impulse_at_point(&mut self, impulse: Vec3, point: Vec3) {
+        self.apply_impulse(impulse);
+        self.apply_angular_impulse((point - self.center_of_mass()).cross(impulse));
+    }
+
+    pub fn apply_angular_impulse(&mut self, impulse: Vec3) {
+        self.angular_velocity += impulse * self.inverse_inertia;
+        self.wake_up();
+    }
+
+    // Used by the contact solver, resting contacts must not keep the body awake
+    pub(crate) fn apply_contact_impulse(&mut self, impulse: Vec3, point: Vec3) {
+        self.linear_velocity += impulse * self.inverse_mass;
+        self.angular_velocity += (point - self.center_of_mass()).cross(impulse) * self.inverse_inertia;
+    }
+
+    pub fn center_of_mass(&self) -> Vec3 {
+        self.world_bounding_box().center()
+    }
+
+    // Rebuilt from the current rotation, so a tumbling body's box grows and shrinks with it
+    pub fn world_bounding_box(&self) -> AABBBoundingBox {
+        self.bounding_box.transformed(Mat4::from_translation(self.node3d.world_position) * self.node3d.rotation.rotation_matrix())
+    }
+
+    pub fn velocity_at_point(&self, point: Vec3) -> Vec3 {
+        self.linear_velocity + self.angular_velocity.cross(point - self.center_of_mass())
+    }
+
+    pub fn save_previous_state(&mut self) {
+        self.previous_node3d = self.node3d;
+    }
+
+    pub fn interpolated_node3d(&self, alpha: f32) -> Node3D {
+        self.node3d.interpolated(&self.previous_node3d, alpha)
+    }
+
+    // Semi-implicit Euler, called once per fixed physics step
+    pub fn integrate(&mut self, dt: f32, gravity: Vec3) {
+        if self.is_static() || self.sleeping {
+            self.force = Vec3::default();
+            self.torque = Vec3::default();
+            return;
+        }
+
+        self.linear_velocity += (gravity + self.force * self.inverse_mass) * dt;
+        self.angular_velocity += self.torque * self.inverse_inertia * dt;
+
+        self.linear_velocity *= 1.0 / (1.0 + self.linear_damping * dt);
+        self.angular_velocity *= 1.0 / (1.0 + self.angular_damping * dt);
+        self.linear_velocity.y = self.linear_velocity.y.max(-TERMINAL_VELOCITY);
+
+        self.node3d.world_position += self.linear_velocity * dt;
+        self.node3d.rotation = self.node3d.rotation.integrated(self.angular_velocity, dt);
+
+        self.force = Vec3::default();
+        self.torque = Vec3::default();
+    }
+
+    pub fn update_sleep_state(&mut self, dt: f32) {
+        if self.is_static() {
+            return;
+        }
+
+        if self.linear_velocity.mag() < SLEEP_LINEAR_VELOCITY && self.angular_velocity.mag() < SLEEP_ANGULAR_VELOCITY {
+            self.sleep_timer += dt;
+            if self.sleep_timer >= SLEEP_TIME {
+                self.sleeping = true;
+                self.linear_velocity = Vec3::default();
+                self.angular_velocity = Vec3::default();
+            }
+        } else {
+            self.sleep_timer = 0.0;
+        }
+    }
+
+    pub fn draw(&self, shader_program: &Shader, alpha: f32) {
+        shader_program.set_mat4("model", self.interpolated_node3d(alpha).model_matrix());
+
+        self.model.get().draw(shader_program);
+    }
+}
+
+fn scaled_model_bounds(model: &Handle<Model>, scale: Vec3) -> AABBBoundingBox {
+    model.get().bounding_box().transformed(Mat4::from_nonuniform_scale(scale))
+}
+
+fn inverse_or_zero(value: f32) -> f32 {
+    if value > 0.0 {
+        1.0 / value
+    } else {
+        0.0
+    }
+}
@@ -1,65 +1,245 @@
 use std::collections::HashSet;
 
-use beryllium::events::{SDL_Keycode, SDLK_a, SDLK_d, SDLK_LCTRL, SDLK_s, SDLK_SPACE, SDLK_w};
+use beryllium::events::{SDL_Keycode, SDLK_a, SDLK_c, SDLK_d, SDLK_LCTRL, SDLK_s, SDLK_SPACE, SDLK_w};
 use ultraviolet::{Mat4, Vec3};
 use ultraviolet::projection::perspective_gl;
 
-use crate::{SCR_HEIGHT, SCR_WIDTH};
-use crate::graphics::player_character::{MovementDirection, PlayerCharacter};
+use crate::assets::handle::Handle;
+use crate::ecs::components::{Light, RenderModel, RigidBody, StaticCollider, Transform};
+use crate::ecs::schedule::Schedule;
+use crate::ecs::systems;
+use crate::ecs::world::{Entity, World};
+use crate::error::EngineResult;
+use crate::graphics::ibl::EnvironmentLighting;
+use crate::graphics::lighting::{LightBlock, LightBuffer};
+use crate::graphics::model::Model;
+use crate::graphics::node_3d::Node3D;
+use crate::graphics::player_character::PlayerCharacter;
+use crate::graphics::render_queue::RenderQueue;
+use crate::graphics::rigid_body_3d::RigidBody3D;
+use crate::graphics::scene_graph::{NodeId, SceneGraph};
+use crate::graphics::shadows::{ShadowCamera, ShadowRenderer};
 use crate::graphics::skybox::Skybox;
 use crate::graphics::static_body_3d::StaticBody3D;
 use crate::graphics::true_type_font::TrueTypeFont;
+use crate::math::aabb_bouding_box::AABBBoundingBox;
+use crate::math::frustum::Frustum;
+use crate::math::ray::Ray;
+use crate::opengl;
+use crate::opengl::framebuffer::Framebuffer;
+use crate::physics;
+use crate::physics::{BodyHandle, EntityProxies};
+use crate::physics::broad_phase::{DynamicAabbTree, ProxyId};
+use crate::physics::character_controller::CharacterInput;
+use crate::physics::collider::Collider;
+use crate::physics::query;
+use crate::physics::query::{CastShape, RaycastHit, ShapeCastHit};
 use crate::shader::Shader;
 
+const NEAR_PLANE: f32 = 0.1;
+const FAR_PLANE: f32 = 100.0;
+
 pub struct Scene<'a> {
     static_bodies: Vec<StaticBody3D>,
+    static_proxies: Vec<ProxyId>,
+    rigid_bodies: Vec<RigidBody3D>,
+    rigid_proxies: Vec<ProxyId>,
+    broad_phase: DynamicAabbTree<BodyHandle>, // Also holds the world's StaticCollider and RigidBody entities
+    entity_proxies: EntityProxies,
+    scene_graph: SceneGraph,
+    camera_node: NodeId,
+    static_nodes: Vec<NodeId>,
+    rigid_nodes: Vec<NodeId>,
     skybox: Option<Skybox>,
     player: PlayerCharacter,
-    font: TrueTypeFont<'a>,
+    font: Handle<TrueTypeFont<'a>>,
+    input: CharacterInput, // Sampled once per frame, consumed by every fixed step of that frame
+    world: World, // Game objects that live outside the fixed body lists, updated by the schedule every fixed step
+    schedule: Schedule,
+    message: Option<String>, // Shown on screen under the position text, e.g. asset reload errors
+    ambient_light: Vec3,
+    light_buffer: LightBuffer,
+    shadows: Option<ShadowRenderer>,
+    environment: Option<EnvironmentLighting>, // Replaces the flat ambient light when set
+    gamma_correction: bool, // Encode the output for the display, off when post-processing does it
     // TODO: Gui?
-    // TODO: lights
     // TODO: particles
 }
 
-fn process_input(pressed_keys: &HashSet<SDL_Keycode>, player: &mut PlayerCharacter, dt: f32) {
-    if pressed_keys.contains(&SDLK_w) {
-        player.process_keyboard(MovementDirection::Forward, dt);
+impl<'a> Scene<'a> {
+    pub fn new(static_bodies: Vec<StaticBody3D>, skybox: Option<Skybox>, player: PlayerCharacter, font: Handle<TrueTypeFont<'a>>) -> EngineResult<Self> {
+        let mut broad_phase = DynamicAabbTree::new();
+        let static_proxies = static_bodies.iter().enumerate()
+            .map(|(i, body)| broad_phase.insert(body.bounding_box(), BodyHandle::Static(i)))
+            .collect();
+
+        // Every body gets a node so other nodes can be attached to it, the camera node follows the player's view
+        let mut scene_graph = SceneGraph::new();
+        let camera_node = scene_graph.add_node("camera", player.get_camera_node3d(1.0), None, None);
+        // Static bodies follow their node, rigid bodies drive theirs. Whether a body casts shadows is set on its node
+        let static_nodes = static_bodies.iter().enumerate()
+            .map(|(i, body)| scene_graph.add_node(&format!("static_body_{i}"), body.node3d, Some(body.model.clone()), None))
+            .collect();
+
+        Ok(Self {
+            static_bodies,
+            static_proxies,
+            rigid_bodies: Vec::new(),
+            rigid_proxies: Vec::new(),
+            broad_phase,
+            entity_proxies: EntityProxies::new(),
+            scene_graph,
+            camera_node,
+            static_nodes,
+            rigid_nodes: Vec::new(),
+            skybox,
+            player,
+            font,
+            input: CharacterInput::default(),
+            world: World::new(),
+            schedule: systems::default_schedule(),
+            message: None,
+            ambient_light: Vec3::broadcast(0.15),
+            light_buffer: LightBuffer::new()?,
+            shadows: None,
+            environment: None,
+            gamma_correction: true,
+        })
     }
 
-    if pressed_keys.contains(&SDLK_s) {
-        player.process_keyboard(MovementDirection::Backward, dt);
+    pub fn add_rigid_body(&mut self, body: RigidBody3D) -> usize {
+        let index = self.rigid_bodies.len();
+        self.rigid_proxies.push(self.broad_phase.insert(body.world_bounding_box(), BodyHandle::Rigid(index)));
+        self.rigid_nodes.push(self.scene_graph.add_node(&format!("rigid_body_{index}"), body.node3d, Some(body.model.clone()), None));
+        self.rigid_bodies.push(body);
+
+        index
     }
 
-    if pressed_keys.contains(&SDLK_a) {
-        player.process_keyboard(MovementDirection::Left, dt);
+    // Rebuilds the colliders and bounds of the scene's and the world's bodies whose model was reloaded
+    pub fn refresh_models(&mut self, reloaded_keys: &[String]) {
+        physics::refresh_models(reloaded_keys, &mut self.static_bodies, &self.static_proxies, &mut self.rigid_bodies, &self.rigid_proxies, &self.world, &mut self.broad_phase);
     }
 
-    if pressed_keys.contains(&SDLK_d) {
-        player.process_keyboard(MovementDirection::Right, dt);
+    pub fn scene_graph(&self) -> &SceneGraph {
+        &self.scene_graph
     }
 
-    if pressed_keys.contains(&SDLK_SPACE) {
-        player.process_keyboard(MovementDirection::Up, dt);
+    pub fn scene_graph_mut(&mut self) -> &mut SceneGraph {
+        &mut self.scene_graph
     }
 
-    if pressed_keys.contains(&SDLK_LCTRL) {
-        player.process_keyboard(MovementDirection::Down, dt);
+    pub fn camera_node(&self) -> NodeId {
+        self.camera_node
     }
-}
 
-impl Scene<'_> {
-    pub fn new(static_bodies: Vec<StaticBody3D>, skybox: Option<Skybox>, player: PlayerCharacter) -> Self {
-        let font = TrueTypeFont::load_from_file("res/fonts/futura.ttf");
+    pub fn static_body_node(&self, index: usize) -> NodeId {
+        self.static_nodes[index]
+    }
 
-        Self {
-            static_bodies,
-            skybox,
-            player,
-            font,
-        }
+    pub fn rigid_body_node(&self, index: usize) -> NodeId {
+        self.rigid_nodes[index]
+    }
+
+    pub fn static_body_nodes(&self) -> &[NodeId] {
+        &self.static_nodes
+    }
+
+    pub fn rigid_body_nodes(&self) -> &[NodeId] {
+        &self.rigid_nodes
+    }
+
+    // Adds a node that moves with `parent`, e.g. a weapon under the camera node or a lamp under a static body's node
+    pub fn attach(&mut self, name: &str, local: Node3D, model: Option<Handle<Model>>, parent: NodeId) -> NodeId {
+        self.scene_graph.add_node(name, local, model, Some(parent))
+    }
+
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    pub fn world_mut(&mut self) -> &mut World {
+        &mut self.world
+    }
+
+    pub fn schedule_mut(&mut self) -> &mut Schedule {
+        &mut self.schedule
+    }
+
+    // Lights are entities of the scene's world, this spawns one with a Transform for its position and rotation
+    pub fn add_light(&mut self, light: Light, node3d: Node3D) -> Entity {
+        let entity = self.world.spawn();
+        self.world.insert(entity, light);
+        self.world.insert(entity, Transform::new(node3d));
+
+        entity
+    }
+
+    pub fn ambient_light(&self) -> Vec3 {
+        self.ambient_light
+    }
+
+    pub fn set_ambient_light(&mut self, ambient_light: Vec3) {
+        self.ambient_light = ambient_light;
+    }
+
+    pub fn shadow_renderer(&self) -> Option<&ShadowRenderer> {
+        self.shadows.as_ref()
+    }
+
+    pub fn shadow_renderer_mut(&mut self) -> Option<&mut ShadowRenderer> {
+        self.shadows.as_mut()
+    }
+
+    pub fn set_shadow_renderer(&mut self, shadows: Option<ShadowRenderer>) {
+        self.shadows = shadows;
+    }
+
+    pub fn skybox(&self) -> Option<&Skybox> {
+        self.skybox.as_ref()
+    }
+
+    pub fn environment_lighting(&self) -> Option<&EnvironmentLighting> {
+        self.environment.as_ref()
+    }
+
+    pub fn environment_lighting_mut(&mut self) -> Option<&mut EnvironmentLighting> {
+        self.environment.as_mut()
+    }
+
+    pub fn set_environment_lighting(&mut self, environment: Option<EnvironmentLighting>) {
+        self.environment = environment;
+    }
+
+    pub fn set_gamma_correction(&mut self, gamma_correction: bool) {
+        self.gamma_correction = gamma_correction;
+    }
+
+    pub fn set_message(&mut self, message: Option<String>) {
+        self.message = message;
+    }
+
+    pub fn static_bodies(&self) -> &[StaticBody3D] {
+        &self.static_bodies
     }
 
-    pub fn update(&mut self, delta_time: f32, held_keys: &HashSet<SDL_Keycode>, mouse_delta: Option<(i32, i32)>) {
+    pub fn player(&self) -> &PlayerCharacter {
+        &self.player
+    }
+
+    pub fn font(&self) -> &Handle<TrueTypeFont<'a>> {
+        &self.font
+    }
+
+    pub fn rigid_bodies(&self) -> &[RigidBody3D] {
+        &self.rigid_bodies
+    }
+
+    pub fn rigid_bodies_mut(&mut self) -> &mut [RigidBody3D] {
+        &mut self.rigid_bodies
+    }
+
+    pub fn process_input(&mut self, held_keys: &HashSet<SDL_Keycode>, mouse_delta: Option<(i32, i32)>) {
         if let Some(delta) = mouse_delta { self.player.process_mouse_movement(delta.0 as f32, delta.1 as f32, true) }
 
 
@@ -67,68 +247,177 @@ impl Scene<'_> {
         let right_direction = self.player.get_right_direction();
         let forward_direction = Vec3::new(look_direction.x, 0.0, look_direction.z);
 
-        let mut desired_movement = Vec3::default();
-        let speed = 10.0;
-        let gravity = 0.981;
+        let mut move_direction = Vec3::default();
 
         if held_keys.contains(&SDLK_w) {
-            desired_movement += forward_direction;
+            move_direction += forward_direction;
         } else if held_keys.contains(&SDLK_s) {
-            desired_movement -= forward_direction;
+            move_direction -= forward_direction;
         }
 
         if held_keys.contains(&SDLK_a) {
-            desired_movement -= right_direction;
+            move_direction -= right_direction;
         } else if held_keys.contains(&SDLK_d) {
-            desired_movement += right_direction;
+            move_direction += right_direction;
         }
 
-        desired_movement *= speed * delta_time;
+        self.input = CharacterInput {
+            move_direction,
+            jump: held_keys.contains(&SDLK_SPACE),
+            crouch: held_keys.contains(&SDLK_LCTRL) || held_keys.contains(&SDLK_c),
+        };
+    }
+
+    // Called zero or more times per frame by the engine loop, always with the same dt
+    pub fn fixed_update(&mut self, dt: f32) {
+        for body in self.rigid_bodies.iter_mut() {
+            body.save_previous_state();
+        }
+
+        self.step_physics(dt);
+        self.schedule.run(&mut self.world, dt);
+
+        // TODO: Update particles
+    }
 
-        let collides_with_ground = self.player.check_collision(self.static_bodies[0].bounding_box);
+    fn step_physics(&mut self, dt: f32) {
+        physics::sync_static_bodies(&mut self.static_bodies, &self.static_proxies, &self.static_nodes, &self.scene_graph, &mut self.broad_phase);
+        physics::sync_entity_proxies(&self.world, &mut self.entity_proxies, &mut self.broad_phase, dt);
 
-        if collides_with_ground && held_keys.contains(&SDLK_SPACE) {
-            self.player.add_vertical_velocity(1.0 * 50.0 * delta_time);
+        let reach_bounding_box = self.player.get_reach_bounding_box(dt);
+        let static_colliders = self.world.storage::<StaticCollider>();
+        let mut static_obstacles = Vec::new();
+        let mut rigid_obstacles = Vec::new();
+        for handle in self.broad_phase.query(reach_bounding_box) {
+            match handle {
+                BodyHandle::Static(index) => static_obstacles.push(self.static_bodies[index].collider()),
+                BodyHandle::StaticEntity(entity) => static_obstacles.extend(static_colliders.as_ref().and_then(|storage| storage.get(entity)).map(|static_collider| static_collider.collider())),
+                BodyHandle::Rigid(_) | BodyHandle::RigidEntity(_) => rigid_obstacles.extend(self.body_bounding_box(handle).map(Collider::from_bounding_box)),
+            }
         }
 
-        let mut pos = self.player.get_position();
-        if collides_with_ground && !held_keys.contains(&SDLK_SPACE) {
-            pos.y = self.static_bodies[0].bounding_box.y_max + self.player.get_half_height();
-            self.player.reset_vertical_velocity();
-        } else {
-            self.player.add_vertical_velocity(-1.0 * gravity * delta_time);
-            pos.y += self.player.get_vertical_velocity();
+        static_obstacles.extend(rigid_obstacles.iter());
+        self.player.step(&self.input, dt, &static_obstacles);
+
+        physics::step_rigid_bodies(&mut self.rigid_bodies, &self.rigid_proxies, &self.static_bodies, &self.world, &mut self.entity_proxies, &mut self.broad_phase, dt);
+    }
+
+    // None for an entity that was despawned or lost its body since the last physics step
+    pub fn body_bounding_box(&self, handle: BodyHandle) -> Option<AABBBoundingBox> {
+        match handle {
+            BodyHandle::Static(index) => Some(self.static_bodies[index].bounding_box()),
+            BodyHandle::Rigid(index) => Some(self.rigid_bodies[index].world_bounding_box()),
+            BodyHandle::StaticEntity(entity) => self.world.get::<StaticCollider>(entity).map(|static_collider| static_collider.collider().bounding_box()),
+            BodyHandle::RigidEntity(entity) => self.world.get::<RigidBody>(entity).map(|rigid_body| rigid_body.body.world_bounding_box()),
         }
+    }
 
-        pos += desired_movement;
-        // TODO: Detect direction of collision and push player out in this direction
-        self.player.set_position(pos.x, pos.y, pos.z);
+    // Static entities without a RenderModel have no triangles to test
+    fn body_matrix_and_model(&self, handle: BodyHandle) -> Option<(Mat4, Handle<Model>)> {
+        match handle {
+            BodyHandle::Static(index) => Some((self.static_bodies[index].world_matrix(), self.static_bodies[index].model.clone())),
+            BodyHandle::Rigid(index) => Some((self.rigid_bodies[index].node3d.model_matrix(), self.rigid_bodies[index].model.clone())),
+            BodyHandle::StaticEntity(entity) => {
+                let (render_model, transform) = (self.world.get::<RenderModel>(entity)?, self.world.get::<Transform>(entity)?);
+                Some((transform.node3d.model_matrix(), render_model.model.clone()))
+            }
+            BodyHandle::RigidEntity(entity) => self.world.get::<RigidBody>(entity).map(|rigid_body| (rigid_body.body.node3d.model_matrix(), rigid_body.body.model.clone())),
+        }
+    }
 
-        // TODO: Update particles, lights, dynamic meshes (entities)
+    // Bodies are first tested against their bounding boxes, then against the triangles of their model
+    pub fn raycast(&self, origin: Vec3, direction: Vec3, max_distance: f32, filter: impl Fn(BodyHandle) -> bool) -> Option<RaycastHit> {
+        let ray = Ray::new(origin, direction.normalized());
+
+        query::raycast_bodies(&self.broad_phase, &ray, max_distance, filter, |handle| self.body_bounding_box(handle), |handle, max| {
+            match self.body_matrix_and_model(handle) {
+                Some((model_matrix, model)) => query::raycast_model(&ray, model_matrix, &model.get(), max),
+                None => ray.intersect_aabb(&self.body_bounding_box(handle)?, max),
+            }
+        })
     }
 
-    pub fn draw(&mut self, shader_program: &Shader, shader_program_font: &Shader) {
-        shader_program.bind();
+    pub fn sphere_cast(&self, origin: Vec3, radius: f32, direction: Vec3, max_distance: f32, filter: impl Fn(BodyHandle) -> bool) -> Option<ShapeCastHit> {
+        self.shape_cast(CastShape::Sphere { radius }, origin, direction, max_distance, filter)
+    }
 
-        let projection = perspective_gl(self.player.get_camera_zoom().to_radians(), SCR_WIDTH as f32 / SCR_HEIGHT as f32, 0.1, 100.0);
-        shader_program.set_mat4("projection", projection);
+    pub fn box_cast(&self, center: Vec3, half_extents: Vec3, direction: Vec3, max_distance: f32, filter: impl Fn(BodyHandle) -> bool) -> Option<ShapeCastHit> {
+        self.shape_cast(CastShape::Box { half_extents }, center, direction, max_distance, filter)
+    }
 
-        let view = self.player.get_camera_view_matrix();
-        shader_program.set_mat4("view", view);
+    pub fn shape_cast(&self, shape: CastShape, origin: Vec3, direction: Vec3, max_distance: f32, filter: impl Fn(BodyHandle) -> bool) -> Option<ShapeCastHit> {
+        let ray = Ray::new(origin, direction.normalized());
 
-        // TODO: Not sure if we need to pass shader from the outside or shaders will be loaded into scene
-        for body in &self.static_bodies {
-            body.draw(shader_program);
+        query::shape_cast_bodies(&self.broad_phase, shape, &ray, max_distance, filter, |handle| self.body_bounding_box(handle))
+    }
+
+    pub fn broad_phase(&self) -> &DynamicAabbTree<BodyHandle> {
+        &self.broad_phase
+    }
+
+    // `alpha` blends moving bodies between their last two fixed steps
+    pub fn draw(&mut self, shader_program: &Shader, aspect_ratio: f32, alpha: f32) {
+        let fov_y = self.player.get_camera_zoom().to_radians();
+        let projection = perspective_gl(fov_y, aspect_ratio, NEAR_PLANE, FAR_PLANE);
+        let view = self.player.get_interpolated_camera_view_matrix(alpha);
+        let frustum = Frustum::from_view_projection(projection * view);
+
+        self.scene_graph.set_local_transform(self.camera_node, self.player.get_camera_node3d(alpha));
+        for (body, node) in self.rigid_bodies.iter().zip(&self.rigid_nodes) {
+            self.scene_graph.set_local_transform(*node, body.interpolated_node3d(alpha));
         }
 
-        if self.skybox.as_ref().is_some() {
-            self.skybox.as_ref().unwrap().draw(view, projection);
+        let camera_position = self.player.get_camera_node3d(alpha).world_position;
+        let mut lights = LightBlock::collect(&self.world, self.ambient_light, camera_position);
+
+        // Shadow passes go first, they mark which lights got a shadow map
+        let shadow_frame = self.shadows.as_ref().map(|shadows| {
+            let (framebuffer, viewport) = (Framebuffer::current_binding(), opengl::get_viewport());
+            let camera = ShadowCamera { view, fov_y, aspect_ratio, near: NEAR_PLANE };
+            let frame = shadows.render(&mut lights, &camera, &self.scene_graph, &self.world, alpha);
+            Framebuffer::restore_binding(framebuffer);
+            opengl::viewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+            frame
+        });
+
+        self.light_buffer.upload(&lights);
+
+        // TODO: Not sure if we need to pass shader from the outside or shaders will be loaded into scene
+        let mut queue = RenderQueue::new(camera_position);
+        self.scene_graph.queue_draws(&mut queue, &frustum);
+        systems::queue_models(&self.world, &mut queue, &frustum, alpha);
+        queue.draw(shader_program, |shader| {
+            shader.set_mat4("projection", projection);
+            shader.set_mat4("view", view);
+            shader.set_bool("gammaCorrect", self.gamma_correction);
+            self.light_buffer.bind_to(shader);
+            match (&self.shadows, &shadow_frame) {
+                (Some(shadows), Some(frame)) => shadows.bind(shader, frame),
+                _ => ShadowRenderer::bind_disabled(shader),
+            }
+            match &self.environment {
+                Some(environment) => environment.bind(shader),
+                None => EnvironmentLighting::bind_disabled(shader),
+            }
+        });
+
+        if let Some(skybox) = &self.skybox {
+            skybox.draw(view, projection, self.gamma_correction);
         }
+    }
 
+    // Text on top of the finished frame, after post-processing
+    pub fn draw_overlay(&mut self, shader_program_font: &Shader) {
         let text_translation = Mat4::from_translation(Vec3::new(0.1, -1.5, 0.0));
         // TODO: It needs orthogonal projection so that actual screen pixel positions can be used
 
         let player_pos = self.player.get_position();
-        self.font.draw(shader_program_font, format!("X: {} Y: {} Z: {}", player_pos.x, player_pos.y, player_pos.z).as_str(), 32.0, text_translation);
+        let mut text = format!("X: {} Y: {} Z: {}", player_pos.x, player_pos.y, player_pos.z);
+        if let Some(message) = &self.message {
+            // Font layout only breaks lines on carriage returns
+            text.push('\r');
+            text.push_str(&message.replace('\n', "\r"));
+        }
+        self.font.get_mut().draw(shader_program_font, text.as_str(), 32.0, text_translation);
     }
 }
\ No newline at end of file
@@ -0,0 +1,702 @@
+use serde::{Deserialize, Serialize};
+use ultraviolet::Vec3;
+
+use crate::assets::handle::Handle;
+use crate::assets::{split_shader_key, AssetManager};
+use crate::camera::Camera;
+use crate::ecs::components::{Light, LightKind, Transform};
+use crate::ecs::world::World;
+use crate::error::{EngineError, EngineResult};
+use crate::graphics::ibl::EnvironmentLighting;
+use crate::graphics::model::Model;
+use crate::graphics::node_3d::Node3D;
+use crate::graphics::player_character::PlayerCharacter;
+use crate::graphics::rigid_body_3d::RigidBody3D;
+use crate::graphics::scene::Scene;
+use crate::graphics::scene_graph::{NodeId, SceneGraph};
+use crate::graphics::shadows::{POINT_SHADOW_FRAGMENT_SHADER, SHADOW_FRAGMENT_SHADER, SHADOW_VERTEX_SHADER, ShadowRenderer, ShadowSettings};
+use crate::graphics::skybox::Skybox;
+use crate::graphics::static_body_3d::StaticBody3D;
+use crate::math::aabb_bouding_box::AABBBoundingBox;
+use crate::math::rotation::{EulerOrder, Rotation};
+use crate::physics::collision_shape::CollisionShape;
+
+// Level layout as stored on disk (JSON), turned into a Scene by build() and read back from one by from_scene()
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SceneDescription {
+    pub player: PlayerDescription,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skybox: Option<SkyboxDescription>,
+    #[serde(default)]
+    pub static_bodies: Vec<StaticBodyDescription>,
+    #[serde(default)]
+    pub rigid_bodies: Vec<RigidBodyDescription>,
+    #[serde(default)]
+    pub nodes: Vec<NodeDescription>, // A node's parent has to be listed before it
+    #[serde(default)]
+    pub lights: Vec<LightDescription>,
+    #[serde(default = "default_ambient_light")]
+    pub ambient_light: Vec3,
+    #[serde(default)]
+    pub shadows: ShadowSettings,
+    #[serde(default = "default_font")]
+    pub font: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PlayerDescription {
+    pub position: Vec3, // Feet
+    #[serde(default = "default_player_height")]
+    pub height: f32,
+    #[serde(default)]
+    pub yaw: f32, // Degrees, like Camera
+    #[serde(default)]
+    pub pitch: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SkyboxDescription {
+    pub vertex_shader: String,
+    pub fragment_shader: String,
+    pub faces: [String; 6], // Right, left, top, bottom, front, back
+    #[serde(default = "default_image_based_lighting")]
+    pub image_based_lighting: bool, // Lights the scene with the sky instead of the flat ambient light
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TransformDescription {
+    pub position: Vec3,
+    #[serde(default)]
+    pub rotation: Vec3, // Euler angles in degrees, applied in XYZ order
+    #[serde(default = "unit_scale")]
+    pub scale: Vec3,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(tag = "type")]
+pub enum CollisionDescription {
+    #[default]
+    ModelTriangles,
+    ModelBoundingBox,
+    ModelConvexHull, // Falls back to the bounding box for flat models
+    Box { half_extents: Vec3 },
+    Sphere { radius: f32 },
+    Capsule { radius: f32, half_height: f32 },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct StaticBodyDescription {
+    pub model: String,
+    pub transform: TransformDescription,
+    #[serde(default)]
+    pub collision: CollisionDescription,
+    #[serde(default = "default_casts_shadows")]
+    pub casts_shadows: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BoundingBoxDescription {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RigidBodyDescription {
+    pub model: String,
+    pub transform: TransformDescription,
+    pub mass: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bounding_box: Option<BoundingBoxDescription>, // Relative to the body's position, the scaled model bounds when missing
+    #[serde(default)]
+    pub linear_velocity: Vec3,
+    #[serde(default)]
+    pub angular_velocity: Vec3,
+    #[serde(default = "default_casts_shadows")]
+    pub casts_shadows: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum ParentDescription {
+    Camera,
+    StaticBody(usize),
+    RigidBody(usize),
+    Node(String),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct NodeDescription {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    pub transform: TransformDescription,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<ParentDescription>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type")]
+pub enum LightKindDescription {
+    Directional { direction: Vec3 },
+    Point { position: Vec3, range: f32 },
+    Spot { position: Vec3, direction: Vec3, range: f32, inner_angle: f32, outer_angle: f32 }, // Degrees
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LightDescription {
+    #[serde(flatten)]
+    pub kind: LightKindDescription,
+    #[serde(default = "unit_scale")]
+    pub color: Vec3,
+    #[serde(default = "default_light_intensity")]
+    pub intensity: f32,
+}
+
+fn default_font() -> String {
+    "res/fonts/futura.ttf".to_owned()
+}
+
+fn default_player_height() -> f32 {
+    1.6
+}
+
+fn default_ambient_light() -> Vec3 {
+    Vec3::broadcast(0.15)
+}
+
+fn default_image_based_lighting() -> bool {
+    true
+}
+
+fn default_casts_shadows() -> bool {
+    true
+}
+
+fn default_light_intensity() -> f32 {
+    1.0
+}
+
+fn unit_scale() -> Vec3 {
+    Vec3::new(1.0, 1.0, 1.0)
+}
+
+// Only assets loaded through the AssetManager remember the file they came from
+fn asset_path<T>(handle: &Handle<T>, what: &str) -> EngineResult<String> {
+    handle.key().map(str::to_owned).ok_or_else(|| EngineError::SceneSave(format!("{what} was not loaded from a file")))
+}
+
+// Nodes the scene creates for the camera and its bodies, descriptions refer to them as parents
+struct ParentNodes<'a> {
+    camera: NodeId,
+    static_bodies: &'a [NodeId],
+    rigid_bodies: &'a [NodeId],
+}
+
+impl ParentNodes<'_> {
+    fn node(&self, parent: &ParentDescription, scene_graph: &SceneGraph) -> Option<NodeId> {
+        match parent {
+            ParentDescription::Camera => Some(self.camera),
+            ParentDescription::StaticBody(index) => self.static_bodies.get(*index).copied(),
+            ParentDescription::RigidBody(index) => self.rigid_bodies.get(*index).copied(),
+            ParentDescription::Node(name) => scene_graph.find(name),
+        }
+    }
+
+    fn description(&self, node: NodeId, scene_graph: &SceneGraph) -> ParentDescription {
+        if node == self.camera {
+            ParentDescription::Camera
+        } else if let Some(index) = self.static_bodies.iter().position(|body_node| *body_node == node) {
+            ParentDescription::StaticBody(index)
+        } else if let Some(index) = self.rigid_bodies.iter().position(|body_node| *body_node == node) {
+            ParentDescription::RigidBody(index)
+        } else {
+            ParentDescription::Node(scene_graph.name(node).unwrap_or_default().to_owned())
+        }
+    }
+
+    fn is_scene_node(&self, node: NodeId) -> bool {
+        node == self.camera || self.static_bodies.contains(&node) || self.rigid_bodies.contains(&node)
+    }
+}
+
+impl PlayerDescription {
+    pub fn from_player(player: &PlayerCharacter) -> Self {
+        Self { position: player.get_position(), height: player.controller().config.height, yaw: player.camera().yaw, pitch: player.camera().pitch }
+    }
+
+    pub fn player(&self) -> PlayerCharacter {
+        let camera = Camera::from_vec3(Vec3::default(), Vec3::unit_y(), self.yaw, self.pitch);
+        let node3d = Node3D { world_position: self.position, scale: unit_scale(), rotation: Rotation::default() };
+
+        PlayerCharacter::new(node3d, camera, self.height)
+    }
+}
+
+impl SkyboxDescription {
+    pub fn from_skybox(skybox: &Skybox, image_based_lighting: bool) -> EngineResult<Self> {
+        let shader_key = asset_path(skybox.shader_program(), "skybox shader")?;
+        let (vertex_shader, fragment_shader) = split_shader_key(&shader_key);
+
+        Ok(Self { vertex_shader: vertex_shader.to_owned(), fragment_shader: fragment_shader.to_owned(), faces: skybox.face_paths().clone(), image_based_lighting })
+    }
+}
+
+impl StaticBodyDescription {
+    pub fn from_body(body: &StaticBody3D, casts_shadows: bool) -> EngineResult<Self> {
+        Ok(Self {
+            model: asset_path(&body.model, "static body model")?,
+            transform: TransformDescription::from_node3d(&Node3D::from_matrix(body.world_matrix())),
+            collision: CollisionDescription::from_shape(body.shape()),
+            casts_shadows,
+        })
+    }
+
+    pub fn body(&self, model: Handle<Model>) -> StaticBody3D {
+        if let CollisionDescription::ModelTriangles = self.collision {
+            return StaticBody3D::with_model_collision(self.transform.node3d(), model);
+        }
+
+        let shape = self.collision.shape(&model.get());
+
+        StaticBody3D::new(self.transform.node3d(), model, shape)
+    }
+}
+
+impl RigidBodyDescription {
+    // The bounding box is left out for bodies built with with_model_bounds, so they follow their model when it reloads
+    pub fn from_body(body: &RigidBody3D, casts_shadows: bool) -> EngineResult<Self> {
+        let bounding_box = (!body.has_model_bounds()).then(|| BoundingBoxDescription { min: body.bounding_box.min(), max: body.bounding_box.max() });
+
+        Ok(Self {
+            model: asset_path(&body.model, "rigid body model")?,
+            transform: TransformDescription::from_node3d(&body.node3d),
+            mass: body.mass(),
+            bounding_box,
+            linear_velocity: body.linear_velocity,
+            angular_velocity: body.angular_velocity,
+            casts_shadows,
+        })
+    }
+
+    pub fn body(&self, model: Handle<Model>) -> RigidBody3D {
+        let node3d = self.transform.node3d();
+        let mut body = match &self.bounding_box {
+            Some(bounds) => RigidBody3D::new(node3d, model, AABBBoundingBox::from_min_max(bounds.min, bounds.max), self.mass),
+            None => RigidBody3D::with_model_bounds(node3d, model, self.mass),
+        };
+        body.linear_velocity = self.linear_velocity;
+        body.angular_velocity = self.angular_velocity;
+
+        body
+    }
+}
+
+impl NodeDescription {
+    // Every node the scene didn't create for its camera or bodies, parents before their children
+    fn from_scene_graph(scene_graph: &SceneGraph, parents: &ParentNodes) -> EngineResult<Vec<Self>> {
+        let mut descriptions = Vec::new();
+        let mut stack: Vec<NodeId> = scene_graph.ids().filter(|id| scene_graph.parent(*id).is_none()).collect();
+        stack.reverse();
+
+        while let Some(id) = stack.pop() {
+            stack.extend(scene_graph.children(id).iter().rev());
+            if parents.is_scene_node(id) {
+                continue;
+            }
+
+            let name = scene_graph.name(id).unwrap_or_default().to_owned();
+            let model = scene_graph.model(id).map(|model| asset_path(model, &format!("model of node {name}"))).transpose()?;
+            let transform = TransformDescription::from_node3d(scene_graph.local_transform(id).unwrap());
+            let parent = scene_graph.parent(id).map(|parent| parents.description(parent, scene_graph));
+            descriptions.push(Self { name, model, transform, parent });
+        }
+
+        Ok(descriptions)
+    }
+
+    fn add_to(descriptions: &[Self], scene_graph: &mut SceneGraph, parents: &ParentNodes, mut load_model: impl FnMut(&str) -> EngineResult<Handle<Model>>) -> EngineResult<()> {
+        for description in descriptions {
+            let model = description.model.as_deref().map(&mut load_model).transpose()?;
+            let parent = match &description.parent {
+                None => None,
+                Some(parent) => match parents.node(parent, scene_graph) {
+                    Some(node) => Some(node),
+                    None => return Err(EngineError::SceneReference { node: description.name.clone(), parent: format!("{parent:?}") }),
+                },
+            };
+
+            scene_graph.add_node(&description.name, description.transform.node3d(), model, parent);
+        }
+
+        Ok(())
+    }
+}
+
+impl TransformDescription {
+    pub fn from_node3d(node3d: &Node3D) -> Self {
+        let rotation = node3d.rotation.to_euler(EulerOrder::XYZ);
+
+        Self {
+            position: node3d.world_position,
+            rotation: Vec3::new(rotation.x.to_degrees(), rotation.y.to_degrees(), rotation.z.to_degrees()),
+            scale: node3d.scale,
+        }
+    }
+
+    pub fn node3d(&self) -> Node3D {
+        let rotation = Vec3::new(self.rotation.x.to_radians(), self.rotation.y.to_radians(), self.rotation.z.to_radians());
+
+        Node3D { world_position: self.position, scale: self.scale, rotation: Rotation::from_euler(rotation, EulerOrder::XYZ) }
+    }
+}
+
+impl CollisionDescription {
+    // Model bounding boxes come back as the Box they were turned into
+    pub fn from_shape(shape: &CollisionShape) -> Self {
+        match shape {
+            CollisionShape::TriangleMesh(_) => CollisionDescription::ModelTriangles,
+            CollisionShape::ConvexHull(_) => CollisionDescription::ModelConvexHull,
+            CollisionShape::Box { half_extents } => CollisionDescription::Box { half_extents: *half_extents },
+            CollisionShape::Sphere { radius } => CollisionDescription::Sphere { radius: *radius },
+            CollisionShape::Capsule { radius, half_height } => CollisionDescription::Capsule { radius: *radius, half_height: *half_height },
+        }
+    }
+
+    fn shape(&self, model: &Model) -> CollisionShape {
+        match self {
+            CollisionDescription::ModelTriangles => CollisionShape::triangle_mesh_from_model(model),
+            CollisionDescription::ModelBoundingBox => CollisionShape::bounding_box_from_model(model),
+            CollisionDescription::ModelConvexHull => CollisionShape::convex_hull_from_model(model).unwrap_or_else(|| CollisionShape::bounding_box_from_model(model)),
+            CollisionDescription::Box { half_extents } => CollisionShape::Box { half_extents: *half_extents },
+            CollisionDescription::Sphere { radius } => CollisionShape::Sphere { radius: *radius },
+            CollisionDescription::Capsule { radius, half_height } => CollisionShape::Capsule { radius: *radius, half_height: *half_height },
+        }
+    }
+}
+
+impl LightDescription {
+    pub fn from_light(light: &Light, node3d: &Node3D) -> Self {
+        let position = node3d.world_position;
+        let kind = match light.kind {
+            LightKind::Directional { direction } => LightKindDescription::Directional { direction },
+            LightKind::Point { range } => LightKindDescription::Point { position, range },
+            LightKind::Spot { direction, range, inner_angle, outer_angle } => {
+                LightKindDescription::Spot { position, direction, range, inner_angle: inner_angle.to_degrees(), outer_angle: outer_angle.to_degrees() }
+            }
+        };
+
+        Self { kind, color: light.color, intensity: light.intensity }
+    }
+
+    // Every entity with a Light and a Transform, other entities are not part of the format
+    pub fn from_world(world: &World) -> Vec<Self> {
+        let mut lights = Vec::new();
+        world.query2::<Light, Transform>(|_, light, transform| lights.push(Self::from_light(light, &transform.node3d)));
+
+        lights
+    }
+
+    pub fn light(&self) -> (Light, Node3D) {
+        let (kind, position) = match self.kind {
+            LightKindDescription::Directional { direction } => (LightKind::Directional { direction }, Vec3::zero()),
+            LightKindDescription::Point { position, range } => (LightKind::Point { range }, position),
+            LightKindDescription::Spot { position, direction, range, inner_angle, outer_angle } => {
+                (LightKind::Spot { direction, range, inner_angle: inner_angle.to_radians(), outer_angle: outer_angle.to_radians() }, position)
+            }
+        };
+
+        (Light::new(kind, self.color, self.intensity), Node3D { world_position: position, scale: unit_scale(), rotation: Rotation::default() })
+    }
+}
+
+impl SceneDescription {
+    pub fn load_from_file(path: &str) -> EngineResult<Self> {
+        let json = std::fs::read_to_string(path).map_err(|source| EngineError::Io { path: path.to_owned(), source })?;
+
+        serde_json::from_str(&json).map_err(|source| EngineError::SceneParse { path: path.to_owned(), source })
+    }
+
+    pub fn save_to_file(&self, path: &str) -> EngineResult<()> {
+        std::fs::write(path, self.to_json()).map_err(|source| EngineError::Io { path: path.to_owned(), source })
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("scene descriptions only contain serializable data")
+    }
+
+    // Models, textures and shaders come from `assets`, so scenes that share a file share the loaded asset
+    pub fn build(&self, assets: &mut AssetManager) -> EngineResult<Scene<'static>> {
+        let mut static_bodies = Vec::with_capacity(self.static_bodies.len());
+        for description in &self.static_bodies {
+            static_bodies.push(description.body(assets.load_model(&description.model)?));
+        }
+
+        let skybox = match &self.skybox {
+            Some(description) => {
+                let shader_program = assets.load_shader(&description.vertex_shader, &description.fragment_shader)?;
+                Some(Skybox::new_from_image_paths(shader_program, description.faces.each_ref().map(String::as_str))?)
+            }
+            None => None,
+        };
+
+        let font = assets.load_font(&self.font)?;
+        let mut scene = Scene::new(static_bodies, skybox, self.player.player(), font)?;
+        for (index, description) in self.static_bodies.iter().enumerate() {
+            let node = scene.static_body_node(index);
+            scene.scene_graph_mut().set_casts_shadows(node, description.casts_shadows);
+        }
+
+        for description in &self.rigid_bodies {
+            let index = scene.add_rigid_body(description.body(assets.load_model(&description.model)?));
+            let node = scene.rigid_body_node(index);
+            scene.scene_graph_mut().set_casts_shadows(node, description.casts_shadows);
+        }
+
+        scene.set_ambient_light(self.ambient_light);
+        for description in &self.lights {
+            let (light, node3d) = description.light();
+            scene.add_light(light, node3d);
+        }
+
+        if self.shadows.enabled {
+            let depth_shader = assets.load_shader(SHADOW_VERTEX_SHADER, SHADOW_FRAGMENT_SHADER)?;
+            let point_depth_shader = assets.load_shader(SHADOW_VERTEX_SHADER, POINT_SHADOW_FRAGMENT_SHADER)?;
+            scene.set_shadow_renderer(Some(ShadowRenderer::new(self.shadows, depth_shader, point_depth_shader)?));
+        }
+
+        let image_based_lighting = self.skybox.as_ref().is_some_and(|description| description.image_based_lighting);
+        if let (true, Some(skybox)) = (image_based_lighting, scene.skybox()) {
+            let environment = EnvironmentLighting::from_skybox(skybox, assets)?;
+            scene.set_environment_lighting(Some(environment));
+        }
+
+        let (static_nodes, rigid_nodes) = (scene.static_body_nodes().to_vec(), scene.rigid_body_nodes().to_vec());
+        let parents = ParentNodes { camera: scene.camera_node(), static_bodies: &static_nodes, rigid_bodies: &rigid_nodes };
+        NodeDescription::add_to(&self.nodes, scene.scene_graph_mut(), &parents, |path| assets.load_model(path))?;
+
+        Ok(scene)
+    }
+
+    // Describes the scene as it is now, e.g. after moving things around in game. Fails when one of its assets
+    // was created in code, as the description can only refer to files
+    pub fn from_scene(scene: &Scene) -> EngineResult<Self> {
+        let scene_graph = scene.scene_graph();
+        let parents = ParentNodes { camera: scene.camera_node(), static_bodies: scene.static_body_nodes(), rigid_bodies: scene.rigid_body_nodes() };
+
+        let static_bodies = scene.static_bodies().iter().zip(scene.static_body_nodes())
+            .map(|(body, node)| StaticBodyDescription::from_body(body, scene_graph.casts_shadows(*node)))
+            .collect::<EngineResult<_>>()?;
+        let rigid_bodies = scene.rigid_bodies().iter().zip(scene.rigid_body_nodes())
+            .map(|(body, node)| RigidBodyDescription::from_body(body, scene_graph.casts_shadows(*node)))
+            .collect::<EngineResult<_>>()?;
+        let skybox = scene.skybox()
+            .map(|skybox| SkyboxDescription::from_skybox(skybox, scene.environment_lighting().is_some()))
+            .transpose()?;
+
+        Ok(Self {
+            player: PlayerDescription::from_player(scene.player()),
+            skybox,
+            static_bodies,
+            rigid_bodies,
+            nodes: NodeDescription::from_scene_graph(scene_graph, &parents)?,
+            lights: LightDescription::from_world(scene.world()),
+            ambient_light: scene.ambient_light(),
+            shadows: scene.shadow_renderer().map_or(ShadowSettings { enabled: false, ..Default::default() }, |shadows| *shadows.settings()),
+            font: asset_path(scene.font(), "font")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+    use crate::assets::handle::AssetCache;
+
+    fn transform(position: Vec3) -> TransformDescription {
+        TransformDescription { position, rotation: Vec3::new(0.0, 40.0, 0.0), scale: Vec3::new(0.05, 0.05, 0.05) }
+    }
+
+    fn description() -> SceneDescription {
+        SceneDescription {
+            player: PlayerDescription { position: Vec3::new(-13.65, 5.6, 13.36), height: 1.6, yaw: -62.0, pitch: -16.29 },
+            skybox: Some(SkyboxDescription {
+                vertex_shader: "res/shaders/skybox.vs".to_owned(),
+                fragment_shader: "res/shaders/skybox.fs".to_owned(),
+                faces: ["right", "left", "top", "bottom", "front", "back"].map(|face| format!("res/models/textures/skybox/{face}.jpg")),
+                image_based_lighting: true,
+            }),
+            static_bodies: vec![
+                StaticBodyDescription { model: "res/models/cottage.obj".to_owned(), transform: transform(Vec3::new(-10.3, 1.25, 5.0)), collision: CollisionDescription::ModelTriangles, casts_shadows: true },
+                StaticBodyDescription { model: "res/models/container.obj".to_owned(), transform: transform(Vec3::default()), collision: CollisionDescription::Capsule { radius: 0.5, half_height: 1.0 }, casts_shadows: false },
+            ],
+            rigid_bodies: vec![
+                RigidBodyDescription {
+                    model: "res/models/container.obj".to_owned(),
+                    transform: transform(Vec3::new(0.0, 3.0, -3.0)),
+                    mass: 1.0,
+                    bounding_box: Some(BoundingBoxDescription { min: Vec3::broadcast(-0.25), max: Vec3::broadcast(0.25) }),
+                    linear_velocity: Vec3::default(),
+                    angular_velocity: Vec3::new(0.5, 1.0, 0.0),
+                    casts_shadows: false,
+                },
+                RigidBodyDescription {
+                    model: "res/models/barrel.obj".to_owned(),
+                    transform: transform(Vec3::new(2.0, 1.0, 0.0)),
+                    mass: 4.0,
+                    bounding_box: None,
+                    linear_velocity: Vec3::new(0.0, -1.0, 0.0),
+                    angular_velocity: Vec3::default(),
+                    casts_shadows: true,
+                },
+            ],
+            nodes: vec![
+                NodeDescription { name: "lamp".to_owned(), model: Some("res/models/container.obj".to_owned()), transform: transform(Vec3::new(0.0, 60.0, 40.0)), parent: Some(ParentDescription::StaticBody(0)) },
+                NodeDescription { name: "bulb".to_owned(), model: None, transform: transform(Vec3::default()), parent: Some(ParentDescription::Node("lamp".to_owned())) },
+            ],
+            lights: vec![
+                LightDescription { kind: LightKindDescription::Directional { direction: Vec3::new(-0.3, -1.0, -0.2) }, color: Vec3::new(1.0, 0.95, 0.85), intensity: 1.0 },
+                LightDescription { kind: LightKindDescription::Spot { position: Vec3::new(0.0, 4.0, 0.0), direction: -Vec3::unit_y(), range: 12.0, inner_angle: 20.0, outer_angle: 30.0 }, color: unit_scale(), intensity: 3.0 },
+            ],
+            ambient_light: default_ambient_light(),
+            shadows: ShadowSettings::default(),
+            font: default_font(),
+        }
+    }
+
+    // Euler angles and scales come back through matrices and quaternions, so numbers only have to match closely
+    fn assert_close(actual: &Value, expected: &Value, path: &str) {
+        match (actual, expected) {
+            (Value::Number(actual), Value::Number(expected)) => assert!((actual.as_f64().unwrap() - expected.as_f64().unwrap()).abs() < 1e-3, "{path}: {actual} != {expected}"),
+            (Value::Array(actual), Value::Array(expected)) => {
+                assert_eq!(actual.len(), expected.len(), "{path}");
+                for (index, (actual, expected)) in actual.iter().zip(expected).enumerate() {
+                    assert_close(actual, expected, &format!("{path}[{index}]"));
+                }
+            }
+            (Value::Object(actual), Value::Object(expected)) => {
+                assert_eq!(actual.keys().collect::<Vec<_>>(), expected.keys().collect::<Vec<_>>(), "{path}");
+                for (key, expected) in expected {
+                    assert_close(&actual[key], expected, &format!("{path}.{key}"));
+                }
+            }
+            _ => assert_eq!(actual, expected, "{path}"),
+        }
+    }
+
+    #[test]
+    fn scene_parts_round_trip_through_build_and_from_scene() {
+        // Scene::new needs a GL context, so this goes through the per-part conversions build and from_scene use
+        let description = description();
+        let mut models = AssetCache::new();
+        let mut load_model = |path: &str| models.get_or_load(path, |_| Ok(Model::from_meshes(Vec::new())));
+
+        let player = description.player.player();
+        let static_bodies: Vec<StaticBody3D> = description.static_bodies.iter().map(|body| body.body(load_model(&body.model).unwrap())).collect();
+        let rigid_bodies: Vec<RigidBody3D> = description.rigid_bodies.iter().map(|body| body.body(load_model(&body.model).unwrap())).collect();
+
+        // The nodes Scene::new and add_rigid_body create, with casts_shadows set as build does
+        let mut scene_graph = SceneGraph::new();
+        let camera = scene_graph.add_node("camera", player.get_camera_node3d(1.0), None, None);
+        let mut body_node = |name: String, node3d: Node3D, model: &Handle<Model>, casts_shadows: bool| {
+            let node = scene_graph.add_node(&name, node3d, Some(model.clone()), None);
+            scene_graph.set_casts_shadows(node, casts_shadows);
+            node
+        };
+        let static_nodes: Vec<NodeId> = static_bodies.iter().zip(&description.static_bodies).enumerate()
+            .map(|(index, (body, body_description))| body_node(format!("static_body_{index}"), body.node3d, &body.model, body_description.casts_shadows))
+            .collect();
+        let rigid_nodes: Vec<NodeId> = rigid_bodies.iter().zip(&description.rigid_bodies).enumerate()
+            .map(|(index, (body, body_description))| body_node(format!("rigid_body_{index}"), body.node3d, &body.model, body_description.casts_shadows))
+            .collect();
+        let parents = ParentNodes { camera, static_bodies: &static_nodes, rigid_bodies: &rigid_nodes };
+        NodeDescription::add_to(&description.nodes, &mut scene_graph, &parents, &mut load_model).unwrap();
+
+        let mut world = World::new();
+        for light_description in &description.lights {
+            let (light, node3d) = light_description.light();
+            let entity = world.spawn();
+            world.insert(entity, light);
+            world.insert(entity, Transform::new(node3d));
+        }
+
+        let round_tripped = SceneDescription {
+            player: PlayerDescription::from_player(&player),
+            static_bodies: static_bodies.iter().zip(&static_nodes).map(|(body, node)| StaticBodyDescription::from_body(body, scene_graph.casts_shadows(*node))).collect::<EngineResult<_>>().unwrap(),
+            rigid_bodies: rigid_bodies.iter().zip(&rigid_nodes).map(|(body, node)| RigidBodyDescription::from_body(body, scene_graph.casts_shadows(*node))).collect::<EngineResult<_>>().unwrap(),
+            nodes: NodeDescription::from_scene_graph(&scene_graph, &parents).unwrap(),
+            lights: LightDescription::from_world(&world),
+            ..description.clone()
+        };
+        assert_close(&serde_json::to_value(&round_tripped).unwrap(), &serde_json::to_value(&description).unwrap(), "scene");
+
+        // Descriptions refer to files, so bodies with a model made in code can't be saved
+        let unsaved = StaticBody3D::new(transform(Vec3::default()).node3d(), Handle::new(Model::from_meshes(Vec::new())), CollisionShape::Sphere { radius: 1.0 });
+        assert!(matches!(StaticBodyDescription::from_body(&unsaved, true), Err(EngineError::SceneSave(_))));
+    }
+
+    #[test]
+    fn json_round_trips() {
+        let description = description();
+        assert_eq!(serde_json::from_str::<SceneDescription>(&description.to_json()).unwrap(), description);
+
+        let path = std::env::temp_dir().join(format!("scene_description_{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        description.save_to_file(path).unwrap();
+        let loaded = SceneDescription::load_from_file(path);
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(loaded.unwrap(), description);
+    }
+
+    #[test]
+    fn omitted_fields_use_defaults() {
+        let json = r#"{
+            "player": { "position": { "x": 0.0, "y": 50.0, "z": 0.0 } },
+            "static_bodies": [{ "model": "floor.obj", "transform": { "position": { "x": 0.0, "y": -1.0, "z": 0.0 } } }]
+        }"#;
+        let description: SceneDescription = serde_json::from_str(json).unwrap();
+
+        assert_eq!(description.player.height, 1.6);
+        assert_eq!(description.static_bodies[0].collision, CollisionDescription::ModelTriangles);
+        assert!(description.static_bodies[0].casts_shadows);
+        assert_eq!(description.static_bodies[0].transform.node3d().scale, Vec3::new(1.0, 1.0, 1.0));
+        assert!(description.skybox.is_none() && description.rigid_bodies.is_empty());
+        assert!(description.lights.is_empty() && description.ambient_light == default_ambient_light());
+        assert_eq!(description.shadows, ShadowSettings::default());
+
+        let skybox: SkyboxDescription = serde_json::from_str(r#"{ "vertex_shader": "sky.vs", "fragment_shader": "sky.fs", "faces": ["r", "l", "t", "b", "f", "k"] }"#).unwrap();
+        assert!(skybox.image_based_lighting);
+
+        let rigid_body: RigidBodyDescription = serde_json::from_str(r#"{ "model": "crate.obj", "transform": { "position": { "x": 0.0, "y": 1.0, "z": 0.0 } }, "mass": 1.0 }"#).unwrap();
+        assert!(rigid_body.casts_shadows && rigid_body.bounding_box.is_none());
+    }
+
+    #[test]
+    fn lights_convert_angles_to_radians() {
+        let json = r#"{ "type": "Spot", "position": { "x": 1.0, "y": 2.0, "z": 3.0 }, "direction": { "x": 0.0, "y": -1.0, "z": 0.0 }, "range": 10.0, "inner_angle": 15.0, "outer_angle": 30.0 }"#;
+        let (light, node3d) = serde_json::from_str::<LightDescription>(json).unwrap().light();
+
+        assert_eq!((light.color, light.intensity), (Vec3::one(), 1.0));
+        assert_eq!(node3d.world_position, Vec3::new(1.0, 2.0, 3.0));
+        let LightKind::Spot { range, inner_angle, outer_angle, .. } = light.kind else { panic!("expected a spot light") };
+        assert_eq!(range, 10.0);
+        assert!((inner_angle - 15f32.to_radians()).abs() < 1e-6 && (outer_angle - 30f32.to_radians()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn shipped_scenes_parse() {
+        for path in ["res/scenes/default.json", "res/scenes/physics_test.json"] {
+            let description = SceneDescription::load_from_file(path).unwrap();
+            assert!(!description.static_bodies.is_empty(), "{path}");
+        }
+    }
+
+    #[test]
+    fn transforms_convert_to_and_from_nodes() {
+        let transform = TransformDescription { position: Vec3::new(1.0, 2.0, 3.0), rotation: Vec3::new(10.0, -30.0, 75.0), scale: Vec3::new(2.0, 1.0, 0.5) };
+        let converted = TransformDescription::from_node3d(&transform.node3d());
+
+        assert!((converted.rotation - transform.rotation).mag() < 1e-3);
+        assert_eq!((converted.position, converted.scale), (transform.position, transform.scale));
+    }
+}
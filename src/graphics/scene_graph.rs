@@ -0,0 +1,340 @@
+use std::cell::Cell;
+
+use ultraviolet::{Mat4, Vec3};
+
+use crate::assets::handle::Handle;
+use crate::graphics::model::Model;
+use crate::graphics::node_3d::Node3D;
+use crate::graphics::render_queue::RenderQueue;
+use crate::math::aabb_bouding_box::AABBBoundingBox;
+use crate::math::frustum::Frustum;
+use crate::shader::Shader;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct NodeId {
+    index: usize,
+    generation: u32, // Ids of removed nodes stay invalid after their slot is reused
+}
+
+struct SceneNode {
+    name: String,
+    local: Node3D,
+    model: Option<Handle<Model>>,
+    casts_shadows: bool,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    world_matrix: Cell<Mat4>,
+    dirty: Cell<bool>, // A dirty node always has dirty descendants, so propagation can stop at the first dirty child
+}
+
+struct Slot {
+    generation: u32,
+    node: Option<SceneNode>,
+}
+
+// Arena of nodes with parent/child links, world transforms are cached and only recomputed after a local change
+#[derive(Default)]
+pub struct SceneGraph {
+    slots: Vec<Slot>,
+    free_slots: Vec<usize>,
+}
+
+impl SceneGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len() - self.free_slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn contains(&self, id: NodeId) -> bool {
+        self.node(id).is_some()
+    }
+
+    pub fn add_node(&mut self, name: &str, local: Node3D, model: Option<Handle<Model>>, parent: Option<NodeId>) -> NodeId {
+        let parent = parent.filter(|parent| self.contains(*parent));
+        let node = SceneNode {
+            name: name.to_owned(),
+            local,
+            model,
+            casts_shadows: true,
+            parent,
+            children: Vec::new(),
+            world_matrix: Cell::new(Mat4::identity()),
+            dirty: Cell::new(true),
+        };
+
+        let id = match self.free_slots.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index];
+                slot.node = Some(node);
+                NodeId { index, generation: slot.generation }
+            }
+            None => {
+                self.slots.push(Slot { generation: 0, node: Some(node) });
+                NodeId { index: self.slots.len() - 1, generation: 0 }
+            }
+        };
+
+        if let Some(parent) = parent {
+            self.node_mut(parent).unwrap().children.push(id);
+        }
+
+        id
+    }
+
+    // Removes the node together with its whole subtree
+    pub fn remove_node(&mut self, id: NodeId) {
+        let Some(node) = self.node(id) else {
+            return;
+        };
+
+        if let Some(parent) = node.parent {
+            if let Some(parent) = self.node_mut(parent) {
+                parent.children.retain(|child| *child != id);
+            }
+        }
+
+        let mut pending = vec![id];
+        while let Some(id) = pending.pop() {
+            let slot = &mut self.slots[id.index];
+            if let Some(node) = slot.node.take() {
+                pending.extend(node.children);
+                slot.generation += 1;
+                self.free_slots.push(id.index);
+            }
+        }
+    }
+
+    // Keeps the node's local transform, so it moves with its new parent. Returns false if that would create a cycle
+    pub fn set_parent(&mut self, id: NodeId, parent: Option<NodeId>) -> bool {
+        if !self.contains(id) || parent.is_some_and(|parent| !self.contains(parent) || self.is_ancestor_or_self(id, parent)) {
+            return false;
+        }
+
+        if let Some(old_parent) = self.node(id).unwrap().parent {
+            self.node_mut(old_parent).unwrap().children.retain(|child| *child != id);
+        }
+        if let Some(parent) = parent {
+            self.node_mut(parent).unwrap().children.push(id);
+        }
+
+        self.node_mut(id).unwrap().parent = parent;
+        self.mark_dirty(id);
+
+        true
+    }
+
+    fn is_ancestor_or_self(&self, ancestor: NodeId, mut id: NodeId) -> bool {
+        loop {
+            if id == ancestor {
+                return true;
+            }
+            match self.node(id).and_then(|node| node.parent) {
+                Some(parent) => id = parent,
+                None => return false,
+            }
+        }
+    }
+
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.node(id)?.parent
+    }
+
+    pub fn children(&self, id: NodeId) -> &[NodeId] {
+        self.node(id).map_or(&[], |node| &node.children)
+    }
+
+    pub fn name(&self, id: NodeId) -> Option<&str> {
+        self.node(id).map(|node| node.name.as_str())
+    }
+
+    pub fn find(&self, name: &str) -> Option<NodeId> {
+        self.ids().find(|id| self.name(*id) == Some(name))
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item=NodeId> + '_ {
+        self.slots.iter().enumerate()
+            .filter(|(_, slot)| slot.node.is_some())
+            .map(|(index, slot)| NodeId { index, generation: slot.generation })
+    }
+
+    pub fn model(&self, id: NodeId) -> Option<&Handle<Model>> {
+        self.node(id)?.model.as_ref()
+    }
+
+    pub fn set_model(&mut self, id: NodeId, model: Option<Handle<Model>>) {
+        if let Some(node) = self.node_mut(id) {
+            node.model = model;
+        }
+    }
+
+    pub fn casts_shadows(&self, id: NodeId) -> bool {
+        self.node(id).is_some_and(|node| node.casts_shadows)
+    }
+
+    pub fn set_casts_shadows(&mut self, id: NodeId, casts_shadows: bool) {
+        if let Some(node) = self.node_mut(id) {
+            node.casts_shadows = casts_shadows;
+        }
+    }
+
+    pub fn local_transform(&self, id: NodeId) -> Option<&Node3D> {
+        self.node(id).map(|node| &node.local)
+    }
+
+    pub fn set_local_transform(&mut self, id: NodeId, local: Node3D) {
+        if let Some(node) = self.node_mut(id) {
+            node.local = local;
+            self.mark_dirty(id);
+        }
+    }
+
+    // Changes the local transform through a closure so the subtree is always marked dirty afterwards
+    pub fn update_local_transform(&mut self, id: NodeId, update: impl FnOnce(&mut Node3D)) {
+        if let Some(node) = self.node_mut(id) {
+            update(&mut node.local);
+            self.mark_dirty(id);
+        }
+    }
+
+    pub fn world_matrix(&self, id: NodeId) -> Mat4 {
+        let Some(node) = self.node(id) else {
+            return Mat4::identity();
+        };
+
+        if node.dirty.get() {
+            let parent_matrix = node.parent.map_or(Mat4::identity(), |parent| self.world_matrix(parent));
+            node.world_matrix.set(parent_matrix * node.local.model_matrix());
+            node.dirty.set(false);
+        }
+
+        node.world_matrix.get()
+    }
+
+    pub fn world_position(&self, id: NodeId) -> Vec3 {
+        self.world_matrix(id).transform_point3(Vec3::default())
+    }
+
+    pub fn world_bounding_box(&self, id: NodeId) -> Option<AABBBoundingBox> {
+        let model = self.model(id)?;
+
+        Some(model.get().bounding_box().transformed(self.world_matrix(id)))
+    }
+
+    pub fn queue_draws(&self, queue: &mut RenderQueue, frustum: &Frustum) {
+        self.visible_models(frustum, false, |model, world_matrix| queue.push_model(model, world_matrix));
+    }
+
+    // Depth-only passes, `frustum` is the light's
+    pub fn draw_shadow_casters(&self, shader_program: &Shader, frustum: &Frustum) {
+        self.visible_models(frustum, true, |model, world_matrix| {
+            shader_program.set_mat4("model", world_matrix);
+            model.get().draw(shader_program);
+        });
+    }
+
+    fn visible_models(&self, frustum: &Frustum, shadow_casters_only: bool, mut visit: impl FnMut(&Handle<Model>, Mat4)) {
+        for id in self.ids() {
+            let Some(node) = self.node(id) else {
+                continue;
+            };
+            let Some(model) = &node.model else {
+                continue;
+            };
+            if shadow_casters_only && !node.casts_shadows {
+                continue;
+            }
+
+            let world_matrix = self.world_matrix(id);
+            if !frustum.intersects_aabb(&model.get().bounding_box().transformed(world_matrix)) {
+                continue;
+            }
+
+            visit(model, world_matrix);
+        }
+    }
+
+    fn mark_dirty(&self, id: NodeId) {
+        let mut pending = vec![id];
+        while let Some(id) = pending.pop() {
+            let Some(node) = self.node(id) else {
+                continue;
+            };
+
+            node.dirty.set(true);
+            pending.extend(node.children.iter().copied().filter(|child| self.node(*child).is_some_and(|child| !child.dirty.get())));
+        }
+    }
+
+    fn node(&self, id: NodeId) -> Option<&SceneNode> {
+        let slot = self.slots.get(id.index)?;
+        if slot.generation != id.generation {
+            return None;
+        }
+
+        slot.node.as_ref()
+    }
+
+    fn node_mut(&mut self, id: NodeId) -> Option<&mut SceneNode> {
+        let slot = self.slots.get_mut(id.index)?;
+        if slot.generation != id.generation {
+            return None;
+        }
+
+        slot.node.as_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::rotation::Rotation;
+
+    fn node_at(position: Vec3) -> Node3D {
+        Node3D { world_position: position, scale: Vec3::new(1.0, 1.0, 1.0), rotation: Rotation::default() }
+    }
+
+    #[test]
+    fn children_follow_parent_transforms() {
+        let mut graph = SceneGraph::new();
+        let cottage = graph.add_node("cottage", node_at(Vec3::new(10.0, 0.0, 0.0)), None, None);
+        let lamp = graph.add_node("lamp", node_at(Vec3::new(0.0, 2.0, 1.0)), None, Some(cottage));
+        assert!((graph.world_position(lamp) - Vec3::new(10.0, 2.0, 1.0)).mag() < 1e-5);
+
+        graph.update_local_transform(cottage, |node| {
+            node.rotation = Rotation::from_rotation_y(90.0_f32.to_radians());
+            node.scale = Vec3::new(2.0, 2.0, 2.0);
+        });
+        assert!((graph.world_position(lamp) - Vec3::new(12.0, 4.0, 0.0)).mag() < 1e-4);
+    }
+
+    #[test]
+    fn reparenting_rejects_cycles_and_removal_drops_subtree() {
+        let mut graph = SceneGraph::new();
+        let root = graph.add_node("root", node_at(Vec3::new(1.0, 0.0, 0.0)), None, None);
+        let child = graph.add_node("child", node_at(Vec3::new(1.0, 0.0, 0.0)), None, Some(root));
+        let grandchild = graph.add_node("grandchild", node_at(Vec3::new(1.0, 0.0, 0.0)), None, Some(child));
+
+        assert!(!graph.set_parent(root, Some(grandchild)));
+        assert!(graph.set_parent(grandchild, None));
+        assert!((graph.world_position(grandchild) - Vec3::new(1.0, 0.0, 0.0)).mag() < 1e-5);
+        assert!(graph.set_parent(grandchild, Some(child)));
+        assert!((graph.world_position(grandchild) - Vec3::new(3.0, 0.0, 0.0)).mag() < 1e-5);
+
+        graph.remove_node(child);
+        assert_eq!(graph.len(), 1);
+        assert!(!graph.contains(grandchild));
+        assert!(graph.children(root).is_empty());
+
+        // A reused slot does not revive the old id
+        let reused = graph.add_node("reused", node_at(Vec3::default()), None, None);
+        assert!(graph.contains(reused));
+        assert!(!graph.contains(child) && !graph.contains(grandchild));
+    }
+}
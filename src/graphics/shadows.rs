@@ -0,0 +1,332 @@
+use serde::{Deserialize, Serialize};
+use ultraviolet::{Mat4, Vec3, Vec4};
+use ultraviolet::projection::{orthographic_gl, perspective_gl};
+
+use crate::assets::handle::Handle;
+use crate::ecs::systems;
+use crate::ecs::world::World;
+use crate::error::EngineResult;
+use crate::graphics::lighting::{LightBlock, ShadowedLights};
+use crate::graphics::scene_graph::SceneGraph;
+use crate::math::cube_map;
+use crate::math::frustum::Frustum;
+use crate::opengl;
+use crate::opengl::ClearBitFlags::DepthBuffer;
+use crate::opengl::framebuffer::{Attachment, Framebuffer};
+use crate::opengl::texture::{MagFilterParam, MinFilterParam, Texture, TextureFormat, TextureType, WrapCoordinate, WrapParam};
+use crate::shader::Shader;
+
+pub const SHADOW_VERTEX_SHADER: &str = "res/shaders/shadow_depth.vs";
+pub const SHADOW_FRAGMENT_SHADER: &str = "res/shaders/shadow_depth.fs";
+pub const POINT_SHADOW_FRAGMENT_SHADER: &str = "res/shaders/point_shadow_depth.fs";
+
+// Must match the sampler declarations in the default shader
+pub const MAX_CASCADES: usize = 4;
+pub const MAX_POINT_SHADOWS: usize = 4;
+
+// Units 0-5 are taken by material textures
+const CASCADE_UNIT: usize = 6;
+const POINT_SHADOW_UNIT: usize = 7;
+
+const POINT_SHADOW_NEAR: f32 = 0.05;
+
+// Map sizes and counts are read when the ShadowRenderer is created, the rest can change every frame
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct ShadowSettings {
+    pub enabled: bool,
+    pub cascade_count: usize, // Up to MAX_CASCADES
+    pub map_size: u32, // Per cascade
+    pub shadow_distance: f32, // Sun shadows end here, measured from the camera
+    pub split_lambda: f32, // 0 splits the distance evenly, 1 logarithmically
+    pub caster_distance: f32, // How far behind a cascade objects still cast into it
+    pub max_point_shadows: usize, // Up to MAX_POINT_SHADOWS, the point lights closest to the camera get them
+    pub point_map_size: u32, // Per cube face
+    pub depth_bias: f32, // Scaled up on surfaces at grazing angles to the light
+    pub normal_bias: f32, // World units the lookup is pushed along the surface normal
+    pub pcf_radius: i32, // Filter kernel is (2r + 1)² samples, 0 takes a single one
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            cascade_count: 3,
+            map_size: 2048,
+            shadow_distance: 60.0,
+            split_lambda: 0.75,
+            caster_distance: 50.0,
+            max_point_shadows: 2,
+            point_map_size: 512,
+            depth_bias: 0.002,
+            normal_bias: 0.03,
+            pcf_radius: 1,
+        }
+    }
+}
+
+// What the main pass needs to know about the camera to fit the cascades
+#[derive(Copy, Clone, Debug)]
+pub struct ShadowCamera {
+    pub view: Mat4,
+    pub fov_y: f32, // Radians
+    pub aspect_ratio: f32,
+    pub near: f32,
+}
+
+// Results of one frame's shadow passes
+#[derive(Clone, Debug, Default)]
+pub struct ShadowFrame {
+    cascade_matrices: Vec<Mat4>,
+    cascade_splits: Vec<f32>, // View space depth where each cascade ends
+    point_shadows: usize,
+}
+
+pub struct ShadowRenderer {
+    settings: ShadowSettings,
+    depth_shader: Handle<Shader>,
+    point_depth_shader: Handle<Shader>,
+    framebuffer: Framebuffer,
+    cascade_maps: Texture,
+    point_maps: Vec<Texture>,
+}
+
+impl ShadowRenderer {
+    pub fn new(settings: ShadowSettings, depth_shader: Handle<Shader>, point_depth_shader: Handle<Shader>) -> EngineResult<Self> {
+        let settings = ShadowSettings {
+            cascade_count: settings.cascade_count.clamp(1, MAX_CASCADES),
+            max_point_shadows: settings.max_point_shadows.min(MAX_POINT_SHADOWS),
+            ..settings
+        };
+
+        // Everything outside the cascades counts as lit
+        let cascade_maps = Texture::new(TextureType::Texture2dArray)?;
+        cascade_maps.load_empty_array(settings.map_size, settings.map_size, settings.cascade_count as u32, TextureFormat::Depth32F);
+        set_shadow_sampling(&cascade_maps, WrapParam::ClampToBorder);
+        cascade_maps.set_border_color([1.0; 4]);
+
+        let mut point_maps = Vec::with_capacity(settings.max_point_shadows);
+        for _ in 0..settings.max_point_shadows {
+            let point_map = Texture::new(TextureType::CubeMap)?;
+            point_map.load_empty_cube(settings.point_map_size, TextureFormat::Depth32F);
+            set_shadow_sampling(&point_map, WrapParam::ClampToEdge);
+            point_maps.push(point_map);
+        }
+
+        let framebuffer = Framebuffer::new()?;
+        framebuffer.bind();
+        Framebuffer::disable_color_buffers();
+        Framebuffer::attach_texture_layer(Attachment::Depth, &cascade_maps, 0);
+        let status = Framebuffer::check_complete();
+        Framebuffer::bind_default();
+        status?;
+
+        Ok(Self { settings, depth_shader, point_depth_shader, framebuffer, cascade_maps, point_maps })
+    }
+
+    pub fn settings(&self) -> &ShadowSettings {
+        &self.settings
+    }
+
+    // Changes to map sizes and counts are ignored, they need a new renderer
+    pub fn set_settings(&mut self, settings: ShadowSettings) {
+        self.settings = ShadowSettings {
+            cascade_count: self.settings.cascade_count,
+            map_size: self.settings.map_size,
+            max_point_shadows: self.settings.max_point_shadows,
+            point_map_size: self.settings.point_map_size,
+            ..settings
+        };
+    }
+
+    // Renders the depth passes and marks the shadowed lights in `lights`. Leaves its own framebuffer bound and the viewport changed
+    pub fn render(&self, lights: &mut LightBlock, camera: &ShadowCamera, scene_graph: &SceneGraph, world: &World, alpha: f32) -> ShadowFrame {
+        let mut frame = ShadowFrame::default();
+        if !self.settings.enabled {
+            return frame;
+        }
+
+        let ShadowedLights { sun, points } = lights.assign_shadow_slots(self.settings.max_point_shadows);
+        self.framebuffer.bind();
+        opengl::enable(opengl::Capability::DepthTest);
+
+        if let Some(direction) = sun {
+            let depth_shader = self.depth_shader.get();
+            depth_shader.bind();
+            opengl::viewport(0, 0, self.settings.map_size as i32, self.settings.map_size as i32);
+
+            let mut near = camera.near;
+            frame.cascade_splits = cascade_splits(camera.near, self.settings.shadow_distance, self.settings.cascade_count, self.settings.split_lambda);
+            for (layer, far) in frame.cascade_splits.iter().enumerate() {
+                let light_space = cascade_matrix(camera, near, *far, direction, self.settings.map_size, self.settings.caster_distance);
+                near = *far;
+
+                Framebuffer::attach_texture_layer(Attachment::Depth, &self.cascade_maps, layer as u32);
+                opengl::clear(DepthBuffer.into());
+                depth_shader.set_mat4("lightSpace", light_space);
+                draw_casters(&depth_shader, &Frustum::from_view_projection(light_space), scene_graph, world, alpha);
+
+                frame.cascade_matrices.push(light_space);
+            }
+        }
+
+        if !points.is_empty() {
+            let point_depth_shader = self.point_depth_shader.get();
+            point_depth_shader.bind();
+            opengl::viewport(0, 0, self.settings.point_map_size as i32, self.settings.point_map_size as i32);
+
+            for ((position, range), point_map) in points.iter().zip(&self.point_maps) {
+                point_depth_shader.set_vec3("lightPosition", position.x, position.y, position.z);
+                point_depth_shader.set_float("farPlane", *range);
+
+                for (face, light_space) in cube_map::face_view_projections(*position, POINT_SHADOW_NEAR, *range).into_iter().enumerate() {
+                    Framebuffer::attach_cube_face(Attachment::Depth, point_map, face as u32, 0);
+                    opengl::clear(DepthBuffer.into());
+                    point_depth_shader.set_mat4("lightSpace", light_space);
+                    draw_casters(&point_depth_shader, &Frustum::from_view_projection(light_space), scene_graph, world, alpha);
+                }
+            }
+            frame.point_shadows = points.len();
+        }
+
+        frame
+    }
+
+    // Call with the main shader bound, after render
+    pub fn bind(&self, shader_program: &Shader, frame: &ShadowFrame) {
+        Self::bind_samplers(shader_program);
+        shader_program.set_bool("shadowsEnabled", self.settings.enabled);
+        if !self.settings.enabled {
+            return;
+        }
+
+        shader_program.set_int("cascadeCount", frame.cascade_matrices.len() as i64);
+        for (i, (matrix, split)) in frame.cascade_matrices.iter().zip(&frame.cascade_splits).enumerate() {
+            shader_program.set_mat4(&format!("cascadeMatrices[{i}]"), *matrix);
+            shader_program.set_float(&format!("cascadeSplits[{i}]"), *split);
+        }
+        shader_program.set_float("depthBias", self.settings.depth_bias);
+        shader_program.set_float("normalBias", self.settings.normal_bias);
+        shader_program.set_int("pcfRadius", self.settings.pcf_radius.max(0) as i64);
+
+        Texture::set_active_texture(CASCADE_UNIT);
+        self.cascade_maps.bind();
+        for (i, point_map) in self.point_maps.iter().take(frame.point_shadows).enumerate() {
+            Texture::set_active_texture(POINT_SHADOW_UNIT + i);
+            point_map.bind();
+        }
+        Texture::set_active_texture(0);
+    }
+
+    // For scenes without shadows. Shadow samplers always get their own units, samplers of different types can't share one
+    pub fn bind_disabled(shader_program: &Shader) {
+        Self::bind_samplers(shader_program);
+        shader_program.set_bool("shadowsEnabled", false);
+    }
+
+    fn bind_samplers(shader_program: &Shader) {
+        shader_program.set_int("shadowCascades", CASCADE_UNIT as i64);
+        for i in 0..MAX_POINT_SHADOWS {
+            shader_program.set_int(&format!("pointShadowMap{i}"), (POINT_SHADOW_UNIT + i) as i64);
+        }
+    }
+}
+
+fn set_shadow_sampling(texture: &Texture, wrap: WrapParam) {
+    texture.set_min_filter(MinFilterParam::Linear);
+    texture.set_mag_filter(MagFilterParam::Linear);
+    texture.set_wrap(WrapCoordinate::S, wrap);
+    texture.set_wrap(WrapCoordinate::T, wrap);
+    texture.set_wrap(WrapCoordinate::R, wrap);
+    texture.set_depth_compare();
+}
+
+fn draw_casters(depth_shader: &Shader, frustum: &Frustum, scene_graph: &SceneGraph, world: &World, alpha: f32) {
+    scene_graph.draw_shadow_casters(depth_shader, frustum);
+    systems::draw_shadow_casters(world, depth_shader, frustum, alpha);
+}
+
+// View space depth where each cascade ends, blending even and logarithmic splits
+pub fn cascade_splits(near: f32, far: f32, count: usize, lambda: f32) -> Vec<f32> {
+    (1..=count).map(|i| {
+        let fraction = i as f32 / count as f32;
+        let logarithmic = near * (far / near).powf(fraction);
+        let uniform = near + (far - near) * fraction;
+
+        lambda * logarithmic + (1.0 - lambda) * uniform
+    }).collect()
+}
+
+// Orthographic light projection around the camera frustum slice between `near` and `far`.
+// Fitting a bounding sphere keeps the size fixed as the camera turns and snapping it to texels stops edges from shimmering
+pub fn cascade_matrix(camera: &ShadowCamera, near: f32, far: f32, light_direction: Vec3, map_size: u32, caster_distance: f32) -> Mat4 {
+    let inverse = (perspective_gl(camera.fov_y, camera.aspect_ratio, near, far) * camera.view).inversed();
+    let mut corners = Vec::with_capacity(8);
+    for x in [-1.0, 1.0] {
+        for y in [-1.0, 1.0] {
+            for z in [-1.0, 1.0] {
+                let corner = inverse * Vec4::new(x, y, z, 1.0);
+                corners.push(corner.xyz() / corner.w);
+            }
+        }
+    }
+
+    let center = corners.iter().fold(Vec3::zero(), |sum, corner| sum + *corner) / corners.len() as f32;
+    let radius = corners.iter().map(|corner| (*corner - center).mag()).fold(0.0, f32::max);
+    let radius = (radius * 16.0).ceil() / 16.0;
+
+    let up = if light_direction.y.abs() > 0.99 { Vec3::unit_z() } else { Vec3::unit_y() };
+    let light_rotation = Mat4::look_at(Vec3::zero(), light_direction, up);
+    let texel = 2.0 * radius / map_size as f32;
+    let mut snapped = light_rotation.transform_point3(center);
+    snapped.x = (snapped.x / texel).floor() * texel;
+    snapped.y = (snapped.y / texel).floor() * texel;
+    let center = light_rotation.inversed().transform_point3(snapped);
+
+    let eye = center - light_direction * (radius + caster_distance);
+    let view = Mat4::look_at(eye, center, up);
+
+    orthographic_gl(-radius, radius, -radius, radius, 0.0, 2.0 * radius + caster_distance) * view
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn camera() -> ShadowCamera {
+        ShadowCamera {
+            view: Mat4::look_at(Vec3::new(3.0, 2.0, 5.0), Vec3::new(4.0, 1.5, -1.0), Vec3::unit_y()),
+            fov_y: 45.0_f32.to_radians(),
+            aspect_ratio: 16.0 / 9.0,
+            near: 0.1,
+        }
+    }
+
+    #[test]
+    fn splits_blend_even_and_logarithmic() {
+        let even = cascade_splits(1.0, 100.0, 4, 0.0);
+        assert_eq!(even, vec![25.75, 50.5, 75.25, 100.0]);
+
+        let logarithmic = cascade_splits(1.0, 100.0, 2, 1.0);
+        assert!((logarithmic[0] - 10.0).abs() < 1e-4);
+        assert!((logarithmic[1] - 100.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn cascades_enclose_their_slice_on_the_texel_grid() {
+        let camera = camera();
+        let direction = Vec3::new(-0.3, -1.0, 0.2).normalized();
+        let light_space = cascade_matrix(&camera, 2.0, 12.0, direction, 1024, 20.0);
+
+        let inverse = (perspective_gl(camera.fov_y, camera.aspect_ratio, 2.0, 12.0) * camera.view).inversed();
+        for corner in [Vec4::new(-1.0, -1.0, -1.0, 1.0), Vec4::new(1.0, 1.0, 1.0, 1.0), Vec4::new(1.0, -1.0, 1.0, 1.0), Vec4::new(-1.0, 1.0, -1.0, 1.0)] {
+            let world = inverse * corner;
+            let projected = light_space.transform_point3(world.xyz() / world.w);
+            assert!(projected.x.abs() <= 1.0 && projected.y.abs() <= 1.0 && projected.z.abs() <= 1.0, "{projected:?}");
+        }
+
+        // The world origin lands on a texel corner
+        let origin = light_space.transform_point3(Vec3::zero()) * 512.0;
+        assert!((origin.x - origin.x.round()).abs() < 1e-2 && (origin.y - origin.y.round()).abs() < 1e-2, "{origin:?}");
+    }
+}
@@ -0,0 +1,18 @@
+use ultraviolet::{Mat4, Vec4};
+
+// Joints are indices into the model's nodes, each with the matrix that takes the mesh into that joint's bind space.
+// Skins are only parsed for now, skinned meshes are drawn in their bind pose
+#[derive(Clone, Debug)]
+pub struct Skin {
+    pub name: Option<String>,
+    pub joints: Vec<usize>,
+    pub inverse_bind_matrices: Vec<Mat4>,
+    pub skeleton: Option<usize>,
+}
+
+// Up to four joint influences of one vertex, joints index into `Skin::joints`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct JointWeights {
+    pub joints: [u16; 4],
+    pub weights: Vec4,
+}
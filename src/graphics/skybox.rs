@@ -2,6 +2,8 @@ use std::mem::size_of;
 
 use ultraviolet::Mat4;
 
+use crate::assets::handle::Handle;
+use crate::error::EngineResult;
 use crate::opengl;
 use crate::opengl::{DepthFunc, gl_depth_func, Primitive};
 use crate::opengl::texture::{MagFilterParam, MinFilterParam, Texture, TextureType, WrapCoordinate, WrapParam};
@@ -59,25 +61,29 @@ const SKYBOX_VERTICES: [SkyboxVertex; 36] = [
 
 pub struct Skybox {
     texture: Texture,
-    shader_program: Shader,
+    shader_program: Handle<Shader>,
+    face_paths: [String; 6],
     vao: VertexArrayObject,
+    #[allow(dead_code)]
     vbo: VertexBufferObject,
 }
 
 impl Skybox {
-    pub fn new_from_image_paths(shader_program: Shader, paths: [&str; 6]) -> Self {
-        let texture = Texture::new(TextureType::CubeMap).expect("Failed to allocate texture for skybox");
+    pub fn new_from_image_paths(shader_program: Handle<Shader>, paths: [&str; 6]) -> EngineResult<Self> {
+        let texture = Texture::new(TextureType::CubeMap)?;
         texture.bind();
-        Texture::load_cube_map_from_paths(paths);
+        Texture::load_cube_map_from_paths(paths)?;
 
-        texture.set_min_filter(MinFilterParam::Linear);
+        // Mipmapped so image based lighting can sample a blurred sky instead of many texels
+        texture.generate_mipmaps();
+        texture.set_min_filter(MinFilterParam::LinearMipmapLinear);
         texture.set_mag_filter(MagFilterParam::Linear);
         texture.set_wrap(WrapCoordinate::S, WrapParam::ClampToEdge);
         texture.set_wrap(WrapCoordinate::T, WrapParam::ClampToEdge);
         texture.set_wrap(WrapCoordinate::R, WrapParam::ClampToEdge);
 
-        let vao = VertexArrayObject::new().expect("Failed to allocate vertex array object for skybox");
-        let vbo = VertexBufferObject::new().expect("Failed to allocate vertex buffer object for skybox");
+        let vao = VertexArrayObject::new()?;
+        let vbo = VertexBufferObject::new()?;
         vao.bind();
         vbo.bind();
 
@@ -85,33 +91,55 @@ impl Skybox {
 
         VertexArrayObject::set_vertex_attribute(0, 3, Float, false, size_of::<SkyboxVertex>(), 0);
 
-        shader_program.bind();
-        shader_program.set_int("skybox", 0);
+        shader_program.get().bind();
+        shader_program.get().set_int("skybox", 0);
 
-        Self {
+        Ok(Self {
             texture,
             shader_program,
+            face_paths: paths.map(str::to_owned),
             vao,
             vbo,
-        }
+        })
     }
 
-    pub fn draw(&self, camera_view: Mat4, projection: Mat4) {
+    // `gamma_correct` when drawing straight to the window, post-processing expects linear colour
+    pub fn draw(&self, camera_view: Mat4, projection: Mat4, gamma_correct: bool) {
         gl_depth_func(DepthFunc::LEqual);
 
-        self.shader_program.bind();
+        let shader_program = self.shader_program.get();
+        shader_program.bind();
         let mut view = camera_view;
         view.cols[3].x = 0.0;
         view.cols[3].y = 0.0;
         view.cols[3].z = 0.0;
-        self.shader_program.set_mat4("view", view);
-        self.shader_program.set_mat4("projection", projection);
+        shader_program.set_mat4("view", view);
+        shader_program.set_mat4("projection", projection);
+        shader_program.set_bool("gammaCorrect", gamma_correct);
 
-        self.vao.bind();
         Texture::set_active_texture(0);
         self.texture.bind();
+        self.draw_cube();
+        gl_depth_func(DepthFunc::Less);
+    }
+
+    pub fn shader_program(&self) -> &Handle<Shader> {
+        &self.shader_program
+    }
+
+    // Right, left, top, bottom, front, back
+    pub fn face_paths(&self) -> &[String; 6] {
+        &self.face_paths
+    }
+
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    // The unit cube with whatever shader is bound, e.g. to render the sky into other cube maps
+    pub(crate) fn draw_cube(&self) {
+        self.vao.bind();
         opengl::draw_arrays(Primitive::Triangles, 0, SKYBOX_VERTICES.len());
         VertexArrayObject::unbind();
-        gl_depth_func(DepthFunc::Less);
     }
 }
\ No newline at end of file
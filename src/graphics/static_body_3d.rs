@@ -1,27 +1,77 @@
-use std::rc::Rc;
-
 use ultraviolet::Mat4;
 
+use crate::assets::handle::Handle;
 use crate::graphics::model::Model;
 use crate::graphics::node_3d::Node3D;
 use crate::math::aabb_bouding_box::AABBBoundingBox;
+use crate::physics::collider::Collider;
+use crate::physics::collision_shape::CollisionShape;
 use crate::shader::Shader;
 
 pub struct StaticBody3D {
-    pub node3d: Node3D,
-    pub model: Rc<Model>, // TODO: Replace with resource manager and get model through it ?
-    pub bounding_box: AABBBoundingBox,
+    pub node3d: Node3D, // Transform the body starts with, in a scene its node is moved instead
+    pub model: Handle<Model>,
+    shape: CollisionShape,
+    model_collision: bool, // The shape is the model's triangles, rebuilt when the model reloads
+    world_matrix: Mat4,
+    collider: Collider,
+    render_bounding_box: AABBBoundingBox,
 }
 
-// TODO: Collision shape (model?)
-
 impl StaticBody3D {
-    pub fn draw(&self, shader_program: &Shader) {
-        let mut model = Mat4::from_translation(self.node3d.world_position);
-        model = model * self.node3d.rotation.rotation_matrix() * Mat4::from_nonuniform_scale(self.node3d.scale);
+    pub fn new(node3d: Node3D, model: Handle<Model>, shape: CollisionShape) -> Self {
+        let collider = Collider::new(&shape, &node3d);
+        let render_bounding_box = node3d.world_bounding_box(model.get().bounding_box());
+
+        Self { node3d, model, shape, model_collision: false, world_matrix: node3d.model_matrix(), collider, render_bounding_box }
+    }
+
+    // Static bodies collide with the exact triangles of their model
+    pub fn with_model_collision(node3d: Node3D, model: Handle<Model>) -> Self {
+        let shape = CollisionShape::triangle_mesh_from_model(&model.get());
+
+        Self { model_collision: true, ..Self::new(node3d, model, shape) }
+    }
+
+    pub fn shape(&self) -> &CollisionShape {
+        &self.shape
+    }
+
+    pub fn world_matrix(&self) -> Mat4 {
+        self.world_matrix
+    }
 
-        shader_program.set_mat4("model", model);
+    // Rebuilds the collider and bounds, used when the body's scene graph node or one of its ancestors moved
+    pub fn set_world_matrix(&mut self, world_matrix: Mat4) {
+        self.world_matrix = world_matrix;
+        self.collider = Collider::with_matrix(&self.shape, world_matrix);
+        self.render_bounding_box = self.model.get().bounding_box().transformed(world_matrix);
+    }
+
+    // Called after the model was reloaded so collision and culling use its new geometry
+    pub fn refresh_model(&mut self) {
+        if self.model_collision {
+            self.shape = CollisionShape::triangle_mesh_from_model(&self.model.get());
+        }
+        self.set_world_matrix(self.world_matrix);
+    }
+
+    pub fn collider(&self) -> &Collider {
+        &self.collider
+    }
+
+    pub fn bounding_box(&self) -> AABBBoundingBox {
+        self.collider.bounding_box()
+    }
+
+    // World space bounds of the model, used for culling
+    pub fn render_bounding_box(&self) -> AABBBoundingBox {
+        self.render_bounding_box
+    }
 
-        self.model.draw(shader_program);
+    pub fn draw(&self, shader_program: &Shader) {
+        shader_program.set_mat4("model", self.world_matrix);
+
+        self.model.get().draw(shader_program);
     }
-}
\ No newline at end of file
+}
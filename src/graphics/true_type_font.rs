@@ -4,14 +4,19 @@ use rusttype::{Font, point, PositionedGlyph, Rect, Scale, vector};
 use rusttype::gpu_cache::Cache;
 use ultraviolet::Mat4;
 
+use crate::error::{EngineError, EngineResult};
+use crate::opengl;
 use crate::opengl::draw_arrays;
 use crate::opengl::Primitive::Triangles;
-use crate::opengl::texture::{MagFilterParam, MinFilterParam, Texture, TextureType, WrapCoordinate, WrapParam};
+use crate::opengl::texture::{MagFilterParam, MinFilterParam, Texture, TextureFormat, TextureType, WrapCoordinate, WrapParam};
 use crate::opengl::vertex_array_object::VertexArrayObject;
 use crate::opengl::vertex_array_object::VertexAttribType::Float;
 use crate::opengl::vertex_buffer_object::{BufferUsage, VertexBufferObject};
 use crate::shader::Shader;
 
+// Glyphs are rasterized into a texture this big and reused while they fit
+const GLYPH_CACHE_SIZE: (u32, u32) = (1024, 1024);
+
 #[derive(Copy, Clone)]
 #[repr(C)]
 struct Vertex {
@@ -22,6 +27,7 @@ struct Vertex {
 
 pub struct TrueTypeFont<'a> {
     vao: VertexArrayObject,
+    #[allow(dead_code)]
     vbo: VertexBufferObject,
     font: Font<'a>,
     cache: Cache<'a>,
@@ -71,43 +77,41 @@ fn layout_paragraph<'a>(
 
 
 impl TrueTypeFont<'_> {
-    pub fn load_from_file(path: &str) -> Self {
-        let mut f = std::fs::File::open(path).expect("Failed to open font file");
-        let mut bytes = vec![];
-        std::io::Read::read_to_end(&mut f, &mut bytes).expect("Failed to read font file");
+    pub fn load_from_file(path: &str) -> EngineResult<Self> {
+        let bytes = std::fs::read(path).map_err(|source| EngineError::Io { path: path.to_owned(), source })?;
 
-        let font = Font::try_from_vec(bytes).expect("Error constructing Font");
+        let font = Font::try_from_vec(bytes).ok_or_else(|| EngineError::FontParse { path: path.to_owned() })?;
 
-        let (cache_width, cache_height) = (1280, 720);
-        let mut cache: Cache<'_> = Cache::builder()
+        let (cache_width, cache_height) = GLYPH_CACHE_SIZE;
+        let cache: Cache<'_> = Cache::builder()
             .dimensions(cache_width, cache_height)
             .build();
 
-        let texture = Texture::new(TextureType::Texture2d).expect("Failed to allocate texture for font");
+        let texture = Texture::new(TextureType::Texture2d)?;
 
         texture.set_wrap(WrapCoordinate::S, WrapParam::ClampToEdge);
         texture.set_wrap(WrapCoordinate::T, WrapParam::ClampToEdge);
         texture.set_min_filter(MinFilterParam::Nearest);
         texture.set_mag_filter(MagFilterParam::Nearest);
 
-        texture.load_empty(1280, 720); // TODO: Harcoded, replace
+        texture.load_empty(cache_width, cache_height, TextureFormat::R8);
 
-        let vao = VertexArrayObject::new().expect("Failed to allocate vertex array object for font");
+        let vao = VertexArrayObject::new()?;
         vao.bind();
 
-        let vbo = VertexBufferObject::new().expect("Failed to allocate vertex buffer for font");
+        let vbo = VertexBufferObject::new()?;
         vbo.bind();
 
         VertexArrayObject::set_vertex_attribute(0, 4, Float, false, size_of::<Vertex>(), 0);
         VertexArrayObject::set_vertex_attribute(1, 4, Float, false, size_of::<Vertex>(), size_of::<[f32; 4]>());
 
-        Self {
+        Ok(Self {
             font,
             cache,
             texture,
             vao,
             vbo,
-        }
+        })
     }
 
     pub fn draw(&mut self, shader_program: &Shader, text: &str, font_size: f32, translation: Mat4) {
@@ -115,7 +119,9 @@ impl TrueTypeFont<'_> {
         // TODO: Also, it's not optimal to reallocate VAO/VBO and set VAO attributes again as they will basically never change and new vertex data can be reloaded into existing buffer
 
         let scale = Scale::uniform(font_size);
-        let (screen_width, screen_height) = (1280.0, 720.0); // TODO: Hardcoded, fix
+        // Text is laid out in pixels of the current viewport
+        let [_, _, viewport_width, viewport_height] = opengl::get_viewport();
+        let (screen_width, screen_height) = (viewport_width.max(1) as f32, viewport_height.max(1) as f32);
 
         let glyphs = layout_paragraph(&self.font, scale, screen_width as u32, text);
 
@@ -1,13 +1,16 @@
 use std::hash::{Hash, Hasher};
+use std::mem::size_of;
 
-use ultraviolet::{Vec2, Vec3};
+use ultraviolet::{Vec2, Vec3, Vec4};
 
-#[repr(C)]
 #[derive(Copy, Clone)]
 pub struct Vertex {
     position: Vec3,
     tex_coord: Vec2,
-    // TODO: Normals, bi-tangent, tangent etc.
+    tex_coord2: Vec2,
+    normal: Vec3, // Zero until loaded or computed
+    tangent: Vec4, // w is the bitangent sign, zero until loaded or computed
+    color: Vec4,
 }
 
 impl Vertex {
@@ -15,14 +18,71 @@ impl Vertex {
         Self {
             position,
             tex_coord,
+            tex_coord2: Vec2::zero(),
+            normal: Vec3::zero(),
+            tangent: Vec4::zero(),
+            color: Vec4::one(),
         }
     }
+
+    pub fn with_normal(mut self, normal: Vec3) -> Self {
+        self.normal = normal;
+        self
+    }
+
+    pub fn with_tangent(mut self, tangent: Vec4) -> Self {
+        self.tangent = tangent;
+        self
+    }
+
+    pub fn with_color(mut self, color: Vec4) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_tex_coord2(mut self, tex_coord2: Vec2) -> Self {
+        self.tex_coord2 = tex_coord2;
+        self
+    }
+
+    pub fn position(&self) -> Vec3 {
+        self.position
+    }
+
+    pub fn tex_coord(&self) -> Vec2 {
+        self.tex_coord
+    }
+
+    pub fn tex_coord2(&self) -> Vec2 {
+        self.tex_coord2
+    }
+
+    pub fn normal(&self) -> Vec3 {
+        self.normal
+    }
+
+    pub fn tangent(&self) -> Vec4 {
+        self.tangent
+    }
+
+    pub fn bitangent(&self) -> Vec3 {
+        self.normal.cross(self.tangent.xyz()) * self.tangent.w
+    }
+
+    pub fn color(&self) -> Vec4 {
+        self.color
+    }
+
+    fn components(&self) -> [f32; 18] {
+        let (p, t, t2, n, tn, c) = (self.position, self.tex_coord, self.tex_coord2, self.normal, self.tangent, self.color);
+
+        [p.x, p.y, p.z, t.x, t.y, t2.x, t2.y, n.x, n.y, n.z, tn.x, tn.y, tn.z, tn.w, c.x, c.y, c.z, c.w]
+    }
 }
 
 impl PartialEq for Vertex {
     fn eq(&self, other: &Self) -> bool {
-        self.position == other.position
-            && self.tex_coord == other.tex_coord
+        self.components() == other.components()
     }
 }
 
@@ -30,10 +90,125 @@ impl Eq for Vertex {}
 
 impl Hash for Vertex {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        self.position[0].to_bits().hash(state);
-        self.position[1].to_bits().hash(state);
-        self.position[2].to_bits().hash(state);
-        self.tex_coord[0].to_bits().hash(state);
-        self.tex_coord[1].to_bits().hash(state);
+        for component in self.components() {
+            component.to_bits().hash(state);
+        }
+    }
+}
+
+// Shader locations are fixed per attribute so shaders don't depend on which other attributes a mesh has
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum VertexAttribute {
+    Position,
+    TexCoord,
+    Normal,
+    Tangent,
+    Bitangent,
+    Color,
+    TexCoord2,
+}
+
+impl VertexAttribute {
+    pub fn location(self) -> usize {
+        self as usize
+    }
+
+    pub fn component_count(self) -> usize {
+        match self {
+            VertexAttribute::TexCoord | VertexAttribute::TexCoord2 => 2,
+            VertexAttribute::Position | VertexAttribute::Normal | VertexAttribute::Bitangent => 3,
+            VertexAttribute::Tangent | VertexAttribute::Color => 4,
+        }
+    }
+
+    fn write(self, vertex: &Vertex, data: &mut Vec<f32>) {
+        match self {
+            VertexAttribute::Position => data.extend_from_slice(vertex.position.as_slice()),
+            VertexAttribute::TexCoord => data.extend_from_slice(vertex.tex_coord.as_slice()),
+            VertexAttribute::Normal => data.extend_from_slice(vertex.normal.as_slice()),
+            VertexAttribute::Tangent => data.extend_from_slice(vertex.tangent.as_slice()),
+            VertexAttribute::Bitangent => data.extend_from_slice(vertex.bitangent().as_slice()),
+            VertexAttribute::Color => data.extend_from_slice(vertex.color.as_slice()),
+            VertexAttribute::TexCoord2 => data.extend_from_slice(vertex.tex_coord2.as_slice()),
+        }
+    }
+}
+
+// Attributes a mesh uploads to the GPU, interleaved in this order
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VertexLayout {
+    attributes: Vec<VertexAttribute>,
+}
+
+impl Default for VertexLayout {
+    fn default() -> Self {
+        Self::new(&[VertexAttribute::Position, VertexAttribute::TexCoord, VertexAttribute::Normal, VertexAttribute::Tangent])
+    }
+}
+
+impl VertexLayout {
+    pub fn new(attributes: &[VertexAttribute]) -> Self {
+        let mut layout = Self { attributes: Vec::new() };
+        for attribute in attributes {
+            layout = layout.with(*attribute);
+        }
+
+        layout
+    }
+
+    pub fn with(mut self, attribute: VertexAttribute) -> Self {
+        if !self.contains(attribute) {
+            self.attributes.push(attribute);
+        }
+
+        self
+    }
+
+    pub fn contains(&self, attribute: VertexAttribute) -> bool {
+        self.attributes.contains(&attribute)
     }
-}
\ No newline at end of file
+
+    pub fn attributes(&self) -> &[VertexAttribute] {
+        &self.attributes
+    }
+
+    pub fn stride(&self) -> usize {
+        self.attributes.iter().map(|attribute| attribute.component_count() * size_of::<f32>()).sum()
+    }
+
+    // Byte offset of each attribute within a vertex
+    pub fn offsets(&self) -> impl Iterator<Item=(VertexAttribute, usize)> + '_ {
+        self.attributes.iter().scan(0, |offset, attribute| {
+            let attribute_offset = *offset;
+            *offset += attribute.component_count() * size_of::<f32>();
+
+            Some((*attribute, attribute_offset))
+        })
+    }
+
+    pub fn interleave(&self, vertices: &[Vertex]) -> Vec<f32> {
+        let mut data = Vec::with_capacity(vertices.len() * self.stride() / size_of::<f32>());
+        for vertex in vertices {
+            for attribute in &self.attributes {
+                attribute.write(vertex, &mut data);
+            }
+        }
+
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layouts_interleave_only_their_attributes() {
+        let layout = VertexLayout::new(&[VertexAttribute::Position, VertexAttribute::Color, VertexAttribute::Position]);
+        assert_eq!(layout.stride(), 28);
+        assert_eq!(layout.offsets().collect::<Vec<_>>(), vec![(VertexAttribute::Position, 0), (VertexAttribute::Color, 12)]);
+
+        let vertex = Vertex::new(Vec3::new(1.0, 2.0, 3.0), Vec2::new(0.5, 0.5)).with_color(Vec4::new(0.1, 0.2, 0.3, 1.0));
+        assert_eq!(layout.interleave(&[vertex]), vec![1.0, 2.0, 3.0, 0.1, 0.2, 0.3, 1.0]);
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+
+use ultraviolet::{Vec3, Vec4};
+
+use crate::graphics::vertex::Vertex;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NormalMode {
+    Smooth,
+    Flat, // Every triangle gets its own vertices, so the mesh is no longer indexed meaningfully
+}
+
+pub fn compute_normals(vertices: &mut Vec<Vertex>, indices: &mut Vec<u32>, mode: NormalMode) {
+    match mode {
+        NormalMode::Smooth => compute_smooth_normals(vertices, indices),
+        NormalMode::Flat => compute_flat_normals(vertices, indices),
+    }
+}
+
+// Face normals are summed per position rather than per vertex so UV seams don't turn into hard edges.
+// The cross product grows with the triangle area, so larger faces weigh more
+fn compute_smooth_normals(vertices: &mut [Vertex], indices: &[u32]) {
+    let position_key = |vertex: &Vertex| vertex.position().as_array().map(f32::to_bits);
+    let mut sums: HashMap<[u32; 3], Vec3> = HashMap::new();
+
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [triangle[0], triangle[1], triangle[2]].map(|index| vertices[index as usize]);
+        let face = (b.position() - a.position()).cross(c.position() - a.position());
+
+        for vertex in [a, b, c] {
+            *sums.entry(position_key(&vertex)).or_insert_with(Vec3::zero) += face;
+        }
+    }
+
+    for vertex in vertices.iter_mut() {
+        let sum = sums.get(&position_key(vertex)).copied().unwrap_or_else(Vec3::zero);
+        *vertex = vertex.with_normal(normalized_or_zero(sum));
+    }
+}
+
+fn compute_flat_normals(vertices: &mut Vec<Vertex>, indices: &mut Vec<u32>) {
+    let mut flat = Vec::with_capacity(indices.len());
+
+    for triangle in indices.chunks_exact(3) {
+        let corners = [triangle[0], triangle[1], triangle[2]].map(|index| vertices[index as usize]);
+        let normal = normalized_or_zero((corners[1].position() - corners[0].position()).cross(corners[2].position() - corners[0].position()));
+
+        flat.extend(corners.map(|vertex| vertex.with_normal(normal)));
+    }
+
+    *indices = (0..flat.len() as u32).collect();
+    *vertices = flat;
+}
+
+// Per-triangle UV gradients accumulated per vertex, then made orthogonal to the normal like MikkTSpace does.
+// Needs normals, vertices without one keep a zero tangent
+pub fn compute_tangents(vertices: &mut [Vertex], indices: &[u32]) {
+    let mut tangents = vec![Vec3::zero(); vertices.len()];
+    let mut bitangents = vec![Vec3::zero(); vertices.len()];
+
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [triangle[0], triangle[1], triangle[2]].map(|index| vertices[index as usize]);
+        let (edge1, edge2) = (b.position() - a.position(), c.position() - a.position());
+        let (uv1, uv2) = (b.tex_coord() - a.tex_coord(), c.tex_coord() - a.tex_coord());
+
+        let determinant = uv1.x * uv2.y - uv2.x * uv1.y;
+        if determinant.abs() < f32::EPSILON {
+            continue;
+        }
+
+        let tangent = (edge1 * uv2.y - edge2 * uv1.y) / determinant;
+        let bitangent = (edge2 * uv1.x - edge1 * uv2.x) / determinant;
+        for index in triangle {
+            tangents[*index as usize] += tangent;
+            bitangents[*index as usize] += bitangent;
+        }
+    }
+
+    for (i, vertex) in vertices.iter_mut().enumerate() {
+        let normal = vertex.normal();
+        if normal.mag_sq() == 0.0 {
+            continue;
+        }
+
+        let mut tangent = tangents[i] - normal * normal.dot(tangents[i]);
+        if tangent.mag_sq() < f32::EPSILON {
+            // No usable UVs, any direction along the surface will do
+            tangent = normal.cross(if normal.x.abs() < 0.9 { Vec3::unit_x() } else { Vec3::unit_y() });
+        }
+        let tangent = tangent.normalized();
+        let handedness = if normal.cross(tangent).dot(bitangents[i]) < 0.0 { -1.0 } else { 1.0 };
+
+        *vertex = vertex.with_tangent(Vec4::new(tangent.x, tangent.y, tangent.z, handedness));
+    }
+}
+
+pub(crate) fn normalized_or_zero(vector: Vec3) -> Vec3 {
+    if vector.mag_sq() > 0.0 { vector.normalized() } else { Vec3::zero() }
+}
+
+#[cfg(test)]
+mod tests {
+    use ultraviolet::Vec2;
+
+    use super::*;
+
+    // Unit quad in the XY plane facing +Z, U along +X and V along +Y
+    fn quad() -> (Vec<Vertex>, Vec<u32>) {
+        let vertices = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)].iter()
+            .map(|&(x, y)| Vertex::new(Vec3::new(x, y, 0.0), Vec2::new(x, y)))
+            .collect();
+
+        (vertices, vec![0, 1, 2, 0, 2, 3])
+    }
+
+    #[test]
+    fn normals_face_out_of_the_winding() {
+        let (mut vertices, mut indices) = quad();
+        compute_normals(&mut vertices, &mut indices, NormalMode::Smooth);
+        assert!(vertices.iter().all(|vertex| vertex.normal() == Vec3::unit_z()));
+
+        let (mut vertices, mut indices) = quad();
+        compute_normals(&mut vertices, &mut indices, NormalMode::Flat);
+        assert_eq!(vertices.len(), 6);
+        assert_eq!(indices, vec![0, 1, 2, 3, 4, 5]);
+        assert!(vertices.iter().all(|vertex| vertex.normal() == Vec3::unit_z()));
+    }
+
+    #[test]
+    fn tangents_follow_the_uvs() {
+        let (mut vertices, mut indices) = quad();
+        compute_normals(&mut vertices, &mut indices, NormalMode::Smooth);
+        compute_tangents(&mut vertices, &indices);
+
+        for vertex in &vertices {
+            assert!((vertex.tangent() - Vec4::new(1.0, 0.0, 0.0, 1.0)).mag() < 1e-5);
+            assert!((vertex.bitangent() - Vec3::unit_y()).mag() < 1e-5);
+        }
+
+        // Mirrored UVs flip the handedness
+        let mut mirrored: Vec<Vertex> = vertices.iter().map(|vertex| Vertex::new(vertex.position(), Vec2::new(vertex.tex_coord().x, -vertex.tex_coord().y)).with_normal(vertex.normal())).collect();
+        compute_tangents(&mut mirrored, &indices);
+        assert!(mirrored.iter().all(|vertex| vertex.tangent().w == -1.0));
+    }
+}
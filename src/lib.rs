@@ -0,0 +1,11 @@
+pub mod assets;
+pub mod camera;
+pub mod ecs;
+pub mod engine;
+pub mod error;
+pub mod graphics;
+pub mod math;
+pub mod opengl;
+pub mod physics;
+pub mod shader;
+pub mod timestep;
@@ -1,4 +1,19 @@
-#[derive(Copy, Clone, Default)]
+use ultraviolet::{Mat4, Vec3};
+
+#[derive(Copy, Clone, Default, Debug, PartialEq)]
+pub struct Contact {
+    pub normal: Vec3, // Points from the other box towards this one
+    pub depth: f32,
+    pub point: Vec3,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SweepHit {
+    pub time: f32, // Fraction of the displacement travelled before touching, in [0, 1]
+    pub normal: Vec3,
+}
+
+#[derive(Copy, Clone, Default, Debug, PartialEq)]
 pub struct AABBBoundingBox {
     pub x_min: f32,
     pub x_max: f32,
@@ -9,7 +24,187 @@ pub struct AABBBoundingBox {
 }
 
 impl AABBBoundingBox {
+    pub fn from_min_max(min: Vec3, max: Vec3) -> Self {
+        Self { x_min: min.x, x_max: max.x, y_min: min.y, y_max: max.y, z_min: min.z, z_max: max.z }
+    }
+
+    pub fn from_center_half_extents(center: Vec3, half_extents: Vec3) -> Self {
+        Self::from_min_max(center - half_extents, center + half_extents)
+    }
+
+    // An empty iterator gives a zero sized box at the origin
+    pub fn from_points<'a>(points: impl IntoIterator<Item = &'a Vec3>) -> Self {
+        let mut points = points.into_iter();
+        let Some(first) = points.next() else {
+            return Self::default();
+        };
+
+        let (min, max) = points.fold((*first, *first), |(min, max), point| (min.min_by_component(*point), max.max_by_component(*point)));
+        Self::from_min_max(min, max)
+    }
+
     pub fn collides_with(&self, other: AABBBoundingBox) -> bool {
         self.x_min <= other.x_max && self.x_max >= other.x_min && self.y_min <= other.y_max && self.y_max >= other.y_min && self.z_min <= other.z_max && self.z_max >= other.z_min
     }
-}
\ No newline at end of file
+
+    pub fn min(&self) -> Vec3 {
+        Vec3::new(self.x_min, self.y_min, self.z_min)
+    }
+
+    pub fn max(&self) -> Vec3 {
+        Vec3::new(self.x_max, self.y_max, self.z_max)
+    }
+
+    pub fn center(&self) -> Vec3 {
+        (self.min() + self.max()) * 0.5
+    }
+
+    pub fn size(&self) -> Vec3 {
+        self.max() - self.min()
+    }
+
+    pub fn translated(&self, offset: Vec3) -> Self {
+        Self::from_min_max(self.min() + offset, self.max() + offset)
+    }
+
+    pub fn expanded(&self, amount: Vec3) -> Self {
+        Self::from_min_max(self.min() - amount, self.max() + amount)
+    }
+
+    pub fn union(&self, other: AABBBoundingBox) -> Self {
+        Self::from_min_max(self.min().min_by_component(other.min()), self.max().max_by_component(other.max()))
+    }
+
+    // Smallest box around the transformed box, accounts for rotation and scale (Arvo's method)
+    pub fn transformed(&self, matrix: Mat4) -> Self {
+        let center = matrix.transform_point3(self.center());
+        let half_size = self.size() * 0.5;
+
+        let mut half_extents = Vec3::default();
+        for row in 0..3 {
+            half_extents[row] = (0..3).map(|column| matrix.cols[column][row].abs() * half_size[column]).sum();
+        }
+
+        Self::from_center_half_extents(center, half_extents)
+    }
+
+    pub fn contains(&self, other: AABBBoundingBox) -> bool {
+        self.x_min <= other.x_min && self.x_max >= other.x_max && self.y_min <= other.y_min && self.y_max >= other.y_max && self.z_min <= other.z_min && self.z_max >= other.z_max
+    }
+
+    pub fn surface_area(&self) -> f32 {
+        let size = self.size();
+        2.0 * (size.x * size.y + size.y * size.z + size.z * size.x)
+    }
+
+    pub fn contact(&self, other: AABBBoundingBox) -> Option<Contact> {
+        if !self.collides_with(other) {
+            return None;
+        }
+
+        let candidates = [
+            (Vec3::new(1.0, 0.0, 0.0), other.x_max - self.x_min),
+            (Vec3::new(-1.0, 0.0, 0.0), self.x_max - other.x_min),
+            (Vec3::new(0.0, 1.0, 0.0), other.y_max - self.y_min),
+            (Vec3::new(0.0, -1.0, 0.0), self.y_max - other.y_min),
+            (Vec3::new(0.0, 0.0, 1.0), other.z_max - self.z_min),
+            (Vec3::new(0.0, 0.0, -1.0), self.z_max - other.z_min),
+        ];
+        let (normal, depth) = candidates.into_iter().min_by(|a, b| a.1.total_cmp(&b.1))?;
+
+        let overlap_min = self.min().max_by_component(other.min());
+        let overlap_max = self.max().min_by_component(other.max());
+
+        Some(Contact { normal, depth, point: (overlap_min + overlap_max) * 0.5 })
+    }
+
+    // Continuous test of this box moving by `displacement` against a static box, boxes that already overlap are not reported
+    pub fn sweep(&self, displacement: Vec3, other: AABBBoundingBox) -> Option<SweepHit> {
+        let half_size = self.size() * 0.5;
+        let expanded = other.expanded(half_size);
+        let origin = self.center();
+
+        let mut entry_time = f32::NEG_INFINITY;
+        let mut exit_time = f32::INFINITY;
+        let mut normal = Vec3::default();
+
+        for axis in 0..3 {
+            let (start, delta, min, max) = (origin[axis], displacement[axis], expanded.min()[axis], expanded.max()[axis]);
+
+            if delta.abs() < f32::EPSILON {
+                if start <= min || start >= max {
+                    return None;
+                }
+                continue;
+            }
+
+            let (near, far) = if delta > 0.0 { (min, max) } else { (max, min) };
+            let axis_entry = (near - start) / delta;
+            let axis_exit = (far - start) / delta;
+
+            if axis_entry > entry_time {
+                entry_time = axis_entry;
+                normal = Vec3::default();
+                normal[axis] = -delta.signum();
+            }
+            exit_time = exit_time.min(axis_exit);
+        }
+
+        if entry_time > exit_time || !(0.0..=1.0).contains(&entry_time) {
+            return None;
+        }
+
+        Some(SweepHit { time: entry_time, normal })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transformed_box_encloses_rotated_and_scaled_corners() {
+        let bounding_box = AABBBoundingBox::from_min_max(Vec3::new(-1.0, 0.0, -2.0), Vec3::new(3.0, 1.0, 2.0));
+        let matrix = Mat4::from_translation(Vec3::new(5.0, -1.0, 0.0)) * Mat4::from_rotation_y(0.7) * Mat4::from_rotation_x(0.3) * Mat4::from_nonuniform_scale(Vec3::new(2.0, 0.5, 1.5));
+        let transformed = bounding_box.transformed(matrix);
+
+        let (min, max) = (bounding_box.min(), bounding_box.max());
+        let corners: Vec<Vec3> = (0..8).map(|i| matrix.transform_point3(Vec3::new(
+            if i & 1 == 0 { min.x } else { max.x },
+            if i & 2 == 0 { min.y } else { max.y },
+            if i & 4 == 0 { min.z } else { max.z },
+        ))).collect();
+        let expected = AABBBoundingBox::from_points(&corners);
+
+        assert!((transformed.min() - expected.min()).mag() < 1e-4);
+        assert!((transformed.max() - expected.max()).mag() < 1e-4);
+    }
+
+    fn unit_box(center: Vec3) -> AABBBoundingBox {
+        AABBBoundingBox::from_center_half_extents(center, Vec3::broadcast(0.5))
+    }
+
+    #[test]
+    fn contact_uses_the_axis_of_least_penetration() {
+        let floor = AABBBoundingBox::from_min_max(Vec3::new(-5.0, -1.0, -5.0), Vec3::new(5.0, 0.0, 5.0));
+        let contact = unit_box(Vec3::new(1.0, 0.4, 0.0)).contact(floor).unwrap();
+
+        assert_eq!(contact.normal, Vec3::unit_y());
+        assert!((contact.depth - 0.1).abs() < 1e-6);
+        assert!((contact.point - Vec3::new(1.0, -0.05, 0.0)).mag() < 1e-6);
+        assert!(unit_box(Vec3::new(0.0, 0.6, 0.0)).contact(floor).is_none());
+    }
+
+    #[test]
+    fn sweep_reports_when_and_where_the_boxes_touch() {
+        let wall = AABBBoundingBox::from_min_max(Vec3::new(2.5, -1.0, -1.0), Vec3::new(3.0, 1.0, 1.0));
+
+        let hit = unit_box(Vec3::default()).sweep(Vec3::new(4.0, 0.0, 0.0), wall).unwrap();
+        assert!((hit.time - 0.5).abs() < 1e-6);
+        assert_eq!(hit.normal, Vec3::new(-1.0, 0.0, 0.0));
+
+        assert!(unit_box(Vec3::default()).sweep(Vec3::new(1.0, 0.0, 0.0), wall).is_none(), "stops short");
+        assert!(unit_box(Vec3::default()).sweep(Vec3::new(-4.0, 0.0, 0.0), wall).is_none(), "moves away");
+        assert!(unit_box(Vec3::new(0.0, 3.0, 0.0)).sweep(Vec3::new(4.0, 0.0, 0.0), wall).is_none(), "passes above");
+    }
+}
@@ -0,0 +1,68 @@
+use ultraviolet::{Mat4, Vec3};
+
+use crate::math::aabb_bouding_box::AABBBoundingBox;
+
+#[derive(Copy, Clone, Default, Debug, PartialEq)]
+pub struct BoundingSphere {
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    pub fn new(center: Vec3, radius: f32) -> Self {
+        Self { center, radius }
+    }
+
+    // Ritter's approximation, within a few percent of the minimal sphere for typical meshes
+    pub fn from_points(points: &[Vec3]) -> Self {
+        let Some(first) = points.first() else {
+            return Self::default();
+        };
+
+        let farthest_from = |from: Vec3| points.iter().copied().max_by(|a, b| (*a - from).mag_sq().total_cmp(&(*b - from).mag_sq())).unwrap_or(from);
+        let a = farthest_from(*first);
+        let b = farthest_from(a);
+
+        let mut sphere = Self { center: (a + b) * 0.5, radius: (b - a).mag() * 0.5 };
+        for point in points {
+            let distance = (*point - sphere.center).mag();
+            if distance > sphere.radius {
+                let radius = (sphere.radius + distance) * 0.5;
+                sphere.center += (*point - sphere.center) * ((radius - sphere.radius) / distance);
+                sphere.radius = radius;
+            }
+        }
+
+        sphere
+    }
+
+    pub fn merged(&self, other: BoundingSphere) -> Self {
+        let offset = other.center - self.center;
+        let distance = offset.mag();
+
+        if distance + other.radius <= self.radius {
+            return *self;
+        }
+        if distance + self.radius <= other.radius {
+            return other;
+        }
+
+        let radius = (distance + self.radius + other.radius) * 0.5;
+        Self { center: self.center + offset * ((radius - self.radius) / distance), radius }
+    }
+
+    // Non-uniform scale grows the sphere by the largest axis scale
+    pub fn transformed(&self, matrix: Mat4) -> Self {
+        let scale = (0..3).map(|i| matrix.cols[i].xyz().mag()).fold(0.0, f32::max);
+
+        Self { center: matrix.transform_point3(self.center), radius: self.radius * scale }
+    }
+
+    pub fn bounding_box(&self) -> AABBBoundingBox {
+        AABBBoundingBox::from_center_half_extents(self.center, Vec3::broadcast(self.radius))
+    }
+
+    pub fn contains_point(&self, point: Vec3) -> bool {
+        (point - self.center).mag_sq() <= self.radius * self.radius
+    }
+}
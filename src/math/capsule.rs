@@ -0,0 +1,91 @@
+use ultraviolet::Vec3;
+
+use crate::math::aabb_bouding_box::AABBBoundingBox;
+
+// Upright capsule, `position` is the bottom of the lower cap and `height` includes both caps
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Capsule {
+    pub position: Vec3,
+    pub radius: f32,
+    pub height: f32,
+}
+
+impl Capsule {
+    pub fn new(position: Vec3, radius: f32, height: f32) -> Self {
+        Self { position, radius, height: height.max(radius * 2.0) }
+    }
+
+    // Centers of the bottom and top cap spheres
+    pub fn segment(&self) -> (Vec3, Vec3) {
+        let bottom = self.position + Vec3::new(0.0, self.radius, 0.0);
+        let top = self.position + Vec3::new(0.0, self.height - self.radius, 0.0);
+
+        (bottom, top)
+    }
+
+    pub fn center(&self) -> Vec3 {
+        self.position + Vec3::new(0.0, self.height * 0.5, 0.0)
+    }
+
+    pub fn translated(&self, offset: Vec3) -> Self {
+        Self { position: self.position + offset, ..*self }
+    }
+
+    pub fn bounding_box(&self) -> AABBBoundingBox {
+        AABBBoundingBox::from_min_max(
+            self.position - Vec3::new(self.radius, 0.0, self.radius),
+            self.position + Vec3::new(self.radius, self.height, self.radius),
+        )
+    }
+}
+
+pub fn closest_point_on_segment(a: Vec3, b: Vec3, point: Vec3) -> Vec3 {
+    let segment = b - a;
+    let length_sq = segment.mag_sq();
+    if length_sq < f32::EPSILON {
+        return a;
+    }
+
+    a + segment * ((point - a).dot(segment) / length_sq).clamp(0.0, 1.0)
+}
+
+// Voronoi region walk from Real-Time Collision Detection (Ericson), section 5.1.5
+pub fn closest_point_on_triangle(triangle: &[Vec3; 3], point: Vec3) -> Vec3 {
+    let [a, b, c] = *triangle;
+    let (ab, ac, ap) = (b - a, c - a, point - a);
+
+    let (d1, d2) = (ab.dot(ap), ac.dot(ap));
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return a;
+    }
+
+    let bp = point - b;
+    let (d3, d4) = (ab.dot(bp), ac.dot(bp));
+    if d3 >= 0.0 && d4 <= d3 {
+        return b;
+    }
+
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        return a + ab * (d1 / (d1 - d3));
+    }
+
+    let cp = point - c;
+    let (d5, d6) = (ab.dot(cp), ac.dot(cp));
+    if d6 >= 0.0 && d5 <= d6 {
+        return c;
+    }
+
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        return a + ac * (d2 / (d2 - d6));
+    }
+
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && d4 - d3 >= 0.0 && d5 - d6 >= 0.0 {
+        return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+    }
+
+    let denominator = 1.0 / (va + vb + vc);
+    a + ab * (vb * denominator) + ac * (vc * denominator)
+}
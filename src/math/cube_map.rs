@@ -0,0 +1,34 @@
+use ultraviolet::{Mat4, Vec3};
+use ultraviolet::projection::perspective_gl;
+
+// Cube map faces in GL order (+X, -X, +Y, -Y, +Z, -Z) with the up vectors cube map lookups expect
+pub const CUBE_FACES: [(Vec3, Vec3); 6] = [
+    (Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, -1.0, 0.0)),
+    (Vec3::new(-1.0, 0.0, 0.0), Vec3::new(0.0, -1.0, 0.0)),
+    (Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, 1.0)),
+    (Vec3::new(0.0, -1.0, 0.0), Vec3::new(0.0, 0.0, -1.0)),
+    (Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, -1.0, 0.0)),
+    (Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, -1.0, 0.0)),
+];
+
+// One 90° view projection per face, for rendering into a cube map from `position`
+pub fn face_view_projections(position: Vec3, near: f32, far: f32) -> [Mat4; 6] {
+    let projection = perspective_gl(std::f32::consts::FRAC_PI_2, 1.0, near, far);
+
+    CUBE_FACES.map(|(direction, up)| projection * Mat4::look_at(position, position + direction, up))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn faces_look_along_the_axes() {
+        let position = Vec3::new(1.0, 2.0, 3.0);
+        for ((direction, _), view_projection) in CUBE_FACES.iter().zip(face_view_projections(position, 0.05, 10.0)) {
+            let projected = view_projection.transform_point3(position + *direction * 5.0);
+            assert!(projected.x.abs() < 1e-4 && projected.y.abs() < 1e-4);
+            assert!(projected.z > -1.0 && projected.z < 1.0);
+        }
+    }
+}
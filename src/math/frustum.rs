@@ -0,0 +1,64 @@
+use ultraviolet::{Mat4, Vec3, Vec4};
+
+use crate::math::aabb_bouding_box::AABBBoundingBox;
+
+#[derive(Copy, Clone, Debug)]
+pub struct Plane {
+    pub normal: Vec3,
+    pub distance: f32,
+}
+
+impl Plane {
+    fn from_vec4(plane: Vec4) -> Self {
+        let normal = plane.xyz();
+        let length = normal.mag();
+
+        Self { normal: normal / length, distance: plane.w / length }
+    }
+
+    pub fn signed_distance(&self, point: Vec3) -> f32 {
+        self.normal.dot(point) + self.distance
+    }
+}
+
+// Planes point inwards: left, right, bottom, top, near, far
+#[derive(Copy, Clone, Debug)]
+pub struct Frustum {
+    pub planes: [Plane; 6],
+}
+
+impl Frustum {
+    // Expects an OpenGL style (-w..w clip space) projection * view matrix
+    pub fn from_view_projection(view_projection: Mat4) -> Self {
+        let row = |i: usize| Vec4::new(view_projection.cols[0][i], view_projection.cols[1][i], view_projection.cols[2][i], view_projection.cols[3][i]);
+        let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+
+        Self {
+            planes: [
+                Plane::from_vec4(w + x),
+                Plane::from_vec4(w - x),
+                Plane::from_vec4(w + y),
+                Plane::from_vec4(w - y),
+                Plane::from_vec4(w + z),
+                Plane::from_vec4(w - z),
+            ],
+        }
+    }
+
+    pub fn contains_point(&self, point: Vec3) -> bool {
+        self.planes.iter().all(|plane| plane.signed_distance(point) >= 0.0)
+    }
+
+    // Conservative: boxes near frustum corners may be reported as intersecting
+    pub fn intersects_aabb(&self, bounding_box: &AABBBoundingBox) -> bool {
+        self.planes.iter().all(|plane| {
+            let farthest_along_normal = Vec3::new(
+                if plane.normal.x >= 0.0 { bounding_box.x_max } else { bounding_box.x_min },
+                if plane.normal.y >= 0.0 { bounding_box.y_max } else { bounding_box.y_min },
+                if plane.normal.z >= 0.0 { bounding_box.z_max } else { bounding_box.z_min },
+            );
+
+            plane.signed_distance(farthest_along_normal) >= 0.0
+        })
+    }
+}
@@ -1,2 +1,7 @@
 pub mod rotation;
-pub mod aabb_bouding_box;
\ No newline at end of file
+pub mod aabb_bouding_box;
+pub mod frustum;
+pub mod ray;
+pub mod capsule;
+pub mod bounding_sphere;
+pub mod cube_map;
@@ -0,0 +1,157 @@
+use ultraviolet::{Mat4, Vec3, Vec4};
+
+use crate::math::aabb_bouding_box::AABBBoundingBox;
+
+const TRIANGLE_EPSILON: f32 = 1e-7;
+
+#[derive(Copy, Clone, Debug)]
+pub struct Ray {
+    pub origin: Vec3,
+    pub direction: Vec3,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RayHit {
+    pub distance: f32, // In units of `direction`, equal to world units when it is normalized
+    pub normal: Vec3,
+}
+
+impl Ray {
+    pub fn new(origin: Vec3, direction: Vec3) -> Self {
+        Self { origin, direction }
+    }
+
+    pub fn at(&self, distance: f32) -> Vec3 {
+        self.origin + self.direction * distance
+    }
+
+    pub fn transformed(&self, matrix: Mat4) -> Self {
+        let origin = matrix * Vec4::new(self.origin.x, self.origin.y, self.origin.z, 1.0);
+        let direction = matrix * Vec4::new(self.direction.x, self.direction.y, self.direction.z, 0.0);
+
+        Self { origin: origin.xyz(), direction: direction.xyz() }
+    }
+
+    // Slab test, a ray starting inside the box hits it at distance 0
+    pub fn intersect_aabb(&self, bounding_box: &AABBBoundingBox, max_distance: f32) -> Option<RayHit> {
+        let (min, max) = (bounding_box.min(), bounding_box.max());
+        let mut entry = 0.0_f32;
+        let mut exit = max_distance;
+        let mut normal = Vec3::default();
+
+        for axis in 0..3 {
+            let (origin, direction) = (self.origin[axis], self.direction[axis]);
+
+            if direction.abs() < f32::EPSILON {
+                if origin < min[axis] || origin > max[axis] {
+                    return None;
+                }
+                continue;
+            }
+
+            let inverse = 1.0 / direction;
+            let (mut near, mut far) = ((min[axis] - origin) * inverse, (max[axis] - origin) * inverse);
+            if near > far {
+                std::mem::swap(&mut near, &mut far);
+            }
+
+            if near > entry {
+                entry = near;
+                normal = Vec3::default();
+                normal[axis] = -direction.signum();
+            }
+            exit = exit.min(far);
+
+            if entry > exit {
+                return None;
+            }
+        }
+
+        Some(RayHit { distance: entry, normal })
+    }
+
+    // Möller–Trumbore, triangles are double sided and the normal faces the ray origin
+    pub fn intersect_triangle(&self, triangle: [Vec3; 3], max_distance: f32) -> Option<RayHit> {
+        let edge1 = triangle[1] - triangle[0];
+        let edge2 = triangle[2] - triangle[0];
+        let p = self.direction.cross(edge2);
+        let determinant = edge1.dot(p);
+
+        if determinant.abs() < TRIANGLE_EPSILON {
+            return None;
+        }
+
+        let inverse_determinant = 1.0 / determinant;
+        let t = self.origin - triangle[0];
+        let u = t.dot(p) * inverse_determinant;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+
+        let q = t.cross(edge1);
+        let v = self.direction.dot(q) * inverse_determinant;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        let distance = edge2.dot(q) * inverse_determinant;
+        if distance < 0.0 || distance > max_distance {
+            return None;
+        }
+
+        let mut normal = edge1.cross(edge2).normalized();
+        if normal.dot(self.direction) > 0.0 {
+            normal = -normal;
+        }
+
+        Some(RayHit { distance, normal })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_box() -> AABBBoundingBox {
+        AABBBoundingBox::from_center_half_extents(Vec3::default(), Vec3::broadcast(1.0))
+    }
+
+    #[test]
+    fn rays_hit_and_miss_boxes() {
+        let hit = Ray::new(Vec3::new(-5.0, 0.5, 0.0), Vec3::unit_x()).intersect_aabb(&unit_box(), 100.0).unwrap();
+        assert_eq!(hit, RayHit { distance: 4.0, normal: -Vec3::unit_x() });
+
+        assert!(Ray::new(Vec3::new(-5.0, 2.0, 0.0), Vec3::unit_x()).intersect_aabb(&unit_box(), 100.0).is_none(), "passes above");
+        assert!(Ray::new(Vec3::new(-5.0, 0.0, 0.0), -Vec3::unit_x()).intersect_aabb(&unit_box(), 100.0).is_none(), "points away");
+        assert!(Ray::new(Vec3::new(-5.0, 0.0, 0.0), Vec3::unit_x()).intersect_aabb(&unit_box(), 3.0).is_none(), "too short");
+    }
+
+    #[test]
+    fn ray_starting_inside_a_box_hits_it_immediately() {
+        let hit = Ray::new(Vec3::new(0.2, -0.3, 0.0), Vec3::unit_y()).intersect_aabb(&unit_box(), 10.0).unwrap();
+        assert_eq!(hit.distance, 0.0);
+    }
+
+    #[test]
+    fn rays_parallel_to_a_face_only_hit_inside_its_slab() {
+        let direction = Vec3::new(1.0, 0.0, 1.0).normalized();
+        let hit = Ray::new(Vec3::new(-3.0, 0.5, -3.0), direction).intersect_aabb(&unit_box(), 100.0).unwrap();
+        assert!((hit.distance - 2.0 * 2.0_f32.sqrt()).abs() < 1e-5);
+
+        assert!(Ray::new(Vec3::new(-3.0, 1.5, -3.0), direction).intersect_aabb(&unit_box(), 100.0).is_none());
+    }
+
+    #[test]
+    fn rays_hit_triangles_from_either_side() {
+        let triangle = [Vec3::new(-1.0, 0.0, -1.0), Vec3::new(1.0, 0.0, -1.0), Vec3::new(0.0, 0.0, 1.0)];
+
+        let from_above = Ray::new(Vec3::new(0.0, 2.0, 0.0), -Vec3::unit_y()).intersect_triangle(triangle, 10.0).unwrap();
+        assert_eq!(from_above, RayHit { distance: 2.0, normal: Vec3::unit_y() });
+        let from_below = Ray::new(Vec3::new(0.0, -3.0, 0.0), Vec3::unit_y()).intersect_triangle(triangle, 10.0).unwrap();
+        assert_eq!(from_below, RayHit { distance: 3.0, normal: -Vec3::unit_y() });
+
+        assert!(Ray::new(Vec3::new(2.0, 2.0, 0.0), -Vec3::unit_y()).intersect_triangle(triangle, 10.0).is_none(), "outside the edges");
+        assert!(Ray::new(Vec3::new(0.0, 2.0, 0.0), Vec3::unit_y()).intersect_triangle(triangle, 10.0).is_none(), "behind the origin");
+        assert!(Ray::new(Vec3::new(-5.0, 0.0, 0.0), Vec3::unit_x()).intersect_triangle(triangle, 10.0).is_none(), "parallel to the plane");
+    }
+}
@@ -1,14 +1,333 @@
-use ultraviolet::Mat4;
+use std::ops::Mul;
 
-#[derive(Default)]
+use ultraviolet::{Mat4, Vec3, Vec4};
+
+const SLERP_LINEAR_THRESHOLD: f32 = 0.9995;
+
+// Order in which the Euler rotation matrices are multiplied, XYZ means X * Y * Z (so Z is applied to vectors first)
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq, Hash)]
+pub enum EulerOrder {
+    #[default]
+    XYZ,
+    XZY,
+    YXZ,
+    YZX,
+    ZXY,
+    ZYX,
+}
+
+impl EulerOrder {
+    fn axes(&self) -> [usize; 3] {
+        match self {
+            EulerOrder::XYZ => [0, 1, 2],
+            EulerOrder::XZY => [0, 2, 1],
+            EulerOrder::YXZ => [1, 0, 2],
+            EulerOrder::YZX => [1, 2, 0],
+            EulerOrder::ZXY => [2, 0, 1],
+            EulerOrder::ZYX => [2, 1, 0],
+        }
+    }
+
+    fn is_even(&self) -> bool {
+        matches!(self, EulerOrder::XYZ | EulerOrder::YZX | EulerOrder::ZXY)
+    }
+}
+
+// Unit quaternion, composes like rotation matrices: (a * b) applies b first
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Rotation {
-    pub angle_x: f32,
-    pub angle_y: f32,
-    pub angle_z: f32,
+    x: f32,
+    y: f32,
+    z: f32,
+    w: f32,
+}
+
+impl Default for Rotation {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
 }
 
 impl Rotation {
+    pub const IDENTITY: Rotation = Rotation { x: 0.0, y: 0.0, z: 0.0, w: 1.0 };
+
+    // Normalizes the components, they do not have to describe a unit quaternion
+    pub fn from_components(x: f32, y: f32, z: f32, w: f32) -> Self {
+        Self { x, y, z, w }.normalized()
+    }
+
+    pub fn components(&self) -> [f32; 4] {
+        [self.x, self.y, self.z, self.w]
+    }
+
+    // Angles in radians, counter-clockwise when looking down the axis towards the origin
+    pub fn from_axis_angle(axis: Vec3, angle: f32) -> Self {
+        if axis.mag_sq() < f32::EPSILON {
+            return Self::IDENTITY;
+        }
+
+        let axis = axis.normalized() * (angle * 0.5).sin();
+
+        Self { x: axis.x, y: axis.y, z: axis.z, w: (angle * 0.5).cos() }
+    }
+
+    // The angle is in [0, PI], the axis is arbitrary for the identity
+    pub fn to_axis_angle(&self) -> (Vec3, f32) {
+        let rotation = if self.w < 0.0 { self.negated() } else { *self };
+        let sin_half = Vec3::new(rotation.x, rotation.y, rotation.z).mag();
+        if sin_half < f32::EPSILON {
+            return (Vec3::unit_x(), 0.0);
+        }
+
+        (Vec3::new(rotation.x, rotation.y, rotation.z) / sin_half, 2.0 * sin_half.atan2(rotation.w))
+    }
+
+    pub fn from_rotation_x(angle: f32) -> Self {
+        Self::from_axis_angle(Vec3::unit_x(), angle)
+    }
+
+    pub fn from_rotation_y(angle: f32) -> Self {
+        Self::from_axis_angle(Vec3::unit_y(), angle)
+    }
+
+    pub fn from_rotation_z(angle: f32) -> Self {
+        Self::from_axis_angle(Vec3::unit_z(), angle)
+    }
+
+    pub fn from_euler(angles: Vec3, order: EulerOrder) -> Self {
+        let [i, j, k] = order.axes();
+        let axis_rotation = |axis: usize| {
+            let mut unit = Vec3::default();
+            unit[axis] = 1.0;
+            Self::from_axis_angle(unit, angles[axis])
+        };
+
+        axis_rotation(i) * axis_rotation(j) * axis_rotation(k)
+    }
+
+    // Returns the angle around each axis in the x, y and z components. At gimbal lock the last axis gets no rotation
+    pub fn to_euler(&self, order: EulerOrder) -> Vec3 {
+        let matrix = self.rotation_matrix();
+        let element = |row: usize, column: usize| matrix.cols[column][row];
+        let [i, j, k] = order.axes();
+        let sign = if order.is_even() { 1.0 } else { -1.0 };
+
+        let mut angles = Vec3::default();
+        let sin_middle = (sign * element(i, k)).clamp(-1.0, 1.0);
+        angles[j] = sin_middle.asin();
+
+        if sin_middle.abs() > 0.9999 {
+            angles[i] = (sign * element(k, j)).atan2(element(j, j));
+        } else {
+            angles[i] = (-sign * element(j, k)).atan2(element(k, k));
+            angles[k] = (-sign * element(i, j)).atan2(element(i, i));
+        }
+
+        angles
+    }
+
+    // Rotation that turns -Z (the camera's forward) towards `direction` while keeping +Y as close to `up` as possible
+    pub fn look_at(direction: Vec3, up: Vec3) -> Self {
+        if direction.mag_sq() < f32::EPSILON {
+            return Self::IDENTITY;
+        }
+
+        let forward = direction.normalized();
+        let mut right = forward.cross(up);
+        if right.mag_sq() < 1e-6 {
+            // Looking straight along `up`, any perpendicular works
+            right = forward.cross(if forward.x.abs() < 0.9 { Vec3::unit_x() } else { Vec3::unit_z() }).cross(forward);
+        }
+        let right = right.normalized();
+        let up = right.cross(forward);
+
+        Self::from_basis(right, up, -forward)
+    }
+
+    // `matrix` must be a pure rotation, scale has to be removed beforehand
+    pub fn from_matrix(matrix: Mat4) -> Self {
+        Self::from_basis(matrix.cols[0].xyz(), matrix.cols[1].xyz(), matrix.cols[2].xyz())
+    }
+
+    // Shepperd's method, picks the largest component to divide by for stability
+    fn from_basis(x_axis: Vec3, y_axis: Vec3, z_axis: Vec3) -> Self {
+        let (m00, m11, m22) = (x_axis.x, y_axis.y, z_axis.z);
+        let trace = m00 + m11 + m22;
+
+        let rotation = if trace > 0.0 {
+            let s = (trace + 1.0).sqrt() * 2.0;
+            Self { x: (y_axis.z - z_axis.y) / s, y: (z_axis.x - x_axis.z) / s, z: (x_axis.y - y_axis.x) / s, w: 0.25 * s }
+        } else if m00 > m11 && m00 > m22 {
+            let s = (1.0 + m00 - m11 - m22).sqrt() * 2.0;
+            Self { x: 0.25 * s, y: (y_axis.x + x_axis.y) / s, z: (z_axis.x + x_axis.z) / s, w: (y_axis.z - z_axis.y) / s }
+        } else if m11 > m22 {
+            let s = (1.0 + m11 - m00 - m22).sqrt() * 2.0;
+            Self { x: (y_axis.x + x_axis.y) / s, y: 0.25 * s, z: (z_axis.y + y_axis.z) / s, w: (z_axis.x - x_axis.z) / s }
+        } else {
+            let s = (1.0 + m22 - m00 - m11).sqrt() * 2.0;
+            Self { x: (z_axis.x + x_axis.z) / s, y: (z_axis.y + y_axis.z) / s, z: 0.25 * s, w: (x_axis.y - y_axis.x) / s }
+        };
+
+        rotation.normalized()
+    }
+
     pub fn rotation_matrix(&self) -> Mat4 {
-        Mat4::from_rotation_x(self.angle_x) * Mat4::from_rotation_y(self.angle_y) * Mat4::from_rotation_z(self.angle_z)
+        let Self { x, y, z, w } = *self;
+        let (xx, yy, zz) = (x * x, y * y, z * z);
+        let (xy, xz, yz) = (x * y, x * z, y * z);
+        let (wx, wy, wz) = (w * x, w * y, w * z);
+
+        Mat4::new(
+            Vec4::new(1.0 - 2.0 * (yy + zz), 2.0 * (xy + wz), 2.0 * (xz - wy), 0.0),
+            Vec4::new(2.0 * (xy - wz), 1.0 - 2.0 * (xx + zz), 2.0 * (yz + wx), 0.0),
+            Vec4::new(2.0 * (xz + wy), 2.0 * (yz - wx), 1.0 - 2.0 * (xx + yy), 0.0),
+            Vec4::unit_w(),
+        )
+    }
+
+    pub fn rotate_vec(&self, vector: Vec3) -> Vec3 {
+        let axis = Vec3::new(self.x, self.y, self.z);
+        let t = axis.cross(vector) * 2.0;
+
+        vector + t * self.w + axis.cross(t)
+    }
+
+    pub fn inverse(&self) -> Self {
+        Self { x: -self.x, y: -self.y, z: -self.z, w: self.w }
+    }
+
+    pub fn dot(&self, other: &Rotation) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z + self.w * other.w
+    }
+
+    // Angle of the rotation that takes this one to `other`, in [0, PI]
+    pub fn angle_to(&self, other: &Rotation) -> f32 {
+        // atan2 stays precise for tiny angles, where acos of the dot product does not
+        let difference = self.inverse() * *other;
+
+        2.0 * Vec3::new(difference.x, difference.y, difference.z).mag().atan2(difference.w.abs())
+    }
+
+    pub fn normalized(&self) -> Self {
+        let length = self.dot(self).sqrt();
+        if length < f32::EPSILON {
+            return Self::IDENTITY;
+        }
+
+        Self { x: self.x / length, y: self.y / length, z: self.z / length, w: self.w / length }
+    }
+
+    // Constant angular speed along the shorter arc
+    pub fn slerp(&self, other: &Rotation, t: f32) -> Self {
+        let mut cos_angle = self.dot(other);
+        let mut other = *other;
+        if cos_angle < 0.0 {
+            other = other.negated();
+            cos_angle = -cos_angle;
+        }
+
+        if cos_angle > SLERP_LINEAR_THRESHOLD {
+            return self.scaled_add(1.0 - t, &other, t).normalized();
+        }
+
+        let angle = cos_angle.acos();
+        let sin_angle = angle.sin();
+
+        self.scaled_add(((1.0 - t) * angle).sin() / sin_angle, &other, (t * angle).sin() / sin_angle)
+    }
+
+    // Advances by a world space angular velocity (radians per second) over dt
+    pub fn integrated(&self, angular_velocity: Vec3, dt: f32) -> Self {
+        let spin = Self { x: angular_velocity.x, y: angular_velocity.y, z: angular_velocity.z, w: 0.0 } * *self;
+
+        self.scaled_add(1.0, &spin, 0.5 * dt).normalized()
+    }
+
+    fn negated(&self) -> Self {
+        Self { x: -self.x, y: -self.y, z: -self.z, w: -self.w }
+    }
+
+    fn scaled_add(&self, a: f32, other: &Rotation, b: f32) -> Self {
+        Self { x: self.x * a + other.x * b, y: self.y * a + other.y * b, z: self.z * a + other.z * b, w: self.w * a + other.w * b }
+    }
+}
+
+impl Mul for Rotation {
+    type Output = Rotation;
+
+    fn mul(self, rhs: Rotation) -> Rotation {
+        Rotation {
+            x: self.w * rhs.x + self.x * rhs.w + self.y * rhs.z - self.z * rhs.y,
+            y: self.w * rhs.y - self.x * rhs.z + self.y * rhs.w + self.z * rhs.x,
+            z: self.w * rhs.z + self.x * rhs.y - self.y * rhs.x + self.z * rhs.w,
+            w: self.w * rhs.w - self.x * rhs.x - self.y * rhs.y - self.z * rhs.z,
+        }
+    }
+}
+
+impl Mul<Vec3> for Rotation {
+    type Output = Vec3;
+
+    fn mul(self, rhs: Vec3) -> Vec3 {
+        self.rotate_vec(rhs)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORDERS: [EulerOrder; 6] = [EulerOrder::XYZ, EulerOrder::XZY, EulerOrder::YXZ, EulerOrder::YZX, EulerOrder::ZXY, EulerOrder::ZYX];
+
+    fn assert_same_rotation(expected: Rotation, actual: Rotation) {
+        assert!(expected.angle_to(&actual) < 1e-3, "{expected:?} != {actual:?}");
+    }
+
+    #[test]
+    fn matches_matrix_rotations() {
+        let angles = Vec3::new(0.3, -0.7, 1.2);
+        let expected = Mat4::from_rotation_x(angles.x) * Mat4::from_rotation_y(angles.y) * Mat4::from_rotation_z(angles.z);
+        let actual = Rotation::from_euler(angles, EulerOrder::XYZ).rotation_matrix();
+        assert!(expected.cols.iter().zip(actual.cols.iter()).all(|(a, b)| (*a - *b).mag() < 1e-5));
+
+        let rotation = Rotation::from_euler(angles, EulerOrder::ZYX);
+        let vector = Vec3::new(1.0, -2.0, 0.5);
+        assert!((rotation * vector - rotation.rotation_matrix().transform_vec3(vector)).mag() < 1e-5);
+        assert_same_rotation(rotation, Rotation::from_matrix(rotation.rotation_matrix()));
+    }
+
+    #[test]
+    fn euler_round_trips_in_every_order() {
+        for order in ORDERS {
+            for angles in [Vec3::new(0.3, -0.7, 1.2), Vec3::new(-2.0, 0.1, -0.4), Vec3::new(0.0, 1.0, 3.0), Vec3::new(0.4, std::f32::consts::FRAC_PI_2, 0.2)] {
+                let rotation = Rotation::from_euler(angles, order);
+                assert_same_rotation(rotation, Rotation::from_euler(rotation.to_euler(order), order));
+            }
+        }
+    }
+
+    #[test]
+    fn axis_angle_and_look_at() {
+        let (axis, angle) = Rotation::from_axis_angle(Vec3::new(0.0, -2.0, 0.0), 0.5).to_axis_angle();
+        assert!((axis - Vec3::new(0.0, -1.0, 0.0)).mag() < 1e-5 && (angle - 0.5).abs() < 1e-5);
+
+        for direction in [Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.3, -0.5, 0.8), Vec3::new(0.0, 1.0, 0.0)] {
+            let rotation = Rotation::look_at(direction, Vec3::unit_y());
+            assert!((rotation * Vec3::new(0.0, 0.0, -1.0) - direction.normalized()).mag() < 1e-5);
+        }
+        assert!((Rotation::look_at(Vec3::new(1.0, 0.0, 0.0), Vec3::unit_y()) * Vec3::unit_y() - Vec3::unit_y()).mag() < 1e-5);
+    }
+
+    #[test]
+    fn slerp_takes_the_short_arc_at_constant_speed() {
+        let start = Rotation::from_rotation_y(0.0);
+        let end = Rotation::from_rotation_y(3.0).negated();
+
+        for t in [0.0, 0.25, 0.5, 1.0] {
+            assert_same_rotation(Rotation::from_rotation_y(3.0 * t), start.slerp(&end, t));
+        }
+
+        let spun = (0..100).fold(Rotation::IDENTITY, |rotation, _| rotation.integrated(Vec3::new(0.0, 0.0, 2.0), 0.01));
+        assert_same_rotation(Rotation::from_rotation_z(2.0), spun);
+    }
+}
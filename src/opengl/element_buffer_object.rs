@@ -1,9 +1,11 @@
-use ogl33::{GL_ELEMENT_ARRAY_BUFFER, GL_STATIC_DRAW, glBindBuffer, glBufferData, glGenBuffers, GLuint};
+use ogl33::{GL_ELEMENT_ARRAY_BUFFER, GL_STATIC_DRAW, glBindBuffer, glBufferData, glDeleteBuffers, glGenBuffers, GLuint};
+
+use crate::error::{EngineError, EngineResult};
 
 pub struct ElementBufferObject(pub GLuint);
 
 impl ElementBufferObject {
-    pub fn new() -> Option<Self> {
+    pub fn new() -> EngineResult<Self> {
         let mut ebo = 0;
 
         unsafe {
@@ -11,9 +13,9 @@ impl ElementBufferObject {
         }
 
         if ebo == 0 {
-            None
+            Err(EngineError::GlAllocation("element buffer object"))
         } else {
-            Some(Self(ebo))
+            Ok(Self(ebo))
         }
     }
 
@@ -34,4 +36,12 @@ impl ElementBufferObject {
             glBufferData(GL_ELEMENT_ARRAY_BUFFER, size.try_into().unwrap(), data_ptr.cast(), GL_STATIC_DRAW);
         }
     }
-}
\ No newline at end of file
+}
+
+impl Drop for ElementBufferObject {
+    fn drop(&mut self) {
+        unsafe {
+            glDeleteBuffers(1, &self.0);
+        }
+    }
+}
@@ -0,0 +1,252 @@
+use ogl33::{GL_COLOR_ATTACHMENT0, GL_DEPTH_ATTACHMENT, GL_DEPTH_STENCIL_ATTACHMENT, GL_DRAW_FRAMEBUFFER, GL_DRAW_FRAMEBUFFER_BINDING, GL_FRAMEBUFFER, GL_FRAMEBUFFER_COMPLETE, GL_FRAMEBUFFER_INCOMPLETE_ATTACHMENT, GL_FRAMEBUFFER_INCOMPLETE_DRAW_BUFFER, GL_FRAMEBUFFER_INCOMPLETE_LAYER_TARGETS, GL_FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT, GL_FRAMEBUFFER_INCOMPLETE_MULTISAMPLE, GL_FRAMEBUFFER_INCOMPLETE_READ_BUFFER, GL_FRAMEBUFFER_UNDEFINED, GL_FRAMEBUFFER_UNSUPPORTED, GL_NONE, GL_READ_FRAMEBUFFER, GL_RENDERBUFFER, GL_STENCIL_ATTACHMENT, glBindFramebuffer, glBindRenderbuffer, glBlitFramebuffer, glCheckFramebufferStatus, glDeleteFramebuffers, glDeleteRenderbuffers, glDrawBuffer, glDrawBuffers, GLenum, glFramebufferRenderbuffer, glFramebufferTexture2D, glFramebufferTextureLayer, glGenFramebuffers, glGenRenderbuffers, glGetIntegerv, GLint, glReadBuffer, glRenderbufferStorageMultisample, GLsizei, GLuint};
+
+use crate::error::{EngineError, EngineResult};
+use crate::opengl::ClearBitMask;
+use crate::opengl::ClearBitFlags::ColorBuffer;
+use crate::opengl::texture::{MagFilterParam, Texture, TextureFormat, TextureType};
+
+// GL 3.3 guarantees at least this many colour attachments
+pub const MAX_COLOR_ATTACHMENTS: u32 = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Attachment {
+    Color(u32),
+    Depth,
+    Stencil,
+    DepthStencil,
+}
+
+impl Attachment {
+    // Where a depth or stencil format goes, colour formats go to Color(index)
+    pub fn for_format(format: TextureFormat, color_index: u32) -> Self {
+        if format.has_stencil() {
+            Attachment::DepthStencil
+        } else if format.has_depth() {
+            Attachment::Depth
+        } else {
+            Attachment::Color(color_index)
+        }
+    }
+
+    pub fn gl_enum(self) -> GLenum {
+        match self {
+            Attachment::Color(index) => GL_COLOR_ATTACHMENT0 + index,
+            Attachment::Depth => GL_DEPTH_ATTACHMENT,
+            Attachment::Stencil => GL_STENCIL_ATTACHMENT,
+            Attachment::DepthStencil => GL_DEPTH_STENCIL_ATTACHMENT,
+        }
+    }
+}
+
+fn status_name(status: GLenum) -> &'static str {
+    match status {
+        GL_FRAMEBUFFER_UNDEFINED => "undefined",
+        GL_FRAMEBUFFER_INCOMPLETE_ATTACHMENT => "incomplete attachment",
+        GL_FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT => "missing attachment",
+        GL_FRAMEBUFFER_INCOMPLETE_DRAW_BUFFER => "incomplete draw buffer",
+        GL_FRAMEBUFFER_INCOMPLETE_READ_BUFFER => "incomplete read buffer",
+        GL_FRAMEBUFFER_UNSUPPORTED => "unsupported format combination",
+        GL_FRAMEBUFFER_INCOMPLETE_MULTISAMPLE => "mismatched sample counts",
+        GL_FRAMEBUFFER_INCOMPLETE_LAYER_TARGETS => "mismatched layer targets",
+        _ => "unknown status",
+    }
+}
+
+// Render-only storage, cheaper than a texture when the result is never sampled, e.g. the depth of a colour pass
+pub struct Renderbuffer(pub GLuint);
+
+impl Renderbuffer {
+    // `samples` of 0 allocates a regular single sample buffer
+    pub fn new(width: u32, height: u32, samples: u32, format: TextureFormat) -> EngineResult<Self> {
+        let mut rbo = 0;
+
+        unsafe {
+            glGenRenderbuffers(1, &mut rbo);
+        }
+
+        if rbo == 0 {
+            return Err(EngineError::GlAllocation("renderbuffer"));
+        }
+
+        let renderbuffer = Self(rbo);
+        renderbuffer.bind();
+        let (internal_format, _, _) = format.gl_params();
+        unsafe {
+            glRenderbufferStorageMultisample(GL_RENDERBUFFER, samples as GLsizei, internal_format, width as GLsizei, height as GLsizei);
+        }
+
+        Ok(renderbuffer)
+    }
+
+    pub fn bind(&self) {
+        unsafe {
+            glBindRenderbuffer(GL_RENDERBUFFER, self.0);
+        }
+    }
+}
+
+impl Drop for Renderbuffer {
+    fn drop(&mut self) {
+        unsafe {
+            glDeleteRenderbuffers(1, &self.0);
+        }
+    }
+}
+
+// Attachment and draw buffer calls work on the currently bound framebuffer
+pub struct Framebuffer(pub GLuint);
+
+impl Framebuffer {
+    pub fn new() -> EngineResult<Self> {
+        let mut fbo = 0;
+
+        unsafe {
+            glGenFramebuffers(1, &mut fbo);
+        }
+
+        if fbo == 0 {
+            Err(EngineError::GlAllocation("framebuffer"))
+        } else {
+            Ok(Self(fbo))
+        }
+    }
+
+    pub fn bind(&self) {
+        unsafe {
+            glBindFramebuffer(GL_FRAMEBUFFER, self.0);
+        }
+    }
+
+    // Back to the window
+    pub fn bind_default() {
+        unsafe {
+            glBindFramebuffer(GL_FRAMEBUFFER, 0);
+        }
+    }
+
+    // Id of the framebuffer being drawn into, 0 for the window. For passes that have to put it back afterwards
+    pub fn current_binding() -> GLuint {
+        let mut fbo = 0;
+
+        unsafe {
+            glGetIntegerv(GL_DRAW_FRAMEBUFFER_BINDING, &mut fbo);
+        }
+
+        fbo as GLuint
+    }
+
+    pub fn restore_binding(fbo: GLuint) {
+        unsafe {
+            glBindFramebuffer(GL_FRAMEBUFFER, fbo);
+        }
+    }
+
+    pub fn attach_texture(attachment: Attachment, texture: &Texture, texture_type: TextureType) {
+        unsafe {
+            glFramebufferTexture2D(GL_FRAMEBUFFER, attachment.gl_enum(), texture_type as GLenum, texture.id(), 0);
+        }
+    }
+
+    // One layer of a Texture2dArray
+    pub fn attach_texture_layer(attachment: Attachment, texture: &Texture, layer: u32) {
+        unsafe {
+            glFramebufferTextureLayer(GL_FRAMEBUFFER, attachment.gl_enum(), texture.id(), 0, layer as GLint);
+        }
+    }
+
+    // Faces are in GL order: +X, -X, +Y, -Y, +Z, -Z
+    pub fn attach_cube_face(attachment: Attachment, texture: &Texture, face: u32, mip_level: u32) {
+        unsafe {
+            glFramebufferTexture2D(GL_FRAMEBUFFER, attachment.gl_enum(), TextureType::TextureCubeMapPositiveX as GLenum + face, texture.id(), mip_level as GLint);
+        }
+    }
+
+    pub fn attach_renderbuffer(attachment: Attachment, renderbuffer: &Renderbuffer) {
+        unsafe {
+            glFramebufferRenderbuffer(GL_FRAMEBUFFER, attachment.gl_enum(), GL_RENDERBUFFER, renderbuffer.0);
+        }
+    }
+
+    // Fragment shader output `n` goes to Color(n), for multiple render targets
+    pub fn set_draw_buffers(count: u32) {
+        let buffers: Vec<GLenum> = (0..count).map(|index| Attachment::Color(index).gl_enum()).collect();
+
+        unsafe {
+            glDrawBuffers(buffers.len() as GLsizei, buffers.as_ptr());
+        }
+    }
+
+    // Depth-only rendering, e.g. shadow maps
+    pub fn disable_color_buffers() {
+        unsafe {
+            glDrawBuffer(GL_NONE);
+            glReadBuffer(GL_NONE);
+        }
+    }
+
+    pub fn check_complete() -> EngineResult<()> {
+        let status = unsafe { glCheckFramebufferStatus(GL_FRAMEBUFFER) };
+
+        if status == GL_FRAMEBUFFER_COMPLETE {
+            Ok(())
+        } else {
+            Err(EngineError::FramebufferIncomplete(status_name(status)))
+        }
+    }
+
+    // Copies between framebuffers, None is the window, otherwise a framebuffer and the colour attachment to read or write.
+    // The attachment is only used for colour blits, depth and stencil blits leave it as None.
+    // Rectangles are x, y, width, height like viewports, resolving a multisampled source needs equal sizes.
+    // Leaves GL_FRAMEBUFFER bound to the window
+    pub fn blit(source: Option<(&Framebuffer, Option<Attachment>)>, source_rect: [i32; 4], destination: Option<(&Framebuffer, Option<Attachment>)>, destination_rect: [i32; 4], mask: ClearBitMask, filter: MagFilterParam) {
+        let [sx, sy, sw, sh] = source_rect;
+        let [dx, dy, dw, dh] = destination_rect;
+
+        unsafe {
+            glBindFramebuffer(GL_READ_FRAMEBUFFER, source.map_or(0, |(framebuffer, _)| framebuffer.0));
+            glBindFramebuffer(GL_DRAW_FRAMEBUFFER, destination.map_or(0, |(framebuffer, _)| framebuffer.0));
+            if let Some(attachment) = blit_color_attachment(source.and_then(|(_, attachment)| attachment), mask) {
+                glReadBuffer(attachment.gl_enum());
+            }
+            if let Some(attachment) = blit_color_attachment(destination.and_then(|(_, attachment)| attachment), mask) {
+                glDrawBuffer(attachment.gl_enum());
+            }
+            glBlitFramebuffer(sx, sy, sx + sw, sy + sh, dx, dy, dx + dw, dy + dh, *mask, filter as GLenum);
+            glBindFramebuffer(GL_FRAMEBUFFER, 0);
+        }
+    }
+}
+
+// Read and draw buffers only select colour attachments, depth and stencil blits don't touch them
+fn blit_color_attachment(attachment: Option<Attachment>, mask: ClearBitMask) -> Option<Attachment> {
+    attachment.filter(|_| mask.contains(ColorBuffer))
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        unsafe {
+            glDeleteFramebuffers(1, &self.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opengl::ClearBitFlags::{DepthBuffer, StencilBuffer};
+
+    #[test]
+    fn formats_pick_their_attachment() {
+        assert_eq!(Attachment::for_format(TextureFormat::Rgba16F, 2), Attachment::Color(2));
+        assert_eq!(Attachment::for_format(TextureFormat::Depth32F, 2), Attachment::Depth);
+        assert_eq!(Attachment::for_format(TextureFormat::Depth24Stencil8, 0), Attachment::DepthStencil);
+        assert_eq!(Attachment::Color(3).gl_enum(), GL_COLOR_ATTACHMENT0 + 3);
+    }
+
+    #[test]
+    fn only_colour_blits_select_an_attachment() {
+        assert_eq!(blit_color_attachment(Some(Attachment::Color(1)), ColorBuffer.into()), Some(Attachment::Color(1)));
+        assert_eq!(blit_color_attachment(Some(Attachment::Color(0)), DepthBuffer | StencilBuffer), None);
+        assert_eq!(blit_color_attachment(None, ColorBuffer | DepthBuffer), None);
+    }
+}
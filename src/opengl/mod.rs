@@ -1,11 +1,14 @@
 use beryllium::video::GlWindow;
 use bitmask::bitmask;
-use ogl33::{GL_BLEND, GL_COLOR_BUFFER_BIT, GL_DEPTH_BUFFER_BIT, GL_DEPTH_TEST, GL_LEQUAL, GL_LESS, GL_LINES, GL_ONE_MINUS_SRC_ALPHA, GL_POINTS, GL_SRC_ALPHA, GL_STENCIL_BUFFER_BIT, GL_TRIANGLES, GL_UNPACK_ALIGNMENT, GL_UNSIGNED_BYTE, GL_UNSIGNED_INT, GL_UNSIGNED_SHORT, glBlendFunc, glClear, glClearColor, glDepthFunc, glDrawArrays, glDrawElements, glEnable, GLenum, GLint, glPixelStorei, GLsizei, load_gl_with};
+use ogl33::{GL_BACK, GL_BLEND, GL_COLOR_BUFFER_BIT, GL_CULL_FACE, GL_DEPTH_BUFFER_BIT, GL_DEPTH_TEST, GL_FALSE, GL_FRONT, GL_LEQUAL, GL_LESS, GL_LINES, GL_ONE, GL_ONE_MINUS_SRC_ALPHA, GL_POINTS, GL_SRC_ALPHA, GL_TEXTURE_CUBE_MAP_SEAMLESS, GL_STENCIL_BUFFER_BIT, GL_TRIANGLES, GL_TRUE, GL_UNPACK_ALIGNMENT, GL_UNSIGNED_BYTE, GL_UNSIGNED_INT, GL_UNSIGNED_SHORT, GL_VIEWPORT, glBlendFunc, glClear, glClearColor, glCullFace, glDepthFunc, glDepthMask, glDisable, glDrawArrays, glDrawElements, glEnable, GLenum, glGetIntegerv, GLint, glPixelStorei, GLsizei, glViewport, load_gl_with};
 
 pub mod vertex_array_object;
 pub mod vertex_buffer_object;
 pub mod texture;
 pub mod element_buffer_object;
+pub mod framebuffer;
+pub mod render_target;
+pub mod uniform_buffer_object;
 
 bitmask! {
     pub mask ClearBitMask: u32 where flags ClearBitFlags {
@@ -26,6 +29,8 @@ pub enum Primitive {
 pub enum Capability {
     DepthTest = GL_DEPTH_TEST,
     Blending = GL_BLEND,
+    CubeMapSeamless = GL_TEXTURE_CUBE_MAP_SEAMLESS, // Filtering blends across face edges, needed for blurry mip levels
+    FaceCulling = GL_CULL_FACE,
     // TODO: Add others
 }
 
@@ -38,6 +43,7 @@ pub enum ElementType {
 
 #[repr(u32)]
 pub enum BlendFactor {
+    One = GL_ONE,
     SrcAlpha = GL_SRC_ALPHA,
     OneMinusSrcAlpha = GL_ONE_MINUS_SRC_ALPHA,
 }
@@ -56,6 +62,12 @@ pub enum DepthFunc {
     Less = GL_LESS,
 }
 
+#[repr(u32)]
+pub enum Face {
+    Front = GL_FRONT,
+    Back = GL_BACK,
+}
+
 pub fn load_gl(gl_window: &GlWindow) {
     unsafe {
         load_gl_with(|f_name| gl_window.get_proc_address(f_name.cast()));
@@ -68,6 +80,12 @@ pub fn enable(capability: Capability) {
     }
 }
 
+pub fn disable(capability: Capability) {
+    unsafe {
+        glDisable(capability as GLenum);
+    }
+}
+
 pub fn clear_color(red: f32, green: f32, blue: f32, alpha: f32) {
     unsafe {
         glClearColor(red, green, blue, alpha);
@@ -98,6 +116,19 @@ pub fn blend_func(s_factor: BlendFactor, d_factor: BlendFactor) {
     }
 }
 
+pub fn cull_face(face: Face) {
+    unsafe {
+        glCullFace(face as GLenum);
+    }
+}
+
+// Whether drawing writes to the depth buffer, the depth test still runs when it doesn't
+pub fn depth_mask(enabled: bool) {
+    unsafe {
+        glDepthMask(if enabled { GL_TRUE } else { GL_FALSE });
+    }
+}
+
 pub fn pixel_store_unpack_alignment(alignment: UnpackAlignment) {
     unsafe {
         glPixelStorei(GL_UNPACK_ALIGNMENT, alignment as GLint);
@@ -108,4 +139,21 @@ pub fn gl_depth_func(depth_func: DepthFunc) {
     unsafe {
         glDepthFunc(depth_func as GLenum);
     }
-}
\ No newline at end of file
+}
+
+pub fn viewport(x: i32, y: i32, width: i32, height: i32) {
+    unsafe {
+        glViewport(x, y, width, height);
+    }
+}
+
+// x, y, width, height
+pub fn get_viewport() -> [i32; 4] {
+    let mut viewport = [0; 4];
+
+    unsafe {
+        glGetIntegerv(GL_VIEWPORT, viewport.as_mut_ptr());
+    }
+
+    viewport
+}
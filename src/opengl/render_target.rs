@@ -0,0 +1,244 @@
+use crate::error::{EngineError, EngineResult};
+use crate::opengl;
+use crate::opengl::ClearBitFlags::{ColorBuffer, DepthBuffer};
+use crate::opengl::framebuffer::{Attachment, Framebuffer, MAX_COLOR_ATTACHMENTS, Renderbuffer};
+use crate::opengl::texture::{MagFilterParam, MinFilterParam, Texture, TextureFormat, TextureType, WrapCoordinate, WrapParam};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RenderTargetConfig {
+    pub width: u32,
+    pub height: u32,
+    pub color_formats: Vec<TextureFormat>, // Fragment shader output n is written to the n-th
+    pub depth_format: Option<TextureFormat>, // Depth24Stencil8 adds a stencil buffer
+    pub sample_depth: bool, // Keeps depth in a texture that can be read after the pass, otherwise a renderbuffer
+    pub samples: u32, // Above 1 renders multisampled, resolve() then copies into sampleable textures
+}
+
+impl RenderTargetConfig {
+    // One RGBA8 colour texture with a depth-stencil renderbuffer
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            color_formats: vec![TextureFormat::Rgba8],
+            depth_format: Some(TextureFormat::Depth24Stencil8),
+            sample_depth: false,
+            samples: 1,
+        }
+    }
+
+    pub fn validate(&self) -> EngineResult<()> {
+        let invalid = |reason: String| Err(EngineError::InvalidRenderTarget(reason));
+
+        if self.width == 0 || self.height == 0 {
+            return invalid(format!("size is {}x{}", self.width, self.height));
+        }
+        if self.color_formats.is_empty() && self.depth_format.is_none() {
+            return invalid("no attachments".to_owned());
+        }
+        if self.color_formats.len() > MAX_COLOR_ATTACHMENTS as usize {
+            return invalid(format!("{} colour attachments, at most {MAX_COLOR_ATTACHMENTS} are supported", self.color_formats.len()));
+        }
+        if let Some(format) = self.color_formats.iter().find(|format| format.has_depth()) {
+            return invalid(format!("{format:?} is not a colour format"));
+        }
+        if let Some(format) = self.depth_format.filter(|format| !format.has_depth()) {
+            return invalid(format!("{format:?} is not a depth format"));
+        }
+
+        Ok(())
+    }
+
+    fn is_multisampled(&self) -> bool {
+        self.samples > 1
+    }
+}
+
+// A framebuffer together with the textures and renderbuffers it draws into
+pub struct RenderTarget {
+    config: RenderTargetConfig,
+    framebuffer: Framebuffer,
+    color_textures: Vec<Texture>,
+    depth_texture: Option<Texture>,
+    _renderbuffers: Vec<Renderbuffer>, // Everything that can't be sampled, kept alive while attached
+    resolved: Option<Box<RenderTarget>>, // Single sample copy of a multisampled target
+}
+
+impl RenderTarget {
+    pub fn new(config: RenderTargetConfig) -> EngineResult<Self> {
+        config.validate()?;
+
+        let framebuffer = Framebuffer::new()?;
+        framebuffer.bind();
+
+        let mut color_textures = Vec::new();
+        let mut depth_texture = None;
+        let mut renderbuffers = Vec::new();
+
+        // Multisampled attachments are renderbuffers, the resolved copy holds the textures
+        for (index, format) in config.color_formats.iter().enumerate() {
+            let attachment = Attachment::Color(index as u32);
+            if config.is_multisampled() {
+                let renderbuffer = Renderbuffer::new(config.width, config.height, config.samples, *format)?;
+                Framebuffer::attach_renderbuffer(attachment, &renderbuffer);
+                renderbuffers.push(renderbuffer);
+            } else {
+                let texture = new_attachment_texture(config.width, config.height, *format)?;
+                Framebuffer::attach_texture(attachment, &texture, TextureType::Texture2d);
+                color_textures.push(texture);
+            }
+        }
+
+        if let Some(format) = config.depth_format {
+            let attachment = Attachment::for_format(format, 0);
+            if config.sample_depth && !config.is_multisampled() {
+                let texture = new_attachment_texture(config.width, config.height, format)?;
+                Framebuffer::attach_texture(attachment, &texture, TextureType::Texture2d);
+                depth_texture = Some(texture);
+            } else {
+                let renderbuffer = Renderbuffer::new(config.width, config.height, config.samples.max(1), format)?;
+                Framebuffer::attach_renderbuffer(attachment, &renderbuffer);
+                renderbuffers.push(renderbuffer);
+            }
+        }
+
+        if config.color_formats.is_empty() {
+            Framebuffer::disable_color_buffers();
+        } else {
+            Framebuffer::set_draw_buffers(config.color_formats.len() as u32);
+        }
+
+        let status = Framebuffer::check_complete();
+        Framebuffer::bind_default();
+        status?;
+
+        let resolved = if config.is_multisampled() {
+            let resolved_config = RenderTargetConfig {
+                depth_format: config.depth_format.filter(|_| config.sample_depth),
+                samples: 1,
+                ..config.clone()
+            };
+            Some(Box::new(RenderTarget::new(resolved_config)?))
+        } else {
+            None
+        };
+
+        Ok(Self { config, framebuffer, color_textures, depth_texture, _renderbuffers: renderbuffers, resolved })
+    }
+
+    pub fn config(&self) -> &RenderTargetConfig {
+        &self.config
+    }
+
+    pub fn width(&self) -> u32 {
+        self.config.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.config.height
+    }
+
+    pub fn framebuffer(&self) -> &Framebuffer {
+        &self.framebuffer
+    }
+
+    // Also covers the whole target with the viewport
+    pub fn bind(&self) {
+        self.framebuffer.bind();
+        opengl::viewport(0, 0, self.config.width as i32, self.config.height as i32);
+    }
+
+    // Sampleable result of the n-th colour output, after resolve() for multisampled targets
+    pub fn color_texture(&self, index: usize) -> Option<&Texture> {
+        match &self.resolved {
+            Some(resolved) => resolved.color_texture(index),
+            None => self.color_textures.get(index),
+        }
+    }
+
+    pub fn depth_texture(&self) -> Option<&Texture> {
+        match &self.resolved {
+            Some(resolved) => resolved.depth_texture(),
+            None => self.depth_texture.as_ref(),
+        }
+    }
+
+    // Contents are lost, e.g. after the window was resized
+    pub fn resize(&mut self, width: u32, height: u32) -> EngineResult<()> {
+        if (width, height) != (self.config.width, self.config.height) {
+            *self = RenderTarget::new(RenderTargetConfig { width, height, ..self.config.clone() })?;
+        }
+
+        Ok(())
+    }
+
+    // Averages the samples of a multisampled target into its textures, does nothing for single sample targets
+    pub fn resolve(&self) {
+        let Some(resolved) = &self.resolved else {
+            return;
+        };
+
+        let rect = self.rect();
+        for index in 0..self.config.color_formats.len() as u32 {
+            let attachment = Attachment::Color(index);
+            Framebuffer::blit(Some((&self.framebuffer, Some(attachment))), rect, Some((&resolved.framebuffer, Some(attachment))), rect, ColorBuffer.into(), MagFilterParam::Nearest);
+        }
+        if resolved.config.depth_format.is_some() {
+            Framebuffer::blit(Some((&self.framebuffer, None)), rect, Some((&resolved.framebuffer, None)), rect, DepthBuffer.into(), MagFilterParam::Nearest);
+        }
+
+        // Blits change the draw buffers, the resolved target may be drawn into afterwards
+        resolved.framebuffer.bind();
+        Framebuffer::set_draw_buffers(self.config.color_formats.len() as u32);
+        Framebuffer::bind_default();
+    }
+
+    // Resolves first, then scales the first colour output into `viewport` of the window
+    pub fn blit_to_screen(&self, viewport: [i32; 4], filter: MagFilterParam) {
+        self.resolve();
+
+        let source = self.resolved.as_deref().unwrap_or(self);
+        Framebuffer::blit(Some((&source.framebuffer, Some(Attachment::Color(0)))), source.rect(), None, viewport, ColorBuffer.into(), filter);
+    }
+
+    fn rect(&self) -> [i32; 4] {
+        [0, 0, self.config.width as i32, self.config.height as i32]
+    }
+}
+
+// Sampled without mipmaps and clamped so post-processing kernels don't wrap around
+fn new_attachment_texture(width: u32, height: u32, format: TextureFormat) -> EngineResult<Texture> {
+    let texture = Texture::new(TextureType::Texture2d)?;
+    texture.load_empty(width, height, format);
+    texture.set_min_filter(MinFilterParam::Linear);
+    texture.set_mag_filter(MagFilterParam::Linear);
+    texture.set_wrap(WrapCoordinate::S, WrapParam::ClampToEdge);
+    texture.set_wrap(WrapCoordinate::T, WrapParam::ClampToEdge);
+
+    Ok(texture)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn configs_are_validated() {
+        let config = RenderTargetConfig::new(1280, 720);
+        assert!(config.validate().is_ok());
+
+        let gbuffer = RenderTargetConfig { color_formats: vec![TextureFormat::Rgba16F, TextureFormat::Rgba8, TextureFormat::Rg16F], samples: 4, ..config.clone() };
+        assert!(gbuffer.validate().is_ok());
+
+        let invalid = [
+            RenderTargetConfig { width: 0, ..config.clone() },
+            RenderTargetConfig { color_formats: Vec::new(), depth_format: None, ..config.clone() },
+            RenderTargetConfig { color_formats: vec![TextureFormat::Rgba8; 9], ..config.clone() },
+            RenderTargetConfig { color_formats: vec![TextureFormat::Depth32F], ..config.clone() },
+            RenderTargetConfig { depth_format: Some(TextureFormat::Rgba16F), ..config.clone() },
+        ];
+        for config in invalid {
+            assert!(matches!(config.validate(), Err(EngineError::InvalidRenderTarget(_))), "{config:?}");
+        }
+    }
+}
@@ -1,7 +1,9 @@
 use std::ptr::null;
 
 use image::{ColorType, DynamicImage};
-use ogl33::{GL_CLAMP_TO_BORDER, GL_CLAMP_TO_EDGE, GL_LINEAR, GL_LINEAR_MIPMAP_LINEAR, GL_LINEAR_MIPMAP_NEAREST, GL_MIRRORED_REPEAT, GL_NEAREST, GL_NEAREST_MIPMAP_LINEAR, GL_NEAREST_MIPMAP_NEAREST, GL_R16, GL_R8, GL_RED, GL_REPEAT, GL_RG, GL_RG16, GL_RG8, GL_RGB, GL_RGB16, GL_RGB8, GL_RGBA, GL_RGBA16, GL_RGBA8, GL_TEXTURE0, GL_TEXTURE_2D, GL_TEXTURE_CUBE_MAP, GL_TEXTURE_CUBE_MAP_POSITIVE_X, GL_TEXTURE_MAG_FILTER, GL_TEXTURE_MIN_FILTER, GL_TEXTURE_WRAP_R, GL_TEXTURE_WRAP_S, GL_TEXTURE_WRAP_T, GL_UNSIGNED_BYTE, GL_UNSIGNED_SHORT, glActiveTexture, glBindTexture, GLenum, glGenerateMipmap, glGenTextures, GLint, glTexImage2D, glTexParameteri, glTexSubImage2D, GLuint};
+use ogl33::{GL_CLAMP_TO_BORDER, GL_CLAMP_TO_EDGE, GL_COMPARE_REF_TO_TEXTURE, GL_DEPTH24_STENCIL8, GL_DEPTH_COMPONENT, GL_DEPTH_COMPONENT24, GL_DEPTH_COMPONENT32F, GL_DEPTH_STENCIL, GL_FLOAT, GL_LEQUAL, GL_LINEAR, GL_LINEAR_MIPMAP_LINEAR, GL_LINEAR_MIPMAP_NEAREST, GL_MIRRORED_REPEAT, GL_NEAREST, GL_NEAREST_MIPMAP_LINEAR, GL_NEAREST_MIPMAP_NEAREST, GL_R11F_G11F_B10F, GL_R16, GL_R16F, GL_R32F, GL_R8, GL_RED, GL_REPEAT, GL_RG, GL_RG16, GL_RG16F, GL_RG8, GL_RGB, GL_RGB16, GL_RGB8, GL_RGBA, GL_RGBA16, GL_RGBA16F, GL_RGBA32F, GL_RGBA8, GL_SRGB8_ALPHA8, GL_TEXTURE0, GL_TEXTURE_2D, GL_TEXTURE_2D_ARRAY, GL_TEXTURE_BORDER_COLOR, GL_TEXTURE_COMPARE_FUNC, GL_TEXTURE_COMPARE_MODE, GL_TEXTURE_CUBE_MAP, GL_TEXTURE_CUBE_MAP_POSITIVE_X, GL_TEXTURE_MAG_FILTER, GL_TEXTURE_MIN_FILTER, GL_TEXTURE_WRAP_R, GL_TEXTURE_WRAP_S, GL_TEXTURE_WRAP_T, GL_UNSIGNED_BYTE, GL_UNSIGNED_INT, GL_UNSIGNED_INT_24_8, GL_UNSIGNED_SHORT, glActiveTexture, glBindTexture, glDeleteTextures, GLenum, glGenerateMipmap, glGenTextures, GLint, glTexImage2D, glTexImage3D, glTexParameterfv, glTexParameteri, glTexSubImage2D, GLuint};
+
+use crate::error::{EngineError, EngineResult};
 
 pub struct Texture {
     id: GLuint,
@@ -12,6 +14,7 @@ pub struct Texture {
 #[derive(Clone, Copy)]
 pub enum TextureType {
     Texture2d = GL_TEXTURE_2D,
+    Texture2dArray = GL_TEXTURE_2D_ARRAY,
     CubeMap = GL_TEXTURE_CUBE_MAP,
     TextureCubeMapPositiveX = GL_TEXTURE_CUBE_MAP_POSITIVE_X,
 }
@@ -51,15 +54,65 @@ pub enum MagFilterParam {
     Linear = GL_LINEAR,
 }
 
-fn load_image_file(path: &str) -> DynamicImage {
-    let mut f = std::fs::File::open(path).unwrap();
-    let mut bytes = vec![];
-    std::io::Read::read_to_end(&mut f, &mut bytes).unwrap();
+// Storage formats for textures and renderbuffers that are rendered into
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextureFormat {
+    R8,
+    Rg8,
+    Rgba8,
+    Srgb8Alpha8,
+    R16F,
+    Rg16F,
+    Rgba16F,
+    R32F,
+    Rgba32F,
+    R11FG11FB10F,
+    Depth24,
+    Depth32F,
+    Depth24Stencil8,
+}
+
+impl TextureFormat {
+    // Internal format, then the pixel format and data type used when allocating without data
+    pub fn gl_params(self) -> (GLenum, GLenum, GLenum) {
+        match self {
+            TextureFormat::R8 => (GL_R8, GL_RED, GL_UNSIGNED_BYTE),
+            TextureFormat::Rg8 => (GL_RG8, GL_RG, GL_UNSIGNED_BYTE),
+            TextureFormat::Rgba8 => (GL_RGBA8, GL_RGBA, GL_UNSIGNED_BYTE),
+            TextureFormat::Srgb8Alpha8 => (GL_SRGB8_ALPHA8, GL_RGBA, GL_UNSIGNED_BYTE),
+            TextureFormat::R16F => (GL_R16F, GL_RED, GL_FLOAT),
+            TextureFormat::Rg16F => (GL_RG16F, GL_RG, GL_FLOAT),
+            TextureFormat::Rgba16F => (GL_RGBA16F, GL_RGBA, GL_FLOAT),
+            TextureFormat::R32F => (GL_R32F, GL_RED, GL_FLOAT),
+            TextureFormat::Rgba32F => (GL_RGBA32F, GL_RGBA, GL_FLOAT),
+            TextureFormat::R11FG11FB10F => (GL_R11F_G11F_B10F, GL_RGB, GL_FLOAT),
+            TextureFormat::Depth24 => (GL_DEPTH_COMPONENT24, GL_DEPTH_COMPONENT, GL_UNSIGNED_INT),
+            TextureFormat::Depth32F => (GL_DEPTH_COMPONENT32F, GL_DEPTH_COMPONENT, GL_FLOAT),
+            TextureFormat::Depth24Stencil8 => (GL_DEPTH24_STENCIL8, GL_DEPTH_STENCIL, GL_UNSIGNED_INT_24_8),
+        }
+    }
 
-    image::load_from_memory(&bytes).unwrap()
+    pub fn has_depth(self) -> bool {
+        matches!(self, TextureFormat::Depth24 | TextureFormat::Depth32F | TextureFormat::Depth24Stencil8)
+    }
+
+    pub fn has_stencil(self) -> bool {
+        self == TextureFormat::Depth24Stencil8
+    }
 }
 
-fn get_gl_image_params_from_color(color: ColorType) -> (GLenum, GLenum, GLenum) {
+fn load_image_file(path: &str) -> EngineResult<DynamicImage> {
+    let bytes = std::fs::read(path).map_err(|source| EngineError::Io { path: path.to_owned(), source })?;
+
+    decode_image(&bytes, path)
+}
+
+// `name` identifies the image in errors, images embedded in other files have no path of their own
+fn decode_image(bytes: &[u8], name: &str) -> EngineResult<DynamicImage> {
+    image::load_from_memory(bytes).map_err(|source| EngineError::ImageDecode { path: name.to_owned(), source })
+}
+
+fn get_gl_image_params_from_color(color: ColorType) -> EngineResult<(GLenum, GLenum, GLenum)> {
     let (internal_format, pixel_format, data_type) = match color {
         ColorType::L8 => (GL_R8, GL_RED, GL_UNSIGNED_BYTE),
         ColorType::L16 => (GL_R16, GL_RED, GL_UNSIGNED_SHORT),
@@ -69,13 +122,13 @@ fn get_gl_image_params_from_color(color: ColorType) -> (GLenum, GLenum, GLenum)
         ColorType::Rgb16 => (GL_RGB16, GL_RGB, GL_UNSIGNED_SHORT),
         ColorType::Rgba8 => (GL_RGBA8, GL_RGBA, GL_UNSIGNED_BYTE),
         ColorType::Rgba16 => (GL_RGBA16, GL_RGBA, GL_UNSIGNED_SHORT),
-        _ => panic!("Unsupported color type"),
+        _ => return Err(EngineError::UnsupportedColorType(color)),
     };
-    (internal_format, pixel_format, data_type)
+    Ok((internal_format, pixel_format, data_type))
 }
 
 impl Texture {
-    pub fn new(texture_type: TextureType) -> Option<Self> {
+    pub fn new(texture_type: TextureType) -> EngineResult<Self> {
         let mut texture = 0;
 
         unsafe {
@@ -83,15 +136,46 @@ impl Texture {
         }
 
         if texture == 0 {
-            None
+            Err(EngineError::GlAllocation("texture"))
         } else {
-            Some(Self {
+            Ok(Self {
                 id: texture,
                 texture_type: texture_type as GLenum,
             })
         }
     }
 
+    // Repeating, linearly filtered 2D texture with mipmaps, the default for model textures
+    pub fn from_image_path(path: &str) -> EngineResult<Self> {
+        let texture = Texture::new_model_texture()?;
+        texture.load_from_image_path(path, true)?;
+
+        Ok(texture)
+    }
+
+    // Same as `from_image_path` for an encoded image that is already in memory, e.g. embedded in a glTF file
+    pub fn from_image_bytes(bytes: &[u8], name: &str) -> EngineResult<Self> {
+        let texture = Texture::new_model_texture()?;
+        texture.load_from_image(&decode_image(bytes, name)?, true)?;
+
+        Ok(texture)
+    }
+
+    fn new_model_texture() -> EngineResult<Self> {
+        let texture = Texture::new(TextureType::Texture2d)?;
+
+        texture.set_wrap(WrapCoordinate::S, WrapParam::Repeat);
+        texture.set_wrap(WrapCoordinate::T, WrapParam::Repeat);
+        texture.set_min_filter(MinFilterParam::Linear);
+        texture.set_mag_filter(MagFilterParam::Linear);
+
+        Ok(texture)
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.id
+    }
+
     pub fn bind(&self) {
         unsafe {
             glBindTexture(self.texture_type, self.id);
@@ -128,43 +212,92 @@ impl Texture {
         }
     }
 
-    pub fn load_from_image_path(&self, image_path: &str, generate_mipmap: bool) {
-        self.bind();
-
-        let image_buffer = {
-            let mut f = std::fs::File::open(image_path).unwrap();
-            let mut bytes = vec![];
-            std::io::Read::read_to_end(&mut f, &mut bytes).unwrap();
+    pub fn load_from_image_path(&self, image_path: &str, generate_mipmap: bool) -> EngineResult<()> {
+        self.load_from_image(&load_image_file(image_path)?, generate_mipmap)
+    }
 
-            image::load_from_memory(&bytes).unwrap()
-        };
-        let color = image_buffer.color();
+    pub fn load_from_image(&self, image_buffer: &DynamicImage, generate_mipmap: bool) -> EngineResult<()> {
+        let (internal_format, pixel_format, data_type) = get_gl_image_params_from_color(image_buffer.color())?;
 
-        let (internal_format, pixel_format, data_type) = get_gl_image_params_from_color(color);
+        self.bind();
         unsafe {
             glTexImage2D(self.texture_type, 0, internal_format as GLint, image_buffer.width().try_into().unwrap(), image_buffer.height().try_into().unwrap(), 0, pixel_format, data_type, image_buffer.as_bytes().as_ptr().cast());
             if generate_mipmap {
                 glGenerateMipmap(self.texture_type);
             }
         }
+
+        Ok(())
     }
 
-    pub fn load_cube_map_from_paths(paths: [&str; 6]) {
+    pub fn load_cube_map_from_paths(paths: [&str; 6]) -> EngineResult<()> {
         for (i, path) in paths.iter().enumerate() {
-            let image_buffer = load_image_file(path);
-            let (internal_format, pixel_format, data_type) = get_gl_image_params_from_color(image_buffer.color());
+            let image_buffer = load_image_file(path)?;
+            let (internal_format, pixel_format, data_type) = get_gl_image_params_from_color(image_buffer.color())?;
 
             unsafe {
                 glTexImage2D(TextureType::TextureCubeMapPositiveX as GLenum + i as GLenum, 0, internal_format as GLint, image_buffer.width().try_into().unwrap(), image_buffer.height().try_into().unwrap(), 0, pixel_format, data_type, image_buffer.as_bytes().as_ptr().cast());
             }
         }
+
+        Ok(())
+    }
+
+    pub fn load_empty(&self, width: u32, height: u32, format: TextureFormat) {
+        self.bind();
+        let (internal_format, pixel_format, data_type) = format.gl_params();
+
+        unsafe {
+            glTexImage2D(self.texture_type, 0, internal_format as GLint, width.try_into().unwrap(), height.try_into().unwrap(), 0, pixel_format, data_type, null());
+        }
+    }
+
+    // For a Texture2dArray, e.g. one shadow cascade per layer
+    pub fn load_empty_array(&self, width: u32, height: u32, layers: u32, format: TextureFormat) {
+        self.bind();
+        let (internal_format, pixel_format, data_type) = format.gl_params();
+
+        unsafe {
+            glTexImage3D(self.texture_type, 0, internal_format as GLint, width.try_into().unwrap(), height.try_into().unwrap(), layers.try_into().unwrap(), 0, pixel_format, data_type, null());
+        }
+    }
+
+    // Allocates all six faces of a CubeMap
+    pub fn load_empty_cube(&self, size: u32, format: TextureFormat) {
+        self.bind();
+        let (internal_format, pixel_format, data_type) = format.gl_params();
+
+        for face in 0..6 {
+            unsafe {
+                glTexImage2D(TextureType::TextureCubeMapPositiveX as GLenum + face, 0, internal_format as GLint, size.try_into().unwrap(), size.try_into().unwrap(), 0, pixel_format, data_type, null());
+            }
+        }
+    }
+
+    // Also allocates the levels of textures created with one of the load_empty functions
+    pub fn generate_mipmaps(&self) {
+        self.bind();
+
+        unsafe {
+            glGenerateMipmap(self.texture_type);
+        }
     }
 
-    pub fn load_empty(&self, width: u32, height: u32) {
+    // Lets shadow samplers compare against the stored depth, linear filtering then blends the comparison results
+    pub fn set_depth_compare(&self) {
         self.bind();
 
         unsafe {
-            glTexImage2D(self.texture_type, 0, GL_R8 as GLint, width.try_into().unwrap(), height.try_into().unwrap(), 0, GL_RED, GL_UNSIGNED_BYTE, null());
+            glTexParameteri(self.texture_type, GL_TEXTURE_COMPARE_MODE, GL_COMPARE_REF_TO_TEXTURE as GLint);
+            glTexParameteri(self.texture_type, GL_TEXTURE_COMPARE_FUNC, GL_LEQUAL as GLint);
+        }
+    }
+
+    pub fn set_border_color(&self, color: [f32; 4]) {
+        self.bind();
+
+        unsafe {
+            glTexParameterfv(self.texture_type, GL_TEXTURE_BORDER_COLOR, color.as_ptr());
         }
     }
 
@@ -183,4 +316,12 @@ impl Texture {
             glTexSubImage2D(self.texture_type, 0, x_offset.try_into().unwrap(), y_offset.try_into().unwrap(), width.try_into().unwrap(), height.try_into().unwrap(), GL_RED, GL_UNSIGNED_BYTE, data_ptr.cast())
         }
     }
-}
\ No newline at end of file
+}
+
+impl Drop for Texture {
+    fn drop(&mut self) {
+        unsafe {
+            glDeleteTextures(1, &self.id);
+        }
+    }
+}
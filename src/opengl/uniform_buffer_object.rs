@@ -0,0 +1,61 @@
+use ogl33::{GL_UNIFORM_BUFFER, glBindBuffer, glBindBufferBase, glBufferData, glBufferSubData, glDeleteBuffers, GLenum, glGenBuffers, GLuint};
+
+use crate::error::{EngineError, EngineResult};
+use crate::opengl::vertex_buffer_object::BufferUsage;
+
+// Data shared by every shader that binds the matching uniform block to the same binding point
+pub struct UniformBufferObject(pub GLuint);
+
+impl UniformBufferObject {
+    pub fn new() -> EngineResult<Self> {
+        let mut ubo = 0;
+
+        unsafe {
+            glGenBuffers(1, &mut ubo);
+        }
+
+        if ubo == 0 {
+            Err(EngineError::GlAllocation("uniform buffer object"))
+        } else {
+            Ok(Self(ubo))
+        }
+    }
+
+    pub fn bind(&self) {
+        unsafe {
+            glBindBuffer(GL_UNIFORM_BUFFER, self.0);
+        }
+    }
+
+    pub fn unbind() {
+        unsafe {
+            glBindBuffer(GL_UNIFORM_BUFFER, 0);
+        }
+    }
+
+    pub fn bind_base(&self, binding: u32) {
+        unsafe {
+            glBindBufferBase(GL_UNIFORM_BUFFER, binding, self.0);
+        }
+    }
+
+    pub fn load_data<T>(size: usize, data_ptr: *const T, usage: BufferUsage) {
+        unsafe {
+            glBufferData(GL_UNIFORM_BUFFER, size.try_into().unwrap(), data_ptr.cast(), usage as GLenum);
+        }
+    }
+
+    pub fn load_sub_data<T>(offset: usize, size: usize, data_ptr: *const T) {
+        unsafe {
+            glBufferSubData(GL_UNIFORM_BUFFER, offset.try_into().unwrap(), size.try_into().unwrap(), data_ptr.cast());
+        }
+    }
+}
+
+impl Drop for UniformBufferObject {
+    fn drop(&mut self) {
+        unsafe {
+            glDeleteBuffers(1, &self.0);
+        }
+    }
+}
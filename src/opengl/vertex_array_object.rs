@@ -1,4 +1,6 @@
-use ogl33::{GL_FLOAT, glBindVertexArray, glEnableVertexAttribArray, GLenum, glGenVertexArrays, GLint, GLuint, glVertexAttribPointer};
+use ogl33::{GL_FLOAT, glBindVertexArray, glDeleteVertexArrays, glEnableVertexAttribArray, GLenum, glGenVertexArrays, GLint, GLuint, glVertexAttrib4f, glVertexAttribPointer};
+
+use crate::error::{EngineError, EngineResult};
 
 pub struct VertexArrayObject(pub GLuint);
 
@@ -8,7 +10,7 @@ pub enum VertexAttribType {
 }
 
 impl VertexArrayObject {
-    pub fn new() -> Option<Self> {
+    pub fn new() -> EngineResult<Self> {
         let mut vao = 0;
 
         unsafe {
@@ -16,9 +18,9 @@ impl VertexArrayObject {
         }
 
         if vao == 0 {
-            None
+            Err(EngineError::GlAllocation("vertex array object"))
         } else {
-            Some(Self(vao))
+            Ok(Self(vao))
         }
     }
 
@@ -40,4 +42,19 @@ impl VertexArrayObject {
             glEnableVertexAttribArray(index as GLuint);
         }
     }
-}
\ No newline at end of file
+
+    // Value a shader input reads when the bound vertex array has no buffer for it
+    pub fn set_constant_attribute(index: usize, value: [f32; 4]) {
+        unsafe {
+            glVertexAttrib4f(index as GLuint, value[0], value[1], value[2], value[3]);
+        }
+    }
+}
+
+impl Drop for VertexArrayObject {
+    fn drop(&mut self) {
+        unsafe {
+            glDeleteVertexArrays(1, &self.0);
+        }
+    }
+}
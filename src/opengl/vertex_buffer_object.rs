@@ -1,4 +1,6 @@
-use ogl33::{GL_ARRAY_BUFFER, GL_DYNAMIC_DRAW, GL_STATIC_DRAW, glBindBuffer, glBufferData, GLenum, glGenBuffers, GLuint};
+use ogl33::{GL_ARRAY_BUFFER, GL_DYNAMIC_DRAW, GL_STATIC_DRAW, glBindBuffer, glBufferData, glDeleteBuffers, GLenum, glGenBuffers, GLuint};
+
+use crate::error::{EngineError, EngineResult};
 
 pub struct VertexBufferObject(pub GLuint);
 
@@ -9,7 +11,7 @@ pub enum BufferUsage {
 }
 
 impl VertexBufferObject {
-    pub fn new() -> Option<Self> {
+    pub fn new() -> EngineResult<Self> {
         let mut vbo = 0;
 
         unsafe {
@@ -17,9 +19,9 @@ impl VertexBufferObject {
         }
 
         if vbo == 0 {
-            None
+            Err(EngineError::GlAllocation("vertex buffer object"))
         } else {
-            Some(Self(vbo))
+            Ok(Self(vbo))
         }
     }
 
@@ -40,4 +42,12 @@ impl VertexBufferObject {
             glBufferData(GL_ARRAY_BUFFER, size.try_into().unwrap(), data_ptr.cast(), usage as GLenum);
         }
     }
-}
\ No newline at end of file
+}
+
+impl Drop for VertexBufferObject {
+    fn drop(&mut self) {
+        unsafe {
+            glDeleteBuffers(1, &self.0);
+        }
+    }
+}
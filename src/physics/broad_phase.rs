@@ -0,0 +1,462 @@
+use ultraviolet::Vec3;
+
+use crate::math::aabb_bouding_box::AABBBoundingBox;
+use crate::math::frustum::Frustum;
+use crate::math::ray::Ray;
+
+pub const DEFAULT_MARGIN: f32 = 0.1;
+const DISPLACEMENT_MULTIPLIER: f32 = 2.0;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ProxyId(usize);
+
+struct TreeNode<T> {
+    bounding_box: AABBBoundingBox,
+    parent: Option<usize>,
+    children: Option<[usize; 2]>,
+    height: i32, // 0 for leaves, -1 for free nodes
+    data: Option<T>,
+}
+
+impl<T> TreeNode<T> {
+    fn is_leaf(&self) -> bool {
+        self.children.is_none()
+    }
+}
+
+// Bounding volume hierarchy of "fat" boxes (enlarged by a margin) so that small movements don't require restructuring the tree
+pub struct DynamicAabbTree<T> {
+    nodes: Vec<TreeNode<T>>,
+    free_list: Vec<usize>,
+    root: Option<usize>,
+    margin: f32,
+    proxy_count: usize,
+}
+
+impl<T: Copy> Default for DynamicAabbTree<T> {
+    fn default() -> Self {
+        Self::with_margin(DEFAULT_MARGIN)
+    }
+}
+
+impl<T: Copy> DynamicAabbTree<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_margin(margin: f32) -> Self {
+        Self {
+            nodes: Vec::new(),
+            free_list: Vec::new(),
+            root: None,
+            margin,
+            proxy_count: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.proxy_count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.proxy_count == 0
+    }
+
+    pub fn height(&self) -> i32 {
+        self.root.map_or(0, |root| self.nodes[root].height)
+    }
+
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.free_list.clear();
+        self.root = None;
+        self.proxy_count = 0;
+    }
+
+    pub fn insert(&mut self, bounding_box: AABBBoundingBox, data: T) -> ProxyId {
+        let leaf = self.allocate_node();
+        let node = &mut self.nodes[leaf];
+        node.bounding_box = bounding_box.expanded(Vec3::broadcast(self.margin));
+        node.height = 0;
+        node.data = Some(data);
+
+        self.insert_leaf(leaf);
+        self.proxy_count += 1;
+
+        ProxyId(leaf)
+    }
+
+    pub fn remove(&mut self, proxy: ProxyId) {
+        self.remove_leaf(proxy.0);
+        self.free_node(proxy.0);
+        self.proxy_count -= 1;
+    }
+
+    // Returns true when the proxy had to be reinserted because it left its fat box
+    pub fn move_proxy(&mut self, proxy: ProxyId, bounding_box: AABBBoundingBox, displacement: Vec3) -> bool {
+        let leaf = proxy.0;
+        if self.nodes[leaf].bounding_box.contains(bounding_box) {
+            return false;
+        }
+
+        self.remove_leaf(leaf);
+
+        // Extend the fat box in the direction of movement to anticipate where the proxy is going
+        let mut fat_box = bounding_box.expanded(Vec3::broadcast(self.margin));
+        let predicted = displacement * DISPLACEMENT_MULTIPLIER;
+        if predicted.x < 0.0 { fat_box.x_min += predicted.x } else { fat_box.x_max += predicted.x }
+        if predicted.y < 0.0 { fat_box.y_min += predicted.y } else { fat_box.y_max += predicted.y }
+        if predicted.z < 0.0 { fat_box.z_min += predicted.z } else { fat_box.z_max += predicted.z }
+        self.nodes[leaf].bounding_box = fat_box;
+
+        self.insert_leaf(leaf);
+
+        true
+    }
+
+    pub fn data(&self, proxy: ProxyId) -> T {
+        self.nodes[proxy.0].data.expect("Proxy was removed from the tree")
+    }
+
+    pub fn fat_bounding_box(&self, proxy: ProxyId) -> AABBBoundingBox {
+        self.nodes[proxy.0].bounding_box
+    }
+
+    pub fn query(&self, bounding_box: AABBBoundingBox) -> Vec<T> {
+        let mut result = Vec::new();
+        self.traverse(|node_box| node_box.collides_with(bounding_box), |_, data| result.push(data));
+
+        result
+    }
+
+    pub fn query_frustum(&self, frustum: &Frustum) -> Vec<T> {
+        let mut result = Vec::new();
+        self.traverse(|node_box| frustum.intersects_aabb(node_box), |_, data| result.push(data));
+
+        result
+    }
+
+    pub fn query_ray(&self, ray: &Ray, max_distance: f32) -> Vec<T> {
+        let mut result = Vec::new();
+        self.traverse(|node_box| ray.intersect_aabb(node_box, max_distance).is_some(), |_, data| result.push(data));
+
+        result
+    }
+
+    // Every pair of proxies whose fat boxes overlap, each pair is reported once
+    pub fn overlapping_pairs(&self) -> Vec<(T, T)> {
+        let mut pairs = Vec::new();
+
+        for (leaf, node) in self.nodes.iter().enumerate() {
+            let Some(data) = node.data else {
+                continue;
+            };
+
+            let leaf_box = node.bounding_box;
+            self.traverse(|node_box| node_box.collides_with(leaf_box), |other, other_data| {
+                if other > leaf {
+                    pairs.push((data, other_data));
+                }
+            });
+        }
+
+        pairs
+    }
+
+    fn traverse(&self, mut test: impl FnMut(&AABBBoundingBox) -> bool, mut visit: impl FnMut(usize, T)) {
+        let mut stack = Vec::with_capacity(64);
+        stack.extend(self.root);
+
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if !test(&node.bounding_box) {
+                continue;
+            }
+
+            match node.children {
+                None => visit(index, node.data.expect("Leaf without data")),
+                Some(children) => stack.extend(children),
+            }
+        }
+    }
+
+    fn allocate_node(&mut self) -> usize {
+        let node = TreeNode { bounding_box: AABBBoundingBox::default(), parent: None, children: None, height: 0, data: None };
+
+        match self.free_list.pop() {
+            Some(index) => {
+                self.nodes[index] = node;
+                index
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    fn free_node(&mut self, index: usize) {
+        let node = &mut self.nodes[index];
+        node.parent = None;
+        node.children = None;
+        node.height = -1;
+        node.data = None;
+        self.free_list.push(index);
+    }
+
+    fn replace_child(&mut self, parent: Option<usize>, old_child: usize, new_child: usize) {
+        match parent {
+            Some(parent) => {
+                let children = self.nodes[parent].children.as_mut().expect("Parent node without children");
+                if children[0] == old_child {
+                    children[0] = new_child;
+                } else {
+                    children[1] = new_child;
+                }
+            }
+            None => self.root = Some(new_child),
+        }
+    }
+
+    fn insertion_cost(&self, child: usize, leaf_box: AABBBoundingBox, inheritance_cost: f32) -> f32 {
+        let node = &self.nodes[child];
+        let combined_area = node.bounding_box.union(leaf_box).surface_area();
+
+        if node.is_leaf() {
+            combined_area + inheritance_cost
+        } else {
+            combined_area - node.bounding_box.surface_area() + inheritance_cost
+        }
+    }
+
+    fn insert_leaf(&mut self, leaf: usize) {
+        let Some(root) = self.root else {
+            self.root = Some(leaf);
+            self.nodes[leaf].parent = None;
+            return;
+        };
+
+        // Find the best sibling using the surface area heuristic
+        let leaf_box = self.nodes[leaf].bounding_box;
+        let mut index = root;
+        while let Some([child1, child2]) = self.nodes[index].children {
+            let area = self.nodes[index].bounding_box.surface_area();
+            let combined_area = self.nodes[index].bounding_box.union(leaf_box).surface_area();
+
+            let cost = 2.0 * combined_area;
+            let inheritance_cost = 2.0 * (combined_area - area);
+            let cost1 = self.insertion_cost(child1, leaf_box, inheritance_cost);
+            let cost2 = self.insertion_cost(child2, leaf_box, inheritance_cost);
+
+            if cost < cost1 && cost < cost2 {
+                break;
+            }
+
+            index = if cost1 < cost2 { child1 } else { child2 };
+        }
+
+        let sibling = index;
+        let old_parent = self.nodes[sibling].parent;
+        let new_parent = self.allocate_node();
+        self.nodes[new_parent].parent = old_parent;
+        self.nodes[new_parent].bounding_box = leaf_box.union(self.nodes[sibling].bounding_box);
+        self.nodes[new_parent].height = self.nodes[sibling].height + 1;
+        self.nodes[new_parent].children = Some([sibling, leaf]);
+
+        self.replace_child(old_parent, sibling, new_parent);
+        self.nodes[sibling].parent = Some(new_parent);
+        self.nodes[leaf].parent = Some(new_parent);
+
+        self.refit_ancestors(self.nodes[leaf].parent);
+    }
+
+    fn remove_leaf(&mut self, leaf: usize) {
+        if self.root == Some(leaf) {
+            self.root = None;
+            return;
+        }
+
+        let parent = self.nodes[leaf].parent.expect("Non-root leaf without parent");
+        let grand_parent = self.nodes[parent].parent;
+        let [child1, child2] = self.nodes[parent].children.expect("Parent node without children");
+        let sibling = if child1 == leaf { child2 } else { child1 };
+
+        self.replace_child(grand_parent, parent, sibling);
+        self.nodes[sibling].parent = grand_parent;
+        self.free_node(parent);
+        self.nodes[leaf].parent = None;
+
+        self.refit_ancestors(grand_parent);
+    }
+
+    fn refit_ancestors(&mut self, start: Option<usize>) {
+        let mut index = start;
+        while let Some(current) = index {
+            let current = self.balance(current);
+            let [child1, child2] = self.nodes[current].children.expect("Internal node without children");
+
+            self.nodes[current].height = 1 + self.nodes[child1].height.max(self.nodes[child2].height);
+            self.nodes[current].bounding_box = self.nodes[child1].bounding_box.union(self.nodes[child2].bounding_box);
+
+            index = self.nodes[current].parent;
+        }
+    }
+
+    // Performs a left or right rotation if `a` is imbalanced, returns the new root of the subtree
+    fn balance(&mut self, a: usize) -> usize {
+        let Some([b, c]) = self.nodes[a].children else {
+            return a;
+        };
+        if self.nodes[a].height < 2 {
+            return a;
+        }
+
+        let balance = self.nodes[c].height - self.nodes[b].height;
+        if balance > 1 {
+            self.rotate_up(a, c, b, 1)
+        } else if balance < -1 {
+            self.rotate_up(a, b, c, 0)
+        } else {
+            a
+        }
+    }
+
+    // Moves `child` (stored in slot `child_slot` of `a`) above `a`, `other` is the remaining child of `a`
+    fn rotate_up(&mut self, a: usize, child: usize, other: usize, child_slot: usize) -> usize {
+        let [grand_child1, grand_child2] = self.nodes[child].children.expect("Rotated node without children");
+
+        let a_parent = self.nodes[a].parent;
+        self.nodes[child].parent = a_parent;
+        self.nodes[a].parent = Some(child);
+        self.replace_child(a_parent, a, child);
+
+        let (taller, shorter) = if self.nodes[grand_child1].height > self.nodes[grand_child2].height {
+            (grand_child1, grand_child2)
+        } else {
+            (grand_child2, grand_child1)
+        };
+
+        self.nodes[child].children = Some([a, taller]);
+        let a_children = self.nodes[a].children.as_mut().expect("Rotated node without children");
+        a_children[child_slot] = shorter;
+        self.nodes[shorter].parent = Some(a);
+
+        self.nodes[a].bounding_box = self.nodes[other].bounding_box.union(self.nodes[shorter].bounding_box);
+        self.nodes[a].height = 1 + self.nodes[other].height.max(self.nodes[shorter].height);
+        self.nodes[child].bounding_box = self.nodes[a].bounding_box.union(self.nodes[taller].bounding_box);
+        self.nodes[child].height = 1 + self.nodes[a].height.max(self.nodes[taller].height);
+
+        child
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use ultraviolet::Vec3;
+    use ultraviolet::projection::perspective_gl;
+
+    use super::*;
+
+    fn grid_boxes(count: usize) -> Vec<AABBBoundingBox> {
+        (0..count).map(|i| {
+            let center = Vec3::new((i % 10) as f32 * 1.5, ((i / 10) % 10) as f32 * 1.5, (i / 100) as f32 * 1.5);
+            AABBBoundingBox::from_center_half_extents(center, Vec3::broadcast(0.5))
+        }).collect()
+    }
+
+    fn brute_force_query(boxes: &[AABBBoundingBox], region: AABBBoundingBox, margin: f32) -> HashSet<usize> {
+        boxes.iter().enumerate().filter(|(_, b)| b.expanded(Vec3::broadcast(margin)).collides_with(region)).map(|(i, _)| i).collect()
+    }
+
+    #[test]
+    fn query_matches_brute_force() {
+        let boxes = grid_boxes(300);
+        let mut tree = DynamicAabbTree::new();
+        for (i, bounding_box) in boxes.iter().enumerate() {
+            tree.insert(*bounding_box, i);
+        }
+
+        let region = AABBBoundingBox::from_min_max(Vec3::new(2.0, 2.0, 0.0), Vec3::new(6.0, 5.0, 2.0));
+        let result: HashSet<usize> = tree.query(region).into_iter().collect();
+
+        assert_eq!(result, brute_force_query(&boxes, region, DEFAULT_MARGIN));
+        assert_eq!(tree.len(), 300);
+    }
+
+    #[test]
+    fn tree_stays_balanced() {
+        let mut tree = DynamicAabbTree::new();
+        for i in 0..1024 {
+            // Inserting along a line is the worst case for an unbalanced tree
+            tree.insert(AABBBoundingBox::from_center_half_extents(Vec3::new(i as f32 * 2.0, 0.0, 0.0), Vec3::broadcast(0.5)), i);
+        }
+
+        assert!(tree.height() <= 20, "tree height {} is too large", tree.height());
+    }
+
+    #[test]
+    fn remove_and_move_keep_queries_consistent() {
+        let boxes = grid_boxes(100);
+        let mut tree = DynamicAabbTree::new();
+        let proxies: Vec<ProxyId> = boxes.iter().enumerate().map(|(i, b)| tree.insert(*b, i)).collect();
+
+        for proxy in proxies.iter().step_by(2) {
+            tree.remove(*proxy);
+        }
+        assert_eq!(tree.len(), 50);
+
+        let far_away = AABBBoundingBox::from_center_half_extents(Vec3::new(100.0, 100.0, 100.0), Vec3::broadcast(0.5));
+        assert!(tree.move_proxy(proxies[1], far_away, Vec3::new(1.0, 0.0, 0.0)));
+        assert!(!tree.move_proxy(proxies[1], far_away, Vec3::default()));
+
+        assert_eq!(tree.query(far_away), vec![1]);
+        assert!(!tree.query(boxes[1]).contains(&1));
+        assert!(!tree.query(boxes[0]).contains(&0));
+        assert_eq!(tree.data(proxies[3]), 3);
+    }
+
+    #[test]
+    fn overlapping_pairs_match_brute_force() {
+        let mut boxes = grid_boxes(200);
+        // Make every other box large enough to touch its neighbours
+        for bounding_box in boxes.iter_mut().step_by(2) {
+            *bounding_box = bounding_box.expanded(Vec3::broadcast(0.6));
+        }
+
+        let mut tree = DynamicAabbTree::with_margin(0.0);
+        for (i, bounding_box) in boxes.iter().enumerate() {
+            tree.insert(*bounding_box, i);
+        }
+
+        let pairs: HashSet<(usize, usize)> = tree.overlapping_pairs().into_iter().map(|(a, b)| (a.min(b), a.max(b))).collect();
+
+        let mut expected = HashSet::new();
+        for i in 0..boxes.len() {
+            for j in i + 1..boxes.len() {
+                if boxes[i].collides_with(boxes[j]) {
+                    expected.insert((i, j));
+                }
+            }
+        }
+
+        assert!(!expected.is_empty());
+        assert_eq!(pairs, expected);
+    }
+
+    #[test]
+    fn frustum_query_culls_boxes_behind_camera() {
+        let mut tree = DynamicAabbTree::with_margin(0.0);
+        tree.insert(AABBBoundingBox::from_center_half_extents(Vec3::new(0.0, 0.0, -10.0), Vec3::broadcast(1.0)), "in front");
+        tree.insert(AABBBoundingBox::from_center_half_extents(Vec3::new(0.0, 0.0, 10.0), Vec3::broadcast(1.0)), "behind");
+        tree.insert(AABBBoundingBox::from_center_half_extents(Vec3::new(0.0, 0.0, -500.0), Vec3::broadcast(1.0)), "too far");
+        tree.insert(AABBBoundingBox::from_center_half_extents(Vec3::new(50.0, 0.0, -10.0), Vec3::broadcast(1.0)), "to the side");
+
+        let projection = perspective_gl(70.0_f32.to_radians(), 16.0 / 9.0, 0.1, 100.0);
+        let view = ultraviolet::Mat4::look_at(Vec3::default(), Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 1.0, 0.0));
+        let frustum = Frustum::from_view_projection(projection * view);
+
+        assert_eq!(tree.query_frustum(&frustum), vec!["in front"]);
+    }
+}
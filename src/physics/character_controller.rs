@@ -0,0 +1,473 @@
+use ultraviolet::Vec3;
+
+use crate::math::aabb_bouding_box::AABBBoundingBox;
+use crate::math::capsule::Capsule;
+use crate::physics::collider::Collider;
+use crate::physics::{GRAVITY, TERMINAL_VELOCITY};
+
+const SKIN_WIDTH: f32 = 0.01;
+const MAX_SLIDE_ITERATIONS: usize = 4;
+const MAX_DEPENETRATION_ITERATIONS: usize = 4;
+const EYE_OFFSET: f32 = 0.1; // Distance from the top of the capsule down to the camera
+
+pub struct CharacterControllerConfig {
+    pub radius: f32,
+    pub height: f32,
+    pub crouch_height: f32,
+    pub walk_speed: f32,
+    pub crouch_speed: f32,
+    pub jump_speed: f32,
+    pub max_slope_angle: f32, // Radians, steeper surfaces are treated as walls
+    pub step_height: f32,
+    pub snap_distance: f32, // How far the character is pulled down to stay on descending ground
+    pub jump_buffer_time: f32, // A jump pressed this long before landing still happens
+    pub coyote_time: f32, // A jump is still allowed this long after walking off a ledge
+}
+
+impl Default for CharacterControllerConfig {
+    fn default() -> Self {
+        Self {
+            radius: 0.25,
+            height: 1.6,
+            crouch_height: 1.0,
+            walk_speed: 10.0,
+            crouch_speed: 4.0,
+            jump_speed: 5.0,
+            max_slope_angle: 45.0_f32.to_radians(),
+            step_height: 0.35,
+            snap_distance: 0.3,
+            jump_buffer_time: 0.15,
+            coyote_time: 0.1,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Default, Debug)]
+pub struct CharacterInput {
+    pub move_direction: Vec3, // Only the horizontal part is used, a length above 1 is normalized
+    pub jump: bool,
+    pub crouch: bool,
+}
+
+struct SlideMove {
+    displacement: Vec3,
+    ground_normal: Option<Vec3>,
+    hit_ceiling: bool,
+    hit_wall: bool,
+}
+
+// Moves an upright capsule through static geometry, positions passed in and out are the bottom of the capsule
+pub struct CharacterController {
+    pub config: CharacterControllerConfig,
+    velocity: Vec3,
+    height: f32,
+    grounded: bool,
+    ground_normal: Vec3,
+    crouching: bool,
+    jump_held: bool,
+    jump_buffer_timer: f32,
+    coyote_timer: f32,
+}
+
+impl CharacterController {
+    pub fn new(config: CharacterControllerConfig) -> Self {
+        Self {
+            height: config.height,
+            config,
+            velocity: Vec3::default(),
+            grounded: false,
+            ground_normal: Vec3::unit_y(),
+            crouching: false,
+            jump_held: false,
+            jump_buffer_timer: 0.0,
+            coyote_timer: 0.0,
+        }
+    }
+
+    pub fn velocity(&self) -> Vec3 {
+        self.velocity
+    }
+
+    pub fn is_grounded(&self) -> bool {
+        self.grounded
+    }
+
+    pub fn ground_normal(&self) -> Vec3 {
+        self.ground_normal
+    }
+
+    pub fn is_crouching(&self) -> bool {
+        self.crouching
+    }
+
+    pub fn height(&self) -> f32 {
+        self.height
+    }
+
+    pub fn eye_height(&self) -> f32 {
+        self.height - EYE_OFFSET
+    }
+
+    pub fn capsule(&self, position: Vec3) -> Capsule {
+        Capsule::new(position, self.config.radius, self.height)
+    }
+
+    // Everything a single step can touch, used to gather obstacles from the broad phase
+    pub fn reach_bounding_box(&self, position: Vec3, dt: f32) -> AABBBoundingBox {
+        let speed = self.velocity.mag() + self.config.walk_speed + self.config.jump_speed - GRAVITY.y * dt;
+        let reach = speed * dt + self.config.step_height + self.config.snap_distance + (self.config.height - self.height) + SKIN_WIDTH;
+
+        Capsule::new(position, self.config.radius, self.config.height).bounding_box().expanded(Vec3::broadcast(reach))
+    }
+
+    pub fn step(&mut self, position: Vec3, input: &CharacterInput, dt: f32, obstacles: &[&Collider]) -> Vec3 {
+        if input.crouch {
+            self.crouching = true;
+            self.height = self.config.crouch_height;
+        } else if self.crouching && self.fits(&Capsule::new(position, self.config.radius, self.config.height), obstacles) {
+            self.crouching = false;
+            self.height = self.config.height;
+        }
+
+        let mut position = self.depenetrate(position, obstacles);
+
+        let jump_pressed = input.jump && !self.jump_held;
+        self.jump_held = input.jump;
+        self.jump_buffer_timer = if jump_pressed { self.config.jump_buffer_time } else { (self.jump_buffer_timer - dt).max(0.0) };
+        self.coyote_timer = if self.grounded { self.config.coyote_time } else { (self.coyote_timer - dt).max(0.0) };
+
+        let speed = if self.crouching { self.config.crouch_speed } else { self.config.walk_speed };
+        let mut horizontal = Vec3::new(input.move_direction.x, 0.0, input.move_direction.z);
+        if horizontal.mag_sq() > 1.0 {
+            horizontal.normalize();
+        }
+        horizontal *= speed;
+        self.velocity.x = horizontal.x;
+        self.velocity.z = horizontal.z;
+
+        let mut jumped = false;
+        if self.jump_buffer_timer > 0.0 && self.coyote_timer > 0.0 {
+            self.velocity.y = self.config.jump_speed;
+            self.jump_buffer_timer = 0.0;
+            self.coyote_timer = 0.0;
+            self.grounded = false;
+            jumped = true;
+        }
+
+        let displacement = if self.grounded {
+            // Walk along the ground plane so slopes neither launch the character nor slowly slide it down
+            self.velocity.y = 0.0;
+            let along_ground = horizontal - self.ground_normal * horizontal.dot(self.ground_normal);
+            if along_ground.mag_sq() > f32::EPSILON { along_ground.normalized() * horizontal.mag() * dt } else { Vec3::default() }
+        } else {
+            self.velocity.y = (self.velocity.y + GRAVITY.y * dt).max(-TERMINAL_VELOCITY);
+            self.velocity * dt
+        };
+
+        let was_grounded = self.grounded;
+        let mut slide = self.slide(position, displacement, obstacles);
+
+        if was_grounded && slide.hit_wall {
+            if let Some(stepped) = self.step_up(position, displacement, obstacles) {
+                let horizontal_progress = |movement: &SlideMove| Vec3::new(movement.displacement.x, 0.0, movement.displacement.z).mag();
+                if horizontal_progress(&stepped) > horizontal_progress(&slide) + SKIN_WIDTH {
+                    slide = stepped;
+                }
+            }
+        }
+
+        position += slide.displacement;
+        if slide.hit_ceiling && self.velocity.y > 0.0 {
+            self.velocity.y = 0.0;
+        }
+
+        self.grounded = false;
+        if let Some(normal) = slide.ground_normal {
+            self.grounded = self.velocity.y <= 0.0;
+            self.ground_normal = normal;
+        }
+
+        if !jumped && self.velocity.y <= 0.0 {
+            // Only snap down when the character was walking, otherwise a short probe just detects landing
+            let snap_distance = if was_grounded { self.config.snap_distance } else { SKIN_WIDTH * 2.0 };
+            if let Some((distance, normal)) = self.probe_ground(position, snap_distance, obstacles) {
+                position.y -= distance;
+                self.grounded = true;
+                self.ground_normal = normal;
+            }
+        }
+
+        if self.grounded && !was_grounded && self.jump_buffer_timer > 0.0 {
+            // A jump pressed just before landing leaves the ground on the frame the character lands
+            self.velocity.y = self.config.jump_speed;
+            self.jump_buffer_timer = 0.0;
+            self.coyote_timer = 0.0;
+            self.grounded = false;
+        } else if self.grounded {
+            self.velocity.y = 0.0;
+        }
+        if !self.grounded {
+            self.ground_normal = Vec3::unit_y();
+        }
+
+        position
+    }
+
+    fn is_walkable(&self, normal: Vec3) -> bool {
+        normal.y >= self.config.max_slope_angle.cos()
+    }
+
+    fn fits(&self, capsule: &Capsule, obstacles: &[&Collider]) -> bool {
+        obstacles.iter().all(|obstacle| obstacle.capsule_contact(capsule).is_none())
+    }
+
+    fn depenetrate(&self, mut position: Vec3, obstacles: &[&Collider]) -> Vec3 {
+        for _ in 0..MAX_DEPENETRATION_ITERATIONS {
+            let mut penetrating = false;
+            for obstacle in obstacles {
+                if let Some(contact) = obstacle.capsule_contact(&self.capsule(position)) {
+                    position += contact.normal * (contact.depth + SKIN_WIDTH);
+                    penetrating = true;
+                }
+            }
+
+            if !penetrating {
+                break;
+            }
+        }
+
+        position
+    }
+
+    fn slide(&self, position: Vec3, displacement: Vec3, obstacles: &[&Collider]) -> SlideMove {
+        let mut result = SlideMove { displacement: Vec3::default(), ground_normal: None, hit_ceiling: false, hit_wall: false };
+
+        let mut remaining = displacement;
+        for _ in 0..MAX_SLIDE_ITERATIONS {
+            if remaining.mag_sq() < f32::EPSILON * f32::EPSILON {
+                break;
+            }
+
+            let capsule = self.capsule(position + result.displacement);
+            let earliest_hit = obstacles.iter()
+                .filter_map(|obstacle| obstacle.capsule_sweep(&capsule, remaining))
+                .min_by(|a, b| a.time.total_cmp(&b.time));
+
+            let Some(hit) = earliest_hit else {
+                result.displacement += remaining;
+                break;
+            };
+
+            result.displacement += remaining * hit.time + hit.normal * SKIN_WIDTH;
+
+            let mut normal = hit.normal;
+            if self.is_walkable(normal) {
+                result.ground_normal = Some(normal);
+            } else if normal.y <= -self.config.max_slope_angle.cos() {
+                result.hit_ceiling = true;
+            } else {
+                result.hit_wall = true;
+
+                // Steep slopes block like walls instead of being climbed, falling onto them still slides down
+                let flattened = Vec3::new(normal.x, 0.0, normal.z);
+                if normal.y > 0.0 && remaining.y >= 0.0 && flattened.mag_sq() > f32::EPSILON {
+                    normal = flattened.normalized();
+                }
+            }
+
+            remaining *= 1.0 - hit.time;
+            let into_surface = remaining.dot(normal);
+            if into_surface < 0.0 {
+                remaining -= normal * into_surface;
+            }
+        }
+
+        result
+    }
+
+    // Lift by the step height, move forward and put the character back down on whatever it stepped onto
+    fn step_up(&self, position: Vec3, displacement: Vec3, obstacles: &[&Collider]) -> Option<SlideMove> {
+        let horizontal = Vec3::new(displacement.x, 0.0, displacement.z);
+        if horizontal.mag_sq() < f32::EPSILON {
+            return None;
+        }
+
+        let lift = self.slide(position, Vec3::new(0.0, self.config.step_height, 0.0), obstacles);
+        let raised = position + lift.displacement;
+        let forward = self.slide(raised, horizontal, obstacles);
+        let moved = raised + forward.displacement;
+
+        let (distance, normal) = self.probe_ground(moved, lift.displacement.y + SKIN_WIDTH * 2.0, obstacles)?;
+
+        Some(SlideMove {
+            displacement: moved - position - Vec3::new(0.0, distance, 0.0),
+            ground_normal: Some(normal),
+            hit_ceiling: false,
+            hit_wall: forward.hit_wall,
+        })
+    }
+
+    // Distance the character can drop before resting on walkable ground, if there is any within `max_distance`
+    fn probe_ground(&self, position: Vec3, max_distance: f32, obstacles: &[&Collider]) -> Option<(f32, Vec3)> {
+        let capsule = self.capsule(position);
+        let down = Vec3::new(0.0, -max_distance, 0.0);
+        let hit = obstacles.iter()
+            .filter_map(|obstacle| obstacle.capsule_sweep(&capsule, down))
+            .min_by(|a, b| a.time.total_cmp(&b.time))?;
+
+        if !self.is_walkable(hit.normal) {
+            return None;
+        }
+
+        Some(((hit.time * max_distance - SKIN_WIDTH).max(0.0), hit.normal))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::node_3d::Node3D;
+    use crate::math::rotation::Rotation;
+    use crate::physics::collision_shape::{CollisionShape, TriangleMesh};
+    use crate::physics::FIXED_TIMESTEP;
+
+    fn box_collider(center: Vec3, half_extents: Vec3) -> Collider {
+        Collider::from_bounding_box(AABBBoundingBox::from_center_half_extents(center, half_extents))
+    }
+
+    fn ramp(angle_degrees: f32) -> Collider {
+        // Rises along +X starting at x = 0
+        let rise = angle_degrees.to_radians().tan() * 20.0;
+        let (a, b, c, d) = (Vec3::new(0.0, 0.0, -10.0), Vec3::new(20.0, rise, -10.0), Vec3::new(20.0, rise, 10.0), Vec3::new(0.0, 0.0, 10.0));
+        let node3d = Node3D { world_position: Vec3::default(), scale: Vec3::new(1.0, 1.0, 1.0), rotation: Rotation::default() };
+
+        Collider::new(&CollisionShape::TriangleMesh(TriangleMesh::new(vec![[a, b, c], [a, c, d]])), &node3d)
+    }
+
+    fn simulate(controller: &mut CharacterController, mut position: Vec3, input: CharacterInput, steps: usize, obstacles: &[&Collider]) -> Vec3 {
+        for _ in 0..steps {
+            position = controller.step(position, &input, FIXED_TIMESTEP, obstacles);
+        }
+
+        position
+    }
+
+    #[test]
+    fn lands_on_floor_and_stays_grounded() {
+        let floor = box_collider(Vec3::new(0.0, -0.5, 0.0), Vec3::new(10.0, 0.5, 10.0));
+        let mut controller = CharacterController::new(CharacterControllerConfig::default());
+
+        let position = simulate(&mut controller, Vec3::new(0.0, 2.0, 0.0), CharacterInput::default(), 120, &[&floor]);
+
+        assert!(controller.is_grounded());
+        assert!(position.y.abs() < 0.05);
+    }
+
+    #[test]
+    fn steps_onto_low_ledge_but_not_tall_wall() {
+        let floor = box_collider(Vec3::new(0.0, -0.5, 0.0), Vec3::new(20.0, 0.5, 20.0));
+        let ledge = box_collider(Vec3::new(3.0, 0.1, 0.0), Vec3::new(1.0, 0.1, 5.0));
+        let wall = box_collider(Vec3::new(-3.0, 1.0, 0.0), Vec3::new(1.0, 1.0, 5.0));
+        let obstacles = [&floor, &ledge, &wall];
+        let mut controller = CharacterController::new(CharacterControllerConfig::default());
+
+        let start = simulate(&mut controller, Vec3::new(0.0, 0.1, 0.0), CharacterInput::default(), 30, &obstacles);
+        let right = CharacterInput { move_direction: Vec3::unit_x(), ..Default::default() };
+        let on_ledge = simulate(&mut controller, start, right, 20, &obstacles);
+        assert!(on_ledge.x > 2.5 && on_ledge.y > 0.15);
+
+        let left = CharacterInput { move_direction: -Vec3::unit_x(), ..Default::default() };
+        let at_wall = simulate(&mut controller, on_ledge, left, 60, &obstacles);
+        assert!(at_wall.x > -2.0 - controller.config.radius - 0.05 && at_wall.y < 0.05);
+    }
+
+    #[test]
+    fn climbs_gentle_slopes_but_not_steep_ones() {
+        let right = CharacterInput { move_direction: Vec3::unit_x(), ..Default::default() };
+
+        let gentle = ramp(20.0);
+        let mut controller = CharacterController::new(CharacterControllerConfig::default());
+        let position = simulate(&mut controller, Vec3::new(1.0, 0.5, 0.0), right, 60, &[&gentle]);
+        assert!(position.x > 5.0 && controller.is_grounded());
+
+        let steep = ramp(60.0);
+        let mut controller = CharacterController::new(CharacterControllerConfig::default());
+        let position = simulate(&mut controller, Vec3::new(1.0, 2.0, 0.0), right, 60, &[&steep]);
+        assert!(position.x < 1.5);
+    }
+
+    #[test]
+    fn snaps_to_ground_when_walking_downhill() {
+        let slope = ramp(20.0);
+        let mut controller = CharacterController::new(CharacterControllerConfig::default());
+        let start = simulate(&mut controller, Vec3::new(15.0, 6.0, 0.0), CharacterInput::default(), 60, &[&slope]);
+
+        let downhill = CharacterInput { move_direction: -Vec3::unit_x(), ..Default::default() };
+        let surface_height = |x: f32| x * 20.0_f32.to_radians().tan();
+        let mut position = start;
+        for _ in 0..30 {
+            let previous = position;
+            position = simulate(&mut controller, position, downhill, 1, &[&slope]);
+            assert!(controller.is_grounded());
+            assert!(position.x < previous.x);
+            // The capsule's rounded bottom rests slightly above the surface directly beneath it
+            assert!((position.y - surface_height(position.x)).abs() < 0.05, "{position:?}");
+        }
+        assert!(position.x < start.x - 3.0);
+    }
+
+    #[test]
+    fn coyote_time_and_jump_buffer_allow_late_and_early_jumps() {
+        let ledge = box_collider(Vec3::new(0.0, -0.5, 0.0), Vec3::new(1.0, 0.5, 1.0));
+        let mut controller = CharacterController::new(CharacterControllerConfig::default());
+        let position = simulate(&mut controller, Vec3::new(0.5, 0.1, 0.0), CharacterInput::default(), 30, &[&ledge]);
+
+        // Walk off the ledge and jump a couple of frames later
+        let right = CharacterInput { move_direction: Vec3::unit_x(), ..Default::default() };
+        let mut position = position;
+        while controller.is_grounded() {
+            position = controller.step(position, &right, FIXED_TIMESTEP, &[&ledge]);
+        }
+        position = controller.step(position, &right, FIXED_TIMESTEP, &[&ledge]);
+        controller.step(position, &CharacterInput { jump: true, ..right }, FIXED_TIMESTEP, &[&ledge]);
+        assert!(controller.velocity().y > 0.0);
+
+        // Pressing jump while still falling jumps on the frame the character lands
+        let floor = box_collider(Vec3::new(0.0, -0.5, 0.0), Vec3::new(10.0, 0.5, 10.0));
+        let mut controller = CharacterController::new(CharacterControllerConfig::default());
+        let mut position = Vec3::new(0.0, 1.0, 0.0);
+        while position.y > 0.3 {
+            position = controller.step(position, &CharacterInput::default(), FIXED_TIMESTEP, &[&floor]);
+            assert!(!controller.is_grounded());
+        }
+
+        let jump = CharacterInput { jump: true, ..Default::default() };
+        let mut frames_held = 0;
+        while controller.velocity().y <= 0.0 {
+            position = controller.step(position, &jump, FIXED_TIMESTEP, &[&floor]);
+            frames_held += 1;
+            assert!(!controller.is_grounded() && frames_held < 10);
+        }
+        assert!(frames_held > 1);
+        assert!(position.y.abs() < 0.05);
+        assert_eq!(controller.velocity().y, controller.config.jump_speed);
+
+        position = controller.step(position, &jump, FIXED_TIMESTEP, &[&floor]);
+        assert!(position.y > 0.05);
+    }
+
+    #[test]
+    fn cannot_stand_up_under_low_ceiling() {
+        let floor = box_collider(Vec3::new(0.0, -0.5, 0.0), Vec3::new(10.0, 0.5, 10.0));
+        let ceiling = box_collider(Vec3::new(0.0, 1.7, 0.0), Vec3::new(10.0, 0.5, 10.0));
+        let mut controller = CharacterController::new(CharacterControllerConfig::default());
+
+        let crouch = CharacterInput { crouch: true, ..Default::default() };
+        let position = simulate(&mut controller, Vec3::new(0.0, 0.05, 0.0), crouch, 10, &[&floor, &ceiling]);
+        assert!(controller.is_crouching());
+
+        simulate(&mut controller, position, CharacterInput::default(), 10, &[&floor, &ceiling]);
+        assert!(controller.is_crouching());
+        assert!(controller.height() < controller.config.height);
+    }
+}
@@ -0,0 +1,438 @@
+use ultraviolet::{Mat4, Vec3};
+
+use crate::graphics::node_3d::Node3D;
+use crate::math::aabb_bouding_box::{AABBBoundingBox, Contact, SweepHit};
+use crate::math::capsule::{Capsule, closest_point_on_segment, closest_point_on_triangle};
+use crate::physics::broad_phase::DynamicAabbTree;
+use crate::physics::collision_shape::{CollisionShape, ConvexHull};
+
+const AXIS_EPSILON: f32 = 1e-6;
+const PARALLEL_EPSILON: f32 = 1e-4;
+const MAX_SWEEP_STEPS: usize = 256;
+const SWEEP_BISECTIONS: usize = 10;
+const CLOSEST_POINT_ITERATIONS: usize = 6;
+
+const WORLD_AXES: [Vec3; 3] = [Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, 1.0)];
+
+enum WorldShape {
+    Box(AABBBoundingBox),
+    Polyhedron { vertices: Vec<Vec3>, faces: Vec<[Vec3; 3]>, face_normals: Vec<Vec3>, edges: Vec<Vec3> },
+    Sphere { center: Vec3, radius: f32 },
+    Capsule { a: Vec3, b: Vec3, radius: f32 },
+    TriangleMesh { triangles: Vec<[Vec3; 3]>, tree: DynamicAabbTree<usize> },
+}
+
+// A collision shape baked into world space, static bodies rebuild theirs only when their node moves
+pub struct Collider {
+    shape: WorldShape,
+    bounding_box: AABBBoundingBox,
+}
+
+impl Collider {
+    pub fn new(shape: &CollisionShape, node3d: &Node3D) -> Self {
+        Self::with_matrix(shape, node3d.model_matrix())
+    }
+
+    pub fn with_matrix(shape: &CollisionShape, matrix: Mat4) -> Self {
+        // Spheres and capsules stay round, so they are scaled by the largest axis
+        let max_scale = (0..3).map(|i| matrix.cols[i].xyz().mag()).fold(0.0, f32::max);
+
+        match shape {
+            CollisionShape::Box { half_extents } => {
+                let corners: Vec<Vec3> = (0..8).map(|i| {
+                    let sign = Vec3::new(if i & 1 == 0 { -1.0 } else { 1.0 }, if i & 2 == 0 { -1.0 } else { 1.0 }, if i & 4 == 0 { -1.0 } else { 1.0 });
+                    matrix.transform_point3(*half_extents * sign)
+                }).collect();
+
+                let axis_aligned = WORLD_AXES.iter().all(|axis| {
+                    let world_axis = matrix.transform_vec3(*axis);
+                    (0..3).filter(|i| world_axis[*i].abs() > AXIS_EPSILON).count() <= 1
+                });
+
+                match ConvexHull::from_points(&corners) {
+                    Some(hull) if !axis_aligned => Self::from_hull(hull),
+                    _ => Self::from_bounding_box(AABBBoundingBox::from_points(&corners)),
+                }
+            }
+            CollisionShape::Sphere { radius } => {
+                let (center, radius) = (matrix.transform_point3(Vec3::default()), radius * max_scale);
+                Self { shape: WorldShape::Sphere { center, radius }, bounding_box: AABBBoundingBox::from_center_half_extents(center, Vec3::broadcast(radius)) }
+            }
+            CollisionShape::Capsule { radius, half_height } => {
+                let a = matrix.transform_point3(Vec3::new(0.0, *half_height, 0.0));
+                let b = matrix.transform_point3(Vec3::new(0.0, -half_height, 0.0));
+                let radius = radius * max_scale;
+                let bounding_box = AABBBoundingBox::from_min_max(a.min_by_component(b), a.max_by_component(b)).expanded(Vec3::broadcast(radius));
+                Self { shape: WorldShape::Capsule { a, b, radius }, bounding_box }
+            }
+            CollisionShape::ConvexHull(hull) => {
+                let vertices: Vec<Vec3> = hull.vertices().iter().map(|vertex| matrix.transform_point3(*vertex)).collect();
+
+                match ConvexHull::from_points(&vertices) {
+                    Some(hull) => Self::from_hull(hull),
+                    None => Self::from_bounding_box(AABBBoundingBox::from_points(&vertices)),
+                }
+            }
+            CollisionShape::TriangleMesh(mesh) => Self::from_triangles(mesh.triangles().iter().map(|triangle| triangle.map(|vertex| matrix.transform_point3(vertex))).collect()),
+        }
+    }
+
+    pub fn from_bounding_box(bounding_box: AABBBoundingBox) -> Self {
+        Self { shape: WorldShape::Box(bounding_box), bounding_box }
+    }
+
+    fn from_hull(hull: ConvexHull) -> Self {
+        let vertices = hull.vertices();
+        let faces: Vec<[Vec3; 3]> = hull.faces().iter().map(|face| face.map(|index| vertices[index])).collect();
+        let mut face_normals = Vec::new();
+        let mut edges = Vec::new();
+
+        for [a, b, c] in faces.iter().copied() {
+            push_unique_direction(&mut face_normals, (b - a).cross(c - a));
+            for (from, to) in [(a, b), (b, c), (c, a)] {
+                push_unique_direction(&mut edges, to - from);
+            }
+        }
+
+        Self { bounding_box: AABBBoundingBox::from_points(vertices), shape: WorldShape::Polyhedron { vertices: vertices.to_vec(), faces, face_normals, edges } }
+    }
+
+    fn from_triangles(triangles: Vec<[Vec3; 3]>) -> Self {
+        let mut tree = DynamicAabbTree::with_margin(0.0);
+        for (index, triangle) in triangles.iter().enumerate() {
+            tree.insert(AABBBoundingBox::from_points(triangle), index);
+        }
+
+        Self { bounding_box: AABBBoundingBox::from_points(triangles.iter().flatten()), shape: WorldShape::TriangleMesh { triangles, tree } }
+    }
+
+    pub fn bounding_box(&self) -> AABBBoundingBox {
+        self.bounding_box
+    }
+
+    // The contact normal points from the collider towards the box, moving the box by normal * depth separates them
+    pub fn contact(&self, bounding_box: AABBBoundingBox) -> Option<Contact> {
+        if !bounding_box.collides_with(self.bounding_box) {
+            return None;
+        }
+
+        match &self.shape {
+            WorldShape::Box(other) => bounding_box.contact(*other),
+            WorldShape::Polyhedron { vertices, face_normals, edges, .. } => {
+                let axes = WORLD_AXES.into_iter()
+                    .chain(face_normals.iter().copied())
+                    .chain(edges.iter().flat_map(|edge| WORLD_AXES.map(|axis| edge.cross(axis))));
+
+                separating_axis_contact(bounding_box, vertices, axes)
+            }
+            WorldShape::Sphere { center, radius } => sphere_contact(bounding_box, *center, *radius),
+            WorldShape::Capsule { a, b, radius } => {
+                let (min, max) = (bounding_box.min(), bounding_box.max());
+                let closest = closest_point_on_segment_to_shape(*a, *b, |point| point.clamped(min, max));
+
+                sphere_contact(bounding_box, closest, *radius)
+            }
+            WorldShape::TriangleMesh { triangles, tree } => {
+                tree.query(bounding_box).into_iter()
+                    .filter_map(|index| triangle_contact(bounding_box, &triangles[index]))
+                    .max_by(|a, b| a.depth.total_cmp(&b.depth))
+            }
+        }
+    }
+
+    // Same contract as AABBBoundingBox::sweep, shapes other than boxes are stepped conservatively and then bisected
+    pub fn sweep(&self, bounding_box: AABBBoundingBox, displacement: Vec3) -> Option<SweepHit> {
+        if let WorldShape::Box(other) = &self.shape {
+            return bounding_box.sweep(displacement, *other);
+        }
+
+        let swept_box = bounding_box.union(bounding_box.translated(displacement));
+        if !swept_box.collides_with(self.bounding_box) || self.contact(bounding_box).is_some() {
+            return None;
+        }
+
+        // Steps of half the smallest box extent cannot skip over a zero thickness triangle
+        let half_size = bounding_box.size() * 0.5;
+        let step_length = half_size.x.min(half_size.y).min(half_size.z) * 0.5;
+
+        conservative_sweep(displacement, step_length, |offset| self.contact(bounding_box.translated(offset)))
+    }
+
+    // The contact normal points from the collider towards the capsule
+    pub fn capsule_contact(&self, capsule: &Capsule) -> Option<Contact> {
+        if !capsule.bounding_box().collides_with(self.bounding_box) {
+            return None;
+        }
+
+        let (a, b) = capsule.segment();
+        let radius = capsule.radius;
+
+        match &self.shape {
+            WorldShape::Box(other) => {
+                let (min, max) = (other.min(), other.max());
+                let closest = closest_point_on_segment_to_shape(a, b, |point| point.clamped(min, max));
+
+                // A segment that passes through the box falls back to pushing out its bounding box
+                point_contact(closest, radius, closest.clamped(min, max)).or_else(|| capsule.bounding_box().contact(*other))
+            }
+            WorldShape::Polyhedron { faces, .. } => {
+                let closest = closest_point_on_segment_to_shape(a, b, |point| closest_point_on_polyhedron(faces, point));
+
+                point_contact(closest, radius, closest_point_on_polyhedron(faces, closest)).or_else(|| {
+                    // Push out through the face that needs the smallest move to clear the whole segment
+                    faces.iter().map(|face| {
+                        let normal = (face[1] - face[0]).cross(face[2] - face[0]).normalized();
+                        let depth = radius - normal.dot(a - face[0]).min(normal.dot(b - face[0]));
+                        Contact { normal, depth, point: closest }
+                    }).min_by(|x, y| x.depth.total_cmp(&y.depth))
+                })
+            }
+            WorldShape::Sphere { center, radius: sphere_radius } => point_contact(closest_point_on_segment(a, b, *center), radius + sphere_radius, *center),
+            WorldShape::Capsule { a: other_a, b: other_b, radius: other_radius } => {
+                let closest = closest_point_on_segment_to_shape(a, b, |point| closest_point_on_segment(*other_a, *other_b, point));
+
+                point_contact(closest, radius + other_radius, closest_point_on_segment(*other_a, *other_b, closest))
+            }
+            WorldShape::TriangleMesh { triangles, tree } => {
+                tree.query(capsule.bounding_box()).into_iter()
+                    .filter_map(|index| triangle_capsule_contact(capsule, &triangles[index]))
+                    .max_by(|x, y| x.depth.total_cmp(&y.depth))
+            }
+        }
+    }
+
+    pub fn capsule_sweep(&self, capsule: &Capsule, displacement: Vec3) -> Option<SweepHit> {
+        let swept_box = capsule.bounding_box().union(capsule.translated(displacement).bounding_box());
+        if !swept_box.collides_with(self.bounding_box) || self.capsule_contact(capsule).is_some() {
+            return None;
+        }
+
+        conservative_sweep(displacement, capsule.radius * 0.5, |offset| self.capsule_contact(&capsule.translated(offset)))
+    }
+}
+
+// Steps along the displacement until `contact` reports an overlap, then bisects towards the time of impact
+fn conservative_sweep(displacement: Vec3, step_length: f32, contact: impl Fn(Vec3) -> Option<Contact>) -> Option<SweepHit> {
+    let steps = ((displacement.mag() / step_length.max(f32::EPSILON)).ceil() as usize).clamp(1, MAX_SWEEP_STEPS);
+
+    let mut free_time = 0.0;
+    for step in 1..=steps {
+        let time = step as f32 / steps as f32;
+        let Some(mut hit_contact) = contact(displacement * time) else {
+            free_time = time;
+            continue;
+        };
+
+        let mut hit_time = time;
+        for _ in 0..SWEEP_BISECTIONS {
+            let middle = (free_time + hit_time) * 0.5;
+            match contact(displacement * middle) {
+                Some(middle_contact) => {
+                    hit_time = middle;
+                    hit_contact = middle_contact;
+                }
+                None => free_time = middle,
+            }
+        }
+
+        return Some(SweepHit { time: free_time, normal: hit_contact.normal });
+    }
+
+    None
+}
+
+// Alternates projections between the segment and a convex shape, converges on the closest pair
+fn closest_point_on_segment_to_shape(a: Vec3, b: Vec3, project: impl Fn(Vec3) -> Vec3) -> Vec3 {
+    let mut closest = closest_point_on_segment(a, b, project((a + b) * 0.5));
+    for _ in 0..CLOSEST_POINT_ITERATIONS {
+        closest = closest_point_on_segment(a, b, project(closest));
+    }
+
+    closest
+}
+
+// Points inside the polyhedron are their own closest point
+fn closest_point_on_polyhedron(faces: &[[Vec3; 3]], point: Vec3) -> Vec3 {
+    let inside = faces.iter().all(|face| (face[1] - face[0]).cross(face[2] - face[0]).dot(point - face[0]) <= 0.0);
+    if inside {
+        return point;
+    }
+
+    faces.iter()
+        .map(|face| closest_point_on_triangle(face, point))
+        .min_by(|x, y| (*x - point).mag_sq().total_cmp(&(*y - point).mag_sq()))
+        .unwrap_or(point)
+}
+
+fn point_contact(point: Vec3, radius: f32, closest: Vec3) -> Option<Contact> {
+    let offset = point - closest;
+    let distance_sq = offset.mag_sq();
+    if distance_sq < AXIS_EPSILON * AXIS_EPSILON || distance_sq >= radius * radius {
+        return None;
+    }
+
+    let distance = distance_sq.sqrt();
+    Some(Contact { normal: offset / distance, depth: radius - distance, point: closest })
+}
+
+fn triangle_capsule_contact(capsule: &Capsule, triangle: &[Vec3; 3]) -> Option<Contact> {
+    let (a, b) = capsule.segment();
+    let closest = closest_point_on_segment_to_shape(a, b, |point| closest_point_on_triangle(triangle, point));
+    let on_triangle = closest_point_on_triangle(triangle, closest);
+
+    if (closest - on_triangle).mag_sq() >= AXIS_EPSILON * AXIS_EPSILON {
+        return point_contact(closest, capsule.radius, on_triangle);
+    }
+
+    // The segment crosses the triangle, push out towards the side the capsule's center is on
+    let mut normal = (triangle[1] - triangle[0]).cross(triangle[2] - triangle[0]);
+    if normal.mag_sq() < AXIS_EPSILON * AXIS_EPSILON {
+        return None;
+    }
+    normal.normalize();
+    if normal.dot(capsule.center() - triangle[0]) < 0.0 {
+        normal = -normal;
+    }
+    let depth = capsule.radius - normal.dot(a - triangle[0]).min(normal.dot(b - triangle[0]));
+
+    Some(Contact { normal, depth, point: on_triangle })
+}
+
+fn push_unique_direction(directions: &mut Vec<Vec3>, direction: Vec3) {
+    if direction.mag_sq() < AXIS_EPSILON * AXIS_EPSILON {
+        return;
+    }
+
+    let direction = direction.normalized();
+    if directions.iter().all(|existing| existing.dot(direction).abs() < 1.0 - PARALLEL_EPSILON) {
+        directions.push(direction);
+    }
+}
+
+fn sphere_contact(bounding_box: AABBBoundingBox, center: Vec3, radius: f32) -> Option<Contact> {
+    let closest = center.clamped(bounding_box.min(), bounding_box.max());
+    let offset = closest - center;
+    let distance_sq = offset.mag_sq();
+
+    if distance_sq > f32::EPSILON {
+        let distance = distance_sq.sqrt();
+        return (distance < radius).then(|| Contact { normal: offset / distance, depth: radius - distance, point: closest });
+    }
+
+    // The center is inside the box, push the box out through the nearest face
+    let (min, max) = (bounding_box.min(), bounding_box.max());
+    let (normal, depth) = (0..3).flat_map(|axis| {
+        let mut normal = Vec3::default();
+        normal[axis] = 1.0;
+        [(normal, center[axis] - min[axis] + radius), (-normal, max[axis] - center[axis] + radius)]
+    }).min_by(|a, b| a.1.total_cmp(&b.1))?;
+
+    Some(Contact { normal, depth, point: center })
+}
+
+fn triangle_contact(bounding_box: AABBBoundingBox, triangle: &[Vec3; 3]) -> Option<Contact> {
+    let edges = [triangle[1] - triangle[0], triangle[2] - triangle[1], triangle[0] - triangle[2]];
+    let axes = WORLD_AXES.into_iter()
+        .chain([edges[0].cross(edges[1])])
+        .chain(edges.into_iter().flat_map(|edge| WORLD_AXES.map(|axis| edge.cross(axis))));
+
+    separating_axis_contact(bounding_box, triangle, axes)
+}
+
+// Finds the axis of least overlap between a box and a convex point set, or None when any axis separates them
+fn separating_axis_contact(bounding_box: AABBBoundingBox, points: &[Vec3], axes: impl Iterator<Item = Vec3>) -> Option<Contact> {
+    let center = bounding_box.center();
+    let half_size = bounding_box.size() * 0.5;
+    let mut best: Option<(Vec3, f32)> = None;
+
+    for axis in axes {
+        let length = axis.mag();
+        if length < AXIS_EPSILON {
+            continue;
+        }
+        let axis = axis / length;
+
+        let box_radius = half_size.dot(axis.abs());
+        let box_center = center.dot(axis);
+        let (points_min, points_max) = points.iter().fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), point| {
+            let projection = point.dot(axis);
+            (min.min(projection), max.max(projection))
+        });
+
+        let push_negative = box_center + box_radius - points_min;
+        let push_positive = points_max - (box_center - box_radius);
+        if push_negative <= 0.0 || push_positive <= 0.0 {
+            return None;
+        }
+
+        let (normal, depth) = if push_negative < push_positive { (-axis, push_negative) } else { (axis, push_positive) };
+        if best.is_none_or(|(_, best_depth)| depth < best_depth) {
+            best = Some((normal, depth));
+        }
+    }
+
+    let (normal, depth) = best?;
+    let point = center - normal * (half_size.dot(normal.abs()) - depth * 0.5);
+
+    Some(Contact { normal, depth, point })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::rotation::Rotation;
+    use crate::physics::collision_shape::TriangleMesh;
+
+    fn node_at(position: Vec3) -> Node3D {
+        Node3D { world_position: position, scale: Vec3::new(1.0, 1.0, 1.0), rotation: Rotation::default() }
+    }
+
+    fn ground() -> Collider {
+        let (a, b, c, d) = (Vec3::new(-10.0, 0.0, -10.0), Vec3::new(10.0, 0.0, -10.0), Vec3::new(10.0, 0.0, 10.0), Vec3::new(-10.0, 0.0, 10.0));
+        Collider::new(&CollisionShape::TriangleMesh(TriangleMesh::new(vec![[a, b, c], [a, c, d]])), &node_at(Vec3::default()))
+    }
+
+    #[test]
+    fn box_overlapping_triangle_mesh_is_pushed_up() {
+        let player = AABBBoundingBox::from_center_half_extents(Vec3::new(0.0, 0.4, 0.0), Vec3::new(0.5, 0.5, 0.5));
+        let contact = ground().contact(player).unwrap();
+
+        assert!((contact.normal - Vec3::new(0.0, 1.0, 0.0)).mag() < 1e-5);
+        assert!((contact.depth - 0.1).abs() < 1e-5);
+    }
+
+    #[test]
+    fn fast_box_does_not_tunnel_through_triangle_mesh() {
+        let player = AABBBoundingBox::from_center_half_extents(Vec3::new(0.0, 5.0, 0.0), Vec3::new(0.3, 0.9, 0.3));
+        let hit = ground().sweep(player, Vec3::new(0.0, -50.0, 0.0)).unwrap();
+
+        assert!((hit.normal - Vec3::new(0.0, 1.0, 0.0)).mag() < 1e-5);
+        assert!((player.translated(Vec3::new(0.0, -50.0 * hit.time, 0.0)).y_min).abs() < 0.01);
+    }
+
+    #[test]
+    fn rotated_box_is_tested_as_oriented_box() {
+        let node3d = Node3D { world_position: Vec3::default(), scale: Vec3::new(1.0, 1.0, 1.0), rotation: Rotation::from_rotation_y(45.0_f32.to_radians()) };
+        let collider = Collider::new(&CollisionShape::Box { half_extents: Vec3::new(1.0, 1.0, 1.0) }, &node3d);
+
+        // Inside the world bounding box corner but outside the rotated box itself
+        let near_corner = AABBBoundingBox::from_center_half_extents(Vec3::new(1.2, 0.0, 1.2), Vec3::broadcast(0.1));
+        assert!(collider.bounding_box().collides_with(near_corner));
+        assert!(collider.contact(near_corner).is_none());
+
+        let near_face = AABBBoundingBox::from_center_half_extents(Vec3::new(1.4, 0.0, 0.0), Vec3::broadcast(0.1));
+        assert!(collider.contact(near_face).is_some());
+    }
+
+    #[test]
+    fn sphere_and_capsule_contacts_point_away_from_shape() {
+        let sphere = Collider::new(&CollisionShape::Sphere { radius: 1.0 }, &node_at(Vec3::default()));
+        let capsule = Collider::new(&CollisionShape::Capsule { radius: 0.5, half_height: 1.0 }, &node_at(Vec3::default()));
+        let above = AABBBoundingBox::from_center_half_extents(Vec3::new(0.0, 1.4, 0.0), Vec3::broadcast(0.5));
+
+        assert!(sphere.contact(above).unwrap().normal.y > 0.99);
+        assert!(capsule.contact(above).unwrap().normal.y > 0.99);
+        assert!(capsule.contact(above.translated(Vec3::new(0.0, 0.7, 0.0))).is_none());
+    }
+}
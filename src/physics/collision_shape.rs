@@ -0,0 +1,190 @@
+use std::collections::HashSet;
+
+use ultraviolet::Vec3;
+
+use crate::graphics::model::Model;
+use crate::math::aabb_bouding_box::AABBBoundingBox;
+
+const HULL_EPSILON: f32 = 1e-5;
+
+// Shapes are described in the local space of the body's Node3D and centered on its origin
+pub enum CollisionShape {
+    Box { half_extents: Vec3 },
+    Sphere { radius: f32 },
+    Capsule { radius: f32, half_height: f32 }, // Along the local Y axis, half_height excludes the caps
+    ConvexHull(ConvexHull),
+    TriangleMesh(TriangleMesh),
+}
+
+impl CollisionShape {
+    pub fn convex_hull_from_model(model: &Model) -> Option<Self> {
+        let points: Vec<Vec3> = model.meshes().iter().flat_map(|mesh| mesh.vertices().iter().map(|vertex| vertex.position())).collect();
+
+        ConvexHull::from_points(&points).map(CollisionShape::ConvexHull)
+    }
+
+    // Cheapest fit for models that are roughly boxes, the model's bounds do not have to be centered on its origin
+    pub fn bounding_box_from_model(model: &Model) -> Self {
+        let bounding_box = model.bounding_box();
+        if bounding_box.center().mag_sq() < HULL_EPSILON * HULL_EPSILON {
+            return CollisionShape::Box { half_extents: bounding_box.size() * 0.5 };
+        }
+
+        let (min, max) = (bounding_box.min(), bounding_box.max());
+        let corners: Vec<Vec3> = (0..8).map(|i| Vec3::new(
+            if i & 1 == 0 { min.x } else { max.x },
+            if i & 2 == 0 { min.y } else { max.y },
+            if i & 4 == 0 { min.z } else { max.z },
+        )).collect();
+
+        match ConvexHull::from_points(&corners) {
+            Some(hull) => CollisionShape::ConvexHull(hull),
+            None => CollisionShape::Box { half_extents: bounding_box.size() * 0.5 },
+        }
+    }
+
+    pub fn triangle_mesh_from_model(model: &Model) -> Self {
+        CollisionShape::TriangleMesh(TriangleMesh::new(model.triangles().collect()))
+    }
+
+    pub fn local_bounding_box(&self) -> AABBBoundingBox {
+        match self {
+            CollisionShape::Box { half_extents } => AABBBoundingBox::from_center_half_extents(Vec3::default(), *half_extents),
+            CollisionShape::Sphere { radius } => AABBBoundingBox::from_center_half_extents(Vec3::default(), Vec3::broadcast(*radius)),
+            CollisionShape::Capsule { radius, half_height } => AABBBoundingBox::from_center_half_extents(Vec3::default(), Vec3::new(*radius, radius + half_height, *radius)),
+            CollisionShape::ConvexHull(hull) => AABBBoundingBox::from_points(hull.vertices()),
+            CollisionShape::TriangleMesh(mesh) => AABBBoundingBox::from_points(mesh.triangles().iter().flatten()),
+        }
+    }
+}
+
+pub struct ConvexHull {
+    vertices: Vec<Vec3>,
+    faces: Vec<[usize; 3]>, // Counter-clockwise when seen from outside
+}
+
+impl ConvexHull {
+    // Incremental hull, returns None when all the points are (nearly) coplanar
+    pub fn from_points(points: &[Vec3]) -> Option<Self> {
+        let [a, b, c, d] = initial_tetrahedron(points)?;
+        let mut faces = vec![[a, b, c], [a, c, d], [a, d, b], [b, d, c]];
+
+        // Orient the tetrahedron so that every face points away from its centroid
+        let centroid = (points[a] + points[b] + points[c] + points[d]) * 0.25;
+        for face in faces.iter_mut() {
+            if face_normal(points, *face).dot(centroid - points[face[0]]) > 0.0 {
+                face.swap(1, 2);
+            }
+        }
+
+        for (index, point) in points.iter().enumerate() {
+            let visible: Vec<bool> = faces.iter().map(|face| {
+                let normal = face_normal(points, *face);
+                normal.dot(*point - points[face[0]]) > HULL_EPSILON * normal.mag().max(1.0)
+            }).collect();
+
+            if !visible.contains(&true) {
+                continue;
+            }
+
+            // Edges of visible faces that are not shared with another visible face form the horizon
+            let visible_edges: HashSet<(usize, usize)> = faces.iter().zip(&visible)
+                .filter(|(_, visible)| **visible)
+                .flat_map(|(face, _)| [(face[0], face[1]), (face[1], face[2]), (face[2], face[0])])
+                .collect();
+
+            let horizon: Vec<(usize, usize)> = visible_edges.iter().copied().filter(|(from, to)| !visible_edges.contains(&(*to, *from))).collect();
+
+            let mut visible = visible.into_iter();
+            faces.retain(|_| !visible.next().unwrap_or(false));
+            faces.extend(horizon.into_iter().map(|(from, to)| [from, to, index]));
+        }
+
+        // Compact the vertex list down to the points that ended up on the hull
+        let mut remap = vec![usize::MAX; points.len()];
+        let mut vertices = Vec::new();
+        for face in faces.iter_mut() {
+            for index in face.iter_mut() {
+                if remap[*index] == usize::MAX {
+                    remap[*index] = vertices.len();
+                    vertices.push(points[*index]);
+                }
+                *index = remap[*index];
+            }
+        }
+
+        Some(Self { vertices, faces })
+    }
+
+    pub fn vertices(&self) -> &[Vec3] {
+        &self.vertices
+    }
+
+    pub fn faces(&self) -> &[[usize; 3]] {
+        &self.faces
+    }
+}
+
+fn face_normal(points: &[Vec3], face: [usize; 3]) -> Vec3 {
+    (points[face[1]] - points[face[0]]).cross(points[face[2]] - points[face[0]])
+}
+
+fn initial_tetrahedron(points: &[Vec3]) -> Option<[usize; 4]> {
+    let a = 0;
+    let b = (0..points.len()).max_by(|i, j| (points[*i] - points[a]).mag_sq().total_cmp(&(points[*j] - points[a]).mag_sq()))?;
+    let line = points[b] - points[a];
+    let c = (0..points.len()).max_by(|i, j| line.cross(points[*i] - points[a]).mag_sq().total_cmp(&line.cross(points[*j] - points[a]).mag_sq()))?;
+    let normal = line.cross(points[c] - points[a]);
+    let d = (0..points.len()).max_by(|i, j| normal.dot(points[*i] - points[a]).abs().total_cmp(&normal.dot(points[*j] - points[a]).abs()))?;
+
+    let scale = line.mag_sq().max(1.0);
+    if normal.mag_sq() < HULL_EPSILON * scale || normal.normalized().dot(points[d] - points[a]).abs() < HULL_EPSILON * scale.sqrt() {
+        return None;
+    }
+
+    Some([a, b, c, d])
+}
+
+pub struct TriangleMesh {
+    triangles: Vec<[Vec3; 3]>,
+}
+
+impl TriangleMesh {
+    pub fn new(triangles: Vec<[Vec3; 3]>) -> Self {
+        Self { triangles }
+    }
+
+    pub fn triangles(&self) -> &[[Vec3; 3]] {
+        &self.triangles
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convex_hull_drops_interior_points() {
+        let mut points = Vec::new();
+        for i in 0..8 {
+            points.push(Vec3::new((i & 1) as f32, ((i >> 1) & 1) as f32, ((i >> 2) & 1) as f32));
+        }
+        points.push(Vec3::new(0.5, 0.5, 0.5));
+        points.push(Vec3::new(0.25, 0.75, 0.5));
+
+        let hull = ConvexHull::from_points(&points).unwrap();
+        assert_eq!(hull.vertices().len(), 8);
+        assert_eq!(hull.faces().len(), 12);
+
+        let center = Vec3::new(0.5, 0.5, 0.5);
+        for face in hull.faces() {
+            assert!(face_normal(hull.vertices(), *face).dot(center - hull.vertices()[face[0]]) < 0.0);
+        }
+    }
+
+    #[test]
+    fn convex_hull_rejects_coplanar_points() {
+        let points = [Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0), Vec3::new(1.0, 0.0, 1.0)];
+        assert!(ConvexHull::from_points(&points).is_none());
+    }
+}
@@ -0,0 +1,120 @@
+use ultraviolet::Vec3;
+
+use crate::math::aabb_bouding_box::AABBBoundingBox;
+use crate::physics::collider::Collider;
+
+const MAX_SLIDE_ITERATIONS: usize = 4;
+const MAX_DEPENETRATION_ITERATIONS: usize = 4;
+const SKIN_WIDTH: f32 = 0.001;
+const GROUND_NORMAL_MIN_Y: f32 = 0.7;
+
+pub struct SlideResult {
+    pub displacement: Vec3,
+    pub velocity: Vec3,
+    pub on_ground: bool,
+    pub hit_ceiling: bool,
+    pub hit_wall: bool,
+}
+
+impl SlideResult {
+    fn register_normal(&mut self, normal: Vec3) {
+        if normal.y >= GROUND_NORMAL_MIN_Y {
+            self.on_ground = true;
+        } else if normal.y <= -GROUND_NORMAL_MIN_Y {
+            self.hit_ceiling = true;
+        } else {
+            self.hit_wall = true;
+        }
+        self.velocity = clip_velocity(self.velocity, normal);
+    }
+}
+
+fn clip_velocity(velocity: Vec3, normal: Vec3) -> Vec3 {
+    let into_surface = velocity.dot(normal);
+    if into_surface < 0.0 {
+        velocity - normal * into_surface
+    } else {
+        velocity
+    }
+}
+
+// Moves a box through static obstacles, sliding along every surface it touches instead of stopping or tunneling through it
+pub fn move_and_slide(bounding_box: AABBBoundingBox, velocity: Vec3, dt: f32, obstacles: &[&Collider]) -> SlideResult {
+    let mut result = SlideResult { displacement: Vec3::default(), velocity, on_ground: false, hit_ceiling: false, hit_wall: false };
+
+    // Push out of anything the box already overlaps before sweeping, pushing out of one triangle can push into another
+    for _ in 0..MAX_DEPENETRATION_ITERATIONS {
+        let mut penetrating = false;
+        for obstacle in obstacles {
+            if let Some(contact) = obstacle.contact(bounding_box.translated(result.displacement)) {
+                result.displacement += contact.normal * (contact.depth + SKIN_WIDTH);
+                result.register_normal(contact.normal);
+                penetrating = true;
+            }
+        }
+
+        if !penetrating {
+            break;
+        }
+    }
+
+    let mut remaining = result.velocity * dt;
+    for _ in 0..MAX_SLIDE_ITERATIONS {
+        if remaining.mag_sq() < f32::EPSILON * f32::EPSILON {
+            break;
+        }
+
+        let moved_box = bounding_box.translated(result.displacement);
+        let earliest_hit = obstacles.iter()
+            .filter_map(|obstacle| obstacle.sweep(moved_box, remaining))
+            .min_by(|a, b| a.time.total_cmp(&b.time));
+
+        match earliest_hit {
+            None => {
+                result.displacement += remaining;
+                break;
+            }
+            Some(hit) => {
+                result.displacement += remaining * hit.time + hit.normal * SKIN_WIDTH;
+                remaining = clip_velocity(remaining * (1.0 - hit.time), hit.normal);
+                result.register_normal(hit.normal);
+            }
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_box(center: Vec3) -> AABBBoundingBox {
+        AABBBoundingBox::from_center_half_extents(center, Vec3::broadcast(0.5))
+    }
+
+    #[test]
+    fn slides_along_a_wall_it_runs_into() {
+        let wall = Collider::from_bounding_box(AABBBoundingBox::from_min_max(Vec3::new(1.0, -5.0, -50.0), Vec3::new(2.0, 5.0, 50.0)));
+        let result = move_and_slide(unit_box(Vec3::default()), Vec3::new(2.0, 0.0, 2.0), 1.0, &[&wall]);
+
+        assert!(result.hit_wall && !result.on_ground);
+        assert!((result.displacement.x - 0.5).abs() < 0.01, "stops at the wall: {:?}", result.displacement);
+        assert!((result.displacement.z - 2.0).abs() < 1e-4, "keeps moving along it: {:?}", result.displacement);
+        assert_eq!(result.velocity, Vec3::new(0.0, 0.0, 2.0));
+    }
+
+    #[test]
+    fn lands_on_the_floor_and_is_pushed_out_of_it() {
+        let floor = Collider::from_bounding_box(AABBBoundingBox::from_min_max(Vec3::new(-5.0, -1.0, -5.0), Vec3::new(5.0, 0.0, 5.0)));
+
+        let falling = move_and_slide(unit_box(Vec3::new(0.0, 1.0, 0.0)), Vec3::new(0.0, -2.0, 0.0), 1.0, &[&floor]);
+        assert!(falling.on_ground && !falling.hit_wall);
+        assert!((falling.displacement.y + 0.5).abs() < 0.01);
+        assert_eq!(falling.velocity, Vec3::default());
+
+        let sunk = move_and_slide(unit_box(Vec3::new(0.0, 0.3, 0.0)), Vec3::default(), 1.0, &[&floor]);
+        assert!(sunk.on_ground);
+        assert!((sunk.displacement.y - 0.2).abs() < 0.01);
+    }
+}
@@ -0,0 +1,514 @@
+pub mod broad_phase;
+pub mod character_controller;
+pub mod collider;
+pub mod collision_shape;
+pub mod kinematic;
+pub mod query;
+
+use std::collections::{HashMap, HashSet};
+
+use ultraviolet::Vec3;
+
+use crate::assets::handle::Handle;
+use crate::ecs::components::{RigidBody, StaticCollider};
+use crate::ecs::world::{Entity, World};
+use crate::graphics::model::Model;
+use crate::graphics::rigid_body_3d::RigidBody3D;
+use crate::graphics::scene_graph::{NodeId, SceneGraph};
+use crate::graphics::static_body_3d::StaticBody3D;
+use crate::math::aabb_bouding_box::Contact;
+use crate::physics::broad_phase::{DynamicAabbTree, ProxyId};
+use crate::physics::collider::Collider;
+
+pub const FIXED_TIMESTEP: f32 = 1.0 / 60.0;
+pub const GRAVITY: Vec3 = Vec3::new(0.0, -9.81, 0.0);
+pub const TERMINAL_VELOCITY: f32 = 55.0;
+
+const POSITION_CORRECTION_SLOP: f32 = 0.005;
+const POSITION_CORRECTION_PERCENT: f32 = 0.8;
+const ROLLING_RESISTANCE: f32 = 4.0;
+const RESTITUTION_VELOCITY_THRESHOLD: f32 = 0.5;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum BodyHandle {
+    Static(usize),
+    Rigid(usize),
+    StaticEntity(Entity), // ECS entity with a StaticCollider
+    RigidEntity(Entity), // ECS entity with a RigidBody
+}
+
+// Broad phase proxies of the ECS world's bodies, keyed by their StaticEntity/RigidEntity handle
+pub type EntityProxies = HashMap<BodyHandle, ProxyId>;
+
+pub(crate) fn pair_mut<T>(items: &mut [T], a: usize, b: usize) -> (&mut T, &mut T) {
+    if a < b {
+        let (head, tail) = items.split_at_mut(b);
+        (&mut head[a], &mut tail[0])
+    } else {
+        let (head, tail) = items.split_at_mut(a);
+        (&mut tail[0], &mut head[b])
+    }
+}
+
+// Moves the colliders and proxies of static bodies whose node moved, was reparented or has an ancestor that moved
+pub fn sync_static_bodies(static_bodies: &mut [StaticBody3D], static_proxies: &[ProxyId], static_nodes: &[NodeId], scene_graph: &SceneGraph, broad_phase: &mut DynamicAabbTree<BodyHandle>) {
+    for ((body, proxy), node) in static_bodies.iter_mut().zip(static_proxies).zip(static_nodes) {
+        if !scene_graph.contains(*node) {
+            continue;
+        }
+
+        let world_matrix = scene_graph.world_matrix(*node);
+        if world_matrix != body.world_matrix() {
+            body.set_world_matrix(world_matrix);
+            broad_phase.move_proxy(*proxy, body.bounding_box(), Vec3::default());
+        }
+    }
+}
+
+// Rebuilds the bodies and colliders whose model was reloaded, the proxies of the world's bodies follow on the next sync_entity_proxies
+pub fn refresh_models(reloaded_keys: &[String], static_bodies: &mut [StaticBody3D], static_proxies: &[ProxyId], rigid_bodies: &mut [RigidBody3D], rigid_proxies: &[ProxyId], world: &World, broad_phase: &mut DynamicAabbTree<BodyHandle>) {
+    for (body, proxy) in static_bodies.iter_mut().zip(static_proxies) {
+        if was_reloaded(&body.model, reloaded_keys) {
+            body.refresh_model();
+            broad_phase.move_proxy(*proxy, body.bounding_box(), Vec3::default());
+        }
+    }
+
+    for (body, proxy) in rigid_bodies.iter_mut().zip(rigid_proxies) {
+        if was_reloaded(&body.model, reloaded_keys) {
+            body.refresh_model();
+            broad_phase.move_proxy(*proxy, body.world_bounding_box(), Vec3::default());
+        }
+    }
+
+    world.query_mut::<StaticCollider>(|_, static_collider| {
+        if static_collider.model().is_some_and(|model| was_reloaded(model, reloaded_keys)) {
+            static_collider.refresh_model();
+        }
+    });
+    world.query_mut::<RigidBody>(|_, rigid_body| {
+        if was_reloaded(&rigid_body.body.model, reloaded_keys) {
+            rigid_body.body.refresh_model();
+        }
+    });
+}
+
+fn was_reloaded(model: &Handle<Model>, reloaded_keys: &[String]) -> bool {
+    model.key().is_some_and(|key| reloaded_keys.iter().any(|reloaded| reloaded == key))
+}
+
+// Gives new StaticCollider and RigidBody components a proxy, moves the existing ones and removes those whose entity or component is gone
+pub fn sync_entity_proxies(world: &World, entity_proxies: &mut EntityProxies, broad_phase: &mut DynamicAabbTree<BodyHandle>, dt: f32) {
+    let mut bodies = Vec::new();
+    world.query::<StaticCollider>(|entity, static_collider| bodies.push((BodyHandle::StaticEntity(entity), static_collider.collider().bounding_box(), Vec3::default())));
+    world.query::<RigidBody>(|entity, rigid_body| bodies.push((BodyHandle::RigidEntity(entity), rigid_body.body.world_bounding_box(), rigid_body.body.linear_velocity * dt)));
+
+    let alive: HashSet<BodyHandle> = bodies.iter().map(|(handle, _, _)| *handle).collect();
+    entity_proxies.retain(|handle, proxy| {
+        if !alive.contains(handle) {
+            broad_phase.remove(*proxy);
+        }
+        alive.contains(handle)
+    });
+
+    for (handle, bounding_box, displacement) in bodies {
+        match entity_proxies.get(&handle) {
+            Some(proxy) => {
+                broad_phase.move_proxy(*proxy, bounding_box, displacement);
+            }
+            None => {
+                entity_proxies.insert(handle, broad_phase.insert(bounding_box, handle));
+            }
+        }
+    }
+}
+
+struct SimulatedBody<'a> {
+    handle: BodyHandle,
+    proxy: ProxyId,
+    body: &'a mut RigidBody3D,
+}
+
+// One step for every body in the broad phase, so the scene's bodies and the world's RigidBody and StaticCollider components all collide with each other
+pub fn step_rigid_bodies(rigid_bodies: &mut [RigidBody3D], rigid_proxies: &[ProxyId], static_bodies: &[StaticBody3D], world: &World, entity_proxies: &mut EntityProxies, broad_phase: &mut DynamicAabbTree<BodyHandle>, dt: f32) {
+    sync_entity_proxies(world, entity_proxies, broad_phase, dt);
+
+    let static_collider_storage = world.storage::<StaticCollider>();
+    let mut static_colliders: HashMap<BodyHandle, &Collider> = static_bodies.iter().enumerate()
+        .map(|(i, body)| (BodyHandle::Static(i), body.collider()))
+        .collect();
+    if let Some(storage) = static_collider_storage.as_ref() {
+        static_colliders.extend(storage.iter().map(|(entity, static_collider)| (BodyHandle::StaticEntity(entity), static_collider.collider())));
+    }
+
+    let mut rigid_body_storage = world.storage_mut::<RigidBody>();
+    let mut bodies: Vec<SimulatedBody> = rigid_bodies.iter_mut().zip(rigid_proxies).enumerate()
+        .map(|(i, (body, proxy))| SimulatedBody { handle: BodyHandle::Rigid(i), proxy: *proxy, body })
+        .collect();
+    if let Some(storage) = rigid_body_storage.as_mut() {
+        bodies.extend(storage.iter_mut().map(|(entity, rigid_body)| {
+            let handle = BodyHandle::RigidEntity(entity);
+            SimulatedBody { handle, proxy: entity_proxies[&handle], body: &mut rigid_body.body }
+        }));
+    }
+
+    solve(&mut bodies, &static_colliders, broad_phase, dt);
+}
+
+fn solve(bodies: &mut [SimulatedBody], static_colliders: &HashMap<BodyHandle, &Collider>, broad_phase: &mut DynamicAabbTree<BodyHandle>, dt: f32) {
+    for simulated in bodies.iter_mut() {
+        simulated.body.integrate(dt, GRAVITY);
+    }
+    sync_simulated_proxies(bodies, broad_phase, dt);
+
+    let indices: HashMap<BodyHandle, usize> = bodies.iter().enumerate().map(|(i, simulated)| (simulated.handle, i)).collect();
+    for i in 0..bodies.len() {
+        if bodies[i].body.is_static() || bodies[i].body.is_sleeping() {
+            continue;
+        }
+
+        for handle in broad_phase.query(bodies[i].body.world_bounding_box()) {
+            if let Some(collider) = static_colliders.get(&handle) {
+                let body = &mut *bodies[i].body;
+                if let Some(contact) = collider.contact(body.world_bounding_box()) {
+                    resolve_static_contact(body, contact, dt);
+                }
+            } else if let Some(&other) = indices.get(&handle) {
+                // Pairs of awake bodies are visited from both sides, only resolve them once
+                if other == i || (other < i && !bodies[other].body.is_static() && !bodies[other].body.is_sleeping()) {
+                    continue;
+                }
+
+                let (a, b) = pair_mut(bodies, i, other);
+                resolve_body_contact(a.body, b.body);
+            }
+        }
+    }
+
+    for simulated in bodies.iter_mut() {
+        simulated.body.update_sleep_state(dt);
+    }
+    sync_simulated_proxies(bodies, broad_phase, dt);
+}
+
+fn sync_simulated_proxies(bodies: &[SimulatedBody], broad_phase: &mut DynamicAabbTree<BodyHandle>, dt: f32) {
+    for simulated in bodies {
+        broad_phase.move_proxy(simulated.proxy, simulated.body.world_bounding_box(), simulated.body.linear_velocity * dt);
+    }
+}
+
+pub(crate) fn resolve_static_contact(body: &mut RigidBody3D, contact: Contact, dt: f32) {
+    resolve_contact(body, None, contact);
+    body.angular_velocity *= 1.0 / (1.0 + ROLLING_RESISTANCE * body.friction * dt);
+}
+
+// Wakes `b` up when `a` pushes into it
+pub(crate) fn resolve_body_contact(a: &mut RigidBody3D, b: &mut RigidBody3D) {
+    if let Some(contact) = a.world_bounding_box().contact(b.world_bounding_box()) {
+        if b.is_sleeping() {
+            b.wake_up();
+        }
+        resolve_contact(a, Some(b), contact);
+    }
+}
+
+fn effective_mass_term(body: &RigidBody3D, r: Vec3, direction: Vec3) -> f32 {
+    body.inverse_mass() + direction.dot((body.inverse_inertia() * r.cross(direction)).cross(r))
+}
+
+// The contact normal points from `b` (or the static geometry when `b` is None) towards `a`
+fn resolve_contact(a: &mut RigidBody3D, mut b: Option<&mut RigidBody3D>, contact: Contact) {
+    let Contact { normal, depth, point } = contact;
+    let inverse_mass_a = a.inverse_mass();
+    let inverse_mass_b = b.as_ref().map_or(0.0, |b| b.inverse_mass());
+    let inverse_mass_sum = inverse_mass_a + inverse_mass_b;
+    if inverse_mass_sum == 0.0 {
+        return;
+    }
+
+    let correction = normal * ((depth - POSITION_CORRECTION_SLOP).max(0.0) / inverse_mass_sum * POSITION_CORRECTION_PERCENT);
+    a.node3d.world_position += correction * inverse_mass_a;
+    if let Some(b) = b.as_mut() {
+        b.node3d.world_position -= correction * inverse_mass_b;
+    }
+
+    let r_a = point - a.center_of_mass();
+    let r_b = b.as_ref().map_or(Vec3::default(), |b| point - b.center_of_mass());
+    let relative_velocity = |a: &RigidBody3D, b: &Option<&mut RigidBody3D>| a.velocity_at_point(point) - b.as_ref().map_or(Vec3::default(), |b| b.velocity_at_point(point));
+
+    let velocity = relative_velocity(a, &b);
+    let normal_velocity = velocity.dot(normal);
+    if normal_velocity >= 0.0 {
+        return;
+    }
+
+    let restitution = if -normal_velocity < RESTITUTION_VELOCITY_THRESHOLD {
+        0.0
+    } else {
+        b.as_ref().map_or(a.restitution, |b| a.restitution.min(b.restitution))
+    };
+    let normal_mass = effective_mass_term(a, r_a, normal) + b.as_ref().map_or(0.0, |b| effective_mass_term(b, r_b, normal));
+    let normal_impulse = -(1.0 + restitution) * normal_velocity / normal_mass;
+
+    a.apply_contact_impulse(normal * normal_impulse, point);
+    if let Some(b) = b.as_mut() {
+        b.apply_contact_impulse(normal * -normal_impulse, point);
+    }
+
+    let velocity = relative_velocity(a, &b);
+    let tangent_velocity = velocity - normal * velocity.dot(normal);
+    if tangent_velocity.mag_sq() < f32::EPSILON {
+        return;
+    }
+
+    let tangent = tangent_velocity.normalized();
+    let friction = b.as_ref().map_or(a.friction, |b| (a.friction * b.friction).sqrt());
+    let tangent_mass = effective_mass_term(a, r_a, tangent) + b.as_ref().map_or(0.0, |b| effective_mass_term(b, r_b, tangent));
+    let tangent_impulse = (-velocity.dot(tangent) / tangent_mass).clamp(-friction * normal_impulse, friction * normal_impulse);
+
+    a.apply_contact_impulse(tangent * tangent_impulse, point);
+    if let Some(b) = b.as_mut() {
+        b.apply_contact_impulse(tangent * -tangent_impulse, point);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ultraviolet::Mat4;
+
+    use super::*;
+    use crate::assets::handle::AssetCache;
+    use crate::graphics::node_3d::Node3D;
+    use crate::math::aabb_bouding_box::AABBBoundingBox;
+    use crate::math::rotation::Rotation;
+    use crate::physics::collision_shape::CollisionShape;
+    use crate::timestep::FixedTimestep;
+
+    // Returns how many steps ran and the final position and velocity of every body
+    fn simulate(mut timestep: FixedTimestep, frame_times: &[f32]) -> (u32, Vec<(Vec3, Vec3)>) {
+        let model = Handle::new(Model::from_meshes(Vec::new()));
+
+        // Wide enough that the spinning boxes knocking each other apart stay on it
+        let static_bodies = vec![StaticBody3D::new(node3d(Vec3::new(0.0, -0.5, 0.0)), model.clone(), CollisionShape::Box { half_extents: Vec3::new(50.0, 0.5, 50.0) })];
+        let mut broad_phase = DynamicAabbTree::new();
+        broad_phase.insert(static_bodies[0].bounding_box(), BodyHandle::Static(0));
+
+        let mut rigid_bodies = Vec::new();
+        let mut rigid_proxies = Vec::new();
+        for i in 0..4 {
+            let mut body = RigidBody3D::new(node3d(Vec3::new(0.2 * i as f32, 1.0 + 1.2 * i as f32, 0.0)), model.clone(), AABBBoundingBox::from_center_half_extents(Vec3::default(), Vec3::broadcast(0.5)), 1.0);
+            body.angular_velocity = Vec3::new(0.3, 1.0, 0.0);
+            rigid_proxies.push(broad_phase.insert(body.world_bounding_box(), BodyHandle::Rigid(i)));
+            rigid_bodies.push(body);
+        }
+
+        let mut steps = 0;
+        for frame_time in frame_times {
+            for _ in 0..timestep.advance(*frame_time) {
+                step_rigid_bodies(&mut rigid_bodies, &rigid_proxies, &static_bodies, &World::new(), &mut EntityProxies::new(), &mut broad_phase, timestep.step());
+                steps += 1;
+            }
+        }
+
+        (steps, rigid_bodies.iter().map(|body| (body.node3d.world_position, body.linear_velocity)).collect())
+    }
+
+    fn node3d(position: Vec3) -> Node3D {
+        Node3D { world_position: position, scale: Vec3::new(1.0, 1.0, 1.0), rotation: Rotation::default() }
+    }
+
+    fn cube(position: Vec3, mass: f32) -> RigidBody3D {
+        RigidBody3D::new(node3d(position), Handle::new(Model::from_meshes(Vec::new())), AABBBoundingBox::from_center_half_extents(Vec3::default(), Vec3::broadcast(0.5)), mass)
+    }
+
+    #[test]
+    fn static_bodies_follow_their_scene_graph_node() {
+        let mut bodies = vec![StaticBody3D::new(node3d(Vec3::default()), Handle::new(Model::from_meshes(Vec::new())), CollisionShape::Box { half_extents: Vec3::broadcast(0.5) })];
+        let mut broad_phase = DynamicAabbTree::new();
+        let proxies = vec![broad_phase.insert(bodies[0].bounding_box(), BodyHandle::Static(0))];
+
+        let mut scene_graph = SceneGraph::new();
+        let parent = scene_graph.add_node("parent", node3d(Vec3::default()), None, None);
+        let other_parent = scene_graph.add_node("other_parent", node3d(Vec3::new(0.0, 0.0, -20.0)), None, None);
+        let nodes = vec![scene_graph.add_node("body", bodies[0].node3d, None, Some(parent))];
+        let found_at = |broad_phase: &DynamicAabbTree<BodyHandle>, position: Vec3| !broad_phase.query(AABBBoundingBox::from_center_half_extents(position, Vec3::broadcast(0.1))).is_empty();
+
+        sync_static_bodies(&mut bodies, &proxies, &nodes, &scene_graph, &mut broad_phase);
+        assert!(found_at(&broad_phase, Vec3::default()));
+
+        // Moving an ancestor moves the collider and its proxy
+        scene_graph.set_local_transform(parent, node3d(Vec3::new(10.0, 0.0, 0.0)));
+        sync_static_bodies(&mut bodies, &proxies, &nodes, &scene_graph, &mut broad_phase);
+        assert_eq!(bodies[0].bounding_box().center(), Vec3::new(10.0, 0.0, 0.0));
+        assert!(bodies[0].collider().contact(AABBBoundingBox::from_center_half_extents(Vec3::new(10.0, 0.9, 0.0), Vec3::broadcast(0.5))).is_some());
+        assert!(found_at(&broad_phase, Vec3::new(10.0, 0.0, 0.0)) && !found_at(&broad_phase, Vec3::default()));
+
+        assert!(scene_graph.set_parent(nodes[0], Some(other_parent)));
+        sync_static_bodies(&mut bodies, &proxies, &nodes, &scene_graph, &mut broad_phase);
+        assert_eq!(bodies[0].bounding_box().center(), Vec3::new(0.0, 0.0, -20.0));
+        assert!(found_at(&broad_phase, Vec3::new(0.0, 0.0, -20.0)) && !found_at(&broad_phase, Vec3::new(10.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn reloaded_models_refresh_rigid_bodies_and_entity_colliders() {
+        let mut models = AssetCache::new();
+        let small = AABBBoundingBox::from_center_half_extents(Vec3::default(), Vec3::broadcast(0.5));
+        let large = AABBBoundingBox::from_center_half_extents(Vec3::default(), Vec3::broadcast(1.0));
+        let model = models.get_or_load("res/models/crate.obj", |_| Ok(Model::with_bounding_box(small))).unwrap();
+        let scaled = |bounding_box: AABBBoundingBox| bounding_box.transformed(Mat4::from_nonuniform_scale(Vec3::broadcast(2.0)));
+
+        let mut scaled_node3d = node3d(Vec3::new(0.0, 5.0, 0.0));
+        scaled_node3d.scale = Vec3::broadcast(2.0);
+        let mut rigid_bodies = vec![
+            RigidBody3D::with_model_bounds(scaled_node3d, model.clone(), 1.0),
+            RigidBody3D::new(node3d(Vec3::new(10.0, 5.0, 0.0)), model.clone(), small, 1.0),
+            RigidBody3D::with_model_bounds(node3d(Vec3::new(20.0, 5.0, 0.0)), Handle::new(Model::with_bounding_box(small)), 1.0),
+        ];
+        let mut broad_phase = DynamicAabbTree::new();
+        let rigid_proxies: Vec<ProxyId> = rigid_bodies.iter().enumerate().map(|(i, body)| broad_phase.insert(body.world_bounding_box(), BodyHandle::Rigid(i))).collect();
+        let inverse_inertia = rigid_bodies[0].inverse_inertia();
+
+        let mut world = World::new();
+        let model_floor = world.spawn();
+        world.insert(model_floor, StaticCollider::with_model_collision(model.clone(), &node3d(Vec3::default())));
+        let box_floor = world.spawn();
+        world.insert(box_floor, StaticCollider::new(CollisionShape::Box { half_extents: Vec3::new(5.0, 0.5, 5.0) }, &node3d(Vec3::default())));
+        let barrel = world.spawn();
+        world.insert(barrel, RigidBody::new(RigidBody3D::with_model_bounds(scaled_node3d, model.clone(), 1.0)));
+
+        model.replace(Model::with_bounding_box(large));
+        refresh_models(&["res/models/other.obj".to_owned()], &mut [], &[], &mut rigid_bodies, &rigid_proxies, &world, &mut broad_phase);
+        assert_eq!(rigid_bodies[0].bounding_box, scaled(small));
+
+        refresh_models(&["res/models/crate.obj".to_owned()], &mut [], &[], &mut rigid_bodies, &rigid_proxies, &world, &mut broad_phase);
+
+        // Only bodies whose bounds came from the reloaded model change
+        assert_eq!(rigid_bodies[0].bounding_box, scaled(large));
+        assert_ne!(rigid_bodies[0].inverse_inertia(), inverse_inertia);
+        assert_eq!(rigid_bodies[1].bounding_box, small);
+        assert_eq!(rigid_bodies[2].bounding_box, small);
+        assert!(broad_phase.query(AABBBoundingBox::from_center_half_extents(Vec3::new(0.0, 6.8, 0.0), Vec3::broadcast(0.1))).contains(&BodyHandle::Rigid(0)));
+        assert_eq!(world.get::<RigidBody>(barrel).unwrap().body.bounding_box, scaled(large));
+
+        // Model colliders are rebuilt from the new triangles, the others keep their shape
+        assert!(matches!(world.get::<StaticCollider>(model_floor).unwrap().shape(), CollisionShape::TriangleMesh(_)));
+        assert!(matches!(world.get::<StaticCollider>(box_floor).unwrap().shape(), CollisionShape::Box { .. }));
+    }
+
+    #[test]
+    fn scene_and_entity_bodies_collide_with_each_other() {
+        let model = Handle::new(Model::from_meshes(Vec::new()));
+        let floor_shape = || CollisionShape::Box { half_extents: Vec3::new(5.0, 0.5, 5.0) };
+        let static_bodies = vec![StaticBody3D::new(node3d(Vec3::new(0.0, -0.5, 0.0)), model.clone(), floor_shape())];
+        let mut broad_phase = DynamicAabbTree::new();
+        broad_phase.insert(static_bodies[0].bounding_box(), BodyHandle::Static(0));
+
+        // A scene body on the scene floor with an entity body on top, and a scene body on an entity floor
+        let mut rigid_bodies = vec![cube(Vec3::new(0.0, 1.0, 0.0), 1.0), cube(Vec3::new(20.0, 1.0, 0.0), 1.0)];
+        let rigid_proxies: Vec<ProxyId> = rigid_bodies.iter().enumerate().map(|(i, body)| broad_phase.insert(body.world_bounding_box(), BodyHandle::Rigid(i))).collect();
+
+        let mut world = World::new();
+        let entity_floor = world.spawn();
+        world.insert(entity_floor, StaticCollider::new(floor_shape(), &node3d(Vec3::new(20.0, -0.5, 0.0))));
+        let entity_body = world.spawn();
+        world.insert(entity_body, RigidBody::new(cube(Vec3::new(0.0, 2.5, 0.0), 1.0)));
+
+        let mut entity_proxies = EntityProxies::new();
+        for _ in 0..180 {
+            step_rigid_bodies(&mut rigid_bodies, &rigid_proxies, &static_bodies, &world, &mut entity_proxies, &mut broad_phase, FIXED_TIMESTEP);
+        }
+
+        assert!((rigid_bodies[0].node3d.world_position.y - 0.5).abs() < 0.05, "{:?}", rigid_bodies[0].node3d);
+        assert!((rigid_bodies[1].node3d.world_position.y - 0.5).abs() < 0.05, "{:?}", rigid_bodies[1].node3d);
+        let entity_height = world.get::<RigidBody>(entity_body).unwrap().body.node3d.world_position.y;
+        assert!((entity_height - 1.5).abs() < 0.05, "entity body rests at {entity_height}");
+
+        // Despawned entities leave the broad phase on the next step
+        assert_eq!(broad_phase.len(), 5);
+        world.despawn(entity_floor);
+        step_rigid_bodies(&mut rigid_bodies, &rigid_proxies, &static_bodies, &world, &mut entity_proxies, &mut broad_phase, FIXED_TIMESTEP);
+        assert_eq!(broad_phase.len(), 4);
+        assert!(!entity_proxies.contains_key(&BodyHandle::StaticEntity(entity_floor)));
+    }
+
+    #[test]
+    fn resting_contact_neither_bounces_nor_sinks() {
+        let floor = StaticBody3D::new(node3d(Vec3::default()), Handle::new(Model::from_meshes(Vec::new())), CollisionShape::Box { half_extents: Vec3::new(5.0, 0.5, 5.0) });
+        let mut broad_phase = DynamicAabbTree::new();
+        broad_phase.insert(floor.bounding_box(), BodyHandle::Static(0));
+
+        let mut bodies = vec![cube(Vec3::new(0.0, 1.0, 0.0), 1.0)];
+        bodies[0].restitution = 1.0;
+        let proxies = vec![broad_phase.insert(bodies[0].world_bounding_box(), BodyHandle::Rigid(0))];
+        for _ in 0..120 {
+            step_rigid_bodies(&mut bodies, &proxies, std::slice::from_ref(&floor), &World::new(), &mut EntityProxies::new(), &mut broad_phase, FIXED_TIMESTEP);
+            let body = &bodies[0];
+            assert!((body.node3d.world_position.y - 1.0).abs() < 0.02, "{:?}", body.node3d.world_position);
+            assert!(body.linear_velocity.y.abs() <= -GRAVITY.y * FIXED_TIMESTEP + 1e-4, "{:?}", body.linear_velocity);
+        }
+    }
+
+    #[test]
+    fn rotated_bodies_rest_on_their_rotated_bounds() {
+        let floor = StaticBody3D::new(node3d(Vec3::default()), Handle::new(Model::from_meshes(Vec::new())), CollisionShape::Box { half_extents: Vec3::new(5.0, 0.5, 5.0) });
+        let mut broad_phase = DynamicAabbTree::new();
+        broad_phase.insert(floor.bounding_box(), BodyHandle::Static(0));
+
+        // Standing on an edge, the box reaches half a diagonal below its center
+        let mut bodies = vec![cube(Vec3::new(0.0, 1.5, 0.0), 1.0)];
+        bodies[0].node3d.rotation = Rotation::from_rotation_z(std::f32::consts::FRAC_PI_4);
+        let proxies = vec![broad_phase.insert(bodies[0].world_bounding_box(), BodyHandle::Rigid(0))];
+        for _ in 0..120 {
+            step_rigid_bodies(&mut bodies, &proxies, std::slice::from_ref(&floor), &World::new(), &mut EntityProxies::new(), &mut broad_phase, FIXED_TIMESTEP);
+        }
+
+        let body = &bodies[0];
+        assert!((body.node3d.world_position.y - (0.5 + 0.5 * std::f32::consts::SQRT_2)).abs() < 0.02, "{:?}", body.node3d.world_position);
+        assert_eq!(body.world_bounding_box(), body.node3d.world_bounding_box(body.bounding_box));
+    }
+
+    #[test]
+    fn head_on_elastic_collision_conserves_momentum_and_energy() {
+        let mut a = cube(Vec3::new(-0.45, 0.0, 0.0), 1.0);
+        let mut b = cube(Vec3::new(0.45, 0.0, 0.0), 2.0);
+        a.linear_velocity = Vec3::new(3.0, 0.0, 0.0);
+        b.linear_velocity = Vec3::new(-1.0, 0.0, 0.0);
+        a.restitution = 1.0;
+        b.restitution = 1.0;
+
+        let momentum = |a: &RigidBody3D, b: &RigidBody3D| a.linear_velocity * a.mass() + b.linear_velocity * b.mass();
+        let energy = |a: &RigidBody3D, b: &RigidBody3D| 0.5 * (a.mass() * a.linear_velocity.mag_sq() + b.mass() * b.linear_velocity.mag_sq());
+        let (momentum_before, energy_before) = (momentum(&a, &b), energy(&a, &b));
+
+        resolve_body_contact(&mut a, &mut b);
+
+        assert!((momentum(&a, &b) - momentum_before).mag() < 1e-5);
+        assert!((energy(&a, &b) - energy_before).abs() < 1e-4);
+        assert!((a.linear_velocity - Vec3::new(-7.0 / 3.0, 0.0, 0.0)).mag() < 1e-5, "{:?}", a.linear_velocity);
+        assert!((b.linear_velocity - Vec3::new(5.0 / 3.0, 0.0, 0.0)).mag() < 1e-5, "{:?}", b.linear_velocity);
+        assert!(a.node3d.world_position.x < -0.45 && b.node3d.world_position.x > 0.45, "overlap is pushed apart");
+    }
+
+    #[test]
+    fn deterministic_runs_match_regardless_of_frame_times() {
+        // 240 steps worth of time cut into frames very differently, the extra half step keeps rounding from dropping the last one
+        let step = FIXED_TIMESTEP;
+        let mut steady = vec![step; 240];
+        let mut jittery: Vec<f32> = [0.25, 2.5, 0.75, 0.5].iter().cycle().take(240).map(|fraction| fraction * step).collect();
+        let mut hitchy: Vec<f32> = (0..20).flat_map(|_| std::iter::repeat_n(0.1 * step, 10).chain(std::iter::once(11.0 * step))).collect();
+        for frame_times in [&mut steady, &mut jittery, &mut hitchy] {
+            frame_times.push(0.5 * step);
+        }
+
+        let (steps, states) = simulate(FixedTimestep::new(step, 16), &steady);
+        assert_eq!(steps, 240);
+        assert_eq!(simulate(FixedTimestep::new(step, 16), &jittery), (steps, states.clone()));
+        assert_eq!(simulate(FixedTimestep::new(step, 16), &hitchy), (steps, states.clone()));
+        assert!(states.iter().all(|(position, _)| position.y > 0.0 && position.y < 5.0), "{:?}", states);
+
+        // Deterministic mode steps once per frame, so only the frame count matters
+        let (deterministic_steps, deterministic_states) = simulate(FixedTimestep::deterministic(step), &jittery);
+        assert_eq!(deterministic_steps, jittery.len() as u32);
+        assert_eq!(simulate(FixedTimestep::deterministic(step), &vec![0.1; jittery.len()]), (deterministic_steps, deterministic_states));
+    }
+}
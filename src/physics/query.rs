@@ -0,0 +1,187 @@
+use ultraviolet::{Mat4, Vec3};
+
+use crate::graphics::model::Model;
+use crate::math::aabb_bouding_box::AABBBoundingBox;
+use crate::math::ray::{Ray, RayHit};
+use crate::physics::BodyHandle;
+use crate::physics::broad_phase::DynamicAabbTree;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RaycastHit {
+    pub body: BodyHandle,
+    pub point: Vec3,
+    pub normal: Vec3,
+    pub distance: f32,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ShapeCastHit {
+    pub body: BodyHandle,
+    pub point: Vec3, // Contact point on the surface that was hit
+    pub normal: Vec3,
+    pub distance: f32, // How far the shape travelled before touching
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CastShape {
+    Sphere { radius: f32 },
+    Box { half_extents: Vec3 },
+}
+
+impl CastShape {
+    // Inflating the target box by this amount turns the shape cast into a ray cast
+    pub fn inflation(&self) -> Vec3 {
+        match self {
+            CastShape::Sphere { radius } => Vec3::broadcast(*radius),
+            CastShape::Box { half_extents } => *half_extents,
+        }
+    }
+
+    pub fn support_offset(&self, normal: Vec3) -> Vec3 {
+        match self {
+            CastShape::Sphere { radius } => normal * *radius,
+            CastShape::Box { half_extents } => normal * half_extents.dot(normal.abs()),
+        }
+    }
+}
+
+// Ray is in world space, triangles are transformed with the model matrix
+pub fn raycast_model(ray: &Ray, model_matrix: Mat4, model: &Model, max_distance: f32) -> Option<RayHit> {
+    raycast_triangles(ray, model_matrix, model.triangles(), max_distance)
+}
+
+// Closest of the triangles after transforming them with `model_matrix`
+pub fn raycast_triangles(ray: &Ray, model_matrix: Mat4, triangles: impl IntoIterator<Item=[Vec3; 3]>, max_distance: f32) -> Option<RayHit> {
+    let local_ray = ray.transformed(model_matrix.inversed());
+    let normal_matrix = model_matrix.inversed().transposed();
+
+    // The ray parameter is preserved by affine transforms, so local distances are world distances
+    let mut closest: Option<RayHit> = None;
+    for triangle in triangles {
+        let max = closest.map_or(max_distance, |hit| hit.distance);
+        if let Some(hit) = local_ray.intersect_triangle(triangle, max) {
+            closest = Some(hit);
+        }
+    }
+
+    closest.map(|hit| {
+        let mut normal = normal_matrix.transform_vec3(hit.normal).normalized();
+        if normal.dot(ray.direction) > 0.0 {
+            normal = -normal;
+        }
+
+        RayHit { distance: hit.distance, normal }
+    })
+}
+
+// Closest hit among the bodies the broad phase finds along the ray. Each body is tested against its bounding box first,
+// then `raycast_body` tests its geometry up to the given distance
+pub fn raycast_bodies(broad_phase: &DynamicAabbTree<BodyHandle>, ray: &Ray, max_distance: f32, filter: impl Fn(BodyHandle) -> bool, bounding_box: impl Fn(BodyHandle) -> Option<AABBBoundingBox>, raycast_body: impl Fn(BodyHandle, f32) -> Option<RayHit>) -> Option<RaycastHit> {
+    let mut closest: Option<RaycastHit> = None;
+
+    for handle in broad_phase.query_ray(ray, max_distance) {
+        let max = closest.map_or(max_distance, |hit| hit.distance);
+        if !filter(handle) || bounding_box(handle).and_then(|bounding_box| ray.intersect_aabb(&bounding_box, max)).is_none() {
+            continue;
+        }
+
+        if let Some(hit) = raycast_body(handle, max) {
+            closest = Some(RaycastHit { body: handle, point: ray.at(hit.distance), normal: hit.normal, distance: hit.distance });
+        }
+    }
+
+    closest
+}
+
+// Sweeps against body bounding boxes, spheres are treated as their bounding cube near box edges
+pub fn shape_cast_bodies(broad_phase: &DynamicAabbTree<BodyHandle>, shape: CastShape, ray: &Ray, max_distance: f32, filter: impl Fn(BodyHandle) -> bool, bounding_box: impl Fn(BodyHandle) -> Option<AABBBoundingBox>) -> Option<ShapeCastHit> {
+    let inflation = shape.inflation();
+    let start_box = AABBBoundingBox::from_center_half_extents(ray.origin, inflation);
+    let swept_box = start_box.union(start_box.translated(ray.direction * max_distance));
+    let mut closest: Option<ShapeCastHit> = None;
+
+    for handle in broad_phase.query(swept_box) {
+        let Some(bounding_box) = bounding_box(handle).filter(|_| filter(handle)) else {
+            continue;
+        };
+
+        let max = closest.map_or(max_distance, |hit| hit.distance);
+        if let Some(hit) = ray.intersect_aabb(&bounding_box.expanded(inflation), max) {
+            let point = ray.at(hit.distance) - shape.support_offset(hit.normal);
+            closest = Some(ShapeCastHit { body: handle, point, normal: hit.normal, distance: hit.distance });
+        }
+    }
+
+    closest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Boxes at x = 2, 5 and 8 along a ray down the x axis
+    fn bodies() -> (DynamicAabbTree<BodyHandle>, Vec<AABBBoundingBox>) {
+        let boxes: Vec<AABBBoundingBox> = [2.0, 5.0, 8.0].iter().map(|x| AABBBoundingBox::from_center_half_extents(Vec3::new(*x, 0.0, 0.0), Vec3::broadcast(0.5))).collect();
+        let mut broad_phase = DynamicAabbTree::new();
+        for (index, bounding_box) in boxes.iter().enumerate() {
+            broad_phase.insert(*bounding_box, BodyHandle::Static(index));
+        }
+
+        (broad_phase, boxes)
+    }
+
+    fn index(handle: BodyHandle) -> usize {
+        match handle {
+            BodyHandle::Static(index) => index,
+            _ => unreachable!("only static bodies are inserted"),
+        }
+    }
+
+    #[test]
+    fn raycast_picks_the_nearest_body() {
+        let (broad_phase, boxes) = bodies();
+        let ray = Ray::new(Vec3::default(), Vec3::unit_x());
+        let raycast = |filter: &dyn Fn(BodyHandle) -> bool, max_distance: f32| {
+            raycast_bodies(&broad_phase, &ray, max_distance, filter, |handle| Some(boxes[index(handle)]), |handle, max| ray.intersect_aabb(&boxes[index(handle)], max))
+        };
+
+        let hit = raycast(&|_| true, 100.0).unwrap();
+        assert_eq!((hit.body, hit.distance, hit.normal), (BodyHandle::Static(0), 1.5, -Vec3::unit_x()));
+        assert_eq!(hit.point, Vec3::new(1.5, 0.0, 0.0));
+
+        let hit = raycast(&|handle| handle != BodyHandle::Static(0), 100.0).unwrap();
+        assert_eq!(hit.body, BodyHandle::Static(1));
+        assert!(raycast(&|_| true, 1.0).is_none());
+        assert!(raycast_bodies(&broad_phase, &Ray::new(Vec3::new(0.0, 3.0, 0.0), Vec3::unit_x()), 100.0, |_| true, |handle| Some(boxes[index(handle)]), |_, _| panic!("no candidates expected")).is_none());
+    }
+
+    #[test]
+    fn raycast_tests_geometry_inside_the_bounding_box() {
+        let (broad_phase, boxes) = bodies();
+        let ray = Ray::new(Vec3::new(0.0, 0.4, 0.0), Vec3::unit_x());
+
+        // Only the far body has geometry at this height, the nearer ones are just boxes the ray passes through
+        let hit = raycast_bodies(&broad_phase, &ray, 100.0, |_| true, |handle| Some(boxes[index(handle)]), |handle, max| {
+            let triangle = [Vec3::new(0.0, -1.0, -1.0), Vec3::new(0.0, 1.0, -1.0), Vec3::new(0.0, 0.0, 1.0)];
+            if index(handle) == 2 { raycast_triangles(&ray, Mat4::from_translation(Vec3::new(8.0, 0.0, 0.0)), [triangle], max) } else { None }
+        }).unwrap();
+
+        assert_eq!(hit.body, BodyHandle::Static(2));
+        assert!((hit.distance - 8.0).abs() < 1e-5 && (hit.normal + Vec3::unit_x()).mag() < 1e-5);
+    }
+
+    #[test]
+    fn shape_casts_stop_where_the_shape_touches() {
+        let (broad_phase, boxes) = bodies();
+        let ray = Ray::new(Vec3::default(), Vec3::unit_x());
+
+        let hit = shape_cast_bodies(&broad_phase, CastShape::Sphere { radius: 0.25 }, &ray, 100.0, |_| true, |handle| Some(boxes[index(handle)])).unwrap();
+        assert_eq!(hit.body, BodyHandle::Static(0));
+        assert!((hit.distance - 1.25).abs() < 1e-5);
+        assert!((hit.point - Vec3::new(1.5, 0.0, 0.0)).mag() < 1e-5);
+
+        let hit = shape_cast_bodies(&broad_phase, CastShape::Box { half_extents: Vec3::broadcast(0.5) }, &Ray::new(Vec3::new(0.0, 0.9, 0.0), Vec3::unit_x()), 100.0, |_| true, |handle| Some(boxes[index(handle)])).unwrap();
+        assert!((hit.distance - 1.0).abs() < 1e-5, "the box clips the corner a ray at this height would miss");
+        assert!(shape_cast_bodies(&broad_phase, CastShape::Sphere { radius: 0.25 }, &Ray::new(Vec3::new(0.0, 2.0, 0.0), Vec3::unit_x()), 100.0, |_| true, |handle| Some(boxes[index(handle)])).is_none());
+    }
+}
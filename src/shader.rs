@@ -2,9 +2,10 @@ use std::ffi::CString;
 use std::fmt::{Display, Formatter};
 use std::fs;
 
-use ogl33::{GL_COMPILE_STATUS, GL_FALSE, GL_FRAGMENT_SHADER, GL_LINK_STATUS, GL_TRUE, GL_VERTEX_SHADER, glAttachShader, glCompileShader, glCreateProgram, glCreateShader, glDeleteShader, glGetProgramInfoLog, glGetProgramiv, glGetShaderInfoLog, glGetShaderiv, glGetUniformLocation, glLinkProgram, glShaderSource, GLuint, glUniform1f, glUniform1i, glUniform3f, glUniformMatrix4fv, glUseProgram};
+use ogl33::{GL_COMPILE_STATUS, GL_FALSE, GL_FRAGMENT_SHADER, GL_INVALID_INDEX, GL_LINK_STATUS, GL_TRUE, GL_VERTEX_SHADER, glAttachShader, glCompileShader, glCreateProgram, glCreateShader, glDeleteProgram, glDeleteShader, glGetProgramInfoLog, glGetProgramiv, glGetShaderInfoLog, glGetShaderiv, glGetUniformBlockIndex, glGetUniformLocation, glLinkProgram, glShaderSource, GLuint, glUniform1f, glUniform1i, glUniform2f, glUniform3f, glUniform4f, glUniformBlockBinding, glUniformMatrix4fv, glUseProgram};
 use ultraviolet::Mat4;
 
+use crate::error::{EngineError, EngineResult};
 use crate::shader::SourceType::{Fragment, Program, Vertex};
 
 pub struct Shader {
@@ -28,40 +29,45 @@ impl Display for SourceType {
 }
 
 impl Shader {
-    pub fn from_files(vertex_path: &str, fragment_path: &str) -> Self {
-        let vertex_data = match fs::read(vertex_path) {
-            Ok(data) => data,
-            Err(e) => panic!("Couldn't find vertex shader file: {e:?}")
-        };
-
-        let fragment_data = match fs::read(fragment_path) {
-            Ok(data) => data,
-            Err(e) => panic!("Couldn't find fragment shader file: {e:?}")
-        };
+    pub fn from_files(vertex_path: &str, fragment_path: &str) -> EngineResult<Self> {
+        let vertex_data = fs::read(vertex_path).map_err(|source| EngineError::Io { path: vertex_path.to_owned(), source })?;
+        let fragment_data = fs::read(fragment_path).map_err(|source| EngineError::Io { path: fragment_path.to_owned(), source })?;
 
         unsafe {
             let vertex = glCreateShader(GL_VERTEX_SHADER);
             glShaderSource(vertex, 1, &(vertex_data.as_ptr().cast()), &(vertex_data.len().try_into().unwrap()));
             glCompileShader(vertex);
-            check_compile_errors(vertex, Vertex);
+            if let Err(log) = check_compile_errors(vertex, Vertex) {
+                glDeleteShader(vertex);
+                return Err(EngineError::ShaderCompile { path: vertex_path.to_owned(), log });
+            }
 
             let fragment = glCreateShader(GL_FRAGMENT_SHADER);
             glShaderSource(fragment, 1, &(fragment_data.as_ptr().cast()), &(fragment_data.len().try_into().unwrap()));
             glCompileShader(fragment);
-            check_compile_errors(fragment, Fragment);
+            if let Err(log) = check_compile_errors(fragment, Fragment) {
+                glDeleteShader(vertex);
+                glDeleteShader(fragment);
+                return Err(EngineError::ShaderCompile { path: fragment_path.to_owned(), log });
+            }
 
             let program_id = glCreateProgram();
             glAttachShader(program_id, vertex);
             glAttachShader(program_id, fragment);
             glLinkProgram(program_id);
-            check_compile_errors(program_id, Program);
+            let link_result = check_compile_errors(program_id, Program);
 
             glDeleteShader(vertex);
             glDeleteShader(fragment);
 
-            Self {
-                program_id
+            if let Err(log) = link_result {
+                glDeleteProgram(program_id);
+                return Err(EngineError::ShaderLink { vertex_path: vertex_path.to_owned(), fragment_path: fragment_path.to_owned(), log });
             }
+
+            Ok(Self {
+                program_id
+            })
         }
     }
 
@@ -89,12 +95,34 @@ impl Shader {
         }
     }
 
+    pub fn set_vec2(&self, name: &str, x: f32, y: f32) {
+        unsafe {
+            glUniform2f(glGetUniformLocation(self.program_id, CString::new(name).unwrap().as_ptr().cast()), x, y);
+        }
+    }
+
     pub fn set_vec3(&self, name: &str, x: f32, y: f32, z: f32) {
         unsafe {
             glUniform3f(glGetUniformLocation(self.program_id, CString::new(name).unwrap().as_ptr().cast()), x, y, z);
         }
     }
 
+    pub fn set_vec4(&self, name: &str, x: f32, y: f32, z: f32, w: f32) {
+        unsafe {
+            glUniform4f(glGetUniformLocation(self.program_id, CString::new(name).unwrap().as_ptr().cast()), x, y, z, w);
+        }
+    }
+
+    // Does nothing if the program doesn't use the block
+    pub fn bind_uniform_block(&self, name: &str, binding: u32) {
+        unsafe {
+            let index = glGetUniformBlockIndex(self.program_id, CString::new(name).unwrap().as_ptr().cast());
+            if index != GL_INVALID_INDEX {
+                glUniformBlockBinding(self.program_id, index, binding);
+            }
+        }
+    }
+
     pub fn set_mat4(&self, name: &str, mat: Mat4) {
         unsafe {
             glUniformMatrix4fv(glGetUniformLocation(self.program_id, CString::new(name).unwrap().as_ptr().cast()), 1, GL_FALSE, mat.as_ptr().cast());
@@ -102,7 +130,15 @@ impl Shader {
     }
 }
 
-unsafe fn check_compile_errors(id: GLuint, source_type: SourceType) {
+impl Drop for Shader {
+    fn drop(&mut self) {
+        unsafe {
+            glDeleteProgram(self.program_id);
+        }
+    }
+}
+
+unsafe fn check_compile_errors(id: GLuint, source_type: SourceType) -> Result<(), String> {
     let mut success = 0;
     let mut buf = Vec::<u8>::with_capacity(1024);
     let mut log_len = 0_i32;
@@ -113,7 +149,7 @@ unsafe fn check_compile_errors(id: GLuint, source_type: SourceType) {
             if success != i32::from(GL_TRUE) {
                 glGetShaderInfoLog(id, 1024, &mut log_len, buf.as_mut_ptr().cast());
                 buf.set_len(log_len.try_into().unwrap());
-                panic!("Failed to compile {source_type}: {}", String::from_utf8_lossy(&buf));
+                return Err(format!("{source_type}: {}", String::from_utf8_lossy(&buf)));
             }
         }
         SourceType::Program => {
@@ -121,8 +157,10 @@ unsafe fn check_compile_errors(id: GLuint, source_type: SourceType) {
             if success != i32::from(GL_TRUE) {
                 glGetProgramInfoLog(id, 1024, &mut log_len, buf.as_mut_ptr().cast());
                 buf.set_len(log_len.try_into().unwrap());
-                panic!("Failed to compile {source_type}: {}", String::from_utf8_lossy(&buf));
+                return Err(format!("{source_type}: {}", String::from_utf8_lossy(&buf)));
             }
         }
     }
+
+    Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,92 @@
+pub const DEFAULT_MAX_STEPS_PER_FRAME: u32 = 5;
+
+// Accumulates real frame time and hands it out in fixed simulation steps
+pub struct FixedTimestep {
+    step: f32,
+    max_steps_per_frame: u32,
+    accumulator: f32,
+    deterministic: bool,
+}
+
+impl FixedTimestep {
+    pub fn new(step: f32, max_steps_per_frame: u32) -> Self {
+        Self { step, max_steps_per_frame: max_steps_per_frame.max(1), accumulator: 0.0, deterministic: false }
+    }
+
+    // Every frame runs exactly one step no matter how long it took, so runs are reproducible
+    pub fn deterministic(step: f32) -> Self {
+        Self { deterministic: true, ..Self::new(step, 1) }
+    }
+
+    pub fn step(&self) -> f32 {
+        self.step
+    }
+
+    pub fn is_deterministic(&self) -> bool {
+        self.deterministic
+    }
+
+    // Returns how many fixed steps to run for a frame that took `frame_time` seconds
+    pub fn advance(&mut self, frame_time: f32) -> u32 {
+        if self.deterministic {
+            return 1;
+        }
+
+        self.accumulator += frame_time.max(0.0);
+        let mut steps = (self.accumulator / self.step) as u32;
+
+        // Drop the time we can't catch up on instead of spiralling into ever longer frames
+        if steps > self.max_steps_per_frame {
+            steps = self.max_steps_per_frame;
+            self.accumulator = self.accumulator % self.step + steps as f32 * self.step;
+        }
+
+        self.accumulator -= steps as f32 * self.step;
+        steps
+    }
+
+    // How far the current time is between the last two steps, used to interpolate rendered transforms
+    pub fn alpha(&self) -> f32 {
+        if self.deterministic {
+            return 1.0;
+        }
+
+        (self.accumulator / self.step).clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accumulates_partial_frames() {
+        let mut timestep = FixedTimestep::new(0.01, 5);
+
+        assert_eq!(timestep.advance(0.004), 0);
+        assert_eq!(timestep.advance(0.004), 0);
+        assert_eq!(timestep.advance(0.004), 1);
+        assert!((timestep.alpha() - 0.2).abs() < 1e-3);
+        assert_eq!(timestep.advance(0.025), 2);
+        assert!((timestep.alpha() - 0.7).abs() < 1e-3);
+    }
+
+    #[test]
+    fn clamps_steps_after_long_frames() {
+        let mut timestep = FixedTimestep::new(0.01, 3);
+
+        assert_eq!(timestep.advance(1.0), 3);
+        assert!(timestep.alpha() < 1.0);
+        assert_eq!(timestep.advance(0.0), 0);
+        assert_eq!(timestep.advance(0.01), 1);
+    }
+
+    #[test]
+    fn deterministic_mode_ignores_frame_time() {
+        let mut timestep = FixedTimestep::deterministic(0.01);
+
+        assert_eq!(timestep.advance(0.0), 1);
+        assert_eq!(timestep.advance(0.5), 1);
+        assert_eq!(timestep.alpha(), 1.0);
+    }
+}